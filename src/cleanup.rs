@@ -1 +1,104 @@
+//! Cleanup of stashes left behind by interrupted updates.
+//!
+//! An update stashes uncommitted changes and pops them at the end; a crash or
+//! kill in between leaves the stash on the stack. Rerunning after repeated
+//! crashes would pile up more, until nobody can tell which one holds the
+//! user's real work. Before stashing, an update counts the stashes git-daily
+//! made (their message is [`GIT_DAILY_STASH_MESSAGE`]) and refuses to add
+//! another once more than [`MAX_GIT_DAILY_STASHES`] are there, unless
+//! `--drop-stale-stashes` lets it drop all but the newest first. `--health`
+//! reports the same pile-up without waiting for an update to need a stash.
+//!
+//! [`GIT_DAILY_STASH_MESSAGE`]: crate::constants::GIT_DAILY_STASH_MESSAGE
 
+use crate::config::Config;
+use crate::constants::MAX_GIT_DAILY_STASHES;
+use crate::git::{self, StashEntry};
+use std::path::Path;
+
+/// Returned (inside the `anyhow::Error`) when too many git-daily stashes are
+/// already on the stack to safely add another.
+///
+/// Check for it with `error.downcast_ref::<StaleStashes>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleStashes {
+    /// Selectors of the git-daily stashes, newest first.
+    pub selectors: Vec<String>,
+}
+
+impl std::fmt::Display for StaleStashes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} git-daily stashes are already on the stack ({}); recover or drop them \
+             (`git stash list`), or rerun with --drop-stale-stashes",
+            self.selectors.len(),
+            self.selectors.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for StaleStashes {}
+
+/// The stashes git-daily made in `path`, newest first.
+pub fn git_daily_stashes(path: &Path, config: &Config) -> anyhow::Result<Vec<StashEntry>> {
+    let stashes = git::stash_list(path, config, config.git_logger())?;
+    Ok(stashes
+        .into_iter()
+        .filter(StashEntry::is_git_daily)
+        .collect())
+}
+
+/// Drops `stashes`, oldest first so the selectors of the rest stay valid.
+pub fn drop_stashes(path: &Path, config: &Config, stashes: &[StashEntry]) -> anyhow::Result<()> {
+    for stash in stashes.iter().rev() {
+        git::stash_drop(path, config, &stash.selector, config.git_logger())?;
+    }
+    Ok(())
+}
+
+/// Returns the git-daily stashes in `path` when there are more than
+/// [`MAX_GIT_DAILY_STASHES`], the point where an update refuses to stash.
+/// A clean repository never stashes, so `--health` uses this to report the
+/// pile-up before an update runs into it.
+pub fn stale_stashes(path: &Path, config: &Config) -> anyhow::Result<Option<StaleStashes>> {
+    let stashes = git_daily_stashes(path, config)?;
+    Ok(
+        (stashes.len() > MAX_GIT_DAILY_STASHES).then(|| StaleStashes {
+            selectors: stashes.into_iter().map(|stash| stash.selector).collect(),
+        }),
+    )
+}
+
+/// Makes sure another git-daily stash can be added to `path`.
+///
+/// Fails with [`StaleStashes`] when more than [`MAX_GIT_DAILY_STASHES`] are
+/// already there, unless `config.drop_stale_stashes` is set: then all but the
+/// newest, the one most likely to hold work not saved anywhere else, are
+/// dropped and a warning naming their commits is returned.
+pub fn ensure_stash_room(path: &Path, config: &Config) -> anyhow::Result<Option<String>> {
+    let stashes = git_daily_stashes(path, config)?;
+    if stashes.len() <= MAX_GIT_DAILY_STASHES {
+        return Ok(None);
+    }
+    if !config.drop_stale_stashes {
+        return Err(StaleStashes {
+            selectors: stashes.iter().map(|stash| stash.selector.clone()).collect(),
+        }
+        .into());
+    }
+    let (newest, older) = stashes.split_at(1);
+    drop_stashes(path, config, older)?;
+    let commits: Vec<&str> = older
+        .iter()
+        .map(|stash| &stash.commit[..stash.commit.len().min(7)])
+        .collect();
+    Ok(Some(format!(
+        "dropped {} stale git-daily {} ({}) and kept the newest ({}); \
+         `git stash store <commit>` brings one back",
+        older.len(),
+        if older.len() == 1 { "stash" } else { "stashes" },
+        commits.join(", "),
+        newest[0].selector
+    )))
+}
@@ -1,12 +1,142 @@
 //! Configuration types for CLI verbosity and options.
 
+use crate::constants::{
+    DEFAULT_GIT_BINARY, DEFAULT_REMOTE, DEFAULT_STALE_AFTER_DAYS, MAIN_BRANCH, MASTER_BRANCH,
+    RAYON_THREAD_COUNT, SECONDS_PER_DAY,
+};
 use crate::git::{self, GitLogger};
+use crate::manifest::Manifest;
+use crate::repo::UpdateStep;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Runtime configuration derived from CLI arguments.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct Config {
     /// Controls the verbosity level of CLI output.
     pub verbosity: Verbosity,
+    /// Stream update events as newline-delimited JSON instead of human output.
+    pub ndjson: bool,
+    /// How the final summary is written to stdout.
+    pub format: OutputFormat,
+    /// Skip repositories with more than this many uncommitted files
+    /// instead of stashing them.
+    pub skip_dirty_over: Option<usize>,
+    /// Ask the remote for the default branch's tip first, and leave a clean
+    /// repository untouched (no fetch, stash or checkout) when it matches
+    /// both the remote-tracking branch and the local branch.
+    pub skip_unchanged: bool,
+    /// Refuse to stash: dirty repositories are skipped untouched instead.
+    pub no_stash: bool,
+    /// Refuse to stash and fail dirty repositories instead, for CI runs
+    /// where a stash could hide a broken checkout.
+    pub fail_on_dirty: bool,
+    /// Drop the stashes earlier interrupted runs left behind instead of
+    /// refusing to stash once more than one has piled up.
+    pub drop_stale_stashes: bool,
+    /// Treat a repository whose only uncommitted changes are whitespace or
+    /// line endings as clean, leaving those changes in place instead of stashing.
+    pub ignore_whitespace_dirty: bool,
+    /// Changes to paths matching these pathspecs do not make a repository
+    /// dirty, e.g. a build file rewritten with a timestamp on every build.
+    /// A repository whose only changes are there is updated without a stash.
+    pub ignore_dirty_paths: Option<Vec<String>>,
+    /// Stash only changes matching these pathspecs instead of everything;
+    /// other changes stay in the working tree during the update.
+    pub stash_pathspec: Option<Vec<String>>,
+    /// Leave a default branch that has local commits of its own as-is, with
+    /// a warning, instead of failing the repository at the fast-forward.
+    pub keep_diverged_default: bool,
+    /// Fail a repository instead of pulling when the new tip of its default
+    /// branch is not validly signed (`git verify-commit`).
+    pub verify_signatures: bool,
+    /// Run the checkouts that switch to master/main and back with hooks
+    /// disabled, so a post-checkout hook cannot change the working tree
+    /// mid-update. Hooks still run for the pull.
+    pub no_checkout_hooks: bool,
+    /// After a successful update, remove the registrations of worktrees whose
+    /// directories are gone (`git worktree prune`). A failed prune is only a warning.
+    pub prune_worktrees: bool,
+    /// What to do when untracked files would be overwritten by checking out master/main.
+    pub untracked_conflict: UntrackedConflictPolicy,
+    /// Which branch to update when both `master` and `main` exist locally.
+    /// `None` lets a live upstream and `origin/HEAD` decide, falling back to `master`.
+    pub branch_preference: Option<BranchPreference>,
+    /// Repository name (directory name) to the branch to update in that repo.
+    /// An override bypasses master/main detection entirely.
+    pub branch_overrides: HashMap<String, String>,
+    /// Which branch an update brings up to date.
+    pub update_mode: UpdateMode,
+    /// Stay silent while running and print only a one-line summary if every
+    /// repository succeeded; print the full summary if anything failed.
+    pub quiet_on_success: bool,
+    /// Print nothing at all if every repository was already up to date;
+    /// print the full summary if anything was pulled, skipped, or failed.
+    pub report_only_changed: bool,
+    /// Collapse failures with the same step and error into one summary entry
+    /// with a count (every path is still listed in verbose mode).
+    pub collapse_failures: bool,
+    /// Follow the summary with per-host counts of updated, failed and
+    /// skipped repositories, grouped by the host of each remote URL.
+    pub group_by_host: bool,
+    /// How workspace progress is drawn (ignored when progress is hidden).
+    pub progress: ProgressMode,
+    /// Groups of repositories to update one after another (`None` updates
+    /// everything at once).
+    pub manifest: Option<Manifest>,
+    /// Number of repositories to update in parallel (`None` uses the default).
+    pub jobs: Option<usize>,
+    /// Start at most this many repositories per [`RAMP_INTERVAL_MS`] until
+    /// every worker is busy (`None` starts them all at once).
+    ///
+    /// [`RAMP_INTERVAL_MS`]: crate::constants::RAMP_INTERVAL_MS
+    pub ramp: Option<usize>,
+    /// Remote to fetch from and pull (`None` uses `origin`).
+    pub remote: Option<String>,
+    /// Default branches to try, in order (`None` tries `master`, then `main`).
+    pub default_branches: Option<Vec<String>>,
+    /// Git executable to run (`None` runs `git` from `PATH`).
+    pub git_binary: Option<PathBuf>,
+    /// Extra environment variables for every spawned git process (and the
+    /// hooks it runs), e.g. `GIT_SSH_COMMAND` to select a key.
+    pub env: HashMap<String, String>,
+    /// Extra arguments appended to every `git fetch`, e.g. `--no-tags`.
+    /// Checked by [`git::validate_extra_fetch_args`] before they are used.
+    pub extra_fetch_args: Vec<String>,
+    /// Let `extra_fetch_args` include options that make git run another
+    /// program, such as `--upload-pack`.
+    pub allow_unsafe_fetch_args: bool,
+    /// Refuse to run any git command that could change a repository, its
+    /// refs or its working tree; such commands fail with
+    /// [`git::ReadOnlyViolation`] instead of running. Updates then fail at
+    /// their first such step, while scans and `--health` still work.
+    pub read_only: bool,
+    /// Share one SSH connection per host across the run's git processes.
+    pub ssh_multiplex: bool,
+    /// Identifier for this invocation, included in JSON/NDJSON output and
+    /// verbose logs so lines from one run can be found in merged logs.
+    pub run_id: Option<String>,
+    /// Repositories a `--continue-from` run skipped as already updated,
+    /// recorded in the JSON report (`None` when not resuming).
+    pub resumed: Option<usize>,
+    /// Check once that the first repository's remote is reachable before
+    /// updating anything, and abort the whole run if it is not.
+    pub preflight: bool,
+    /// Days without a commit on the default branch after which `--health`
+    /// flags a repository as stale (`None` uses the default).
+    pub stale_after_days: Option<u64>,
+    /// Answer yes to every confirmation prompt instead of asking.
+    pub assume_yes: bool,
+    /// Run a repository's whole update again, up to this many times, when it
+    /// fails at a retried step and could be put back as it started.
+    pub repo_retries: u32,
+    /// Steps whose failures are retried (`None` retries fetch, checkout and
+    /// branch restore failures).
+    pub repo_retry_steps: Option<Vec<UpdateStep>>,
+    /// Fraction of repositories (0.0 to 1.0) allowed to fail before the run
+    /// exits non-zero (`None` fails the run on any failure).
+    pub fail_threshold: Option<f32>,
 }
 
 impl Config {
@@ -20,6 +150,85 @@ impl Config {
         self.verbosity == Verbosity::Verbose
     }
 
+    /// Returns true if progress bars should be hidden.
+    ///
+    /// Progress is hidden in quiet and verbose modes, when output is deferred,
+    /// and when stdout carries machine-readable output that a progress bar would corrupt.
+    #[must_use]
+    pub fn hides_progress(&self) -> bool {
+        self.is_quiet() || self.is_verbose() || self.ndjson || self.defers_output()
+    }
+
+    /// Returns true if stdout carries a JSON document or event stream, so
+    /// nothing else may be printed there.
+    #[must_use]
+    pub fn has_machine_readable_stdout(&self) -> bool {
+        self.ndjson || self.format.is_machine_readable()
+    }
+
+    /// Returns true if nothing should be printed until the outcome of the run
+    /// is known (`--quiet-on-success` and `--report-only-changed`).
+    #[must_use]
+    pub fn defers_output(&self) -> bool {
+        self.quiet_on_success || self.report_only_changed
+    }
+
+    /// Returns the number of worker threads for workspace updates.
+    #[must_use]
+    pub fn thread_count(&self) -> usize {
+        self.jobs.unwrap_or(RAYON_THREAD_COUNT).max(1)
+    }
+
+    /// Returns how old a default branch's last commit may be before the
+    /// health scan flags the repository as stale.
+    #[must_use]
+    pub fn stale_after(&self) -> Duration {
+        let days = self.stale_after_days.unwrap_or(DEFAULT_STALE_AFTER_DAYS);
+        Duration::from_secs(days * SECONDS_PER_DAY)
+    }
+
+    /// Returns the remote to fetch from and pull.
+    #[must_use]
+    pub fn remote(&self) -> &str {
+        self.remote.as_deref().unwrap_or(DEFAULT_REMOTE)
+    }
+
+    /// Returns the git executable to run.
+    #[must_use]
+    pub fn git_binary(&self) -> &Path {
+        self.git_binary
+            .as_deref()
+            .unwrap_or(Path::new(DEFAULT_GIT_BINARY))
+    }
+
+    /// Returns the default branches to try, in order.
+    #[must_use]
+    pub fn default_branches(&self) -> Vec<&str> {
+        match &self.default_branches {
+            Some(branches) => branches.iter().map(String::as_str).collect(),
+            None => vec![MASTER_BRANCH, MAIN_BRANCH],
+        }
+    }
+
+    /// Returns true if a failure at `step` should be retried (see `repo_retries`).
+    #[must_use]
+    pub fn retries_failures_at(&self, step: UpdateStep) -> bool {
+        match &self.repo_retry_steps {
+            Some(steps) => steps.contains(&step),
+            None => matches!(
+                step,
+                UpdateStep::Fetching | UpdateStep::CheckingOut | UpdateStep::RestoringBranch
+            ),
+        }
+    }
+
+    /// Returns the branch override configured for the repository at `path`, if any.
+    #[must_use]
+    pub fn branch_override(&self, path: &Path) -> Option<&str> {
+        let name = path.file_name()?.to_str()?;
+        self.branch_overrides.get(name).map(String::as_str)
+    }
+
     /// Returns the appropriate git logger based on verbosity settings.
     ///
     /// This is a presentation-layer concern: config controls which logger
@@ -35,6 +244,17 @@ impl Config {
     }
 }
 
+/// Generates a run ID from the current time and process ID
+/// (`<unix seconds>-<pid>`), unique enough to tell runs on one host apart.
+#[must_use]
+pub fn generate_run_id() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    format!("{}-{}", secs, std::process::id())
+}
+
 /// Verbosity level for CLI output.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Verbosity {
@@ -44,6 +264,92 @@ pub enum Verbosity {
     Verbose,
 }
 
+/// Which branch an update brings up to date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UpdateMode {
+    /// Update master/main and return to the original branch.
+    #[default]
+    DefaultBranch,
+    /// Fast-forward the currently checked-out branch with a single `git pull --ff-only`.
+    CurrentBranch,
+    /// Rebase the currently checked-out branch onto its upstream with
+    /// `git pull --rebase --autostash`, aborting on conflicts.
+    CurrentBranchRebase,
+    /// Only fetch (with prune), refreshing remote-tracking refs without
+    /// touching any branch, the stash, or the working tree.
+    FetchOnly,
+    /// Only drop remote-tracking refs whose branches were deleted on the
+    /// remote (`git remote prune`): the remote's refs are listed, but no
+    /// objects are fetched and nothing local is touched.
+    PruneRemoteOnly,
+}
+
+/// How workspace progress is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ProgressMode {
+    /// A progress bar plus the most recently completed repositories.
+    #[default]
+    Full,
+    /// A single line with the completed and failed counts, for CI logs.
+    Compact,
+    /// One line per repository showing its current step, then ✓ or ✗,
+    /// capped at the terminal height (for wide terminals and tmux panes).
+    Lines,
+}
+
+/// How the final summary is written to stdout (`--format`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Colored summary for a terminal.
+    #[default]
+    Human,
+    /// A JSON run report, reusable with `--only-failed-from`.
+    Json,
+    /// One CSV row per repository under a header row.
+    Csv,
+    /// A markdown table for pasting into chat, issues, or pull requests.
+    Markdown,
+    /// One tab-separated line per repository, stable for scripts.
+    Porcelain,
+}
+
+impl OutputFormat {
+    /// Returns true if the format is meant for programs, so nothing else
+    /// may be printed to stdout alongside it.
+    #[must_use]
+    pub fn is_machine_readable(self) -> bool {
+        matches!(self, Self::Json | Self::Csv | Self::Porcelain)
+    }
+}
+
+/// Policy for untracked files that would be overwritten by checking out master/main.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum UntrackedConflictPolicy {
+    /// Fail the update, naming the conflicting files.
+    #[default]
+    Fail,
+    /// Skip the repository, leaving its branch and working tree as they were.
+    Skip,
+    /// Stash untracked files along with other changes and restore them afterwards.
+    StashUntracked,
+}
+
+/// Explicit choice for repositories that have both `master` and `main`
+/// branches locally. When given, it wins over the stale-leftover and
+/// `origin/HEAD` rules.
+///
+/// A warning is reported whichever policy is used, since one of the two is
+/// usually a leftover from a rename.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum BranchPreference {
+    /// Use `master`.
+    Master,
+    /// Use `main`.
+    Main,
+    /// Use whichever branch still has an `origin/` counterpart, falling back to `master`.
+    Upstream,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -53,21 +359,88 @@ mod tests {
     fn test_config_quiet_and_verbose_flags() {
         let quiet = Config {
             verbosity: Verbosity::Quiet,
+            ..Default::default()
         };
         assert!(quiet.is_quiet());
         assert!(!quiet.is_verbose());
 
         let verbose = Config {
             verbosity: Verbosity::Verbose,
+            ..Default::default()
         };
         assert!(!verbose.is_quiet());
         assert!(verbose.is_verbose());
     }
 
+    #[test]
+    fn test_branch_override_matches_repo_directory_name() {
+        let config = Config {
+            branch_overrides: HashMap::from([("fork".to_string(), "develop".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.branch_override(Path::new("/ws/fork")),
+            Some("develop")
+        );
+        assert_eq!(config.branch_override(Path::new("/ws/other")), None);
+    }
+
+    #[test]
+    fn test_thread_count_defaults_and_never_drops_to_zero() {
+        assert_eq!(Config::default().thread_count(), RAYON_THREAD_COUNT);
+        let config = Config {
+            jobs: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(config.thread_count(), 1);
+    }
+
+    #[test]
+    fn test_remote_and_default_branches_fall_back_to_defaults() {
+        let config = Config::default();
+        assert_eq!(config.remote(), "origin");
+        assert_eq!(config.default_branches(), vec!["master", "main"]);
+        assert_eq!(config.git_binary(), Path::new("git"));
+
+        let config = Config {
+            remote: Some("upstream".to_string()),
+            default_branches: Some(vec!["develop".to_string()]),
+            git_binary: Some(PathBuf::from("/opt/git/bin/git")),
+            ..Default::default()
+        };
+        assert_eq!(config.remote(), "upstream");
+        assert_eq!(config.default_branches(), vec!["develop"]);
+        assert_eq!(config.git_binary(), Path::new("/opt/git/bin/git"));
+    }
+
+    #[test]
+    fn test_retries_failures_at_defaults_and_configured_steps() {
+        let config = Config::default();
+        assert!(config.retries_failures_at(UpdateStep::Fetching));
+        assert!(config.retries_failures_at(UpdateStep::CheckingOut));
+        assert!(!config.retries_failures_at(UpdateStep::PoppingStash));
+
+        let config = Config {
+            repo_retry_steps: Some(vec![UpdateStep::PoppingStash]),
+            ..Default::default()
+        };
+        assert!(config.retries_failures_at(UpdateStep::PoppingStash));
+        assert!(!config.retries_failures_at(UpdateStep::Fetching));
+    }
+
+    #[test]
+    fn test_generate_run_id_is_time_and_pid() {
+        let run_id = generate_run_id();
+        let (secs, pid) = run_id.split_once('-').unwrap();
+        assert!(secs.parse::<u64>().unwrap() > 0);
+        assert_eq!(pid, std::process::id().to_string());
+    }
+
     #[test]
     fn test_git_logger_selects_verbose_or_no_op() {
         let verbose = Config {
             verbosity: Verbosity::Verbose,
+            ..Default::default()
         };
         assert!(std::ptr::fn_addr_eq(
             verbose.git_logger() as GitLogger,
@@ -76,6 +449,7 @@ mod tests {
 
         let normal = Config {
             verbosity: Verbosity::Normal,
+            ..Default::default()
         };
         assert!(std::ptr::fn_addr_eq(
             normal.git_logger() as GitLogger,
@@ -21,21 +21,109 @@ pub fn git_timeout() -> Duration {
         .unwrap_or(Duration::from_secs(DEFAULT_GIT_TIMEOUT_SECS))
 }
 
+/// Default limit for enumerating the workspace directory (in seconds).
+const DEFAULT_DISCOVERY_TIMEOUT_SECS: u64 = 30;
+
+/// Returns the workspace discovery timeout.
+///
+/// Can be customized via the GIT_DAILY_DISCOVERY_TIMEOUT environment variable (in seconds).
+/// Falls back to 30 seconds if not set or invalid.
+///
+/// Example: `GIT_DAILY_DISCOVERY_TIMEOUT=120 git-daily-v2`
+pub fn discovery_timeout() -> Duration {
+    std::env::var("GIT_DAILY_DISCOVERY_TIMEOUT")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(DEFAULT_DISCOVERY_TIMEOUT_SECS))
+}
+
+/// Default cap on the stdout or stderr kept from a single git command (in bytes).
+const DEFAULT_GIT_OUTPUT_LIMIT_BYTES: usize = 4 * 1024 * 1024;
+
+/// Returns how many bytes of each git output stream are kept.
+///
+/// Can be customized via the GIT_DAILY_MAX_OUTPUT environment variable (in bytes).
+/// Falls back to 4 MiB if not set or invalid. Anything past the limit is read
+/// and discarded, and the kept output ends with a truncation marker.
+///
+/// Example: `GIT_DAILY_MAX_OUTPUT=16777216 git-daily-v2`
+pub fn git_output_limit() -> usize {
+    std::env::var("GIT_DAILY_MAX_OUTPUT")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_GIT_OUTPUT_LIMIT_BYTES)
+}
+
+/// Git executable run when none is configured, looked up on `PATH`.
+pub const DEFAULT_GIT_BINARY: &str = "git";
+
+/// Remote fetched from and pulled when none is configured.
+pub const DEFAULT_REMOTE: &str = "origin";
+
 /// Number of threads for parallel repository updates.
 /// Higher than CPU count because git operations are I/O-bound (network, disk).
 pub const RAYON_THREAD_COUNT: usize = 60;
 
+/// Interval in milliseconds in which `--ramp N` starts up to N repositories.
+pub const RAMP_INTERVAL_MS: u64 = 100;
+
 /// Progress bar tick interval in milliseconds.
 /// Controls how often the spinner/bar animates.
 pub const PROGRESS_TICK_MS: u64 = 80;
 
+/// Seconds an idle shared SSH connection stays open (`--ssh-multiplex`).
+/// Long enough to bridge the gap between a repo's fetch and its pull.
+pub const SSH_CONTROL_PERSIST_SECS: u64 = 30;
+
 /// Maximum number of completed repositories to show in the workspace progress display.
 pub const MAX_VISIBLE_COMPLETIONS: usize = 5;
 
+/// Terminal height assumed by `--progress=lines` when it cannot be read.
+pub const DEFAULT_TERMINAL_ROWS: usize = 24;
+
+/// Days without a commit after which `--health` flags a repository's default
+/// branch as stale (see `--stale-after`).
+pub const DEFAULT_STALE_AFTER_DAYS: u64 = 180;
+
+/// Seconds in a day, for `--stale-after` and the ages shown by `--health`.
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Number of repository names shown for a collapsed group of identical failures.
+pub const MAX_COLLAPSED_FAILURE_SAMPLES: usize = 3;
+
+/// Message of every stash git-daily makes, so leftovers from interrupted runs
+/// can be told apart from the user's own stashes.
+pub const GIT_DAILY_STASH_MESSAGE: &str = "git-daily: changes stashed before update";
+
+/// git-daily stashes an update tolerates on the stack before it refuses to
+/// add another (see `--drop-stale-stashes`).
+pub const MAX_GIT_DAILY_STASHES: usize = 1;
+
 /// Default branch names to try when checking out the main branch.
 pub const MASTER_BRANCH: &str = "master";
 pub const MAIN_BRANCH: &str = "main";
 
+/// What `git rev-parse --abbrev-ref HEAD` prints when HEAD is detached.
+/// Never a real branch name: it must not be passed to `git checkout`.
+pub const DETACHED_HEAD: &str = "HEAD";
+
+/// Host group for repositories whose remote is a local path or `file://` URL.
+pub const LOCAL_REMOTE_HOST: &str = "(local)";
+
+/// Host group for repositories without the configured remote.
+pub const NO_REMOTE_HOST: &str = "(no remote)";
+
+/// Directories refused as a workspace root without `--force`, along with `$HOME`.
+/// Running from one of these would sweep up every repository on the machine.
+pub const DANGEROUS_WORKSPACE_ROOTS: &[&str] = &[
+    "/", "/home", "/Users", "/root", "/usr", "/etc", "/var", "/opt",
+];
+
+/// Longest path the classic Windows file APIs accept; longer paths need the
+/// `\\?\` extended-length prefix.
+pub const WINDOWS_MAX_PATH: usize = 260;
+
 /// Git directory name used to detect repositories.
 pub const GIT_DIR: &str = ".git";
 
@@ -0,0 +1,172 @@
+//! Environment variable overrides for configuration defaults.
+//!
+//! All `GIT_DAILY_*` variables that feed into [`Config`] are read here.
+//! Command-line flags take precedence over the environment, which takes
+//! precedence over the built-in defaults. Invalid values are ignored with a
+//! warning.
+//!
+//! The process-wide limits `GIT_DAILY_TIMEOUT`, `GIT_DAILY_DISCOVERY_TIMEOUT`
+//! and `GIT_DAILY_MAX_OUTPUT` have no flag and are not part of [`Config`]:
+//! [`crate::constants`] reads them where they apply, and falls back to the
+//! default without a warning when one is invalid.
+
+use crate::config::Config;
+
+/// Number of repositories to update in parallel (like `--jobs`).
+pub const JOBS_VAR: &str = "GIT_DAILY_JOBS";
+/// Remote to fetch from and pull instead of `origin`.
+pub const REMOTE_VAR: &str = "GIT_DAILY_REMOTE";
+/// Comma-separated default branches to try in order, e.g. `develop,main`.
+pub const DEFAULT_BRANCHES_VAR: &str = "GIT_DAILY_DEFAULT_BRANCHES";
+
+/// Configuration values read from the environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvOverrides {
+    pub jobs: Option<usize>,
+    pub remote: Option<String>,
+    pub default_branches: Option<Vec<String>>,
+    /// One message per variable that was set but ignored as invalid.
+    pub warnings: Vec<String>,
+}
+
+impl EnvOverrides {
+    /// Reads overrides from the process environment.
+    #[must_use]
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// Reads overrides through `lookup`, which returns a variable's value if set.
+    #[must_use]
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let mut overrides = Self::default();
+
+        if let Some(value) = lookup(JOBS_VAR) {
+            match value.trim().parse::<usize>() {
+                Ok(jobs) if jobs > 0 => overrides.jobs = Some(jobs),
+                _ => overrides.warn(JOBS_VAR, &value, "expected a positive number"),
+            }
+        }
+
+        if let Some(value) = lookup(REMOTE_VAR) {
+            let remote = value.trim();
+            if is_valid_name(remote) {
+                overrides.remote = Some(remote.to_string());
+            } else {
+                overrides.warn(REMOTE_VAR, &value, "expected a remote name");
+            }
+        }
+
+        if let Some(value) = lookup(DEFAULT_BRANCHES_VAR) {
+            let branches: Vec<String> = value
+                .split(',')
+                .map(str::trim)
+                .filter(|b| !b.is_empty())
+                .map(str::to_string)
+                .collect();
+            if !branches.is_empty() && branches.iter().all(|b| is_valid_name(b)) {
+                overrides.default_branches = Some(branches);
+            } else {
+                overrides.warn(
+                    DEFAULT_BRANCHES_VAR,
+                    &value,
+                    "expected comma-separated branch names",
+                );
+            }
+        }
+
+        overrides
+    }
+
+    /// Fills in `config` fields that were not set on the command line.
+    pub fn apply(&self, config: &mut Config) {
+        if config.jobs.is_none() {
+            config.jobs = self.jobs;
+        }
+        if config.remote.is_none() {
+            config.remote.clone_from(&self.remote);
+        }
+        if config.default_branches.is_none() {
+            config.default_branches.clone_from(&self.default_branches);
+        }
+    }
+
+    fn warn(&mut self, var: &str, value: &str, expected: &str) {
+        self.warnings
+            .push(format!("ignoring {}='{}': {}", var, value, expected));
+    }
+}
+
+/// Rejects empty names and names git would read as an option.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('-') && !name.chars().any(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn overrides(vars: &[(&str, &str)]) -> EnvOverrides {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        EnvOverrides::from_lookup(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn test_each_variable_maps_to_its_config_field() {
+        let mut config = Config::default();
+        overrides(&[
+            (JOBS_VAR, "8"),
+            (REMOTE_VAR, "upstream"),
+            (DEFAULT_BRANCHES_VAR, "develop, main"),
+        ])
+        .apply(&mut config);
+
+        assert_eq!(config.jobs, Some(8));
+        assert_eq!(config.remote(), "upstream");
+        assert_eq!(config.default_branches(), vec!["develop", "main"]);
+    }
+
+    #[test]
+    fn test_unset_variables_leave_defaults() {
+        let env = overrides(&[]);
+        assert_eq!(env, EnvOverrides::default());
+
+        let mut config = Config::default();
+        env.apply(&mut config);
+        assert_eq!(config.jobs, None);
+        assert_eq!(config.remote(), "origin");
+    }
+
+    #[test]
+    fn test_command_line_values_win_over_environment() {
+        let mut config = Config {
+            jobs: Some(2),
+            ..Default::default()
+        };
+        overrides(&[(JOBS_VAR, "8")]).apply(&mut config);
+        assert_eq!(config.jobs, Some(2));
+    }
+
+    #[test]
+    fn test_invalid_values_warn_and_fall_back() {
+        let env = overrides(&[
+            (JOBS_VAR, "lots"),
+            (REMOTE_VAR, "--upload-pack=evil"),
+            (DEFAULT_BRANCHES_VAR, " , "),
+        ]);
+
+        assert_eq!(env.jobs, None);
+        assert_eq!(env.remote, None);
+        assert_eq!(env.default_branches, None);
+        assert_eq!(env.warnings.len(), 3);
+        assert_eq!(
+            env.warnings[0],
+            "ignoring GIT_DAILY_JOBS='lots': expected a positive number"
+        );
+        assert!(overrides(&[(JOBS_VAR, "0")]).jobs.is_none());
+    }
+}
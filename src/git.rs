@@ -6,7 +6,7 @@
 use crate::config::Config;
 use crate::constants;
 use anyhow::Context;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
 /// Callback for logging git commands and their output.
@@ -24,7 +24,13 @@ pub fn verbose_logger(config: &Config, args: &[&str], output: Option<&str>) {
     }
 
     for line in build_verbose_logger_lines(args, output) {
-        eprintln!("{}", line);
+        match &config.run_id {
+            Some(run_id) => {
+                use colored::Colorize;
+                eprintln!("{} {}", format!("[{}]", run_id).dimmed(), line);
+            }
+            None => eprintln!("{}", line),
+        }
     }
 }
 
@@ -33,6 +39,13 @@ pub fn run_git(repo: &Path, config: &Config, args: &[&str]) -> anyhow::Result<St
     run_git_with_logger(repo, config, args, no_op_logger)
 }
 
+/// Runs `git --version` with the configured binary, so a missing or broken
+/// git is reported once at startup instead of as a failure in every repo.
+pub fn check_git_binary(config: &Config) -> anyhow::Result<String> {
+    run_git(Path::new("."), config, &["--version"])
+        .with_context(|| format!("Cannot run git binary '{}'", config.git_binary().display()))
+}
+
 /// Executes a git command with a custom logging callback.
 /// The logger is called once before execution (output=None) and once after (output=Some).
 pub fn run_git_with_logger(
@@ -48,13 +61,150 @@ pub fn run_git_with_logger(
         Ok(stdout)
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("git {} failed: {}", args.join(" "), stderr)
+        Err(git_failure(args, output.status.code(), &stderr))
+    }
+}
+
+/// Executes a git command, handing each stderr line to `on_stderr_line` while
+/// the command is still running.
+///
+/// Git separates progress updates with `\r`, so both `\r` and `\n` end a line.
+/// Lines for which `on_stderr_line` returns `true` are treated as consumed and
+/// left out of the error message if the command fails. The rest are kept for
+/// that message up to [`constants::git_output_limit`] bytes, like stdout.
+pub fn run_git_streaming_stderr(
+    repo: &Path,
+    config: &Config,
+    args: &[&str],
+    logger: GitLogger,
+    on_stderr_line: &dyn Fn(&str) -> bool,
+) -> anyhow::Result<String> {
+    run_git_streaming_stderr_with_limit(
+        repo,
+        config,
+        args,
+        logger,
+        on_stderr_line,
+        constants::git_output_limit(),
+    )
+}
+
+fn run_git_streaming_stderr_with_limit(
+    repo: &Path,
+    config: &Config,
+    args: &[&str],
+    logger: GitLogger,
+    on_stderr_line: &dyn Fn(&str) -> bool,
+    output_limit: usize,
+) -> anyhow::Result<String> {
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    check_read_only(config, args)?;
+    logger(config, args, None);
+
+    let mut child = git_command(repo, config, args)
+        .spawn()
+        .context("Failed to spawn git command")?;
+    let stdout = child
+        .stdout
+        .take()
+        .map(|stdout| spawn_output_reader(stdout, output_limit));
+
+    // Read stderr on its own thread so progress arrives while git is running
+    // and a chatty command can never block on a full pipe
+    let stderr = child.stderr.take().context("git stderr was not captured")?;
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for_each_stderr_line(stderr, |line| sender.send(line.to_string()).is_ok())
+    });
+
+    let timeout = constants::git_timeout();
+    let start = Instant::now();
+    let poll_interval = std::time::Duration::from_millis(100);
+    let mut unconsumed = Vec::new();
+    let mut kept = 0;
+    let mut dropped = 0;
+    let mut handle_line = |line: String| {
+        if on_stderr_line(&line) {
+            return;
+        }
+        // Counting the newline each line is joined with
+        let size = line.len() + 1;
+        if dropped == 0 && kept + size <= output_limit {
+            kept += size;
+            unconsumed.push(line);
+        } else {
+            dropped += size;
+        }
+    };
+
+    let status = loop {
+        match receiver.recv_timeout(poll_interval) {
+            Ok(line) => handle_line(line),
+            Err(mpsc::RecvTimeoutError::Timeout | mpsc::RecvTimeoutError::Disconnected) => {}
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => {
+                if start.elapsed() > timeout {
+                    let _ = child.kill();
+                    anyhow::bail!("git command timed out after {} seconds", timeout.as_secs());
+                }
+            }
+            Err(e) => {
+                let _ = child.kill();
+                return Err(e).context("Failed to wait for git process");
+            }
+        }
+    };
+    // The reader thread finishes once git closes stderr
+    for line in receiver {
+        handle_line(line);
+    }
+
+    if dropped > 0 {
+        unconsumed.push(truncation_marker(dropped as u64, output_limit));
+    }
+
+    let stdout = join_output_reader(stdout, "stdout")?;
+    if status.success() {
+        let stdout = String::from_utf8_lossy(&stdout).trim().to_string();
+        logger(config, args, Some(&stdout));
+        Ok(stdout)
+    } else {
+        Err(git_failure(args, status.code(), &unconsumed.join("\n")))
+    }
+}
+
+/// Splits a byte stream on `\r` and `\n`, calling `on_line` for each non-empty line.
+/// Stops early if `on_line` returns `false`.
+fn for_each_stderr_line<R: std::io::Read>(reader: R, mut on_line: impl FnMut(&str) -> bool) {
+    use std::io::Read;
+
+    let mut pending = Vec::new();
+    for byte in std::io::BufReader::new(reader).bytes() {
+        let Ok(byte) = byte else { break };
+        if byte == b'\r' || byte == b'\n' {
+            if !pending.is_empty() {
+                let keep_going = on_line(&String::from_utf8_lossy(&pending));
+                pending.clear();
+                if !keep_going {
+                    return;
+                }
+            }
+        } else {
+            pending.push(byte);
+        }
+    }
+    if !pending.is_empty() {
+        on_line(&String::from_utf8_lossy(&pending));
     }
 }
 
 /// Waits for a child process with a timeout.
 fn wait_with_timeout(
-    child: &mut std::process::Child,
+    child: &mut CapturedChild,
     timeout: std::time::Duration,
 ) -> anyhow::Result<std::process::Output> {
     wait_with_timeout_inner(child, timeout)
@@ -103,28 +253,90 @@ trait WaitableChild {
     fn read_stderr(&mut self) -> anyhow::Result<Vec<u8>>;
 }
 
-impl WaitableChild for std::process::Child {
+type OutputReader = std::thread::JoinHandle<std::io::Result<Vec<u8>>>;
+
+/// A git process whose stdout and stderr are drained on background threads
+/// while it runs, so a chatty command can never block on a full pipe.
+struct CapturedChild {
+    child: std::process::Child,
+    stdout: Option<OutputReader>,
+    stderr: Option<OutputReader>,
+}
+
+impl CapturedChild {
+    /// Takes over the child's pipes, keeping at most `limit` bytes of each.
+    fn new(mut child: std::process::Child, limit: usize) -> Self {
+        let stdout = child
+            .stdout
+            .take()
+            .map(|stdout| spawn_output_reader(stdout, limit));
+        let stderr = child
+            .stderr
+            .take()
+            .map(|stderr| spawn_output_reader(stderr, limit));
+        Self {
+            child,
+            stdout,
+            stderr,
+        }
+    }
+}
+
+impl WaitableChild for CapturedChild {
     fn try_wait(&mut self) -> std::io::Result<Option<std::process::ExitStatus>> {
-        std::process::Child::try_wait(self)
+        self.child.try_wait()
     }
 
     fn read_stdout(&mut self) -> anyhow::Result<Vec<u8>> {
-        let mut buf = Vec::new();
-        if let Some(mut stdout) = self.stdout.take() {
-            std::io::Read::read_to_end(&mut stdout, &mut buf)
-                .context("Failed to read stdout from git process")?;
-        }
-        Ok(buf)
+        join_output_reader(self.stdout.take(), "stdout")
     }
 
     fn read_stderr(&mut self) -> anyhow::Result<Vec<u8>> {
-        let mut buf = Vec::new();
-        if let Some(mut stderr) = self.stderr.take() {
-            std::io::Read::read_to_end(&mut stderr, &mut buf)
-                .context("Failed to read stderr from git process")?;
-        }
-        Ok(buf)
+        join_output_reader(self.stderr.take(), "stderr")
+    }
+}
+
+fn spawn_output_reader<R>(reader: R, limit: usize) -> OutputReader
+where
+    R: std::io::Read + Send + 'static,
+{
+    std::thread::spawn(move || read_capped(reader, limit))
+}
+
+fn join_output_reader(reader: Option<OutputReader>, stream: &str) -> anyhow::Result<Vec<u8>> {
+    let Some(reader) = reader else {
+        return Ok(Vec::new());
+    };
+    reader
+        .join()
+        .map_err(|_| anyhow::anyhow!("{} reader thread panicked", stream))?
+        .with_context(|| format!("Failed to read {} from git process", stream))
+}
+
+/// Reads `reader` to the end, keeping only the first `limit` bytes.
+///
+/// The rest is still read, so the writer never blocks, but is discarded. When
+/// anything was dropped the kept bytes end with a truncation marker.
+fn read_capped<R: std::io::Read>(reader: R, limit: usize) -> std::io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut reader = reader.take(limit as u64);
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    let dropped = std::io::copy(&mut reader.into_inner(), &mut std::io::sink())?;
+    if dropped > 0 {
+        buf.push(b'\n');
+        buf.extend_from_slice(truncation_marker(dropped, limit).as_bytes());
     }
+    Ok(buf)
+}
+
+/// Marks where output past `limit` bytes was dropped.
+fn truncation_marker(dropped: u64, limit: usize) -> String {
+    format!(
+        "[output truncated: {} bytes past the {} byte limit dropped]",
+        dropped, limit
+    )
 }
 
 fn build_verbose_logger_lines(args: &[&str], output: Option<&str>) -> Vec<String> {
@@ -140,6 +352,8 @@ fn build_verbose_logger_lines(args: &[&str], output: Option<&str>) -> Vec<String
     }
 }
 
+/// Returns the checked-out branch name, or
+/// [`DETACHED_HEAD`](crate::constants::DETACHED_HEAD) when HEAD is detached.
 pub fn get_current_branch(
     repo: &Path,
     config: &Config,
@@ -158,6 +372,75 @@ pub fn get_current_commit(
         .context("Failed to get current commit")
 }
 
+/// Returns the committer time of `rev` (`git log -1 --format=%ct <rev>`).
+pub fn commit_time(
+    repo: &Path,
+    config: &Config,
+    rev: &str,
+    logger: GitLogger,
+) -> anyhow::Result<std::time::SystemTime> {
+    let output = run_git_with_logger(repo, config, &["log", "-1", "--format=%ct", rev], logger)
+        .with_context(|| format!("Failed to read commit time of '{}'", rev))?;
+    let seconds: u64 = output
+        .parse()
+        .with_context(|| format!("Unexpected commit time output: '{}'", output))?;
+    Ok(std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds))
+}
+
+/// Returns the absolute path of the repository's git directory.
+///
+/// Resolves `.git` files of linked worktrees and submodules to the real
+/// directory, so callers can inspect state files like `MERGE_HEAD`.
+pub fn git_dir(repo: &Path, config: &Config, logger: GitLogger) -> anyhow::Result<PathBuf> {
+    run_git_with_logger(repo, config, &["rev-parse", "--absolute-git-dir"], logger)
+        .map(PathBuf::from)
+        .context("Failed to resolve git directory")
+}
+
+/// Returns the tag pointing exactly at HEAD, if any.
+///
+/// Uses `git describe --exact-match --tags`, so lightweight and annotated tags
+/// are both recognized. Returns `None` when HEAD is not tagged.
+pub fn get_exact_tag(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+) -> anyhow::Result<Option<String>> {
+    let args = ["describe", "--exact-match", "--tags", "HEAD"];
+    let output = run_git_output(repo, config, &args, logger)?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let tag = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    logger(config, &args, Some(&tag));
+    Ok(Some(tag).filter(|t| !t.is_empty()))
+}
+
+/// Returns a concise description of HEAD via `git describe --tags --always --dirty`
+/// (e.g. `v1.2.3-4-gabcdef0-dirty`). Falls back to an abbreviated SHA when
+/// the repository has no tags.
+pub fn describe(repo: &Path, config: &Config, logger: GitLogger) -> anyhow::Result<String> {
+    run_git_with_logger(
+        repo,
+        config,
+        &["describe", "--tags", "--always", "--dirty"],
+        logger,
+    )
+    .context("Failed to describe HEAD")
+}
+
+/// Returns true if a remote with this name is configured.
+pub fn has_remote(
+    repo: &Path,
+    config: &Config,
+    remote: &str,
+    logger: GitLogger,
+) -> anyhow::Result<bool> {
+    let remotes =
+        run_git_with_logger(repo, config, &["remote"], logger).context("Failed to list remotes")?;
+    Ok(remotes.lines().any(|name| name == remote))
+}
+
 /// Returns true if the remote tracking ref exists.
 ///
 /// `remote_ref` must be in `<remote>/<branch>` form (for example, `origin/feature-x`),
@@ -179,6 +462,145 @@ pub fn remote_ref_exists(
     Ok(output.status.success())
 }
 
+/// Returns true if a local branch with this name exists.
+pub fn local_branch_exists(
+    repo: &Path,
+    config: &Config,
+    branch: &str,
+    logger: GitLogger,
+) -> anyhow::Result<bool> {
+    validate_branch_name(branch)?;
+    let ref_path = format!("refs/heads/{}", branch);
+    let output = run_git_output(
+        repo,
+        config,
+        &["rev-parse", "--verify", "--quiet", ref_path.as_str()],
+        logger,
+    )?;
+    Ok(output.status.success())
+}
+
+/// Returns true if `path` is inside a git work tree, per
+/// `git rev-parse --is-inside-work-tree`. Unlike looking for a `.git` entry,
+/// this also accepts subdirectories, worktrees and symlinked checkouts.
+/// A path that does not exist is reported as `false`.
+pub fn is_inside_work_tree(
+    path: &Path,
+    config: &Config,
+    logger: GitLogger,
+) -> anyhow::Result<bool> {
+    if !path.is_dir() {
+        return Ok(false);
+    }
+    let args = ["rev-parse", "--is-inside-work-tree"];
+    let output = run_git_output(path, config, &args, logger)?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    logger(config, &args, Some(&stdout));
+    Ok(output.status.success() && stdout == "true")
+}
+
+/// Returns true for a bare repository: a clone or mirror without a working tree.
+pub fn is_bare_repository(repo: &Path, config: &Config, logger: GitLogger) -> anyhow::Result<bool> {
+    let output = run_git_with_logger(repo, config, &["rev-parse", "--is-bare-repository"], logger)
+        .context("Failed to check for a bare repository")?;
+    Ok(output == "true")
+}
+
+/// Removes the administrative entries (`.git/worktrees/<name>`) of worktrees
+/// whose directories no longer exist (`git worktree prune`).
+pub fn prune_worktrees(repo: &Path, config: &Config, logger: GitLogger) -> anyhow::Result<()> {
+    run_git_with_logger(repo, config, &["worktree", "prune"], logger)
+        .context("Failed to prune worktrees")?;
+    Ok(())
+}
+
+/// Returns the upstream configured for a local branch in `<remote>/<branch>`
+/// form (for example, `origin/main`), or `None` if it has no upstream.
+///
+/// The upstream is reported even if its remote branch has since been deleted;
+/// combine with [`remote_ref_exists`] to check that it is still live.
+pub fn branch_upstream(
+    repo: &Path,
+    config: &Config,
+    branch: &str,
+    logger: GitLogger,
+) -> anyhow::Result<Option<String>> {
+    validate_branch_name(branch)?;
+    let ref_path = format!("refs/heads/{}", branch);
+    let upstream = run_git_with_logger(
+        repo,
+        config,
+        &[
+            "for-each-ref",
+            "--format=%(upstream:short)",
+            ref_path.as_str(),
+        ],
+        logger,
+    )
+    .with_context(|| format!("Failed to read upstream of '{}'", branch))?;
+    Ok(Some(upstream).filter(|u| !u.is_empty()))
+}
+
+/// Returns the remote and the branch on it that a local branch pulls from
+/// (`branch.<name>.remote` and `branch.<name>.merge`), e.g. `("origin", "main")`,
+/// or `None` if it has no upstream.
+///
+/// Unlike [`branch_upstream`] the two parts are kept apart, so a remote name
+/// containing `/` is not mistaken for part of the branch.
+pub fn branch_upstream_source(
+    repo: &Path,
+    config: &Config,
+    branch: &str,
+    logger: GitLogger,
+) -> anyhow::Result<Option<(String, String)>> {
+    validate_branch_name(branch)?;
+    let ref_path = format!("refs/heads/{}", branch);
+    let output = run_git_with_logger(
+        repo,
+        config,
+        &[
+            "for-each-ref",
+            "--format=%(upstream:remotename)|%(upstream:remoteref)",
+            ref_path.as_str(),
+        ],
+        logger,
+    )
+    .with_context(|| format!("Failed to read upstream of '{}'", branch))?;
+    Ok(output.split_once('|').and_then(|(remote, merge)| {
+        let merge = merge.strip_prefix("refs/heads/").unwrap_or(merge);
+        (!remote.is_empty() && !merge.is_empty()).then(|| (remote.to_string(), merge.to_string()))
+    }))
+}
+
+/// Returns the branch `refs/remotes/<remote>/HEAD` points to, short form
+/// (e.g. `origin/main`): the remote's default branch as of the clone or the
+/// last `git remote set-head`. `None` when it is not set.
+pub fn remote_default_branch(
+    repo: &Path,
+    config: &Config,
+    remote: &str,
+    logger: GitLogger,
+) -> anyhow::Result<Option<String>> {
+    validate_branch_name(remote)?;
+    let head_ref = format!("refs/remotes/{}/HEAD", remote);
+    let args = ["symbolic-ref", "--quiet", "--short", head_ref.as_str()];
+    let output = run_git_output(repo, config, &args, logger)?;
+    // Exit code 1 just means the remote HEAD is not set
+    let result = match output.status.code() {
+        Some(0) => {
+            let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            logger(config, &args, Some(&branch));
+            Ok(Some(branch).filter(|branch| !branch.is_empty()))
+        }
+        Some(1) => Ok(None),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(git_failure(&args, output.status.code(), &stderr))
+        }
+    };
+    result.with_context(|| format!("Failed to read the default branch of '{}'", remote))
+}
+
 pub fn has_uncommitted_changes(
     repo: &Path,
     config: &Config,
@@ -189,45 +611,1370 @@ pub fn has_uncommitted_changes(
         .context("Failed to check for uncommitted changes")
 }
 
-pub fn fetch_prune(repo: &Path, config: &Config, logger: GitLogger) -> anyhow::Result<()> {
-    run_git_with_logger(repo, config, &["fetch", "--prune"], logger)
-        .context("Failed to fetch from remote")?;
-    Ok(())
+/// Returns the number of files reported by `git status --porcelain`
+/// (modified, staged, and untracked).
+pub fn count_uncommitted_files(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+) -> anyhow::Result<usize> {
+    run_git_with_logger(repo, config, &["status", "--porcelain"], logger)
+        .map(|output| output.lines().filter(|line| !line.is_empty()).count())
+        .context("Failed to count uncommitted changes")
+}
+
+/// Branch, commit and dirtiness of a working tree, read with one
+/// `git status --porcelain=v2 --branch`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoStatus {
+    /// Checked-out branch, or [`DETACHED_HEAD`](crate::constants::DETACHED_HEAD)
+    /// when HEAD is detached.
+    pub branch: String,
+    /// Commit HEAD points at, or `None` before the first commit.
+    pub commit: Option<String>,
+    /// Number of changed and untracked files, as [`count_uncommitted_files`] counts them.
+    pub dirty_files: usize,
+}
+
+/// Reads the branch, HEAD commit and uncommitted file count in a single git
+/// call, instead of one each for [`get_current_branch`],
+/// [`get_current_commit`] and [`count_uncommitted_files`].
+///
+/// Changes to paths matching `excluded` are not counted, as in
+/// [`count_uncommitted_files_excluding`].
+pub fn repo_status(
+    repo: &Path,
+    config: &Config,
+    excluded: &[String],
+    logger: GitLogger,
+) -> anyhow::Result<RepoStatus> {
+    for pathspec in excluded {
+        validate_pathspec(pathspec)?;
+    }
+    let exclusions: Vec<String> = excluded
+        .iter()
+        .map(|pathspec| format!(":(exclude){}", pathspec))
+        .collect();
+    let mut args = vec!["status", "--porcelain=v2", "--branch"];
+    if !exclusions.is_empty() {
+        args.push("--");
+        args.extend(exclusions.iter().map(String::as_str));
+    }
+    let output = run_git_with_logger(repo, config, &args, logger)
+        .context("Failed to read repository status")?;
+    parse_repo_status(&output)
+}
+
+/// Parses `git status --porcelain=v2 --branch` output.
+fn parse_repo_status(output: &str) -> anyhow::Result<RepoStatus> {
+    let mut branch = None;
+    let mut commit = None;
+    let mut dirty_files = 0;
+    for line in output.lines().filter(|line| !line.is_empty()) {
+        if let Some(oid) = line.strip_prefix("# branch.oid ") {
+            commit = (oid != "(initial)").then(|| oid.to_string());
+        } else if let Some(head) = line.strip_prefix("# branch.head ") {
+            branch = Some(if head == "(detached)" {
+                crate::constants::DETACHED_HEAD.to_string()
+            } else {
+                head.to_string()
+            });
+        } else if !line.starts_with('#') {
+            dirty_files += 1;
+        }
+    }
+    let branch =
+        branch.with_context(|| format!("No branch header in git status output: '{}'", output))?;
+    Ok(RepoStatus {
+        branch,
+        commit,
+        dirty_files,
+    })
+}
+
+/// Like [`count_uncommitted_files`], but leaves out changes to paths matching
+/// `excluded` (`git status --porcelain -- ':(exclude)<path>'...`).
+pub fn count_uncommitted_files_excluding(
+    repo: &Path,
+    config: &Config,
+    excluded: &[String],
+    logger: GitLogger,
+) -> anyhow::Result<usize> {
+    for pathspec in excluded {
+        validate_pathspec(pathspec)?;
+    }
+    let exclusions: Vec<String> = excluded
+        .iter()
+        .map(|pathspec| format!(":(exclude){}", pathspec))
+        .collect();
+    let mut args = vec!["status", "--porcelain", "--"];
+    args.extend(exclusions.iter().map(String::as_str));
+    run_git_with_logger(repo, config, &args, logger)
+        .map(|output| output.lines().filter(|line| !line.is_empty()).count())
+        .context("Failed to count uncommitted changes")
+}
+
+/// Returns true if every uncommitted change to tracked files, staged or not,
+/// is whitespace or line endings only (`git diff HEAD --quiet --ignore-all-space`)
+/// and there are no untracked files. A clean tree also counts.
+pub fn has_only_whitespace_changes(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+) -> anyhow::Result<bool> {
+    let untracked = run_git_with_logger(
+        repo,
+        config,
+        &["ls-files", "--others", "--exclude-standard"],
+        logger,
+    )
+    .context("Failed to list untracked files")?;
+    if !untracked.is_empty() {
+        return Ok(false);
+    }
+    let args = ["diff", "HEAD", "--quiet", "--ignore-all-space"];
+    let output = run_git_output(repo, config, &args, logger)?;
+    // --quiet exits 1 when something other than whitespace differs
+    let result = match output.status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(git_failure(&args, output.status.code(), &stderr))
+        }
+    };
+    result.context("Failed to check for whitespace-only changes")
+}
+
+/// Returns the number of commits reachable from HEAD but not from `base`
+/// (`git rev-list --count <base>..HEAD`).
+pub fn count_commits_since(
+    repo: &Path,
+    config: &Config,
+    base: &str,
+    logger: GitLogger,
+) -> anyhow::Result<usize> {
+    let range = format!("{}..HEAD", base);
+    let output = run_git_with_logger(repo, config, &["rev-list", "--count", &range], logger)
+        .context("Failed to count new commits")?;
+    output
+        .trim()
+        .parse()
+        .with_context(|| format!("Unexpected rev-list output: '{}'", output))
+}
+
+/// Returns how many commits `left` has that `right` does not (ahead) and
+/// vice versa (behind), via `git rev-list --left-right --count left...right`.
+///
+/// Both sides may be any commit-ish: branches, tags, remote refs, or SHAs.
+/// An unknown ref is reported as an error naming it.
+pub fn ahead_behind(
+    repo: &Path,
+    config: &Config,
+    left: &str,
+    right: &str,
+    logger: GitLogger,
+) -> anyhow::Result<(usize, usize)> {
+    for rev in [left, right] {
+        validate_branch_name(rev)?;
+        let commit = format!("{}^{{commit}}", rev);
+        let output = run_git_output(
+            repo,
+            config,
+            &["rev-parse", "--verify", "--quiet", commit.as_str()],
+            logger,
+        )?;
+        if !output.status.success() {
+            anyhow::bail!("Unknown ref '{}'", rev);
+        }
+    }
+    let range = format!("{}...{}", left, right);
+    let output = run_git_with_logger(
+        repo,
+        config,
+        &["rev-list", "--left-right", "--count", range.as_str()],
+        logger,
+    )
+    .with_context(|| format!("Failed to compare '{}' with '{}'", left, right))?;
+    parse_ahead_behind(&output)
+}
+
+/// Parses `rev-list --left-right --count` output (`"<ahead>\t<behind>"`).
+/// Any whitespace is accepted as the separator.
+fn parse_ahead_behind(output: &str) -> anyhow::Result<(usize, usize)> {
+    let mut columns = output.split_whitespace();
+    match (columns.next(), columns.next(), columns.next()) {
+        (Some(ahead), Some(behind), None) => {
+            let parse = |count: &str| {
+                count
+                    .parse::<usize>()
+                    .with_context(|| format!("Unexpected ahead/behind output: '{}'", output))
+            };
+            Ok((parse(ahead)?, parse(behind)?))
+        }
+        _ => anyhow::bail!("Unexpected ahead/behind output: '{}'", output),
+    }
+}
+
+/// Fetches from the configured remote, pruning deleted remote branches.
+///
+/// Returns the fetch summary (the `From <url>` header and one line per
+/// updated ref, e.g. `abc..def  main -> origin/main`). Git versions disagree
+/// on whether this goes to stdout or stderr, so both streams are searched.
+pub fn fetch_prune(repo: &Path, config: &Config, logger: GitLogger) -> anyhow::Result<Vec<String>> {
+    let args = fetch_prune_args(repo, config, logger, false)?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output = run_git_output(repo, config, &args, logger)?;
+    let result = if output.status.success() {
+        let summary = fetch_summary_from_output(&output);
+        logger(config, &args, Some(&summary.join("\n")));
+        Ok(summary)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(git_failure(&args, output.status.code(), &stderr))
+    };
+    result.context("Failed to fetch from remote")
+}
+
+/// Builds `git fetch --prune [--progress] [--filter=<spec>] <remote>`.
+///
+/// A partial clone's fetch passes the clone's own filter explicitly, so the
+/// fetch can never fall back to downloading every blob. Full clones get no
+/// `--filter`, which would turn them into partial clones.
+fn fetch_prune_args(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+    progress: bool,
+) -> anyhow::Result<Vec<String>> {
+    let mut args = vec!["fetch".to_string(), "--prune".to_string()];
+    if progress {
+        args.push("--progress".to_string());
+    }
+    if let Some(filter) = partial_clone_filter(repo, config, config.remote(), logger)? {
+        args.push(format!("--filter={}", filter));
+    }
+    args.push(config.remote().to_string());
+    validate_extra_fetch_args(&config.extra_fetch_args, config.allow_unsafe_fetch_args)?;
+    args.extend(config.extra_fetch_args.iter().cloned());
+    Ok(args)
+}
+
+/// Returns the URL configured for `remote` (`remote.<name>.url`), or `None`
+/// if the remote does not exist.
+pub fn remote_url(
+    repo: &Path,
+    config: &Config,
+    remote: &str,
+    logger: GitLogger,
+) -> anyhow::Result<Option<String>> {
+    validate_branch_name(remote)?;
+    config_value(repo, config, &[], &format!("remote.{}.url", remote), logger)
+}
+
+/// Returns the lowercased host of a remote URL, for both URL syntax
+/// (`https://host/...`, `ssh://user@host:22/...`) and scp-like syntax
+/// (`git@host:org/repo.git`). Local paths and `file://` URLs have no host.
+#[must_use]
+pub fn remote_host(url: &str) -> Option<String> {
+    let authority = match url.split_once("://") {
+        Some(("file", _)) => return None,
+        Some((_, rest)) => rest.split('/').next()?,
+        None => {
+            let (authority, _) = url.split_once(':')?;
+            // `C:\repos\x` is a Windows path, `../x:y` a relative one
+            if authority.len() == 1 || authority.contains(['/', '\\']) {
+                return None;
+            }
+            authority
+        }
+    };
+    let host_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match host_port.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next()?,
+        None => host_port.split(':').next()?,
+    };
+    Some(host.to_lowercase()).filter(|host| !host.is_empty())
+}
+
+/// Returns the `core.sshCommand` git would use in `repo` (from its own,
+/// global or system config), or `None` if it is not set.
+pub fn ssh_command(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+) -> anyhow::Result<Option<String>> {
+    config_value(repo, config, &[], "core.sshCommand", logger)
+}
+
+/// Returns the object filter of a partial clone from `remote` (e.g.
+/// `blob:none`), or `None` if `remote` is not a promisor remote or has no
+/// filter configured.
+pub fn partial_clone_filter(
+    repo: &Path,
+    config: &Config,
+    remote: &str,
+    logger: GitLogger,
+) -> anyhow::Result<Option<String>> {
+    validate_branch_name(remote)?;
+    let promisor = config_value(
+        repo,
+        config,
+        &["--type=bool"],
+        &format!("remote.{}.promisor", remote),
+        logger,
+    )?;
+    if promisor.as_deref() != Some("true") {
+        return Ok(None);
+    }
+    config_value(
+        repo,
+        config,
+        &[],
+        &format!("remote.{}.partialclonefilter", remote),
+        logger,
+    )
+}
+
+/// Reads a config value with `git config <options> --get <key>`, e.g. with
+/// `--type=bool` to normalize booleans to `true`/`false`. Returns `None` if
+/// the key is not set.
+fn config_value(
+    repo: &Path,
+    config: &Config,
+    options: &[&str],
+    key: &str,
+    logger: GitLogger,
+) -> anyhow::Result<Option<String>> {
+    let mut args = vec!["config"];
+    args.extend(options);
+    args.extend(["--get", key]);
+    let output = run_git_output(repo, config, &args, logger)?;
+    // Exit code 1 just means the key is not set
+    let result = match output.status.code() {
+        Some(0) => {
+            let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            logger(config, &args, Some(&value));
+            Ok(Some(value).filter(|value| !value.is_empty()))
+        }
+        Some(1) => Ok(None),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(git_failure(&args, output.status.code(), &stderr))
+        }
+    };
+    result.with_context(|| format!("Failed to read config '{}'", key))
+}
+
+/// Collects the fetch summary lines from stdout and stderr, in that order.
+fn fetch_summary_from_output(output: &std::process::Output) -> Vec<String> {
+    [&output.stdout, &output.stderr]
+        .into_iter()
+        .flat_map(|stream| {
+            String::from_utf8_lossy(stream)
+                .lines()
+                .filter(|line| is_fetch_summary_line(line))
+                .map(|line| line.trim().to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Returns true for the `From <url>` header and ref update lines
+/// (`abc..def  main -> origin/main`, `* [new branch]  x -> origin/x`, ...).
+fn is_fetch_summary_line(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("From ") || line.contains(" -> ")
+}
+
+/// Checks that `remote` can be reached by asking it for its `HEAD`
+/// (`git ls-remote <remote> HEAD`). Nothing is written to the repository.
+pub fn ls_remote_head(
+    repo: &Path,
+    config: &Config,
+    remote: &str,
+    logger: GitLogger,
+) -> anyhow::Result<()> {
+    run_git_with_logger(repo, config, &["ls-remote", remote, "HEAD"], logger)
+        .with_context(|| format!("Failed to reach remote '{}'", remote))?;
+    Ok(())
+}
+
+/// Returns the commit `branch` points to on `remote`, asked of the remote
+/// itself (`git ls-remote <remote> refs/heads/<branch>`), or `None` if the
+/// remote has no such branch. Nothing is fetched or written.
+pub fn remote_branch_tip(
+    repo: &Path,
+    config: &Config,
+    remote: &str,
+    branch: &str,
+    logger: GitLogger,
+) -> anyhow::Result<Option<String>> {
+    validate_branch_name(remote)?;
+    validate_branch_name(branch)?;
+    let head_ref = format!("refs/heads/{}", branch);
+    let output = run_git_with_logger(repo, config, &["ls-remote", remote, &head_ref], logger)
+        .with_context(|| format!("Failed to reach remote '{}'", remote))?;
+    Ok(output
+        .lines()
+        .find_map(|line| line.split_once('\t').filter(|(_, name)| *name == head_ref))
+        .map(|(sha, _)| sha.to_string()))
+}
+
+/// Returns the commit `rev` resolves to, or `None` if it does not name one
+/// (`git rev-parse --verify --quiet <rev>^{commit}`).
+pub fn resolve_commit(
+    repo: &Path,
+    config: &Config,
+    rev: &str,
+    logger: GitLogger,
+) -> anyhow::Result<Option<String>> {
+    validate_branch_name(rev)?;
+    let spec = format!("{}^{{commit}}", rev);
+    let args = ["rev-parse", "--verify", "--quiet", spec.as_str()];
+    let output = run_git_output(repo, config, &args, logger)?;
+    let result = match output.status.code() {
+        Some(0) => {
+            let sha = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            logger(config, &args, Some(&sha));
+            Ok(Some(sha))
+        }
+        Some(1) => Ok(None),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(git_failure(&args, output.status.code(), &stderr))
+        }
+    };
+    result.with_context(|| format!("Failed to resolve '{}'", rev))
+}
+
+/// Fetch progress parsed from a git `Receiving objects:` line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FetchProgress {
+    pub received_bytes: u64,
+    /// Git never reports the total size up front, so this is only known
+    /// once the transfer reaches 100%.
+    pub total_bytes: Option<u64>,
+}
+
+/// Like [`fetch_prune`], but reports transfer progress while the fetch runs.
+///
+/// `on_progress` is called for every progress line that includes a byte
+/// count. Small fetches may finish without reporting any.
+pub fn fetch_prune_with_progress(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+    on_progress: &dyn Fn(FetchProgress),
+) -> anyhow::Result<()> {
+    let args = fetch_prune_args(repo, config, logger, true)?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    run_git_streaming_stderr(
+        repo,
+        config,
+        &args,
+        logger,
+        &|line| match parse_fetch_progress(line) {
+            Some(progress) => {
+                on_progress(progress);
+                true
+            }
+            None => is_progress_line(line),
+        },
+    )
+    .context("Failed to fetch from remote")?;
+    Ok(())
+}
+
+/// Like [`fetch_prune_with_progress`], but returns the branches of the
+/// configured remote that the fetch created, sorted by name.
+///
+/// Found by comparing [`remote_branches`] before and after the fetch.
+pub fn fetch_prune_new_branches(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+    on_progress: &dyn Fn(FetchProgress),
+) -> anyhow::Result<Vec<String>> {
+    let before: std::collections::HashSet<String> =
+        remote_branches(repo, config, config.remote(), logger)?
+            .into_iter()
+            .collect();
+    fetch_prune_with_progress(repo, config, logger, on_progress)?;
+    let mut created: Vec<String> = remote_branches(repo, config, config.remote(), logger)?
+        .into_iter()
+        .filter(|branch| !before.contains(branch))
+        .collect();
+    created.sort();
+    Ok(created)
+}
+
+/// Deletes the remote-tracking branches whose branches no longer exist on
+/// the remote (`git remote prune <remote>`), without fetching any objects.
+///
+/// Returns the pruned branch names, sorted. They are found by comparing the
+/// remote-tracking branches before and after, not by reading git's output.
+pub fn remote_prune(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+) -> anyhow::Result<Vec<String>> {
+    let remote = config.remote();
+    let before = remote_branches(repo, config, remote, logger)?;
+    run_git_with_logger(repo, config, &["remote", "prune", remote], logger)
+        .with_context(|| format!("Failed to prune remote '{}'", remote))?;
+    let after: std::collections::HashSet<String> = remote_branches(repo, config, remote, logger)?
+        .into_iter()
+        .collect();
+    let mut pruned: Vec<String> = before
+        .into_iter()
+        .filter(|branch| !after.contains(branch))
+        .collect();
+    pruned.sort();
+    Ok(pruned)
+}
+
+/// Lists the remote-tracking branches of `remote` (`refs/remotes/<remote>/*`)
+/// by branch name, without the remote prefix and without `HEAD`.
+pub fn remote_branches(
+    repo: &Path,
+    config: &Config,
+    remote: &str,
+    logger: GitLogger,
+) -> anyhow::Result<Vec<String>> {
+    validate_branch_name(remote)?;
+    let pattern = format!("refs/remotes/{}/", remote);
+    let output = run_git_with_logger(
+        repo,
+        config,
+        &[
+            "for-each-ref",
+            "--format=%(refname:lstrip=3)",
+            pattern.as_str(),
+        ],
+        logger,
+    )
+    .with_context(|| format!("Failed to list branches of remote '{}'", remote))?;
+    Ok(output
+        .lines()
+        .filter(|branch| !branch.is_empty() && *branch != "HEAD")
+        .map(str::to_string)
+        .collect())
+}
+
+/// Parses a git fetch progress line such as
+/// `Receiving objects:  45% (123/273), 1.20 MiB | 2.00 MiB/s`.
+///
+/// Returns `None` for other lines and for progress lines without a byte count.
+#[must_use]
+pub fn parse_fetch_progress(line: &str) -> Option<FetchProgress> {
+    let rest = line.trim().strip_prefix("Receiving objects:")?;
+    let (percent, rest) = rest.split_once('%')?;
+    let percent: u32 = percent.trim().parse().ok()?;
+    let (_, size) = rest.split_once("), ")?;
+    let size = size.split(['|', ',']).next()?.trim();
+    let received_bytes = parse_byte_size(size)?;
+    let total_bytes = (percent == 100).then_some(received_bytes);
+    Some(FetchProgress {
+        received_bytes,
+        total_bytes,
+    })
+}
+
+/// Parses sizes in git's throughput format (`512 bytes`, `1.20 KiB`, `3.50 MiB`, `1.00 GiB`).
+fn parse_byte_size(size: &str) -> Option<u64> {
+    let (number, unit) = size.split_once(' ')?;
+    let multiplier: u64 = match unit.trim() {
+        "byte" | "bytes" => 1,
+        "KiB" => 1 << 10,
+        "MiB" => 1 << 20,
+        "GiB" => 1 << 30,
+        _ => return None,
+    };
+    let number: f64 = number.parse().ok()?;
+    Some((number * multiplier as f64).round() as u64)
+}
+
+/// Returns true for git's transient progress lines (counting, compressing, ...),
+/// which are noise in an error message.
+fn is_progress_line(line: &str) -> bool {
+    let line = line.trim_start_matches("remote:").trim();
+    line.contains("% (") || line.ends_with(", done.")
+}
+
+pub fn stash(repo: &Path, config: &Config, logger: GitLogger) -> anyhow::Result<bool> {
+    let output = run_git_with_logger(
+        repo,
+        config,
+        &[
+            "stash",
+            "push",
+            "--message",
+            constants::GIT_DAILY_STASH_MESSAGE,
+        ],
+        logger,
+    )
+    .context("Failed to stash changes")?;
+    Ok(!output.contains("No local changes to save"))
+}
+
+/// Stashes only the changes matching `pathspecs` (`git stash push -- <pathspec>...`),
+/// leaving every other change in the working tree. Exclusions such as
+/// `:(exclude)generated.txt` are supported. Returns false if nothing matched.
+pub fn stash_paths(
+    repo: &Path,
+    config: &Config,
+    pathspecs: &[String],
+    logger: GitLogger,
+) -> anyhow::Result<bool> {
+    for pathspec in pathspecs {
+        validate_pathspec(pathspec)?;
+    }
+    let mut args = vec![
+        "stash",
+        "push",
+        "--message",
+        constants::GIT_DAILY_STASH_MESSAGE,
+        "--",
+    ];
+    args.extend(pathspecs.iter().map(String::as_str));
+    let output = run_git_with_logger(repo, config, &args, logger)
+        .context("Failed to stash selected paths")?;
+    Ok(!output.contains("No local changes to save"))
+}
+
+/// Pops the most recent stash.
+///
+/// A pop that stops on conflicts is reported as [`StashConflict`] naming the
+/// conflicted files; git keeps the stash in that case.
+pub fn stash_pop(repo: &Path, config: &Config, logger: GitLogger) -> anyhow::Result<()> {
+    let args = ["stash", "pop"];
+    let output = run_git_output(repo, config, &args, logger)?;
+    let result = if output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        logger(config, &args, Some(&stdout));
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match unmerged_paths(repo, config, logger) {
+            Ok(paths) if !paths.is_empty() => Err(StashConflict { paths }.into()),
+            _ => Err(git_failure(&args, output.status.code(), &stderr)),
+        }
+    };
+    result.context("Failed to pop stash")
+}
+
+/// One entry of `git stash list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StashEntry {
+    /// Reflog selector such as `stash@{0}`; shifts as entries are dropped.
+    pub selector: String,
+    pub commit: String,
+    /// Reflog subject, e.g. `On master: git-daily: ...`.
+    pub message: String,
+}
+
+impl StashEntry {
+    /// Whether git-daily made this stash: its message is
+    /// [`GIT_DAILY_STASH_MESSAGE`](constants::GIT_DAILY_STASH_MESSAGE) after
+    /// git's `On <branch>: ` prefix.
+    #[must_use]
+    pub fn is_git_daily(&self) -> bool {
+        self.message
+            .split_once(": ")
+            .is_some_and(|(_, message)| message == constants::GIT_DAILY_STASH_MESSAGE)
+    }
+}
+
+/// Lists stash entries, newest first.
+pub fn stash_list(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+) -> anyhow::Result<Vec<StashEntry>> {
+    let output = run_git_with_logger(
+        repo,
+        config,
+        &["stash", "list", "--format=%gd%x00%H%x00%gs"],
+        logger,
+    )
+    .context("Failed to list stashes")?;
+    Ok(parse_stash_list(&output))
+}
+
+fn parse_stash_list(output: &str) -> Vec<StashEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\0');
+            Some(StashEntry {
+                selector: fields.next()?.to_string(),
+                commit: fields.next()?.to_string(),
+                message: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Drops one stash entry (`git stash drop <selector>`).
+pub fn stash_drop(
+    repo: &Path,
+    config: &Config,
+    selector: &str,
+    logger: GitLogger,
+) -> anyhow::Result<()> {
+    run_git_with_logger(
+        repo,
+        config,
+        &["stash", "drop", "--quiet", selector],
+        logger,
+    )
+    .with_context(|| format!("Failed to drop {}", selector))?;
+    Ok(())
+}
+
+/// Returned (inside the `anyhow::Error`) when popping a stash left merge
+/// conflicts in the working tree.
+///
+/// Check for it with `error.downcast_ref::<StashConflict>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StashConflict {
+    pub paths: Vec<String>,
+}
+
+impl std::fmt::Display for StashConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "stash pop conflicted in {}; resolve them, then run `git stash drop`",
+            self.paths.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for StashConflict {}
+
+/// Lists files with unresolved merge conflicts
+/// (`git diff --name-only --diff-filter=U`), in git's path order.
+pub fn unmerged_paths(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+) -> anyhow::Result<Vec<String>> {
+    let output = run_git_with_logger(
+        repo,
+        config,
+        &["diff", "--name-only", "--diff-filter=U"],
+        logger,
+    )
+    .context("Failed to list unmerged paths")?;
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+pub fn checkout(
+    repo: &Path,
+    config: &Config,
+    branch: &str,
+    logger: GitLogger,
+) -> anyhow::Result<()> {
+    validate_branch_name(branch)?;
+    run_checkout(repo, config, &["checkout", branch], branch, logger)
+        .with_context(|| format!("Failed to checkout branch '{}'", branch))?;
+    Ok(())
+}
+
+/// Detaches HEAD at `commit` (`git checkout --detach <commit>`), with git's
+/// "You are in 'detached HEAD' state" advice turned off since detaching is intended.
+pub fn checkout_detached(
+    repo: &Path,
+    config: &Config,
+    commit: &str,
+    logger: GitLogger,
+) -> anyhow::Result<()> {
+    validate_branch_name(commit)?;
+    run_checkout(
+        repo,
+        config,
+        &[
+            "-c",
+            "advice.detachedHead=false",
+            "checkout",
+            "--detach",
+            commit,
+        ],
+        commit,
+        logger,
+    )
+    .with_context(|| format!("Failed to detach HEAD at '{}'", commit))?;
+    Ok(())
+}
+
+/// A submodule whose `.gitmodules` entry sets `branch`, so it follows the tip
+/// of that branch rather than the commit pinned by the superproject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackedSubmodule {
+    pub path: String,
+    pub branch: String,
+}
+
+/// Lists the submodules that `.gitmodules` configures with `branch = ...`,
+/// in file order. A repository without `.gitmodules` has none.
+pub fn tracked_submodules(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+) -> anyhow::Result<Vec<TrackedSubmodule>> {
+    if !repo.join(".gitmodules").is_file() {
+        return Ok(Vec::new());
+    }
+    let args = [
+        "config",
+        "--file",
+        ".gitmodules",
+        "--get-regexp",
+        r"^submodule\..*\.(path|branch)$",
+    ];
+    let output = run_git_output(repo, config, &args, logger)?;
+    // Exit code 1 just means no key matched
+    let result = match output.status.code() {
+        Some(0) | Some(1) => {
+            let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            logger(config, &args, Some(&stdout));
+            Ok(parse_tracked_submodules(&stdout))
+        }
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(git_failure(&args, output.status.code(), &stderr))
+        }
+    };
+    result.context("Failed to read .gitmodules")
+}
+
+/// Pairs up `submodule.<name>.path` and `submodule.<name>.branch` lines from
+/// `git config --get-regexp`, keeping only submodules that have both.
+fn parse_tracked_submodules(output: &str) -> Vec<TrackedSubmodule> {
+    let mut entries: Vec<(&str, Option<&str>, Option<&str>)> = Vec::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(' ') else {
+            continue;
+        };
+        let Some(key) = key.strip_prefix("submodule.") else {
+            continue;
+        };
+        let (name, field) = match (key.strip_suffix(".path"), key.strip_suffix(".branch")) {
+            (Some(name), _) => (name, "path"),
+            (_, Some(name)) => (name, "branch"),
+            _ => continue,
+        };
+        let index = match entries.iter().position(|(n, _, _)| *n == name) {
+            Some(index) => index,
+            None => {
+                entries.push((name, None, None));
+                entries.len() - 1
+            }
+        };
+        if field == "path" {
+            entries[index].1 = Some(value);
+        } else {
+            entries[index].2 = Some(value);
+        }
+    }
+    entries
+        .into_iter()
+        .filter_map(|(_, path, branch)| {
+            Some(TrackedSubmodule {
+                path: path?.to_string(),
+                branch: branch?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Checks out every submodule at the commit the superproject pins
+/// (`git submodule update --init --recursive`), then moves the
+/// [`tracked_submodules`] to the tip of their configured branch with `--remote`.
+///
+/// Returns the submodules that were advanced to their branch tip.
+pub fn update_submodules(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+) -> anyhow::Result<Vec<TrackedSubmodule>> {
+    run_git_with_logger(
+        repo,
+        config,
+        &["submodule", "update", "--init", "--recursive"],
+        logger,
+    )
+    .context("Failed to update submodules")?;
+
+    let tracked = tracked_submodules(repo, config, logger)?;
+    if tracked.is_empty() {
+        return Ok(tracked);
+    }
+    let mut args = vec!["submodule", "update", "--remote", "--"];
+    args.extend(tracked.iter().map(|submodule| submodule.path.as_str()));
+    run_git_with_logger(repo, config, &args, logger)
+        .context("Failed to advance branch-tracking submodules")?;
+    Ok(tracked)
+}
+
+/// Creates local `branch` tracking `remote_ref` (e.g. `origin/main`) and checks it out.
+pub fn checkout_tracking(
+    repo: &Path,
+    config: &Config,
+    branch: &str,
+    remote_ref: &str,
+    logger: GitLogger,
+) -> anyhow::Result<()> {
+    validate_branch_name(branch)?;
+    validate_remote_ref(remote_ref)?;
+    run_checkout(
+        repo,
+        config,
+        &["checkout", "-b", branch, "--track", remote_ref],
+        branch,
+        logger,
+    )
+    .with_context(|| format!("Failed to create branch '{}' from '{}'", branch, remote_ref))?;
+    Ok(())
+}
+
+/// Returned (inside the `anyhow::Error`) when a checkout is refused because
+/// it would overwrite untracked files in the working tree.
+///
+/// Check for it with `error.downcast_ref::<UntrackedConflict>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UntrackedConflict {
+    pub branch: String,
+    pub files: Vec<String>,
+}
+
+impl std::fmt::Display for UntrackedConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "untracked files would be overwritten by checking out '{}': {}; move or remove them",
+            self.branch,
+            self.files.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UntrackedConflict {}
+
+/// Returned (inside the `anyhow::Error`) when a checkout is refused because
+/// it would overwrite uncommitted changes to tracked files, such as changes
+/// left unstashed by `--ignore-dirty-path` or `--ignore-whitespace-dirty`.
+///
+/// Check for it with `error.downcast_ref::<LocalChangesConflict>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalChangesConflict {
+    pub branch: String,
+    pub files: Vec<String>,
+}
+
+impl std::fmt::Display for LocalChangesConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "uncommitted changes would be overwritten by checking out '{}': {}; commit or stash them",
+            self.branch,
+            self.files.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for LocalChangesConflict {}
+
+/// Runs a checkout command with hooks disabled when `config.no_checkout_hooks`
+/// is set, reporting untracked-file conflicts as [`UntrackedConflict`] and
+/// conflicts with uncommitted changes as [`LocalChangesConflict`].
+fn run_checkout(
+    repo: &Path,
+    config: &Config,
+    args: &[&str],
+    branch: &str,
+    logger: GitLogger,
+) -> anyhow::Result<()> {
+    let args: Vec<&str> = if config.no_checkout_hooks {
+        ["-c", "core.hooksPath=/dev/null"]
+            .into_iter()
+            .chain(args.iter().copied())
+            .collect()
+    } else {
+        args.to_vec()
+    };
+    let args = args.as_slice();
+    let output = run_git_output(repo, config, args, logger)?;
+    if output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        logger(config, args, Some(&stdout));
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if let Some(files) = parse_untracked_conflict(&stderr) {
+        return Err(UntrackedConflict {
+            branch: branch.to_string(),
+            files,
+        }
+        .into());
+    }
+    match parse_local_changes_conflict(&stderr) {
+        Some(files) => Err(LocalChangesConflict {
+            branch: branch.to_string(),
+            files,
+        }
+        .into()),
+        None => Err(git_failure(args, output.status.code(), &stderr)),
+    }
+}
+
+/// Extracts the file list from git's "untracked working tree files would be
+/// overwritten by checkout" error, or returns `None` for any other error.
+fn parse_untracked_conflict(stderr: &str) -> Option<Vec<String>> {
+    parse_overwrite_conflict(stderr, "untracked working tree files would be overwritten")
+}
+
+/// Extracts the file list from git's "Your local changes to the following
+/// files would be overwritten by checkout" error, or returns `None` for any
+/// other error.
+fn parse_local_changes_conflict(stderr: &str) -> Option<Vec<String>> {
+    parse_overwrite_conflict(
+        stderr,
+        "local changes to the following files would be overwritten",
+    )
+}
+
+/// Reads the tab-indented file list git prints after the line containing `marker`.
+fn parse_overwrite_conflict(stderr: &str, marker: &str) -> Option<Vec<String>> {
+    let mut lines = stderr.lines();
+    lines.find(|line| line.contains(marker))?;
+    let files = lines
+        .take_while(|line| line.starts_with('\t') || line.starts_with("    "))
+        .map(|line| line.trim().to_string())
+        .collect();
+    Some(files)
+}
+
+/// Stashes all changes including untracked files (`git stash push --include-untracked`).
+/// Returns false if there was nothing to stash.
+pub fn stash_include_untracked(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+) -> anyhow::Result<bool> {
+    let output = run_git_with_logger(
+        repo,
+        config,
+        &[
+            "stash",
+            "push",
+            "--include-untracked",
+            "--message",
+            constants::GIT_DAILY_STASH_MESSAGE,
+        ],
+        logger,
+    )
+    .context("Failed to stash changes including untracked files")?;
+    Ok(!output.contains("No local changes to save"))
+}
+
+pub fn pull(repo: &Path, config: &Config, branch: &str, logger: GitLogger) -> anyhow::Result<()> {
+    pull_ff_only(repo, config, config.remote(), branch, logger)
+}
+
+/// Returned (inside the `anyhow::Error`) when a fast-forward-only pull is
+/// refused because the local branch has diverged from the remote.
+///
+/// Check for it with `error.downcast_ref::<NotFastForward>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotFastForward {
+    pub remote: String,
+    pub branch: String,
+}
+
+impl std::fmt::Display for NotFastForward {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "'{}' has diverged from '{}/{}' and cannot be fast-forwarded; merge or rebase it manually",
+            self.branch, self.remote, self.branch
+        )
+    }
+}
+
+impl std::error::Error for NotFastForward {}
+
+/// Fetches `branch` from `remote` and fast-forwards the current branch to it
+/// in a single `git pull --ff-only` invocation.
+///
+/// A refused fast-forward is reported as [`NotFastForward`] rather than a
+/// generic git failure.
+pub fn pull_ff_only(
+    repo: &Path,
+    config: &Config,
+    remote: &str,
+    branch: &str,
+    logger: GitLogger,
+) -> anyhow::Result<()> {
+    validate_branch_name(remote)?;
+    validate_branch_name(branch)?;
+    let args = ["pull", "--ff-only", remote, branch];
+    let output = run_git_output(repo, config, &args, logger)?;
+    let result = if output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        logger(config, &args, Some(&stdout));
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if is_not_fast_forward(&stderr) {
+            Err(NotFastForward {
+                remote: remote.to_string(),
+                branch: branch.to_string(),
+            }
+            .into())
+        } else {
+            Err(git_failure(&args, output.status.code(), &stderr))
+        }
+    };
+    result.with_context(|| format!("Failed to pull '{}' from {}", branch, remote))
+}
+
+/// Fast-forwards the current branch to `sha`, a commit or ref already in the
+/// local repository, with `git merge --ff-only`, without fetching. Used
+/// instead of [`pull_ff_only`] when the remote was fetched just before, or
+/// when the commit was checked first and nothing else may be merged.
+///
+/// A refused fast-forward is reported as [`NotFastForward`] for `remote` and
+/// `branch`, as with [`pull_ff_only`].
+pub fn fast_forward_to(
+    repo: &Path,
+    config: &Config,
+    remote: &str,
+    branch: &str,
+    sha: &str,
+    logger: GitLogger,
+) -> anyhow::Result<()> {
+    validate_branch_name(sha)?;
+    let args = ["merge", "--ff-only", sha];
+    let output = run_git_output(repo, config, &args, logger)?;
+    let result = if output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        logger(config, &args, Some(&stdout));
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if is_not_fast_forward(&stderr) {
+            Err(NotFastForward {
+                remote: remote.to_string(),
+                branch: branch.to_string(),
+            }
+            .into())
+        } else {
+            Err(git_failure(&args, output.status.code(), &stderr))
+        }
+    };
+    result.with_context(|| format!("Failed to fast-forward '{}' to {}", branch, sha))
+}
+
+/// Recognizes git's refusal to fast-forward across versions
+/// ("Not possible to fast-forward" on current git, "Not a fast-forward" on older ones).
+fn is_not_fast_forward(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    stderr.contains("not possible to fast-forward") || stderr.contains("not a fast-forward")
+}
+
+/// Returned (inside the `anyhow::Error`) when [`verify_commit`] finds a
+/// commit without a valid signature: unsigned, badly signed, or signed with
+/// an unknown or untrusted key.
+///
+/// Check for it with `error.downcast_ref::<UnsignedCommit>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsignedCommit {
+    pub rev: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for UnsignedCommit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' is not validly signed: {}", self.rev, self.reason)
+    }
+}
+
+impl std::error::Error for UnsignedCommit {}
+
+/// Returned (inside the `anyhow::Error`) when [`verify_commit`] cannot check
+/// a signature at all, e.g. because gpg is not installed or SSH signatures
+/// have no `gpg.ssh.allowedSignersFile`. Never treated as a pass.
+///
+/// Check for it with `error.downcast_ref::<SignatureCheckUnavailable>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureCheckUnavailable {
+    pub detail: String,
+}
+
+impl std::fmt::Display for SignatureCheckUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cannot verify commit signatures ({}); set up gpg, or gpg.ssh.allowedSignersFile for SSH signatures",
+            self.detail
+        )
+    }
+}
+
+impl std::error::Error for SignatureCheckUnavailable {}
+
+/// Checks that `rev` carries a valid signature (`git verify-commit <rev>`).
+///
+/// A missing, bad or untrusted signature is reported as [`UnsignedCommit`];
+/// a signature that cannot be checked as [`SignatureCheckUnavailable`].
+pub fn verify_commit(
+    repo: &Path,
+    config: &Config,
+    rev: &str,
+    logger: GitLogger,
+) -> anyhow::Result<()> {
+    validate_branch_name(rev)?;
+    let args = ["verify-commit", rev];
+    let output = run_git_output(repo, config, &args, logger)?;
+    // gpg and ssh-keygen report on stderr, whatever the outcome
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let result = match output.status.code() {
+        Some(0) => {
+            logger(config, &args, Some(&stderr));
+            Ok(())
+        }
+        Some(1) if stderr.is_empty() => Err(UnsignedCommit {
+            rev: rev.to_string(),
+            reason: "no signature".to_string(),
+        }
+        .into()),
+        _ if is_signature_check_unavailable(&stderr) => Err(SignatureCheckUnavailable {
+            detail: first_line(&stderr).to_string(),
+        }
+        .into()),
+        Some(1) => Err(UnsignedCommit {
+            rev: rev.to_string(),
+            reason: signature_failure_reason(&stderr),
+        }
+        .into()),
+        _ => Err(git_failure(&args, output.status.code(), &stderr)),
+    };
+    result.with_context(|| format!("Failed to verify the signature of '{}'", rev))
+}
+
+/// Recognizes git failing to run or configure the signature program,
+/// as opposed to the program rejecting the signature.
+fn is_signature_check_unavailable(stderr: &str) -> bool {
+    stderr.contains("cannot run ") || stderr.contains("needs to be configured")
+}
+
+/// Picks the line of gpg or ssh-keygen output that says why a signature was
+/// rejected, e.g. `gpg: BAD signature from ...`.
+fn signature_failure_reason(stderr: &str) -> String {
+    const MARKERS: &[&str] = &[
+        "BAD signature",
+        "No public key",
+        "No principal matched",
+        "Could not verify",
+    ];
+    stderr
+        .lines()
+        .find(|line| MARKERS.iter().any(|marker| line.contains(marker)))
+        .unwrap_or_else(|| first_line(stderr))
+        .trim()
+        .to_string()
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default()
 }
 
-pub fn stash(repo: &Path, config: &Config, logger: GitLogger) -> anyhow::Result<bool> {
-    let output =
-        run_git_with_logger(repo, config, &["stash"], logger).context("Failed to stash changes")?;
-    Ok(!output.contains("No local changes to save"))
+/// Returned (inside the `anyhow::Error`) when rebasing the current branch onto
+/// its upstream stopped on conflicts. The rebase has already been aborted.
+///
+/// Check for it with `error.downcast_ref::<RebaseConflict>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RebaseConflict {
+    pub remote: String,
+    pub branch: String,
 }
 
-pub fn stash_pop(repo: &Path, config: &Config, logger: GitLogger) -> anyhow::Result<()> {
-    run_git_with_logger(repo, config, &["stash", "pop"], logger).context("Failed to pop stash")?;
-    Ok(())
+impl std::fmt::Display for RebaseConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "rebasing '{}' onto '{}/{}' conflicted; the rebase was aborted and the branch left as it was",
+            self.branch, self.remote, self.branch
+        )
+    }
 }
 
-pub fn checkout(
+impl std::error::Error for RebaseConflict {}
+
+/// Fetches `branch` from `remote` and rebases the current branch onto it with
+/// `git pull --rebase --autostash`, which stashes and restores local changes itself.
+///
+/// If the rebase stops on conflicts it is aborted with `git rebase --abort`,
+/// which also reapplies the autostash, and [`RebaseConflict`] is reported.
+pub fn pull_rebase_autostash(
     repo: &Path,
     config: &Config,
+    remote: &str,
     branch: &str,
     logger: GitLogger,
 ) -> anyhow::Result<()> {
+    validate_branch_name(remote)?;
     validate_branch_name(branch)?;
-    run_git_with_logger(repo, config, &["checkout", branch], logger)
-        .with_context(|| format!("Failed to checkout branch '{}'", branch))?;
-    Ok(())
+    let args = ["pull", "--rebase", "--autostash", remote, branch];
+    let output = run_git_output(repo, config, &args, logger)?;
+    let result = if output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        logger(config, &args, Some(&stdout));
+        Ok(())
+    } else if rebase_in_progress(repo, config, logger)? {
+        run_git_with_logger(repo, config, &["rebase", "--abort"], logger)
+            .context("Failed to abort the conflicted rebase")?;
+        Err(RebaseConflict {
+            remote: remote.to_string(),
+            branch: branch.to_string(),
+        }
+        .into())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(git_failure(&args, output.status.code(), &stderr))
+    };
+    result.with_context(|| format!("Failed to rebase '{}' onto {}", branch, remote))
 }
 
-pub fn pull(repo: &Path, config: &Config, branch: &str, logger: GitLogger) -> anyhow::Result<()> {
-    validate_branch_name(branch)?;
-    run_git_with_logger(
+/// Returns true while a rebase is stopped, waiting for `--continue` or `--abort`.
+fn rebase_in_progress(repo: &Path, config: &Config, logger: GitLogger) -> anyhow::Result<bool> {
+    let git_dir = git_dir(repo, config, logger)?;
+    Ok(git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists())
+}
+
+/// Lists the local branches (`refs/heads/*`) by short name.
+pub fn local_branches(
+    repo: &Path,
+    config: &Config,
+    logger: GitLogger,
+) -> anyhow::Result<Vec<String>> {
+    let output = run_git_with_logger(
         repo,
         config,
-        &["pull", "--ff-only", "origin", branch],
+        &["for-each-ref", "--format=%(refname:short)", "refs/heads/"],
         logger,
     )
-    .with_context(|| format!("Failed to pull '{}' from origin", branch))?;
-    Ok(())
+    .context("Failed to list local branches")?;
+    Ok(output
+        .lines()
+        .filter(|branch| !branch.is_empty())
+        .map(str::to_string)
+        .collect())
 }
 
 /// Lists local branches with their upstream tracking refs.
@@ -324,6 +2071,256 @@ pub fn merge_tree(
     })
 }
 
+/// The error for a git command that exited unsuccessfully with `stderr`.
+///
+/// A full disk is reported as [`DiskFull`] so it stands out, with git's own
+/// message (naming the file it could not write) kept alongside.
+fn git_failure(args: &[&str], exit_code: Option<i32>, stderr: &str) -> anyhow::Error {
+    if is_disk_full(stderr) {
+        return DiskFull {
+            command: args.join(" "),
+            exit_code,
+            stderr: stderr.to_string(),
+        }
+        .into();
+    }
+    GitCommandFailed {
+        command: args.join(" "),
+        exit_code,
+        stderr: stderr.to_string(),
+    }
+    .into()
+}
+
+/// Returned (inside the `anyhow::Error`) when a git command exits non-zero
+/// and no more specific error applies.
+///
+/// Check for it with `error.downcast_ref::<GitCommandFailed>()`, or use
+/// [`exit_code`] to read the exit code through any added context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitCommandFailed {
+    pub command: String,
+    /// `None` when git was killed by a signal.
+    pub exit_code: Option<i32>,
+    pub stderr: String,
+}
+
+impl std::fmt::Display for GitCommandFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "git {} failed: {}", self.command, self.stderr)
+    }
+}
+
+impl std::error::Error for GitCommandFailed {}
+
+/// Returns the exit code of the git command behind `error`, if a git command
+/// failed anywhere in its chain (128 means git hit a fatal error).
+pub fn exit_code(error: &anyhow::Error) -> Option<i32> {
+    error.chain().find_map(|cause| {
+        if let Some(failed) = cause.downcast_ref::<GitCommandFailed>() {
+            failed.exit_code
+        } else {
+            cause
+                .downcast_ref::<DiskFull>()
+                .and_then(|disk_full| disk_full.exit_code)
+        }
+    })
+}
+
+/// Recognizes running out of disk space (`ENOSPC`) or quota (`EDQUOT`) in
+/// git's stderr, as worded by Unix and Windows.
+pub fn is_disk_full(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    [
+        "no space left on device",
+        "disk quota exceeded",
+        "not enough space on the disk",
+        "enospc",
+    ]
+    .iter()
+    .any(|pattern| stderr.contains(pattern))
+}
+
+/// Returned (inside the `anyhow::Error`) when a git command failed because
+/// the disk is full. Every other repository on the same disk will fail the
+/// same way, and a fetch cut short may have left partial objects behind.
+///
+/// Check for it with `error.downcast_ref::<DiskFull>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskFull {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub stderr: String,
+}
+
+impl std::fmt::Display for DiskFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "disk full: no space left on device during 'git {}' ({}); free up space, then \
+             run 'git fsck' in this repository and rerun",
+            self.command,
+            self.stderr.trim()
+        )
+    }
+}
+
+impl std::error::Error for DiskFull {}
+
+/// Returned (inside the `anyhow::Error`) when [`Config::read_only`] refuses
+/// to run a git command that could change the repository. Nothing was run.
+///
+/// Check for it with `error.downcast_ref::<ReadOnlyViolation>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOnlyViolation {
+    pub command: String,
+}
+
+impl std::fmt::Display for ReadOnlyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "read-only mode: refused to run 'git {}', which can change the repository",
+            self.command
+        )
+    }
+}
+
+impl std::error::Error for ReadOnlyViolation {}
+
+/// Subcommands that only read the repository whatever their arguments. In
+/// read-only mode anything else is refused unless [`is_read_form`] knows its
+/// reading form, so a command nobody thought of fails closed.
+const READ_ONLY_SUBCOMMANDS: &[&str] = &[
+    "cat-file",
+    "describe",
+    "for-each-ref",
+    "ls-files",
+    "ls-remote",
+    "merge-base",
+    "merge-tree",
+    "rev-list",
+    "rev-parse",
+    "show-ref",
+    "status",
+    "verify-commit",
+    "version",
+];
+
+/// Returns true if git run with `args` could change the repository: every
+/// subcommand outside [`READ_ONLY_SUBCOMMANDS`] except the reading forms of
+/// the commands that also write. Global options such as `-c key=value` before
+/// the subcommand are skipped; options alone (`--version`) only read.
+fn is_mutating_command(args: &[&str]) -> bool {
+    let mut args = args.iter().copied();
+    let subcommand = loop {
+        match args.next() {
+            Some("-c" | "-C") => {
+                args.next();
+            }
+            Some(arg) if arg.starts_with('-') => {}
+            Some(arg) => break arg,
+            None => return false,
+        }
+    };
+    let rest: Vec<&str> = args.collect();
+    !(READ_ONLY_SUBCOMMANDS.contains(&subcommand) || is_read_form(subcommand, &rest))
+}
+
+/// Recognizes the reading forms of subcommands that can also write, e.g.
+/// `git branch --merged` but not `git branch new-name`.
+fn is_read_form(subcommand: &str, rest: &[&str]) -> bool {
+    let has_any = |flags: &[&str]| rest.iter().any(|arg| flags.contains(arg));
+    let positional = rest.iter().filter(|arg| !arg.starts_with('-')).count();
+    match subcommand {
+        "stash" => matches!(rest.first(), Some(&"list" | &"show")),
+        // `--output=<file>` and `--output-directory` write files
+        "diff" | "log" | "show" => !rest.iter().any(|arg| arg.starts_with("--output")),
+        "branch" => {
+            let writes = has_any(&[
+                "-d",
+                "-D",
+                "--delete",
+                "-m",
+                "-M",
+                "--move",
+                "-c",
+                "-C",
+                "--copy",
+                "-f",
+                "--force",
+                "-u",
+                "--unset-upstream",
+                "--edit-description",
+            ]) || rest.iter().any(|arg| arg.starts_with("--set-upstream-to"));
+            // Without a listing option, a name creates a branch
+            let lists = positional == 0
+                || has_any(&[
+                    "-l",
+                    "--list",
+                    "--merged",
+                    "--no-merged",
+                    "--contains",
+                    "--no-contains",
+                    "--points-at",
+                ]);
+            !writes && lists
+        }
+        "remote" => matches!(
+            rest.first(),
+            None | Some(&("-v" | "--verbose" | "get-url" | "show"))
+        ),
+        "tag" => {
+            (rest.is_empty() || has_any(&["-l", "--list"]))
+                && !has_any(&["-d", "--delete", "-f", "--force"])
+        }
+        "config" => {
+            has_any(&["--get", "--get-all", "--get-regexp", "--list", "-l"])
+                && !rest.iter().any(|arg| {
+                    arg.starts_with("--unset")
+                        || matches!(*arg, "--add" | "--replace-all" | "--rename-section")
+                        || matches!(*arg, "--remove-section" | "-e" | "--edit")
+                })
+        }
+        // Reading HEAD names one ref; a second argument points it elsewhere
+        "symbolic-ref" => positional <= 1 && !has_any(&["-d", "--delete"]),
+        "worktree" => rest.first() == Some(&"list"),
+        "reflog" => matches!(rest.first(), None | Some(&"show")),
+        _ => false,
+    }
+}
+
+/// Fails with [`ReadOnlyViolation`] when `config.read_only` is set and
+/// `args` could change the repository.
+fn check_read_only(config: &Config, args: &[&str]) -> anyhow::Result<()> {
+    if config.read_only && is_mutating_command(args) {
+        return Err(ReadOnlyViolation {
+            command: args.join(" "),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Builds a command running `config.git_binary()` with piped output and
+/// `config.env` applied. Hooks run by git inherit the same environment.
+///
+/// In read-only mode, `GIT_OPTIONAL_LOCKS=0` also stops `git status` from
+/// refreshing the index.
+fn git_command(repo: &Path, config: &Config, args: &[&str]) -> Command {
+    let mut command = Command::new(config.git_binary());
+    command
+        .current_dir(repo)
+        .args(args)
+        .envs(&config.env)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if config.read_only {
+        command.env("GIT_OPTIONAL_LOCKS", "0");
+    }
+    command
+}
+
 /// Executes a git command and returns the raw output without interpreting exit status.
 fn run_git_output(
     repo: &Path,
@@ -331,15 +2328,23 @@ fn run_git_output(
     args: &[&str],
     logger: GitLogger,
 ) -> anyhow::Result<std::process::Output> {
+    run_git_output_with_limit(repo, config, args, logger, constants::git_output_limit())
+}
+
+fn run_git_output_with_limit(
+    repo: &Path,
+    config: &Config,
+    args: &[&str],
+    logger: GitLogger,
+    output_limit: usize,
+) -> anyhow::Result<std::process::Output> {
+    check_read_only(config, args)?;
     logger(config, args, None);
 
-    let mut child = Command::new("git")
-        .current_dir(repo)
-        .args(args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
+    let child = git_command(repo, config, args)
         .spawn()
         .context("Failed to spawn git command")?;
+    let mut child = CapturedChild::new(child, output_limit);
 
     let result = wait_with_timeout(&mut child, constants::git_timeout());
 
@@ -347,7 +2352,7 @@ fn run_git_output(
         Ok(output) => Ok(output),
         Err(e) => {
             // Kill the process if it's still running after timeout
-            let _ = child.kill();
+            let _ = child.child.kill();
             Err(e)
         }
     }
@@ -369,8 +2374,55 @@ fn validate_remote_ref(remote_ref: &str) -> anyhow::Result<()> {
     validate_branch_name(remote_ref)
 }
 
+/// Validates a pathspec. Pathspecs always follow `--`, so this guards against
+/// values that look like options being passed through by mistake.
+fn validate_pathspec(pathspec: &str) -> anyhow::Result<()> {
+    if pathspec.is_empty() {
+        anyhow::bail!("Pathspec cannot be empty");
+    }
+    if pathspec.contains('\0') {
+        anyhow::bail!("Pathspec cannot contain NUL: {:?}", pathspec);
+    }
+    if pathspec.starts_with('-') {
+        anyhow::bail!("Pathspec cannot start with '-': {:?}", pathspec);
+    }
+    Ok(())
+}
+
+/// Fetch options that name a program for git to run on the remote side.
+const UNSAFE_FETCH_OPTIONS: &[&str] = &["--upload-pack"];
+
+/// Validates arguments passed through to `git fetch` by `--fetch-arg`.
+///
+/// Options that make git run another program (`--upload-pack=...`, or any
+/// abbreviation git would accept for it) are rejected unless `allow_unsafe`
+/// is set, so a copied config or script cannot smuggle a command in.
+pub fn validate_extra_fetch_args(args: &[String], allow_unsafe: bool) -> anyhow::Result<()> {
+    for arg in args {
+        if arg.contains('\0') {
+            anyhow::bail!("Fetch argument cannot contain NUL: {:?}", arg);
+        }
+        if allow_unsafe {
+            continue;
+        }
+        let name = arg.split_once('=').map_or(arg.as_str(), |(name, _)| name);
+        if name.len() > 2
+            && let Some(option) = UNSAFE_FETCH_OPTIONS
+                .iter()
+                .find(|option| option.starts_with(name))
+        {
+            anyhow::bail!(
+                "Fetch argument {:?} would set {}, which runs a program; pass --allow-unsafe-fetch-args to use it",
+                arg,
+                option
+            );
+        }
+    }
+    Ok(())
+}
+
 /// Validates branch name to prevent command and argument injection.
-fn validate_branch_name(branch: &str) -> anyhow::Result<()> {
+pub fn validate_branch_name(branch: &str) -> anyhow::Result<()> {
     if branch.is_empty() {
         anyhow::bail!("Branch name cannot be empty");
     }
@@ -396,6 +2448,377 @@ mod tests {
     #[cfg(unix)]
     use std::os::unix::process::ExitStatusExt;
 
+    #[test]
+    fn test_is_mutating_command_skips_global_options() {
+        assert!(is_mutating_command(&["checkout", "master"]));
+        assert!(is_mutating_command(&[
+            "-c",
+            "core.hooksPath=/dev/null",
+            "checkout",
+            "master"
+        ]));
+        assert!(is_mutating_command(&["fetch", "--prune", "origin"]));
+        assert!(is_mutating_command(&["stash", "push", "--message", "wip"]));
+        assert!(is_mutating_command(&["branch", "-d", "old"]));
+        assert!(is_mutating_command(&["remote", "prune", "origin"]));
+        assert!(!is_mutating_command(&["stash", "list"]));
+        assert!(!is_mutating_command(&["branch", "--merged", "master"]));
+        assert!(!is_mutating_command(&["remote"]));
+        assert!(!is_mutating_command(&["status", "--porcelain"]));
+        assert!(!is_mutating_command(&[
+            "config",
+            "--get",
+            "remote.origin.url"
+        ]));
+        assert!(!is_mutating_command(&["--version"]));
+    }
+
+    #[test]
+    fn test_is_mutating_command_refuses_ref_and_index_writers() {
+        for args in [
+            &["symbolic-ref", "HEAD", "refs/heads/other"][..],
+            &["symbolic-ref", "--delete", "refs/remotes/origin/HEAD"],
+            &["update-index", "--refresh"],
+            &["notes", "add", "-m", "note"],
+            &["replace", "abc123", "def456"],
+            &["reflog", "expire", "--expire=now", "--all"],
+            &["reflog", "delete", "HEAD@{1}"],
+            &["sparse-checkout", "set", "src"],
+            &["bisect", "start"],
+            &["init"],
+            &["clone", "https://example.com/repo.git"],
+            &["maintenance", "run"],
+            &["pack-refs", "--all"],
+            &["branch", "new-feature"],
+            &["tag", "v1.0"],
+            &["config", "--unset", "--get", "user.name"],
+            &["worktree", "prune"],
+            &["diff", "--output=x"],
+            &["log", "--output=x"],
+            &["log", "--output-directory", "patches"],
+            &["show", "--output=x", "HEAD"],
+        ] {
+            assert!(is_mutating_command(args), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_is_mutating_command_allows_read_forms_the_crate_runs() {
+        for args in [
+            &[
+                "symbolic-ref",
+                "--quiet",
+                "--short",
+                "refs/remotes/origin/HEAD",
+            ][..],
+            &["reflog", "show", "HEAD"],
+            &["worktree", "list"],
+            &["branch", "--list", "feature/*"],
+            &["tag", "--list"],
+            &["remote", "get-url", "origin"],
+            &["config", "--file", ".gitmodules", "--get-regexp", "path"],
+            &["for-each-ref", "--format=%(refname:short)", "refs/heads/"],
+            &["merge-tree", "base", "ours", "theirs"],
+            &["log", "-1", "--format=%ct", "HEAD"],
+            &["diff", "--name-only", "--diff-filter=U"],
+            &["show", "HEAD"],
+        ] {
+            assert!(!is_mutating_command(args), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_parse_fetch_progress_reads_received_bytes() {
+        let progress =
+            parse_fetch_progress("Receiving objects:  45% (123/273), 1.20 MiB | 2.00 MiB/s")
+                .unwrap();
+        assert_eq!(progress.received_bytes, 1_258_291);
+        assert_eq!(progress.total_bytes, None);
+
+        let progress =
+            parse_fetch_progress("Receiving objects:  12% (1/8), 512 bytes | 512.00 KiB/s")
+                .unwrap();
+        assert_eq!(progress.received_bytes, 512);
+    }
+
+    #[test]
+    fn test_parse_fetch_progress_knows_total_when_done() {
+        let progress =
+            parse_fetch_progress("Receiving objects: 100% (273/273), 2.50 KiB | 1.25 MiB/s, done.")
+                .unwrap();
+        assert_eq!(progress.received_bytes, 2560);
+        assert_eq!(progress.total_bytes, Some(2560));
+    }
+
+    #[test]
+    fn test_parse_fetch_progress_ignores_lines_without_bytes() {
+        assert_eq!(
+            parse_fetch_progress("Receiving objects: 100% (3/3), done."),
+            None
+        );
+        assert_eq!(
+            parse_fetch_progress("remote: Counting objects: 100% (5/5), done."),
+            None
+        );
+        assert_eq!(parse_fetch_progress("Resolving deltas:  50% (1/2)"), None);
+        assert_eq!(parse_fetch_progress("From /tmp/remote"), None);
+    }
+
+    #[test]
+    fn test_is_progress_line_filters_transient_noise() {
+        assert!(is_progress_line("remote: Compressing objects:  50% (2/4)"));
+        assert!(is_progress_line("Resolving deltas: 100% (1/1), done."));
+        assert!(!is_progress_line(
+            "fatal: 'origin' does not appear to be a git repository"
+        ));
+    }
+
+    #[test]
+    fn test_for_each_stderr_line_splits_on_carriage_returns() {
+        let stream =
+            "Receiving objects:  50% (1/2)\rReceiving objects: 100% (2/2), done.\nFrom x\n";
+        let mut lines = Vec::new();
+        for_each_stderr_line(stream.as_bytes(), |line| {
+            lines.push(line.to_string());
+            true
+        });
+        assert_eq!(
+            lines,
+            vec![
+                "Receiving objects:  50% (1/2)",
+                "Receiving objects: 100% (2/2), done.",
+                "From x",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_not_fast_forward_matches_git_messages() {
+        assert!(is_not_fast_forward(
+            "fatal: Not possible to fast-forward, aborting."
+        ));
+        assert!(is_not_fast_forward("fatal: Not a fast-forward"));
+        assert!(!is_not_fast_forward("fatal: couldn't find remote ref nope"));
+    }
+
+    #[test]
+    fn test_remote_host_parses_url_and_scp_syntax() {
+        let cases = [
+            ("https://github.com/org/repo.git", Some("github.com")),
+            (
+                "https://token@GitLab.Example.com:8443/g/r",
+                Some("gitlab.example.com"),
+            ),
+            (
+                "ssh://git@git.example.org:2222/r.git",
+                Some("git.example.org"),
+            ),
+            ("ssh://[::1]:22/r.git", Some("::1")),
+            ("git@github.com:org/repo.git", Some("github.com")),
+            ("mirror:repo.git", Some("mirror")),
+            ("file:///srv/git/repo.git", None),
+            ("/srv/git/repo.git", None),
+            ("../repo-remote", None),
+            ("C:\\repos\\repo", None),
+        ];
+        for (url, host) in cases {
+            assert_eq!(remote_host(url).as_deref(), host, "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_parse_repo_status_reads_branch_commit_and_dirtiness() {
+        let status = parse_repo_status(
+            "# branch.oid 1f3c2a9d0e5b7c8a6f4e2d1c0b9a8f7e6d5c4b3a\n\
+             # branch.head feature/login\n\
+             # branch.upstream origin/feature/login\n\
+             # branch.ab +1 -2\n\
+             1 .M N... 100644 100644 100644 3b18e51 3b18e51 src/main.rs\n\
+             2 R. N... 100644 100644 100644 e69de29 e69de29 R100 new.rs\told.rs\n\
+             ? notes.txt\n",
+        )
+        .unwrap();
+
+        assert_eq!(status.branch, "feature/login");
+        assert_eq!(
+            status.commit.as_deref(),
+            Some("1f3c2a9d0e5b7c8a6f4e2d1c0b9a8f7e6d5c4b3a")
+        );
+        assert_eq!(status.dirty_files, 3);
+    }
+
+    #[test]
+    fn test_parse_repo_status_maps_detached_and_unborn_heads() {
+        let detached =
+            parse_repo_status("# branch.oid 1f3c2a9\n# branch.head (detached)\n").unwrap();
+        assert_eq!(detached.branch, crate::constants::DETACHED_HEAD);
+        assert_eq!(detached.dirty_files, 0);
+
+        let unborn = parse_repo_status("# branch.oid (initial)\n# branch.head master\n").unwrap();
+        assert_eq!(unborn.branch, "master");
+        assert_eq!(unborn.commit, None);
+
+        assert!(parse_repo_status("? notes.txt\n").is_err());
+    }
+
+    #[test]
+    fn test_validate_extra_fetch_args_rejects_upload_pack() {
+        let args = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        assert!(validate_extra_fetch_args(&args(&["--no-tags", "--force", "-v"]), false).is_ok());
+        assert!(
+            validate_extra_fetch_args(&args(&["--refmap=+refs/heads/*:refs/x/*"]), false).is_ok()
+        );
+        for unsafe_arg in ["--upload-pack=touch /tmp/x", "--upload-pack", "--upload=sh"] {
+            let err = validate_extra_fetch_args(&args(&[unsafe_arg]), false).unwrap_err();
+            assert!(
+                err.to_string().contains("--allow-unsafe-fetch-args"),
+                "{}",
+                err
+            );
+        }
+        assert!(validate_extra_fetch_args(&args(&["--upload-pack=git-upload-pack"]), true).is_ok());
+        assert!(validate_extra_fetch_args(&args(&["--tags\0"]), true).is_err());
+    }
+
+    #[test]
+    fn test_signature_failures_are_told_apart_from_missing_tools() {
+        assert!(is_signature_check_unavailable(
+            "fatal: cannot run gpg: No such file or directory"
+        ));
+        assert!(is_signature_check_unavailable(
+            "error: gpg.ssh.allowedSignersFile needs to be configured and exist for ssh signature verification"
+        ));
+        assert!(!is_signature_check_unavailable(
+            "gpg: Signature made Tue 01 Oct 2024\ngpg: BAD signature from \"Mallory\""
+        ));
+
+        assert_eq!(
+            signature_failure_reason(
+                "gpg: Signature made Tue 01 Oct 2024\ngpg:                using RSA key 1234\ngpg: Can't check signature: No public key"
+            ),
+            "gpg: Can't check signature: No public key"
+        );
+        assert_eq!(
+            signature_failure_reason("something else\nmore"),
+            "something else"
+        );
+    }
+
+    #[test]
+    fn test_is_disk_full_matches_enospc_messages() {
+        assert!(is_disk_full(
+            "error: unable to write file .git/objects/pack/tmp_pack_a1b2c3: No space left on device\n\
+             fatal: index-pack failed"
+        ));
+        assert!(is_disk_full(
+            "fatal: write error: Disk quota exceeded\nfatal: fetch-pack: invalid index-pack output"
+        ));
+        assert!(is_disk_full(
+            "error: unable to create temporary file: There is not enough space on the disk."
+        ));
+        assert!(!is_disk_full("fatal: couldn't find remote ref nope"));
+    }
+
+    #[test]
+    fn test_git_failure_reports_disk_full_first() {
+        let error = git_failure(
+            &["fetch", "--prune", "origin"],
+            Some(128),
+            "fatal: write error: No space left on device",
+        );
+        let disk_full = error.downcast_ref::<DiskFull>().unwrap();
+        assert_eq!(disk_full.command, "fetch --prune origin");
+        assert!(error.to_string().starts_with("disk full:"), "{}", error);
+        assert!(
+            error
+                .to_string()
+                .contains("(fatal: write error: No space left on device)"),
+            "{}",
+            error
+        );
+        assert_eq!(exit_code(&error), Some(128));
+
+        let error = git_failure(&["fetch"], Some(1), "fatal: unable to access remote");
+        assert!(error.downcast_ref::<DiskFull>().is_none());
+        assert_eq!(
+            error.to_string(),
+            "git fetch failed: fatal: unable to access remote"
+        );
+        assert_eq!(exit_code(&error.context("Failed to fetch")), Some(1));
+    }
+
+    #[test]
+    fn test_parse_stash_list_recognizes_git_daily_stashes() {
+        let output = format!(
+            "stash@{{0}}\0aaa111\0On main: {}\nstash@{{1}}\0bbb222\0WIP on main: 1234567 Fix",
+            constants::GIT_DAILY_STASH_MESSAGE
+        );
+        let entries = parse_stash_list(&output);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].selector, "stash@{0}");
+        assert_eq!(entries[0].commit, "aaa111");
+        assert!(entries[0].is_git_daily());
+        assert!(!entries[1].is_git_daily());
+    }
+
+    #[test]
+    fn test_parse_untracked_conflict_lists_files() {
+        let stderr = "error: The following untracked working tree files would be overwritten by checkout:\n\
+                      \tbuild.log\n\
+                      \tdocs/notes.md\n\
+                      Please move or remove them before you switch branches.\n\
+                      Aborting\n";
+        assert_eq!(
+            parse_untracked_conflict(stderr),
+            Some(vec!["build.log".to_string(), "docs/notes.md".to_string()])
+        );
+        assert_eq!(
+            parse_untracked_conflict(
+                "error: pathspec 'nope' did not match any file(s) known to git"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_local_changes_conflict_lists_files() {
+        let stderr = "error: Your local changes to the following files would be overwritten by checkout:\n\
+                      \tconfig.toml\n\
+                      Please commit your changes or stash them before you switch branches.\n\
+                      Aborting\n";
+        assert_eq!(
+            parse_local_changes_conflict(stderr),
+            Some(vec!["config.toml".to_string()])
+        );
+        assert_eq!(parse_untracked_conflict(stderr), None);
+    }
+
+    #[test]
+    fn test_parse_ahead_behind_reads_both_columns() {
+        assert_eq!(parse_ahead_behind("2\t5").unwrap(), (2, 5));
+        assert_eq!(parse_ahead_behind("  0   12\n").unwrap(), (0, 12));
+    }
+
+    #[test]
+    fn test_parse_ahead_behind_rejects_malformed_output() {
+        let err = parse_ahead_behind("7").unwrap_err();
+        assert!(err.to_string().contains("Unexpected ahead/behind output"));
+        assert!(parse_ahead_behind("").is_err());
+        assert!(parse_ahead_behind("1\t2\t3").is_err());
+        assert!(parse_ahead_behind("a\tb").is_err());
+    }
+
+    #[test]
+    fn test_validate_pathspec_rejects_option_like_values() {
+        assert!(validate_pathspec("src/lib.rs").is_ok());
+        assert!(validate_pathspec(":(exclude)generated.txt").is_ok());
+        assert!(validate_pathspec("-p").is_err());
+        assert!(validate_pathspec("--all").is_err());
+        assert!(validate_pathspec("").is_err());
+    }
+
     #[test]
     fn test_validate_branch_name_accepts_valid_names() {
         assert!(validate_branch_name("main").is_ok());
@@ -537,6 +2960,7 @@ mod tests {
         colored::control::set_override(false);
         let config = Config {
             verbosity: crate::config::Verbosity::Normal,
+            ..Default::default()
         };
         verbose_logger(&config, &["status"], None);
         verbose_logger(&config, &["status"], Some("output"));
@@ -548,9 +2972,20 @@ mod tests {
         stderr: Vec<u8>,
     }
 
+    fn success_status() -> std::process::ExitStatus {
+        #[cfg(unix)]
+        {
+            std::process::ExitStatus::from_raw(0)
+        }
+        #[cfg(not(unix))]
+        {
+            std::process::ExitStatus::default()
+        }
+    }
+
     impl WaitableChild for FakeChild {
         fn try_wait(&mut self) -> io::Result<Option<std::process::ExitStatus>> {
-            self.try_wait.take().unwrap_or_else(|| Ok(None))
+            self.try_wait.take().unwrap_or(Ok(None))
         }
 
         fn read_stdout(&mut self) -> anyhow::Result<Vec<u8>> {
@@ -577,7 +3012,7 @@ mod tests {
     #[test]
     fn test_wait_with_timeout_propagates_try_wait_error() {
         let mut child = FakeChild {
-            try_wait: Some(Err(io::Error::new(io::ErrorKind::Other, "boom"))),
+            try_wait: Some(Err(io::Error::other("boom"))),
             stdout: Vec::new(),
             stderr: Vec::new(),
         };
@@ -587,20 +3022,133 @@ mod tests {
     }
 
     #[test]
-    fn test_wait_with_timeout_reads_output() {
-        let status = {
-            #[cfg(unix)]
-            {
-                std::process::ExitStatus::from_raw(0)
-            }
-            #[cfg(not(unix))]
-            {
-                std::process::ExitStatus::default()
-            }
+    fn test_fetch_summary_is_captured_from_stderr() {
+        let mut child = FakeChild {
+            try_wait: Some(Ok(Some(success_status()))),
+            stdout: Vec::new(),
+            stderr: b"From /srv/git/app\n   abc1234..def5678  main       -> origin/main\n * [new branch]      feature    -> origin/feature\n".to_vec(),
+        };
+        let output = wait_with_timeout_inner(&mut child, std::time::Duration::from_secs(1))
+            .expect("expected output");
+
+        assert_eq!(
+            fetch_summary_from_output(&output),
+            vec![
+                "From /srv/git/app",
+                "abc1234..def5678  main       -> origin/main",
+                "* [new branch]      feature    -> origin/feature",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fetch_summary_is_captured_from_stdout_and_skips_other_lines() {
+        let mut child = FakeChild {
+            try_wait: Some(Ok(Some(success_status()))),
+            stdout: b"From /srv/git/app\n - [deleted]         (none)     -> origin/old\n".to_vec(),
+            stderr: b"remote: Counting objects: 100% (3/3), done.\n".to_vec(),
         };
+        let output = wait_with_timeout_inner(&mut child, std::time::Duration::from_secs(1))
+            .expect("expected output");
 
+        assert_eq!(
+            fetch_summary_from_output(&output),
+            vec![
+                "From /srv/git/app",
+                "- [deleted]         (none)     -> origin/old"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_tracked_submodules_keeps_only_branch_tracking_entries() {
+        let output = "submodule.lib.path vendor/lib\n\
+                      submodule.lib.branch main\n\
+                      submodule.pinned.path pinned\n\
+                      submodule.dotted.name.branch .\n\
+                      submodule.dotted.name.path dotted";
+
+        assert_eq!(
+            parse_tracked_submodules(output),
+            vec![
+                TrackedSubmodule {
+                    path: "vendor/lib".to_string(),
+                    branch: "main".to_string(),
+                },
+                TrackedSubmodule {
+                    path: "dotted".to_string(),
+                    branch: ".".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_capped_truncates_and_flags_oversized_output() {
+        use std::io::Read;
+
+        let output = read_capped(io::repeat(b'x').take(10_000), 100).unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.starts_with(&"x".repeat(100)));
+        assert!(text.ends_with("\n[output truncated: 9900 bytes past the 100 byte limit dropped]"));
+        assert_eq!(read_capped(&b"short"[..], 100).unwrap(), b"short");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_git_output_caps_output_larger_than_a_pipe_buffer() {
+        let temp = tempfile::tempdir().unwrap();
+        let args = ["-c", "alias.flood=!head -c 1000000 /dev/zero", "flood"];
+
+        let output =
+            run_git_output_with_limit(temp.path(), &Config::default(), &args, no_op_logger, 1000)
+                .expect("expected output");
+
+        assert!(output.status.success());
+        assert!(output.stdout[..1000].iter().all(|&byte| byte == 0));
+        assert!(
+            String::from_utf8_lossy(&output.stdout[1000..])
+                .contains("[output truncated: 999000 bytes past the 1000 byte limit dropped]")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_git_streaming_stderr_caps_unconsumed_stderr() {
+        let temp = tempfile::tempdir().unwrap();
+        // A remote or hook that never stops talking, then fails
+        let args = [
+            "-c",
+            "alias.spam=!yes 'remote: spam' | head -n 100000 >&2; echo 'fatal: gave up' >&2; exit 1",
+            "spam",
+        ];
+        let consume_progress = |line: &str| line.starts_with("Receiving objects");
+
+        let error = run_git_streaming_stderr_with_limit(
+            temp.path(),
+            &Config::default(),
+            &args,
+            no_op_logger,
+            &consume_progress,
+            1000,
+        )
+        .unwrap_err();
+
+        let message = format!("{:#}", error);
+        assert!(message.len() < 2000, "{} bytes", message.len());
+        assert!(message.contains("remote: spam"), "{}", message);
+        assert!(
+            message.contains("bytes past the 1000 byte limit dropped]"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_wait_with_timeout_reads_output() {
         let mut child = FakeChild {
-            try_wait: Some(Ok(Some(status))),
+            try_wait: Some(Ok(Some(success_status()))),
             stdout: b"ok\n".to_vec(),
             stderr: b"warn\n".to_vec(),
         };
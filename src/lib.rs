@@ -21,6 +21,7 @@
 //! match result.outcome {
 //!     repo::UpdateOutcome::Success(s) => println!("Updated from {}", s.original_head.display()),
 //!     repo::UpdateOutcome::Failed(f) => eprintln!("Failed: {}", f),
+//!     repo::UpdateOutcome::Skipped(s) => println!("Skipped: {}", s.reason),
 //! }
 //! ```
 //!
@@ -41,7 +42,11 @@
 pub mod cleanup;
 pub mod config;
 pub mod constants;
+pub mod env;
 pub mod git;
+pub mod manifest;
 pub mod output;
 pub mod prompt;
 pub mod repo;
+pub mod report;
+pub mod ssh;
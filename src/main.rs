@@ -1,11 +1,27 @@
 //! CLI entry point for git-daily-v2.
 
-use clap::Parser;
-use git_daily_rust::config::{Config, Verbosity};
-use git_daily_rust::constants::{DEFAULT_REPO_NAME, RAYON_THREAD_COUNT};
-use git_daily_rust::repo::UpdateOutcome;
-use git_daily_rust::{output, repo};
-use std::path::Path;
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
+use git_daily_rust::config::{
+    BranchPreference, Config, OutputFormat, ProgressMode, UntrackedConflictPolicy, UpdateMode,
+    Verbosity, generate_run_id,
+};
+use git_daily_rust::constants::{self, DEFAULT_REPO_NAME};
+use git_daily_rust::env::EnvOverrides;
+use git_daily_rust::manifest::Manifest;
+use git_daily_rust::output::CompositeCallbacks;
+use git_daily_rust::repo::{
+    UpdateCallbacks, UpdateOutcome, UpdateStep, WorkspaceOutcome, WorkspaceStats,
+};
+use git_daily_rust::report::{PlanReport, RunReport, RunState};
+use git_daily_rust::{git, output, repo, ssh};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "git-daily-v2")]
@@ -14,7 +30,7 @@ use std::path::Path;
 )]
 #[command(version)]
 #[command(
-    after_help = "EXIT CODES:\n  0  All repositories updated successfully\n  1  Some repositories failed\n  2  All repositories failed"
+    after_help = "EXIT CODES:\n  0  All repositories updated successfully\n  1  Some repositories failed\n  2  All repositories failed\n  3  Repositories were found but none were left to update\n\nWith --fail-threshold, failures up to that share of repositories exit 0 instead of 1 or 2"
 )]
 struct Args {
     /// Show git commands being executed (runs sequentially in workspace mode)
@@ -24,9 +40,279 @@ struct Args {
     /// Minimal output (errors only). Ideal for CI/scripts
     #[arg(short, long, conflicts_with = "verbose")]
     quiet: bool,
+
+    /// Skip repositories with more than N uncommitted files instead of stashing them
+    #[arg(long, value_name = "N")]
+    skip_dirty_over: Option<usize>,
+
+    /// Number of repositories to update in parallel (default: 60)
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Start at most N repos per 100ms until all --jobs are busy (eases DNS/connection spikes)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    ramp: Option<u32>,
+
+    /// Fast-forward the current branch in place instead of updating master/main
+    #[arg(long)]
+    current_branch: bool,
+
+    /// Only fetch (with prune) in every repo, without touching branches, stashes, or working trees
+    #[arg(long, conflicts_with = "current_branch")]
+    fetch_only: bool,
+
+    /// Only drop tracking refs for branches deleted on the remote (git remote prune); fetches no objects
+    #[arg(long, conflicts_with_all = ["current_branch", "fetch_only"])]
+    prune_remote_only: bool,
+
+    /// With --current-branch, rebase local commits onto the upstream (git pull --rebase --autostash)
+    #[arg(long, requires = "current_branch")]
+    rebase: bool,
+
+    /// Stash only changes matching PATHSPEC, leaving other changes in place (repeatable)
+    #[arg(
+        long = "stash-path",
+        value_name = "PATHSPEC",
+        conflicts_with = "no_stash"
+    )]
+    stash_paths: Vec<String>,
+
+    /// Ignore changes to PATHSPEC when deciding whether a repo is dirty, e.g. a generated file (repeatable)
+    #[arg(long = "ignore-dirty-path", value_name = "PATHSPEC")]
+    ignore_dirty_paths: Vec<String>,
+
+    /// What to do when untracked files would be overwritten by checking out master/main
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = UntrackedConflictPolicy::Fail)]
+    untracked_conflict: UntrackedConflictPolicy,
+
+    /// Branch to update when a repo has both master and main locally [default: the one
+    /// tracking a live upstream or origin/HEAD, else master]
+    #[arg(long, value_enum, value_name = "BRANCH")]
+    prefer_branch: Option<BranchPreference>,
+
+    /// Update BRANCH instead of master/main in the repo named REPO (repeatable)
+    #[arg(long = "branch-override", value_name = "REPO=BRANCH", value_parser = parse_branch_override)]
+    branch_overrides: Vec<(String, String)>,
+
+    /// Workspace progress style: full, compact (one updating line for CI logs), or lines (one line per repo)
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = ProgressMode::Full)]
+    progress: ProgressMode,
+
+    /// Print one line if everything succeeded, the full summary if anything failed (for cron)
+    #[arg(long, conflicts_with_all = ["verbose", "quiet", "ndjson"])]
+    quiet_on_success: bool,
+
+    /// Print nothing if every repo was already up to date; full summary otherwise (for monitoring)
+    #[arg(long, conflicts_with_all = ["verbose", "quiet", "ndjson", "quiet_on_success"])]
+    report_only_changed: bool,
+
+    /// Leave clean repos untouched when the remote master/main has not moved (one git ls-remote each)
+    #[arg(long, conflicts_with_all = ["current_branch", "fetch_only", "prune_remote_only"])]
+    skip_unchanged: bool,
+
+    /// Never stash: skip repositories with uncommitted changes and leave them untouched
+    #[arg(long, conflicts_with = "skip_dirty_over")]
+    no_stash: bool,
+
+    /// Never stash: fail repositories with uncommitted changes (strict mode for CI)
+    #[arg(long, conflicts_with_all = ["no_stash", "skip_dirty_over", "stash_paths"])]
+    fail_on_dirty: bool,
+
+    /// Drop stashes left by earlier interrupted runs, all but the newest, instead of refusing to
+    /// stash on top of them (the dropped commits are printed for `git stash store`)
+    #[arg(long, conflicts_with_all = ["no_stash", "fail_on_dirty"])]
+    drop_stale_stashes: bool,
+
+    /// Treat repos whose only changes are whitespace or line endings as clean (no stash)
+    #[arg(long)]
+    ignore_whitespace_dirty: bool,
+
+    /// Leave a master/main with local commits of its own as-is with a warning instead of failing the repo
+    #[arg(long, conflicts_with_all = ["current_branch", "fetch_only", "prune_remote_only"])]
+    keep_diverged_default: bool,
+
+    /// Fail a repo instead of pulling when the new master/main tip is not validly signed (git verify-commit)
+    #[arg(long, conflicts_with_all = ["current_branch", "fetch_only", "prune_remote_only"])]
+    verify_signatures: bool,
+
+    /// Disable git hooks for the checkouts to master/main and back (hooks still run for the pull)
+    #[arg(long)]
+    no_checkout_hooks: bool,
+
+    /// After a successful update, prune registrations of worktrees whose directories are gone
+    #[arg(long)]
+    prune_worktrees: bool,
+
+    /// Run a failed repo's whole update again up to N times (see --retry-on)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    repo_retries: u32,
+
+    /// Step whose failures --repo-retries retries (repeatable; default: fetching, checking-out, restoring-branch)
+    #[arg(
+        long = "retry-on",
+        value_enum,
+        value_name = "STEP",
+        requires = "repo_retries"
+    )]
+    retry_on: Vec<UpdateStep>,
+
+    /// Exit 0 unless more than this fraction of repos failed, e.g. 0.05 tolerates up to 5% (0.0 to 1.0)
+    #[arg(long, value_name = "FRACTION", value_parser = parse_fail_threshold)]
+    fail_threshold: Option<f32>,
+
+    /// Pass ARG to every git fetch after the standard arguments, e.g. --no-tags (repeatable)
+    #[arg(long = "fetch-arg", value_name = "ARG", allow_hyphen_values = true)]
+    fetch_args: Vec<String>,
+
+    /// Allow --fetch-arg options that make git run a program, such as --upload-pack
+    #[arg(long, requires = "fetch_args")]
+    allow_unsafe_fetch_args: bool,
+
+    /// Share one SSH connection per host across all repos (only helps SSH remotes)
+    #[arg(long)]
+    ssh_multiplex: bool,
+
+    /// Refuse to run any git command that could change a repo (for audits; pair with --health)
+    #[arg(long)]
+    read_only: bool,
+
+    /// Print the steps the update will run for the (first) repo, given its state, before running them
+    #[arg(long)]
+    explain: bool,
+
+    /// Print what the update would do in every repo as a JSON array, without changing anything
+    #[arg(long, conflicts_with_all = ["explain", "list_repos", "health"])]
+    dry_run_json: bool,
+
+    /// Run the workspace update N times and print min/median/max wall-clock (fetches every time)
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["list_repos", "health", "continue_from", "only_failed_from", "ndjson"]
+    )]
+    bench: Option<u32>,
+
+    /// Check that the first repo's remote is reachable before updating anything
+    #[arg(long)]
+    preflight: bool,
+
+    /// Stream update events as newline-delimited JSON to stdout
+    #[arg(long, conflicts_with_all = ["verbose", "quiet"])]
+    ndjson: bool,
+
+    /// Summary format: human, json (run report, reusable with --only-failed-from), csv, markdown, or porcelain
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with = "ndjson")]
+    format: Option<OutputFormat>,
+
+    /// Shorthand for --format json
+    #[arg(long, conflicts_with_all = ["ndjson", "format"])]
+    json: bool,
+
+    /// Collapse failures with the same step and error into one summary entry with a count
+    #[arg(long)]
+    collapse_failures: bool,
+
+    /// After the summary, count updated/failed/skipped repos per remote host (e.g. to spot a server that is down)
+    #[arg(long, conflicts_with = "ndjson")]
+    group_by_host: bool,
+
+    /// Shorthand for --format markdown (a table for pasting into issues or chat)
+    #[arg(long, conflicts_with_all = ["ndjson", "json", "format"])]
+    markdown: bool,
+
+    /// Git executable to run instead of the first `git` on PATH
+    #[arg(long = "git", value_name = "PATH")]
+    git_binary: Option<PathBuf>,
+
+    /// Set an environment variable for git and its hooks, e.g. GIT_SSH_COMMAND=... (repeatable)
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_var)]
+    env: Vec<(String, String)>,
+
+    /// ID to tag this run's JSON/NDJSON output and verbose logs with (default: generated)
+    #[arg(long, value_name = "ID")]
+    run_id: Option<String>,
+
+    /// Print the repositories that would be updated, one per line, and exit
+    #[arg(long, conflicts_with = "ndjson")]
+    list_repos: bool,
+
+    /// Report each repo as current, behind, dirty, detached, diverged, or without a remote, and exit
+    #[arg(long, conflicts_with_all = ["ndjson", "list_repos"])]
+    health: bool,
+
+    /// With --health, flag repos whose default branch has no commit in DAYS days (default: 180)
+    #[arg(long, value_name = "DAYS", requires = "health")]
+    stale_after: Option<u64>,
+
+    /// Update repos in the waves listed in a TOML manifest, one wave after another
+    #[arg(long, value_name = "PATH")]
+    manifest: Option<PathBuf>,
+
+    /// Record each finished repo in STATE and skip repos it already lists as updated (workspace runs)
+    #[arg(long, visible_alias = "resume", value_name = "STATE")]
+    continue_from: Option<PathBuf>,
+
+    /// Re-run only the repositories that failed in a previous JSON run report
+    #[arg(long, value_name = "REPORT")]
+    only_failed_from: Option<PathBuf>,
+
+    /// Update the repositories inside the current directory even if it is itself a git repository
+    #[arg(long)]
+    workspace: bool,
+
+    /// Update a workspace even when it is rooted at $HOME or a system directory such as /
+    #[arg(long)]
+    force: bool,
+
+    /// Answer yes to every confirmation prompt (prompts are declined when stdin is not a terminal)
+    #[arg(short = 'y', long)]
+    assume_yes: bool,
 }
 
 impl Args {
+    /// The summary format: `--format`, or its `--json` / `--markdown` shorthand.
+    fn output_format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else if self.markdown {
+            OutputFormat::Markdown
+        } else {
+            self.format.unwrap_or_default()
+        }
+    }
+
+    /// Rejects flags that make no sense with the chosen summary format.
+    /// Checked here because clap cannot make a flag conflict with one value.
+    fn check_output_format(&self) -> anyhow::Result<()> {
+        let format = self.output_format();
+        let machine_readable = format.is_machine_readable();
+        let conflicts = [
+            (
+                "--quiet-on-success",
+                self.quiet_on_success && machine_readable,
+            ),
+            (
+                "--report-only-changed",
+                self.report_only_changed && machine_readable,
+            ),
+            ("--list-repos", self.list_repos && machine_readable),
+            ("--group-by-host", self.group_by_host),
+            ("--health", self.health),
+            ("--bench", self.bench.is_some()),
+        ];
+        if format != OutputFormat::Human
+            && let Some((flag, _)) = conflicts.iter().find(|(_, set)| *set)
+        {
+            let name = format
+                .to_possible_value()
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default();
+            anyhow::bail!("--format {} cannot be used with {}", name, flag);
+        }
+        Ok(())
+    }
+
     fn to_config(&self) -> Config {
         let verbosity = if self.quiet {
             Verbosity::Quiet
@@ -35,80 +321,622 @@ impl Args {
         } else {
             Verbosity::Normal
         };
-        Config { verbosity }
+        Config {
+            verbosity,
+            ndjson: self.ndjson,
+            format: self.output_format(),
+            skip_dirty_over: self.skip_dirty_over,
+            no_stash: self.no_stash,
+            skip_unchanged: self.skip_unchanged,
+            fail_on_dirty: self.fail_on_dirty,
+            drop_stale_stashes: self.drop_stale_stashes,
+            ignore_whitespace_dirty: self.ignore_whitespace_dirty,
+            no_checkout_hooks: self.no_checkout_hooks,
+            prune_worktrees: self.prune_worktrees,
+            keep_diverged_default: self.keep_diverged_default,
+            verify_signatures: self.verify_signatures,
+            stash_pathspec: Some(self.stash_paths.clone()).filter(|paths| !paths.is_empty()),
+            ignore_dirty_paths: Some(self.ignore_dirty_paths.clone())
+                .filter(|paths| !paths.is_empty()),
+            untracked_conflict: self.untracked_conflict,
+            branch_preference: self.prefer_branch,
+            branch_overrides: self.branch_overrides.iter().cloned().collect(),
+            update_mode: if self.fetch_only {
+                UpdateMode::FetchOnly
+            } else if self.prune_remote_only {
+                UpdateMode::PruneRemoteOnly
+            } else if self.rebase {
+                UpdateMode::CurrentBranchRebase
+            } else if self.current_branch {
+                UpdateMode::CurrentBranch
+            } else {
+                UpdateMode::DefaultBranch
+            },
+            quiet_on_success: self.quiet_on_success,
+            report_only_changed: self.report_only_changed,
+            collapse_failures: self.collapse_failures,
+            group_by_host: self.group_by_host,
+            progress: self.progress,
+            // Read from `--manifest` by main, since loading it can fail
+            manifest: None,
+            jobs: self.jobs,
+            ramp: self.ramp.map(|per_interval| per_interval as usize),
+            remote: None,
+            default_branches: None,
+            preflight: self.preflight,
+            assume_yes: self.assume_yes,
+            stale_after_days: self.stale_after,
+            repo_retries: self.repo_retries,
+            repo_retry_steps: Some(self.retry_on.clone()).filter(|steps| !steps.is_empty()),
+            fail_threshold: self.fail_threshold,
+            git_binary: self.git_binary.clone(),
+            env: self.env.iter().cloned().collect(),
+            extra_fetch_args: self.fetch_args.clone(),
+            allow_unsafe_fetch_args: self.allow_unsafe_fetch_args,
+            ssh_multiplex: self.ssh_multiplex,
+            read_only: self.read_only,
+            run_id: Some(self.run_id.clone().unwrap_or_else(generate_run_id)),
+            resumed: None,
+        }
+    }
+}
+
+fn parse_branch_override(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((repo, branch)) if !repo.is_empty() && !branch.is_empty() => {
+            Ok((repo.to_string(), branch.to_string()))
+        }
+        _ => Err(format!("expected REPO=BRANCH, got '{}'", value)),
+    }
+}
+
+fn parse_fail_threshold(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(fraction) if (0.0..=1.0).contains(&fraction) => Ok(fraction),
+        _ => Err(format!(
+            "expected a fraction from 0.0 to 1.0, got '{}'",
+            value
+        )),
+    }
+}
+
+fn parse_env_var(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, val)) if !key.is_empty() => Ok((key.to_string(), val.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", value)),
     }
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
-    let config = args.to_config();
+    args.check_output_format()?;
+    let mut config = args.to_config();
+    let env_overrides = EnvOverrides::from_env();
+    output::print_warnings(&env_overrides.warnings);
+    env_overrides.apply(&mut config);
+    if let Some(manifest_path) = &args.manifest {
+        config.manifest = Some(Manifest::load(manifest_path)?);
+    }
 
-    // High thread count is fine for I/O-bound git operations
-    let _ = rayon::ThreadPoolBuilder::new()
-        .num_threads(RAYON_THREAD_COUNT)
-        .build_global();
+    git::validate_extra_fetch_args(&config.extra_fetch_args, config.allow_unsafe_fetch_args)?;
+    git::check_git_binary(&config)?;
 
     let start = std::time::Instant::now();
     let cwd = std::env::current_dir()?;
+    let single_repo = is_single_repo(&cwd, &args);
+    if single_repo && args.only_failed_from.is_none() {
+        // Only a warning: a scan that hangs on a network mount is given up silently
+        let nested = repo::find_git_repos_with_timeout(&cwd, constants::discovery_timeout())
+            .unwrap_or_default();
+        if !nested.is_empty() {
+            output::print_warnings(&[nested_repos_warning(&cwd, nested.len())]);
+        }
+    }
+
+    if args.list_repos {
+        output::print_repo_list(&target_repos(&cwd, &args, &config)?);
+        return Ok(());
+    }
+    if args.health {
+        let report = repo::health_scan(&target_repos(&cwd, &args, &config)?, &config);
+        output::print_health_report(&report, &config);
+        return Ok(());
+    }
+    if args.dry_run_json {
+        let plans = plan_reports(&target_repos(&cwd, &args, &config)?, &config);
+        return output::print_plan_reports(&plans);
+    }
 
     output::print_working_dir(&cwd, &config);
+    if args.explain {
+        explain_first_repo(&target_repos(&cwd, &args, &config)?, &config);
+    }
 
-    let results: Vec<_> = if repo::is_git_repo(&cwd) {
-        run_single_repo(&cwd, &config)
+    if let Some(iterations) = args.bench {
+        if single_repo {
+            anyhow::bail!(
+                "--bench needs a workspace; run it from the directory holding the repositories"
+            );
+        }
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        check_workspace_root(&cwd, home.as_deref(), args.force)?;
+        output::print_warnings(&[format!(
+            "--bench performs {} real updates: every iteration fetches from each remote",
+            iterations
+        )]);
+        let timings = run_bench(iterations, |iteration| {
+            let stats = Arc::new(WorkspaceStats::default());
+            let duration = run_workspace(&cwd, &config, &stats, None, &mut false)?.duration;
+            eprintln!(
+                "bench run {}/{}: {:.2}s",
+                iteration,
+                iterations,
+                duration.as_secs_f32()
+            );
+            Ok(duration)
+        })?;
+        println!("{}", timings.summary());
+        return Ok(());
+    }
+
+    let stats = Arc::new(WorkspaceStats::default());
+    let resume = args
+        .continue_from
+        .as_deref()
+        .map(Resume::open)
+        .transpose()?;
+    // Set when filtering leaves nothing to update; only `nothing_matched` is an exit code of its own
+    let mut nothing_matched = false;
+    let mut nothing_to_retry = false;
+    let results: Vec<_> = if let Some(report_path) = &args.only_failed_from {
+        let (listed, failed) = load_failed_repos(report_path, &config)?;
+        if failed.is_empty() {
+            let reason = format!("none failed in '{}'", report_path.display());
+            output::print_nothing_matched(listed, &reason);
+            nothing_to_retry = true;
+            Vec::new()
+        } else {
+            let failed = remaining_repos(failed, resume.as_ref(), &config);
+            nothing_matched = report_nothing_matched(listed, &failed, RESUMED_REASON);
+            if !nothing_matched {
+                output::print_workspace_start(failed.len(), &config);
+            }
+            run_repos(&failed, &config, &stats, resume.as_ref())?
+        }
+    } else if single_repo {
+        run_single_repo(&cwd, &config, &stats)
+    } else {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        check_workspace_root(&cwd, home.as_deref(), args.force)?;
+        run_workspace(&cwd, &config, &stats, resume.as_ref(), &mut nothing_matched)?.results
+    };
+
+    let exit_code = if nothing_matched {
+        NOTHING_MATCHED_EXIT_CODE
     } else {
-        run_workspace(&cwd, &config)
+        compute_exit_code(&results, config.fail_threshold)
+    };
+    // With nothing to update, the message above says it all, except to
+    // machine-readable formats, which still get their (empty) report
+    let nothing_to_do = (nothing_matched || nothing_to_retry) && results.is_empty();
+    if (config.report_only_changed && nothing_changed(&results))
+        || (nothing_to_do && !config.has_machine_readable_stdout())
+    {
+        std::process::exit(exit_code);
+    }
+
+    let mut summary_config = summary_config(&config, &results);
+    summary_config.resumed = resume
+        .as_ref()
+        .map(|resume| resume.skipped.load(Ordering::Relaxed));
+    output::print_summary(&results, start.elapsed(), &stats, &summary_config);
+    if config.group_by_host && !summary_config.is_quiet() {
+        output::print_host_groups(&repo::group_by_host(&results, &config));
+    }
+
+    std::process::exit(exit_code);
+}
+
+/// Explains that only `root` itself is updated although it holds `count`
+/// repositories of its own, and how to update those instead.
+fn nested_repos_warning(root: &Path, count: usize) -> String {
+    format!(
+        "'{}' is a git repository, so only it is updated; the {} {} inside it {} not. \
+         Run with --workspace to update those instead.",
+        root.display(),
+        count,
+        if count == 1 {
+            "repository"
+        } else {
+            "repositories"
+        },
+        if count == 1 { "is" } else { "are" },
+    )
+}
+
+/// Refuses a workspace run rooted at the home directory or a system directory
+/// unless `force` is set.
+fn check_workspace_root(root: &Path, home: Option<&Path>, force: bool) -> anyhow::Result<()> {
+    if force {
+        return Ok(());
+    }
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let is_home = home
+        .and_then(|home| home.canonicalize().ok())
+        .is_some_and(|home| home == root);
+    let is_system = constants::DANGEROUS_WORKSPACE_ROOTS
+        .iter()
+        .any(|dir| root == Path::new(dir));
+    if is_home || is_system {
+        anyhow::bail!(
+            "Refusing to update every repository under '{}' because it is {}. \
+             Run git-daily-v2 from the directory that holds your repositories, \
+             or pass --force to update this one anyway.",
+            root.display(),
+            if is_home {
+                "your home directory"
+            } else {
+                "a system directory"
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Prints the update plan of the first of `repos`; the rest follow the same
+/// rules, and explaining hundreds of repos would bury the run itself.
+fn explain_first_repo(repos: &[PathBuf], config: &Config) {
+    let Some(first) = repos.first() else {
+        return;
+    };
+    match repo::plan_update(first, config) {
+        Ok(plan) => output::print_update_plan(first, &plan, repos.len()),
+        Err(e) => {
+            output::print_warnings(&[format!("cannot explain '{}': {:#}", first.display(), e)])
+        }
+    }
+}
+
+/// Plans the update of each of `repos` without changing anything; a repo
+/// whose state cannot be read gets a plan carrying the error.
+fn plan_reports(repos: &[PathBuf], config: &Config) -> Vec<PlanReport> {
+    repos
+        .iter()
+        .map(|path| match repo::plan_update(path, config) {
+            Ok(plan) => PlanReport::from_plan(path, &plan),
+            Err(e) => PlanReport::from_error(path, format!("{:#}", e)),
+        })
+        .collect()
+}
+
+/// Returns true if `cwd` is updated as one repository rather than scanned as a
+/// workspace: a work tree, a bare clone or mirror, or a broken worktree link
+/// (so it fails with a clear diagnosis instead of its subdirectories being
+/// scanned), unless `--workspace` is given.
+fn is_single_repo(cwd: &Path, args: &Args) -> bool {
+    !args.workspace
+        && (repo::is_git_repo(cwd) || repo::is_bare_repo(cwd) || repo::is_broken_worktree_link(cwd))
+}
+
+/// Resolves the repositories a run would update, applying the same discovery
+/// and filters as a real run, sorted by path.
+fn target_repos(cwd: &Path, args: &Args, config: &Config) -> anyhow::Result<Vec<PathBuf>> {
+    let mut repos = if let Some(report_path) = &args.only_failed_from {
+        load_failed_repos(report_path, config)?.1
+    } else if is_single_repo(cwd, args) {
+        vec![cwd.to_path_buf()]
+    } else {
+        repo::find_git_repos_with_timeout(cwd, constants::discovery_timeout())?
+    };
+    repos.sort();
+    if let Some(state_path) = &args.continue_from {
+        let completed = RunState::load(state_path)?.completed_paths();
+        repos.retain(|repo| !completed.contains(repo));
+    }
+    Ok(repos)
+}
+
+/// A resumable run (`--continue-from`): repositories already updated according
+/// to the state file, and the file itself, opened for appending new outcomes.
+struct Resume {
+    completed: HashSet<PathBuf>,
+    state_file: Arc<Mutex<File>>,
+    /// How many repositories this run skipped as already updated.
+    skipped: AtomicUsize,
+}
+
+impl Resume {
+    /// Loads the state file and opens it for appending. A torn last line left
+    /// by an interrupted run is cut off first, so the next outcome starts on a
+    /// line of its own instead of being glued onto it.
+    fn open(state_path: &Path) -> anyhow::Result<Self> {
+        let mut state_file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(state_path)
+            .with_context(|| format!("Failed to open run state '{}'", state_path.display()))?;
+        let mut contents = Vec::new();
+        state_file
+            .read_to_end(&mut contents)
+            .with_context(|| format!("Failed to read run state '{}'", state_path.display()))?;
+        let complete = contents
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        state_file
+            .set_len(complete as u64)
+            .with_context(|| format!("Failed to repair run state '{}'", state_path.display()))?;
+        let contents = std::str::from_utf8(&contents[..complete])
+            .with_context(|| format!("Run state '{}' is not UTF-8", state_path.display()))?;
+        Ok(Self {
+            completed: RunState::from_lines(contents)?.completed_paths(),
+            state_file: Arc::new(Mutex::new(state_file)),
+            skipped: AtomicUsize::new(0),
+        })
+    }
+}
+
+/// Drops the repositories a resumed run already updated.
+fn remaining_repos(repos: Vec<PathBuf>, resume: Option<&Resume>, config: &Config) -> Vec<PathBuf> {
+    let Some(resume) = resume else {
+        return repos;
     };
+    let total = repos.len();
+    let remaining: Vec<PathBuf> = repos
+        .into_iter()
+        .filter(|repo| !resume.completed.contains(repo))
+        .collect();
+    resume
+        .skipped
+        .fetch_add(total - remaining.len(), Ordering::Relaxed);
+    output::print_resume_skipped(total - remaining.len(), config);
+    remaining
+}
+
+/// Why a resumed run has nothing left to update.
+const RESUMED_REASON: &str = "all already updated according to --continue-from";
 
-    output::print_summary(&results, start.elapsed(), &config);
+/// Returns true, after saying so, when repositories were found but filtering
+/// left none to update. `main` then exits with [`NOTHING_MATCHED_EXIT_CODE`],
+/// so scripts can tell this apart from a run in which everything succeeded.
+fn report_nothing_matched(found: usize, remaining: &[PathBuf], reason: &str) -> bool {
+    let nothing_matched = found > 0 && remaining.is_empty();
+    if nothing_matched {
+        output::print_nothing_matched(found, reason);
+    }
+    nothing_matched
+}
 
-    std::process::exit(compute_exit_code(&results));
+/// Loads the failed repositories from a run report, rejecting any path that
+/// is no longer a git work tree. Also returns how many repositories the
+/// report lists in total.
+fn load_failed_repos(report_path: &Path, config: &Config) -> anyhow::Result<(usize, Vec<PathBuf>)> {
+    let report = RunReport::load(report_path)?;
+    let failed = report.failed_paths();
+    for path in &failed {
+        if !git::is_inside_work_tree(path, config, config.git_logger())? {
+            anyhow::bail!(
+                "'{}' from run report '{}' is not inside a git work tree",
+                path.display(),
+                report_path.display()
+            );
+        }
+    }
+    Ok((report.repos.len(), failed))
 }
 
-fn run_single_repo(path: &Path, config: &Config) -> Vec<repo::UpdateResult> {
+fn run_single_repo(
+    path: &Path,
+    config: &Config,
+    stats: &WorkspaceStats,
+) -> Vec<repo::UpdateResult> {
+    if config.ndjson {
+        let callbacks = output::NdjsonCallbacks::new(path, Arc::new(Mutex::new(io::stdout())))
+            .with_run_id(config.run_id.as_deref());
+        let result = repo::update(path, &callbacks, config);
+        callbacks.on_complete(&result);
+        return vec![result];
+    }
+
     let progress = output::create_single_repo_progress(config);
-    let callbacks = output::SingleRepoCallbacks::new(progress, *config);
+    let callbacks = output::SingleRepoCallbacks::new(progress, config.clone());
     let result = repo::update(path, &callbacks, config);
     callbacks.finish(&result);
+    stats.record(&result);
 
     vec![result]
 }
 
-fn run_workspace(path: &Path, config: &Config) -> Vec<repo::UpdateResult> {
-    let sub_dirs = repo::find_git_repos(path);
-    output::print_workspace_start(sub_dirs.len(), config);
+fn run_workspace(
+    path: &Path,
+    config: &Config,
+    stats: &Arc<WorkspaceStats>,
+    resume: Option<&Resume>,
+    nothing_matched: &mut bool,
+) -> anyhow::Result<WorkspaceOutcome> {
+    let mut updated = 0;
+    let outcome = repo::run_workspace_with(path, |repos| {
+        let found = repos.len();
+        let repos = remaining_repos(repos, resume, config);
+        *nothing_matched = report_nothing_matched(found, &repos, RESUMED_REASON);
+        if *nothing_matched {
+            return Ok(Vec::new());
+        }
+        output::print_workspace_start(repos.len(), config);
+        let results = run_repos(&repos, config, stats, resume)?;
+        updated = results.len();
+        Ok(results)
+    })?;
+    if config.ndjson {
+        // Broken worktree links never reached an update, so nothing streamed them yet
+        let stdout = Arc::new(Mutex::new(io::stdout()));
+        for result in &outcome.results[updated..] {
+            output::NdjsonCallbacks::new(&result.path, Arc::clone(&stdout))
+                .with_run_id(config.run_id.as_deref())
+                .on_complete(result);
+        }
+    }
+    if config.is_verbose() {
+        output::print_rejected_dirs(&outcome.filtered_out, config);
+    }
+    Ok(outcome)
+}
+
+/// Wall-clock durations of the `--bench` iterations, in the order they ran.
+#[derive(Debug)]
+struct BenchTimings {
+    durations: Vec<Duration>,
+}
 
-    if sub_dirs.is_empty() {
-        return vec![];
+impl BenchTimings {
+    fn sorted(&self) -> Vec<Duration> {
+        let mut sorted = self.durations.clone();
+        sorted.sort();
+        sorted
     }
 
-    let workspace_progress = output::create_workspace_progress(sub_dirs.len(), config);
-    let results = repo::update_workspace(
-        &sub_dirs,
-        |dir| workspace_progress.create_repo_tracker(get_repo_name(dir), *config),
-        config,
-    );
+    fn min(&self) -> Duration {
+        self.sorted()[0]
+    }
 
-    workspace_progress.finish();
-    results
-}
+    fn max(&self) -> Duration {
+        self.sorted()[self.durations.len() - 1]
+    }
 
-fn compute_exit_code(results: &[repo::UpdateResult]) -> i32 {
-    if results.is_empty() {
-        return 0;
+    fn median(&self) -> Duration {
+        let sorted = self.sorted();
+        let middle = sorted.len() / 2;
+        if sorted.len().is_multiple_of(2) {
+            (sorted[middle - 1] + sorted[middle]) / 2
+        } else {
+            sorted[middle]
+        }
     }
 
-    let failure_count = results
-        .iter()
-        .filter(|r| matches!(r.outcome, UpdateOutcome::Failed(_)))
-        .count();
+    fn summary(&self) -> String {
+        format!(
+            "Bench: {} runs, min {:.2}s, median {:.2}s, max {:.2}s",
+            self.durations.len(),
+            self.min().as_secs_f32(),
+            self.median().as_secs_f32(),
+            self.max().as_secs_f32()
+        )
+    }
+}
 
-    if failure_count == results.len() {
-        2 // All failed
-    } else if failure_count > 0 {
-        1 // Partial failure
+/// Calls `run` with iteration numbers 1 to `iterations` (at least 1),
+/// collecting the duration each one reports. Stops at the first error.
+fn run_bench(
+    iterations: u32,
+    mut run: impl FnMut(u32) -> anyhow::Result<Duration>,
+) -> anyhow::Result<BenchTimings> {
+    let durations = (1..=iterations.max(1))
+        .map(&mut run)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(BenchTimings { durations })
+}
+
+fn run_repos(
+    repos: &[PathBuf],
+    config: &Config,
+    stats: &Arc<WorkspaceStats>,
+    resume: Option<&Resume>,
+) -> anyhow::Result<Vec<repo::UpdateResult>> {
+    if repos.is_empty() {
+        return Ok(vec![]);
+    }
+    // Lives until every repo is done; dropping it closes the shared connections
+    let mut config = config.clone();
+    let _ssh_multiplex = if config.ssh_multiplex {
+        let multiplex =
+            ssh::SshMultiplex::start().context("Failed to create SSH control directory")?;
+        // core.sshCommand can differ per repository; one set anywhere keeps it off
+        let configured = repos.iter().find_map(|repo| {
+            git::ssh_command(repo, &config, config.git_logger())
+                .ok()
+                .flatten()
+        });
+        let inherited = |name: &str| std::env::var(name).ok();
+        if let Some(warning) = multiplex.apply(&mut config, inherited, configured.as_deref()) {
+            output::print_warnings(&[warning]);
+        }
+        Some(multiplex)
     } else {
-        0 // All success
+        None
+    };
+    let config = &config;
+    if config.preflight {
+        repo::check_connectivity(repos, config)?;
+    }
+
+    // Each repo's outcome is appended to the state file the moment it finishes
+    let with_state = |callbacks: CompositeCallbacks| match resume {
+        Some(resume) => callbacks.with(output::StateFileCallbacks::new(Arc::clone(
+            &resume.state_file,
+        ))),
+        None => callbacks,
+    };
+
+    if config.ndjson {
+        let stdout = Arc::new(Mutex::new(io::stdout()));
+        return Ok(repo::update_workspace(
+            repos,
+            |dir| {
+                with_state(
+                    CompositeCallbacks::default().with(
+                        output::NdjsonCallbacks::new(dir, Arc::clone(&stdout))
+                            .with_run_id(config.run_id.as_deref()),
+                    ),
+                )
+            },
+            config,
+        ));
+    }
+
+    let workspace_progress =
+        output::create_workspace_progress(repos.len(), config, Arc::clone(stats));
+    let results =
+        repo::update_workspace(
+            repos,
+            |dir| {
+                with_state(CompositeCallbacks::default().with(
+                    workspace_progress.create_repo_tracker(get_repo_name(dir), config.clone()),
+                ))
+            },
+            config,
+        );
+
+    workspace_progress.finish();
+    Ok(results)
+}
+
+/// Picks how the summary is printed once the outcome is known.
+/// In quiet-on-success mode, a clean run gets the one-line quiet summary and
+/// a run with failures gets the full summary, even failures `--fail-threshold`
+/// tolerates.
+fn summary_config(config: &Config, results: &[repo::UpdateResult]) -> Config {
+    let mut summary_config = config.clone();
+    if config.quiet_on_success && compute_exit_code(results, None) == 0 {
+        summary_config.verbosity = Verbosity::Quiet;
     }
+    summary_config
+}
+
+/// Returns true if every repository was updated successfully without pulling anything.
+fn nothing_changed(results: &[repo::UpdateResult]) -> bool {
+    results.iter().all(|r| match &r.outcome {
+        UpdateOutcome::Success(success) => success.commits_pulled == 0,
+        _ => false,
+    })
+}
+
+/// Exit code when repositories were found but none were left after filtering.
+const NOTHING_MATCHED_EXIT_CODE: i32 = 3;
+
+/// Exit code for the run's results; see [`RunReport::exit_code`].
+fn compute_exit_code(results: &[repo::UpdateResult], fail_threshold: Option<f32>) -> i32 {
+    RunReport::from_results(results).exit_code(fail_threshold)
 }
 
 fn get_repo_name(path: &Path) -> &str {
@@ -121,9 +949,18 @@ fn get_repo_name(path: &Path) -> &str {
 mod tests {
     use super::*;
     use git_daily_rust::repo::{UpdateFailure, UpdateResult, UpdateSuccess};
-    use std::path::PathBuf;
     use std::time::Duration;
 
+    #[test]
+    fn test_help_lists_every_exit_code() {
+        use clap::CommandFactory;
+        let help = Args::command().render_help().to_string();
+        for code in ["0", "1", "2", "3"] {
+            assert!(help.contains(&format!("\n  {}  ", code)), "{}", help);
+        }
+        assert!(help.contains("--fail-threshold, failures"), "{}", help);
+    }
+
     #[test]
     fn test_args_to_config_respects_quiet_and_verbose() {
         let quiet = Args::parse_from(["git-daily-v2", "--quiet"]);
@@ -143,6 +980,386 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_args_ndjson_sets_config_and_conflicts_with_verbose() {
+        let ndjson = Args::parse_from(["git-daily-v2", "--ndjson"]);
+        assert!(ndjson.to_config().ndjson);
+        assert!(ndjson.to_config().hides_progress());
+
+        let result = Args::try_parse_from(["git-daily-v2", "--ndjson", "--verbose"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_args_json_and_only_failed_from() {
+        let args = Args::parse_from([
+            "git-daily-v2",
+            "--json",
+            "--only-failed-from",
+            "previous.json",
+        ]);
+        assert_eq!(args.to_config().format, OutputFormat::Json);
+        assert_eq!(args.only_failed_from, Some(PathBuf::from("previous.json")));
+
+        let result = Args::try_parse_from(["git-daily-v2", "--json", "--ndjson"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_args_markdown_sets_config_and_conflicts_with_json() {
+        let args = Args::parse_from(["git-daily-v2", "--quiet", "--markdown"]);
+        assert_eq!(args.to_config().format, OutputFormat::Markdown);
+
+        let result = Args::try_parse_from(["git-daily-v2", "--markdown", "--json"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_args_format_selects_one_summary_writer() {
+        assert_eq!(
+            Args::parse_from(["git-daily-v2"]).to_config().format,
+            OutputFormat::Human
+        );
+        let args = Args::parse_from(["git-daily-v2", "--format", "porcelain"]);
+        assert_eq!(args.to_config().format, OutputFormat::Porcelain);
+        assert!(args.check_output_format().is_ok());
+
+        let result = Args::try_parse_from(["git-daily-v2", "--format", "csv", "--json"]);
+        assert!(result.is_err());
+
+        let args = Args::parse_from(["git-daily-v2", "--format", "json", "--group-by-host"]);
+        let error = args.check_output_format().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "--format json cannot be used with --group-by-host"
+        );
+        let args = Args::parse_from(["git-daily-v2", "--format", "markdown", "--list-repos"]);
+        assert!(args.check_output_format().is_ok());
+    }
+
+    #[test]
+    fn test_args_progress_defaults_to_full() {
+        let args = Args::parse_from(["git-daily-v2"]);
+        assert_eq!(args.to_config().progress, ProgressMode::Full);
+
+        let args = Args::parse_from(["git-daily-v2", "--progress=compact"]);
+        assert_eq!(args.to_config().progress, ProgressMode::Compact);
+
+        let args = Args::parse_from(["git-daily-v2", "--progress=lines"]);
+        assert_eq!(args.to_config().progress, ProgressMode::Lines);
+    }
+
+    #[test]
+    fn test_args_retry_on_parses_steps_and_requires_repo_retries() {
+        let args = Args::parse_from([
+            "git-daily-v2",
+            "--repo-retries",
+            "2",
+            "--retry-on",
+            "checking-out",
+            "--retry-on",
+            "popping-stash",
+        ]);
+        let config = args.to_config();
+        assert_eq!(config.repo_retries, 2);
+        assert_eq!(
+            config.repo_retry_steps,
+            Some(vec![UpdateStep::CheckingOut, UpdateStep::PoppingStash])
+        );
+        assert_eq!(
+            Args::parse_from(["git-daily-v2"])
+                .to_config()
+                .repo_retry_steps,
+            None
+        );
+
+        let result = Args::try_parse_from(["git-daily-v2", "--retry-on", "fetching"]);
+        assert!(result.is_err());
+        let result = Args::try_parse_from([
+            "git-daily-v2",
+            "--repo-retries",
+            "1",
+            "--retry-on",
+            "started",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_args_resume_is_an_alias_for_continue_from() {
+        let args = Args::parse_from(["git-daily-v2", "--resume", "state.jsonl"]);
+        assert_eq!(args.continue_from, Some(PathBuf::from("state.jsonl")));
+    }
+
+    #[test]
+    fn test_nested_repos_warning_names_root_and_count() {
+        let warning = nested_repos_warning(Path::new("/ws"), 3);
+        assert!(
+            warning.starts_with("'/ws' is a git repository"),
+            "{}",
+            warning
+        );
+        assert!(
+            warning.contains("the 3 repositories inside it are not"),
+            "{}",
+            warning
+        );
+        assert!(warning.contains("--workspace"), "{}", warning);
+
+        let warning = nested_repos_warning(Path::new("/ws"), 1);
+        assert!(
+            warning.contains("the 1 repository inside it is not"),
+            "{}",
+            warning
+        );
+    }
+
+    #[test]
+    fn test_check_workspace_root_refuses_home_and_system_dirs_unless_forced() {
+        let home = tempfile::tempdir().unwrap();
+        let workspace = tempfile::tempdir().unwrap();
+
+        let err = check_workspace_root(home.path(), Some(home.path()), false).unwrap_err();
+        assert!(err.to_string().contains("your home directory"), "{}", err);
+        assert!(err.to_string().contains("--force"), "{}", err);
+
+        let err = check_workspace_root(Path::new("/"), Some(home.path()), false).unwrap_err();
+        assert!(err.to_string().contains("a system directory"), "{}", err);
+
+        assert!(check_workspace_root(home.path(), Some(home.path()), true).is_ok());
+        assert!(check_workspace_root(Path::new("/"), None, true).is_ok());
+        assert!(check_workspace_root(workspace.path(), Some(home.path()), false).is_ok());
+    }
+
+    #[test]
+    fn test_remaining_repos_drops_completed_ones() {
+        let state_dir = tempfile::TempDir::new().unwrap();
+        let state_path = state_dir.path().join("state.jsonl");
+        std::fs::write(
+            &state_path,
+            "{\"path\":\"/ws/a\",\"status\":\"success\",\"duration_ms\":1}\n\
+             {\"path\":\"/ws/b\",\"status\":\"failed\",\"duration_ms\":1}\n",
+        )
+        .unwrap();
+        let resume = Resume::open(&state_path).unwrap();
+        let repos = vec![PathBuf::from("/ws/a"), PathBuf::from("/ws/b")];
+
+        let config = Config {
+            verbosity: Verbosity::Quiet,
+            ..Default::default()
+        };
+        assert_eq!(
+            remaining_repos(repos.clone(), Some(&resume), &config),
+            vec![PathBuf::from("/ws/b")]
+        );
+        assert_eq!(remaining_repos(repos.clone(), None, &config), repos);
+        assert_eq!(resume.skipped.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_resume_survives_two_interrupted_runs_in_a_row() {
+        use std::io::Write;
+
+        let state_dir = tempfile::TempDir::new().unwrap();
+        let state_path = state_dir.path().join("state.jsonl");
+        let entry = |repo: &str| {
+            format!(
+                "{{\"path\":\"/ws/{}\",\"status\":\"success\",\"duration_ms\":1}}\n",
+                repo
+            )
+        };
+        // Each run is killed halfway through writing its last outcome
+        std::fs::write(&state_path, format!("{}{{\"path\":\"/ws/", entry("a"))).unwrap();
+        for repo in ["b", "c"] {
+            let resume = Resume::open(&state_path).unwrap();
+            let mut state_file = resume.state_file.lock().unwrap();
+            write!(state_file, "{}{{\"path\":\"/ws/", entry(repo)).unwrap();
+        }
+
+        let resume = Resume::open(&state_path).unwrap();
+        let expected: HashSet<PathBuf> = ["/ws/a", "/ws/b", "/ws/c"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(resume.completed, expected);
+        assert_eq!(
+            std::fs::read_to_string(&state_path).unwrap(),
+            format!("{}{}{}", entry("a"), entry("b"), entry("c"))
+        );
+    }
+
+    #[test]
+    fn test_args_prefer_branch_parses_policy() {
+        let args = Args::parse_from(["git-daily-v2", "--prefer-branch", "upstream"]);
+        assert_eq!(
+            args.to_config().branch_preference,
+            Some(BranchPreference::Upstream)
+        );
+
+        let default = Args::parse_from(["git-daily-v2"]);
+        assert_eq!(default.to_config().branch_preference, None);
+    }
+
+    #[test]
+    fn test_args_branch_overrides_collect_into_config() {
+        let args = Args::parse_from([
+            "git-daily-v2",
+            "--branch-override",
+            "fork=develop",
+            "--branch-override",
+            "mirror=trunk",
+        ]);
+        let config = args.to_config();
+        assert_eq!(
+            config.branch_override(Path::new("/ws/fork")),
+            Some("develop")
+        );
+        assert_eq!(
+            config.branch_override(Path::new("/ws/mirror")),
+            Some("trunk")
+        );
+
+        let result = Args::try_parse_from(["git-daily-v2", "--branch-override", "fork"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_args_assume_yes_sets_config() {
+        assert!(
+            Args::parse_from(["git-daily-v2", "-y"])
+                .to_config()
+                .assume_yes
+        );
+        assert!(!Args::parse_from(["git-daily-v2"]).to_config().assume_yes);
+    }
+
+    #[test]
+    fn test_args_fetch_only_sets_mode_and_conflicts_with_current_branch() {
+        let args = Args::parse_from(["git-daily-v2", "--fetch-only"]);
+        assert_eq!(args.to_config().update_mode, UpdateMode::FetchOnly);
+        assert!(
+            Args::try_parse_from(["git-daily-v2", "--fetch-only", "--current-branch"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_args_prune_remote_only_sets_mode_and_conflicts_with_fetch_only() {
+        let args = Args::parse_from(["git-daily-v2", "--prune-remote-only"]);
+        assert_eq!(args.to_config().update_mode, UpdateMode::PruneRemoteOnly);
+        assert!(
+            Args::try_parse_from(["git-daily-v2", "--prune-remote-only", "--fetch-only"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_args_rebase_requires_current_branch() {
+        assert!(Args::try_parse_from(["git-daily-v2", "--rebase"]).is_err());
+
+        let args = Args::parse_from(["git-daily-v2", "--current-branch", "--rebase"]);
+        assert_eq!(
+            args.to_config().update_mode,
+            UpdateMode::CurrentBranchRebase
+        );
+    }
+
+    #[test]
+    fn test_args_no_stash_conflicts_with_skip_dirty_over() {
+        let args = Args::parse_from(["git-daily-v2", "--no-stash"]);
+        assert!(args.to_config().no_stash);
+
+        let result = Args::try_parse_from(["git-daily-v2", "--no-stash", "--skip-dirty-over", "5"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_args_fail_on_dirty_conflicts_with_other_dirty_policies() {
+        let args = Args::parse_from(["git-daily-v2", "--fail-on-dirty"]);
+        assert!(args.to_config().fail_on_dirty);
+
+        for other in [
+            &["--no-stash"][..],
+            &["--skip-dirty-over", "5"],
+            &["--stash-path", "src"],
+        ] {
+            let mut argv = vec!["git-daily-v2", "--fail-on-dirty"];
+            argv.extend(other);
+            assert!(Args::try_parse_from(argv).is_err(), "{:?}", other);
+        }
+    }
+
+    #[test]
+    fn test_run_bench_runs_every_iteration_and_aggregates_timings() -> anyhow::Result<()> {
+        let mut seen = Vec::new();
+        let timings = run_bench(4, |iteration| {
+            seen.push(iteration);
+            Ok(Duration::from_millis(
+                [300, 100, 400, 200][iteration as usize - 1],
+            ))
+        })?;
+
+        assert_eq!(seen, vec![1, 2, 3, 4]);
+        assert_eq!(timings.min(), Duration::from_millis(100));
+        assert_eq!(timings.median(), Duration::from_millis(250));
+        assert_eq!(timings.max(), Duration::from_millis(400));
+        assert_eq!(
+            timings.summary(),
+            "Bench: 4 runs, min 0.10s, median 0.25s, max 0.40s"
+        );
+
+        let timings = run_bench(3, |iteration| Ok(Duration::from_secs(u64::from(iteration))))?;
+        assert_eq!(timings.median(), Duration::from_secs(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_bench_stops_at_the_first_failed_iteration() {
+        let mut runs = 0;
+        let result = run_bench(5, |iteration| {
+            runs += 1;
+            if iteration == 2 {
+                anyhow::bail!("discovery timed out");
+            }
+            Ok(Duration::from_secs(1))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(runs, 2);
+        assert!(Args::try_parse_from(["git-daily-v2", "--bench", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_args_ramp_must_be_positive() {
+        assert!(Args::try_parse_from(["git-daily-v2", "--ramp", "0"]).is_err());
+        let args = Args::parse_from(["git-daily-v2", "--ramp", "5"]);
+        assert_eq!(args.to_config().ramp, Some(5));
+    }
+
+    #[test]
+    fn test_args_run_id_is_generated_unless_given() {
+        let args = Args::parse_from(["git-daily-v2", "--run-id", "nightly-7"]);
+        assert_eq!(args.to_config().run_id.as_deref(), Some("nightly-7"));
+
+        let generated = Args::parse_from(["git-daily-v2"]).to_config().run_id;
+        assert!(generated.is_some_and(|id| !id.is_empty()));
+    }
+
+    #[test]
+    fn test_args_env_collects_key_value_pairs() {
+        let args = Args::parse_from([
+            "git-daily-v2",
+            "--env",
+            "GIT_SSH_COMMAND=ssh -i ~/.ssh/work",
+            "--env",
+            "EMPTY=",
+        ]);
+        let config = args.to_config();
+        assert_eq!(config.env["GIT_SSH_COMMAND"], "ssh -i ~/.ssh/work");
+        assert_eq!(config.env["EMPTY"], "");
+
+        let result = Args::try_parse_from(["git-daily-v2", "--env", "=value"]);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_args_rejects_unknown_flag() {
         let result = Args::try_parse_from(["git-daily-v2", "--nope"]);
@@ -154,13 +1371,26 @@ mod tests {
         let results = vec![UpdateResult {
             path: PathBuf::from("/repo"),
             outcome: UpdateOutcome::Success(UpdateSuccess {
-                original_head: repo::OriginalHead::Branch("main".to_string()),
-                master_branch: "main",
-                had_stash: false,
+                master_branch: Some("main".to_string()),
+                ..UpdateSuccess::new(repo::OriginalHead::Branch("main".to_string()))
+            }),
+            duration: Duration::from_secs(1),
+        }];
+        assert_eq!(compute_exit_code(&results, None), 0);
+    }
+
+    #[test]
+    fn test_compute_exit_code_ignores_warnings() {
+        let results = vec![UpdateResult {
+            path: PathBuf::from("/repo"),
+            outcome: UpdateOutcome::Success(UpdateSuccess {
+                master_branch: Some("main".to_string()),
+                warnings: vec!["feature is 3 behind origin/feature — consider pulling".to_string()],
+                ..UpdateSuccess::new(repo::OriginalHead::Branch("feature".to_string()))
             }),
             duration: Duration::from_secs(1),
         }];
-        assert_eq!(compute_exit_code(&results), 0);
+        assert_eq!(compute_exit_code(&results, None), 0);
     }
 
     #[test]
@@ -168,39 +1398,90 @@ mod tests {
         let success = UpdateResult {
             path: PathBuf::from("/repo-success"),
             outcome: UpdateOutcome::Success(UpdateSuccess {
-                original_head: repo::OriginalHead::Branch("main".to_string()),
-                master_branch: "main",
-                had_stash: false,
+                master_branch: Some("main".to_string()),
+                ..UpdateSuccess::new(repo::OriginalHead::Branch("main".to_string()))
             }),
             duration: Duration::from_secs(1),
         };
         let failure = UpdateResult {
             path: PathBuf::from("/repo-fail"),
-            outcome: UpdateOutcome::Failed(UpdateFailure {
-                error: "boom".to_string(),
-                step: repo::UpdateStep::Fetching,
-            }),
+            outcome: UpdateOutcome::Failed(UpdateFailure::new(repo::UpdateStep::Fetching, "boom")),
             duration: Duration::from_secs(1),
         };
-        assert_eq!(compute_exit_code(&[success, failure]), 1);
+        assert_eq!(compute_exit_code(&[success, failure], None), 1);
     }
 
     #[test]
     fn test_compute_exit_code_all_failed() {
         let failure = UpdateResult {
             path: PathBuf::from("/repo-fail"),
-            outcome: UpdateOutcome::Failed(UpdateFailure {
-                error: "boom".to_string(),
-                step: repo::UpdateStep::Fetching,
+            outcome: UpdateOutcome::Failed(UpdateFailure::new(repo::UpdateStep::Fetching, "boom")),
+            duration: Duration::from_secs(1),
+        };
+        assert_eq!(compute_exit_code(&[failure], None), 2);
+    }
+
+    #[test]
+    fn test_compute_exit_code_ignores_skipped() {
+        let skipped = UpdateResult {
+            path: PathBuf::from("/repo-skip"),
+            outcome: UpdateOutcome::Skipped(repo::UpdateSkipped {
+                reason: "too dirty".to_string(),
+                step: repo::UpdateStep::CheckingChanges,
+            }),
+            duration: Duration::from_secs(1),
+        };
+        assert_eq!(compute_exit_code(&[skipped], None), 0);
+    }
+
+    #[test]
+    fn test_nothing_changed_requires_all_up_to_date_successes() {
+        let up_to_date = |commits_pulled| UpdateResult {
+            path: PathBuf::from("/repo"),
+            outcome: UpdateOutcome::Success(UpdateSuccess {
+                master_branch: Some("main".to_string()),
+                commits_pulled,
+                ..UpdateSuccess::new(repo::OriginalHead::Branch("main".to_string()))
+            }),
+            duration: Duration::from_secs(1),
+        };
+        let skipped = UpdateResult {
+            path: PathBuf::from("/repo-dirty"),
+            outcome: UpdateOutcome::Skipped(repo::UpdateSkipped {
+                reason: "dirty".to_string(),
+                step: repo::UpdateStep::CheckingChanges,
             }),
             duration: Duration::from_secs(1),
         };
-        assert_eq!(compute_exit_code(&[failure]), 2);
+
+        assert!(nothing_changed(&[]));
+        assert!(nothing_changed(&[up_to_date(0), up_to_date(0)]));
+        assert!(!nothing_changed(&[up_to_date(0), up_to_date(3)]));
+        assert!(!nothing_changed(&[up_to_date(0), skipped]));
+    }
+
+    #[test]
+    fn test_args_fail_threshold_must_be_a_fraction() {
+        let args = Args::parse_from(["git-daily-v2", "--fail-threshold", "0.05"]);
+        assert_eq!(args.to_config().fail_threshold, Some(0.05));
+        assert_eq!(
+            Args::parse_from(["git-daily-v2"])
+                .to_config()
+                .fail_threshold,
+            None
+        );
+        for bad in ["1.5", "-0.1", "five", "NaN"] {
+            assert!(
+                Args::try_parse_from(["git-daily-v2", "--fail-threshold", bad]).is_err(),
+                "{}",
+                bad
+            );
+        }
     }
 
     #[test]
     fn test_compute_exit_code_empty() {
-        assert_eq!(compute_exit_code(&[]), 0);
+        assert_eq!(compute_exit_code(&[], None), 0);
     }
 
     #[test]
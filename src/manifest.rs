@@ -0,0 +1,181 @@
+//! Update ordering manifests.
+//!
+//! A [`Manifest`] groups repositories (by directory name) into waves that are
+//! updated one after another, for sets of repositories that depend on each
+//! other. Repositories within a wave are still updated in parallel.
+//!
+//! ```toml
+//! unlisted = "last"   # or "first"
+//!
+//! [[wave]]
+//! repos = ["core", "proto"]
+//!
+//! [[wave]]
+//! repos = ["api", "web"]
+//! ```
+
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Ordered groups of repositories to update sequentially.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default, rename = "wave")]
+    pub waves: Vec<Wave>,
+    /// Where repositories that no wave lists are updated.
+    #[serde(default)]
+    pub unlisted: UnlistedPlacement,
+}
+
+/// One group of repositories, matched by directory name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Wave {
+    pub repos: Vec<String>,
+}
+
+/// Whether repositories missing from the manifest run before or after every wave.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnlistedPlacement {
+    First,
+    #[default]
+    Last,
+}
+
+impl Manifest {
+    /// Parses a manifest, rejecting repositories listed in more than one wave.
+    pub fn from_toml(text: &str) -> anyhow::Result<Self> {
+        let manifest: Self = toml::from_str(text).context("Failed to parse manifest")?;
+        let mut seen = HashMap::new();
+        for (index, wave) in manifest.waves.iter().enumerate() {
+            for repo in &wave.repos {
+                if let Some(first) = seen.insert(repo.as_str(), index) {
+                    anyhow::bail!(
+                        "repo '{}' is listed in both wave {} and wave {}",
+                        repo,
+                        first + 1,
+                        index + 1
+                    );
+                }
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Reads and parses a manifest file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest '{}'", path.display()))?;
+        Self::from_toml(&text).with_context(|| format!("Invalid manifest '{}'", path.display()))
+    }
+
+    /// Splits `repos` into the waves to run, in order. Repositories keep their
+    /// relative input order within a wave, and empty waves are dropped.
+    #[must_use]
+    pub fn waves(&self, repos: &[PathBuf]) -> Vec<Vec<PathBuf>> {
+        let wave_of: HashMap<&str, usize> = self
+            .waves
+            .iter()
+            .enumerate()
+            .flat_map(|(index, wave)| wave.repos.iter().map(move |repo| (repo.as_str(), index)))
+            .collect();
+
+        let mut waves = vec![Vec::new(); self.waves.len()];
+        let mut unlisted = Vec::new();
+        for repo in repos {
+            let index = repo
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| wave_of.get(name));
+            match index {
+                Some(&index) => waves[index].push(repo.clone()),
+                None => unlisted.push(repo.clone()),
+            }
+        }
+
+        match self.unlisted {
+            UnlistedPlacement::First => waves.insert(0, unlisted),
+            UnlistedPlacement::Last => waves.push(unlisted),
+        }
+        waves.retain(|wave| !wave.is_empty());
+        waves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names
+            .iter()
+            .map(|name| Path::new("/ws").join(name))
+            .collect()
+    }
+
+    #[test]
+    fn test_waves_follow_manifest_order_with_unlisted_last() {
+        let manifest = Manifest::from_toml(
+            r#"
+            [[wave]]
+            repos = ["web", "api"]
+
+            [[wave]]
+            repos = ["core"]
+            "#,
+        )
+        .unwrap();
+
+        let waves = manifest.waves(&paths(&["api", "core", "docs", "web"]));
+
+        assert_eq!(
+            waves,
+            vec![paths(&["api", "web"]), paths(&["core"]), paths(&["docs"])]
+        );
+    }
+
+    #[test]
+    fn test_waves_put_unlisted_first_and_drop_empty_waves() {
+        let manifest = Manifest::from_toml(
+            r#"
+            unlisted = "first"
+
+            [[wave]]
+            repos = ["missing"]
+
+            [[wave]]
+            repos = ["core"]
+            "#,
+        )
+        .unwrap();
+
+        let waves = manifest.waves(&paths(&["core", "docs"]));
+
+        assert_eq!(waves, vec![paths(&["docs"]), paths(&["core"])]);
+    }
+
+    #[test]
+    fn test_from_toml_rejects_duplicates_and_unknown_keys() {
+        let duplicate = Manifest::from_toml(
+            r#"
+            [[wave]]
+            repos = ["core"]
+
+            [[wave]]
+            repos = ["core"]
+            "#,
+        );
+        assert!(
+            duplicate
+                .unwrap_err()
+                .to_string()
+                .contains("'core' is listed in both wave 1 and wave 2")
+        );
+
+        assert!(Manifest::from_toml("waves = []").is_err());
+    }
+}
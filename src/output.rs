@@ -3,14 +3,23 @@
 //! This module provides visual feedback during repository updates including
 //! spinners, progress bars, and colored summary output.
 
-use crate::config::Config;
-use crate::constants::{DEFAULT_REPO_NAME, MAX_VISIBLE_COMPLETIONS, PROGRESS_TICK_MS};
-use crate::repo::{UpdateCallbacks, UpdateOutcome, UpdateResult, UpdateStep};
+use crate::config::{Config, OutputFormat, ProgressMode, UpdateMode};
+use crate::constants::{
+    DEFAULT_REPO_NAME, DEFAULT_TERMINAL_ROWS, DETACHED_HEAD, MAX_COLLAPSED_FAILURE_SAMPLES,
+    MAX_VISIBLE_COMPLETIONS, PROGRESS_TICK_MS, SECONDS_PER_DAY,
+};
+use crate::repo::{
+    BranchAge, HealthBucket, HealthReport, HostGroup, NotARepoReason, UpdateCallbacks,
+    UpdateOutcome, UpdatePlan, UpdateResult, UpdateStep, UpdateSuccess, WorkspaceStats,
+};
+use crate::report::{PlanReport, RepoReport, ReportStatus, RunReport};
+use anyhow::Context;
 use colored::Colorize;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use std::collections::VecDeque;
+use std::io::Write;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::time::Duration;
 
 /// No-op callbacks for when progress tracking is not needed.
@@ -24,6 +33,216 @@ impl UpdateCallbacks for NoOpCallbacks {
     fn on_complete(&self, _result: &UpdateResult) {}
 }
 
+/// Callbacks that forward every event to several independent observers.
+///
+/// Lets embedders attach, say, a progress bar, a logger and a metrics emitter
+/// without writing a combined type. Observers are called in insertion order;
+/// a repository is updated only if every observer's `should_update` agrees.
+#[derive(Default)]
+pub struct CompositeCallbacks {
+    observers: Vec<Box<dyn UpdateCallbacks>>,
+}
+
+impl CompositeCallbacks {
+    pub fn new(observers: Vec<Box<dyn UpdateCallbacks>>) -> Self {
+        Self { observers }
+    }
+
+    /// Adds another observer after the existing ones.
+    #[must_use]
+    pub fn with(mut self, observer: impl UpdateCallbacks + 'static) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+}
+
+impl UpdateCallbacks for CompositeCallbacks {
+    fn should_update(&self, repo: &Path) -> bool {
+        self.observers
+            .iter()
+            .all(|observer| observer.should_update(repo))
+    }
+
+    fn on_begin(&self, config: &Config) {
+        for observer in &self.observers {
+            observer.on_begin(config);
+        }
+    }
+
+    fn on_update_start(&self, repo_name: &str) {
+        for observer in &self.observers {
+            observer.on_update_start(repo_name);
+        }
+    }
+
+    fn on_step(&self, step: &UpdateStep) {
+        for observer in &self.observers {
+            observer.on_step(step);
+        }
+    }
+
+    fn on_step_execute(&self, step: &UpdateStep) {
+        for observer in &self.observers {
+            observer.on_step_execute(step);
+        }
+    }
+
+    fn on_complete(&self, result: &UpdateResult) {
+        for observer in &self.observers {
+            observer.on_complete(result);
+        }
+    }
+
+    fn on_completion_status(&self, success: bool, error: Option<&str>) {
+        for observer in &self.observers {
+            observer.on_completion_status(success, error);
+        }
+    }
+
+    fn on_skipped(&self, reason: &str) {
+        for observer in &self.observers {
+            observer.on_skipped(reason);
+        }
+    }
+
+    fn on_fetch_progress(&self, received_bytes: u64, total_bytes: Option<u64>) {
+        for observer in &self.observers {
+            observer.on_fetch_progress(received_bytes, total_bytes);
+        }
+    }
+}
+
+/// Callbacks that append each finished repository to a run state file as a
+/// single JSON line (see [`RunState`](crate::report::RunState)).
+///
+/// Every line is flushed as soon as it is written, so the file stays usable
+/// for `--continue-from` even if the run is interrupted.
+pub struct StateFileCallbacks<W: Write + Send> {
+    writer: Arc<Mutex<W>>,
+}
+
+impl<W: Write + Send> StateFileCallbacks<W> {
+    pub fn new(writer: Arc<Mutex<W>>) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write + Send> UpdateCallbacks for StateFileCallbacks<W> {
+    fn on_step(&self, _step: &UpdateStep) {}
+
+    fn on_complete(&self, result: &UpdateResult) {
+        let Ok(line) = serde_json::to_string(&RepoReport::from_result(result)) else {
+            return;
+        };
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        // A failed write must not abort the update; the repo is simply redone next time
+        let _ = writeln!(writer, "{}", line);
+        let _ = writer.flush();
+    }
+}
+
+/// Callbacks that stream update events as newline-delimited JSON (NDJSON).
+///
+/// Each event (`update_start`, `step`, `complete`) is written as a single line
+/// as soon as it happens. Writes go through a shared mutex so events from repos
+/// updated in parallel never interleave within a line. A failed `complete`
+/// event carries `git_exit_code` (null unless a git command failed) and
+/// `disk_full`.
+pub struct NdjsonCallbacks<W: Write + Send> {
+    repo: String,
+    run_id: Option<String>,
+    writer: Arc<Mutex<W>>,
+}
+
+impl<W: Write + Send> NdjsonCallbacks<W> {
+    pub fn new(path: &Path, writer: Arc<Mutex<W>>) -> Self {
+        Self {
+            repo: path.display().to_string(),
+            run_id: None,
+            writer,
+        }
+    }
+
+    /// Tags every emitted event with `run_id` (when `Some`).
+    #[must_use]
+    pub fn with_run_id(mut self, run_id: Option<&str>) -> Self {
+        self.run_id = run_id.map(str::to_string);
+        self
+    }
+
+    fn emit(&self, mut event: serde_json::Value) {
+        if let Some(run_id) = &self.run_id {
+            event["run_id"] = serde_json::Value::String(run_id.clone());
+        }
+        // A panic mid-write leaves at worst a torn line; keep streaming the rest
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        // Output errors (e.g. a closed pipe) must not abort the update itself
+        let _ = writeln!(writer, "{}", event);
+        let _ = writer.flush();
+    }
+}
+
+impl<W: Write + Send> UpdateCallbacks for NdjsonCallbacks<W> {
+    fn on_update_start(&self, _repo_name: &str) {
+        self.emit(serde_json::json!({
+            "event": "update_start",
+            "repo": self.repo,
+        }));
+    }
+
+    fn on_step(&self, step: &UpdateStep) {
+        self.emit(serde_json::json!({
+            "event": "step",
+            "repo": self.repo,
+            "step": format!("{:?}", step),
+        }));
+    }
+
+    fn on_complete(&self, result: &UpdateResult) {
+        let duration_ms = result.duration.as_millis() as u64;
+        let event = match &result.outcome {
+            UpdateOutcome::Success(success) => {
+                let mut event = serde_json::json!({
+                    "event": "complete",
+                    "repo": self.repo,
+                    "status": "success",
+                    "branch": success.original_head.git_ref(),
+                    "had_stash": success.had_stash,
+                    "warnings": success.warnings,
+                    "new_remote_branches": success.new_remote_branches,
+                    "pruned_remote_branches": success.pruned_remote_branches,
+                    "duration_ms": duration_ms,
+                });
+                // Fetch-only updates bring no branch up to date
+                if let Some(master_branch) = &success.master_branch {
+                    event["master_branch"] = serde_json::Value::String(master_branch.clone());
+                }
+                event
+            }
+            UpdateOutcome::Failed(failure) => serde_json::json!({
+                "event": "complete",
+                "repo": self.repo,
+                "status": "failed",
+                "step": format!("{:?}", failure.step),
+                "error": failure.error,
+                "recovery_hint": failure.recovery_hint,
+                "git_exit_code": failure.git_exit_code,
+                "disk_full": failure.disk_full,
+                "duration_ms": duration_ms,
+            }),
+            UpdateOutcome::Skipped(skipped) => serde_json::json!({
+                "event": "complete",
+                "repo": self.repo,
+                "status": "skipped",
+                "step": format!("{:?}", skipped.step),
+                "reason": skipped.reason,
+                "duration_ms": duration_ms,
+            }),
+        };
+        self.emit(event);
+    }
+}
+
 /// Prints a repository header in verbose mode.
 pub fn print_repo_header(config: &Config, repo_name: &str) {
     if !config.is_verbose() {
@@ -50,6 +269,14 @@ pub fn print_completion_status(config: &Config, success: bool, error: Option<&st
     }
 }
 
+/// Prints skip status (verbose mode only).
+pub fn print_skipped_status(config: &Config, reason: &str) {
+    if !config.is_verbose() {
+        return;
+    }
+    eprintln!("{}", build_skipped_status_line(reason));
+}
+
 /// Progress wrapper for single repository updates.
 /// Displays a spinner with step-by-step status messages.
 /// Uses `Option` to avoid allocation when progress is hidden (quiet/verbose modes).
@@ -65,6 +292,17 @@ impl SingleRepoProgress {
         }
     }
 
+    /// Appends the amount of data received so far to the fetching message.
+    pub fn update_fetch_progress(&self, received_bytes: u64) {
+        if let Some(spinner) = &self.spinner {
+            spinner.set_message(format!(
+                "{} {}",
+                format_step_message(&UpdateStep::Fetching),
+                format_bytes(received_bytes).dimmed()
+            ));
+        }
+    }
+
     pub fn finish_success(&self, repo_name: &str) {
         if let Some(spinner) = &self.spinner {
             spinner.finish_with_message(format!(
@@ -80,6 +318,17 @@ impl SingleRepoProgress {
             spinner.finish_with_message(format!("{} {} failed: {}", "✗".red(), repo_name, error));
         }
     }
+
+    pub fn finish_skipped(&self, repo_name: &str, reason: &str) {
+        if let Some(spinner) = &self.spinner {
+            spinner.finish_with_message(format!(
+                "{} {} skipped: {}",
+                "-".yellow(),
+                repo_name,
+                reason
+            ));
+        }
+    }
 }
 
 /// Callbacks for single repository updates.
@@ -109,6 +358,9 @@ impl SingleRepoCallbacks {
             UpdateOutcome::Failed(failure) => {
                 self.progress.finish_failed(repo_name, &failure.error);
             }
+            UpdateOutcome::Skipped(skipped) => {
+                self.progress.finish_skipped(repo_name, &skipped.reason);
+            }
         }
     }
 }
@@ -133,6 +385,14 @@ impl UpdateCallbacks for SingleRepoCallbacks {
     fn on_completion_status(&self, success: bool, error: Option<&str>) {
         print_completion_status(&self.config, success, error);
     }
+
+    fn on_skipped(&self, reason: &str) {
+        print_skipped_status(&self.config, reason);
+    }
+
+    fn on_fetch_progress(&self, received_bytes: u64, _total_bytes: Option<u64>) {
+        self.progress.update_fetch_progress(received_bytes);
+    }
 }
 
 /// Consolidated state for workspace progress tracking.
@@ -147,14 +407,33 @@ struct CompletionState {
     total_completed: usize,
 }
 
+/// One-line-per-repository display of [`ProgressMode::Lines`].
+///
+/// Only repositories being updated need a line, so once `capacity` lines
+/// exist, the line of the oldest successfully finished repository is reused.
+/// Failed repositories keep theirs; with every line taken by a running or
+/// failed repository, further repositories are only counted on the main line.
+struct RepoLines {
+    capacity: usize,
+    lines: Vec<ProgressBar>,
+    /// Lines of finished, non-failed repositories, oldest first.
+    reusable: VecDeque<ProgressBar>,
+}
+
 /// Thread-safe progress tracker for workspace mode.
-/// Shows a progress bar with the completion count and recent results.
+/// Shows a progress bar with the completion count and recent results, a
+/// single "Updating repos: N/M" line in [`ProgressMode::Compact`], or that
+/// line plus one line per repository in [`ProgressMode::Lines`].
 #[derive(Clone)]
 pub struct WorkspaceProgress {
-    _multi: Arc<MultiProgress>,
+    multi: Arc<MultiProgress>,
     main_bar: ProgressBar,
     completion_slots: Vec<ProgressBar>,
+    repo_lines: Arc<Mutex<RepoLines>>,
     state: Arc<Mutex<CompletionState>>,
+    stats: Arc<WorkspaceStats>,
+    mode: ProgressMode,
+    total: usize,
 }
 
 impl WorkspaceProgress {
@@ -163,6 +442,37 @@ impl WorkspaceProgress {
             repo_name: repo_name.to_string(),
             workspace: self.clone(),
             config,
+            line: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Hands out a line for a repository that is starting, if one is free.
+    fn acquire_line(&self) -> Option<ProgressBar> {
+        let mut repo_lines = self
+            .repo_lines
+            .lock()
+            .expect("WorkspaceProgress lines mutex poisoned");
+        if repo_lines.lines.len() < repo_lines.capacity {
+            let line = self.multi.add(ProgressBar::new_spinner());
+            line.set_style(
+                ProgressStyle::default_spinner()
+                    .template("  {msg}")
+                    .unwrap(),
+            );
+            repo_lines.lines.push(line.clone());
+            return Some(line);
+        }
+        repo_lines.reusable.pop_front()
+    }
+
+    /// Lets a finished repository's line be reused, unless it failed.
+    fn release_line(&self, line: ProgressBar, failed: bool) {
+        if !failed {
+            self.repo_lines
+                .lock()
+                .expect("WorkspaceProgress lines mutex poisoned")
+                .reusable
+                .push_back(line);
         }
     }
 
@@ -174,13 +484,25 @@ impl WorkspaceProgress {
             .lock()
             .expect("WorkspaceProgress state mutex poisoned");
 
+        state.total_completed += 1;
         if !success {
             state.failed_count += 1;
+        }
+
+        if matches!(self.mode, ProgressMode::Compact | ProgressMode::Lines) {
+            self.main_bar.set_message(build_compact_progress_line(
+                state.total_completed,
+                self.total,
+                state.failed_count,
+            ));
+            return;
+        }
+
+        if !success {
             self.main_bar
                 .set_message(format!("│ {} failed", state.failed_count).red().to_string());
         }
 
-        state.total_completed += 1;
         state.repos.push_back((repo_name.to_string(), success));
 
         while state.repos.len() > MAX_VISIBLE_COMPLETIONS {
@@ -195,6 +517,13 @@ impl WorkspaceProgress {
         for slot in &self.completion_slots {
             slot.finish_and_clear();
         }
+        let repo_lines = self
+            .repo_lines
+            .lock()
+            .expect("WorkspaceProgress lines mutex poisoned");
+        for line in &repo_lines.lines {
+            line.finish_and_clear();
+        }
     }
 
     fn redraw_completions(&self, state: &CompletionState) {
@@ -224,34 +553,70 @@ pub struct RepoProgressTracker {
     repo_name: String,
     workspace: WorkspaceProgress,
     config: Config,
+    /// This repository's line in [`ProgressMode::Lines`], while it has one.
+    line: Arc<Mutex<Option<ProgressBar>>>,
+}
+
+impl RepoProgressTracker {
+    fn set_line(&self, message: String) {
+        if let Some(line) = self
+            .line
+            .lock()
+            .expect("tracker line mutex poisoned")
+            .as_ref()
+        {
+            line.set_message(message);
+        }
+    }
 }
 
 impl UpdateCallbacks for RepoProgressTracker {
     fn on_update_start(&self, repo_name: &str) {
         print_repo_header(&self.config, repo_name);
+        if let Some(line) = self.workspace.acquire_line() {
+            line.set_message(build_repo_step_line(&self.repo_name, &UpdateStep::Started));
+            *self.line.lock().expect("tracker line mutex poisoned") = Some(line);
+        }
     }
 
-    fn on_step(&self, _step: &UpdateStep) {}
+    fn on_step(&self, step: &UpdateStep) {
+        self.set_line(build_repo_step_line(&self.repo_name, step));
+    }
 
     fn on_step_execute(&self, step: &UpdateStep) {
         print_step(&self.config, step);
     }
 
     fn on_complete(&self, result: &UpdateResult) {
-        let success = matches!(result.outcome, UpdateOutcome::Success(_));
+        // Skipped repos are not failures
+        let success = !matches!(result.outcome, UpdateOutcome::Failed(_));
+        self.workspace.stats.record(result);
         self.workspace.mark_completed(&self.repo_name, success);
+        if let Some(line) = self
+            .line
+            .lock()
+            .expect("tracker line mutex poisoned")
+            .take()
+        {
+            line.set_message(build_repo_done_line(&self.repo_name, &result.outcome));
+            self.workspace.release_line(line, !success);
+        }
     }
 
     fn on_completion_status(&self, success: bool, error: Option<&str>) {
         print_completion_status(&self.config, success, error);
     }
+
+    fn on_skipped(&self, reason: &str) {
+        print_skipped_status(&self.config, reason);
+    }
 }
 
 /// Creates a spinner-based progress tracker for single repository updates.
-/// Returns `None` in quiet or verbose mode to avoid allocation.
+/// Returns `None` when progress is hidden (quiet, verbose, NDJSON) to avoid allocation.
 #[must_use]
 pub fn create_single_repo_progress(config: &Config) -> SingleRepoProgress {
-    let spinner = if config.is_quiet() || config.is_verbose() {
+    let spinner = if config.hides_progress() {
         None
     } else {
         let spinner = ProgressBar::new_spinner();
@@ -269,14 +634,42 @@ pub fn create_single_repo_progress(config: &Config) -> SingleRepoProgress {
 }
 
 /// Creates a progress bar for workspace updates showing completion count.
-/// Returns hidden progress bars in quiet or verbose mode.
+/// Returns hidden progress bars when progress is hidden (quiet, verbose, NDJSON).
+/// Each tracker records its finished repository into `stats`.
 #[must_use]
-pub fn create_workspace_progress(total: usize, config: &Config) -> WorkspaceProgress {
+pub fn create_workspace_progress(
+    total: usize,
+    config: &Config,
+    stats: Arc<WorkspaceStats>,
+) -> WorkspaceProgress {
+    let rows = console::Term::stderr()
+        .size_checked()
+        .map_or(DEFAULT_TERMINAL_ROWS, |(rows, _)| usize::from(rows));
+    // Leave room for the main line and the shell prompt
+    build_workspace_progress(total, config, stats, rows.saturating_sub(2).max(1))
+}
+
+/// Builds the workspace progress display, with at most `max_repo_lines`
+/// per-repository lines in [`ProgressMode::Lines`].
+fn build_workspace_progress(
+    total: usize,
+    config: &Config,
+    stats: Arc<WorkspaceStats>,
+    max_repo_lines: usize,
+) -> WorkspaceProgress {
     let multi = Arc::new(MultiProgress::new());
-    let hide_progress = config.is_quiet() || config.is_verbose();
+    let hide_progress = config.hides_progress();
+
+    let compact = matches!(config.progress, ProgressMode::Compact | ProgressMode::Lines);
 
     let main_bar = if hide_progress {
         ProgressBar::hidden()
+    } else if compact {
+        // No bar or spinner: the line only changes when a repository finishes
+        let bar = multi.add(ProgressBar::new(total as u64));
+        bar.set_style(ProgressStyle::default_bar().template("{msg}").unwrap());
+        bar.set_message(build_compact_progress_line(0, total, 0));
+        bar
     } else {
         let bar = multi.add(ProgressBar::new(total as u64));
         bar.set_style(
@@ -289,7 +682,7 @@ pub fn create_workspace_progress(total: usize, config: &Config) -> WorkspaceProg
         bar
     };
 
-    let completion_slots: Vec<ProgressBar> = if hide_progress {
+    let completion_slots: Vec<ProgressBar> = if hide_progress || compact {
         vec![]
     } else {
         (0..MAX_VISIBLE_COMPLETIONS)
@@ -305,40 +698,172 @@ pub fn create_workspace_progress(total: usize, config: &Config) -> WorkspaceProg
             .collect()
     };
 
+    let repo_line_capacity = if hide_progress || config.progress != ProgressMode::Lines {
+        0
+    } else {
+        total.min(max_repo_lines)
+    };
+
     WorkspaceProgress {
-        _multi: multi,
+        multi,
         main_bar,
         completion_slots,
+        repo_lines: Arc::new(Mutex::new(RepoLines {
+            capacity: repo_line_capacity,
+            lines: Vec::new(),
+            reusable: VecDeque::new(),
+        })),
         state: Arc::new(Mutex::new(CompletionState {
             repos: VecDeque::new(),
             failed_count: 0,
             total_completed: 0,
         })),
+        stats,
+        mode: config.progress,
+        total,
     }
 }
 
 pub fn print_working_dir(path: &Path, config: &Config) {
-    if config.is_quiet() {
+    if config.is_quiet() || config.has_machine_readable_stdout() || config.defers_output() {
         return;
     }
     println!("{}", build_working_dir_line(path));
 }
 
 pub fn print_workspace_start(count: usize, config: &Config) {
-    if config.is_quiet() {
+    if config.is_quiet() || config.has_machine_readable_stdout() || config.defers_output() {
         return;
     }
     println!("{}", build_workspace_start_line(count));
 }
 
-pub fn print_summary(results: &[UpdateResult], duration: Duration, config: &Config) {
-    if config.is_quiet() {
+/// Prints how many repositories a resumed run skips as already updated.
+pub fn print_resume_skipped(count: usize, config: &Config) {
+    if count == 0
+        || config.is_quiet()
+        || config.has_machine_readable_stdout()
+        || config.defers_output()
+    {
+        return;
+    }
+    println!("{}", build_resume_skipped_line(count));
+}
+
+/// Prints to stderr that filtering left none of the `found` repositories.
+pub fn print_nothing_matched(found: usize, reason: &str) {
+    eprintln!("{}", build_nothing_matched_line(found, reason));
+}
+
+fn build_nothing_matched_line(found: usize, reason: &str) -> String {
+    format!("No repositories matched ({} found, {})", found, reason)
+        .yellow()
+        .to_string()
+}
+
+/// Prints warnings to stderr, one per line.
+pub fn print_warnings(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("{} {}", "warning:".yellow(), warning);
+    }
+}
+
+/// Prints repository paths one per line, with no decoration (for `--list-repos`).
+pub fn print_repo_list(repos: &[std::path::PathBuf]) {
+    for repo in repos {
+        println!("{}", repo.display());
+    }
+}
+
+/// Prints the plans as one JSON array on stdout (`--dry-run-json`).
+pub fn print_plan_reports(plans: &[PlanReport]) -> anyhow::Result<()> {
+    let json = serde_json::to_string_pretty(plans).context("Failed to serialize update plans")?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Prints a health scan grouped by bucket, then the repositories whose default
+/// branch is older than `config.stale_after()`, followed by a one-line tally.
+pub fn print_health_report(report: &HealthReport, config: &Config) {
+    print!("{}", build_health_report(report, config.stale_after()));
+}
+
+/// Prints per-host update counts after the summary (`--group-by-host`).
+pub fn print_host_groups(groups: &[HostGroup]) {
+    print!("{}", build_host_groups(groups));
+}
+
+/// Explains which directories discovery ignored and why (verbose mode only).
+pub fn print_rejected_dirs(rejected: &[(std::path::PathBuf, NotARepoReason)], config: &Config) {
+    if !config.is_verbose() {
+        return;
+    }
+    for (path, reason) in rejected {
+        eprintln!("{}", build_rejected_dir_line(path, reason));
+    }
+}
+
+/// Prints the steps an update of `path` is about to run (`--explain`).
+/// `repo_count` is the number of repositories in the run, of which `path` is the first.
+pub fn print_update_plan(path: &Path, plan: &UpdatePlan, repo_count: usize) {
+    eprint!("{}", build_update_plan(path, plan, repo_count));
+}
+
+pub fn print_summary(
+    results: &[UpdateResult],
+    duration: Duration,
+    stats: &WorkspaceStats,
+    config: &Config,
+) {
+    if config.ndjson {
+        // Every result was already streamed as a `complete` event
+        return;
+    }
+    if config.format != OutputFormat::Human {
+        if let Err(e) = write_formatted(results, config, &mut std::io::stdout()) {
+            eprintln!("{} {:#}", "✗".red(), e);
+        }
+    } else if config.is_quiet() {
         print_quiet_summary(results);
     } else {
-        print_normal_summary(results, duration);
+        print_normal_summary(results, duration, stats, config);
+        if config.is_verbose()
+            && let Some(run_id) = &config.run_id
+        {
+            println!("{}", build_run_id_line(run_id));
+        }
     }
 }
 
+/// Writes the results in `config.format`. The human format has no single
+/// writer (it splits between stdout and stderr) and writes nothing here.
+pub fn write_formatted<W: Write>(
+    results: &[UpdateResult],
+    config: &Config,
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    let output = match config.format {
+        OutputFormat::Human => return Ok(()),
+        OutputFormat::Json => {
+            let report = RunReport::from_results(results)
+                .with_run_id(config.run_id.as_deref())
+                .with_resumed(config.resumed);
+            format!("{}\n", report.to_json()?)
+        }
+        OutputFormat::Csv => build_csv(results),
+        OutputFormat::Markdown => build_markdown(results),
+        OutputFormat::Porcelain => build_porcelain(results),
+    };
+    writer.write_all(output.as_bytes())?;
+    Ok(())
+}
+
+/// Writes the results as a markdown table (one row per repository) followed
+/// by a summary line, ready to paste into an issue or chat message.
+pub fn write_markdown<W: Write>(results: &[UpdateResult], writer: &mut W) -> std::io::Result<()> {
+    writer.write_all(build_markdown(results).as_bytes())
+}
+
 fn print_quiet_summary(results: &[UpdateResult]) {
     let (stdout_line, stderr_lines) = build_quiet_summary(results);
     println!("{}", stdout_line);
@@ -347,8 +872,13 @@ fn print_quiet_summary(results: &[UpdateResult]) {
     }
 }
 
-fn print_normal_summary(results: &[UpdateResult], duration: Duration) {
-    let output = build_normal_summary(results, duration);
+fn print_normal_summary(
+    results: &[UpdateResult],
+    duration: Duration,
+    stats: &WorkspaceStats,
+    config: &Config,
+) {
+    let output = build_normal_summary(results, duration, stats, config);
     print!("{}", output);
 }
 
@@ -368,6 +898,10 @@ fn build_completion_status_line(success: bool, error: Option<&str>) -> Option<St
     }
 }
 
+fn build_skipped_status_line(reason: &str) -> String {
+    format!("  {} skipped: {}", "-".yellow(), reason)
+}
+
 fn build_working_dir_line(path: &Path) -> String {
     format!(
         "{} {}",
@@ -386,24 +920,128 @@ fn build_workspace_start_line(count: usize) -> String {
     }
 }
 
+fn build_resume_skipped_line(count: usize) -> String {
+    format!(
+        "Resuming: skipping {} already updated {}",
+        count,
+        if count == 1 {
+            "repository"
+        } else {
+            "repositories"
+        }
+    )
+    .dimmed()
+    .to_string()
+}
+
+fn build_update_plan(path: &Path, plan: &UpdatePlan, repo_count: usize) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_else(|| DEFAULT_REPO_NAME.into());
+    let head = if plan.branch == DETACHED_HEAD {
+        "detached HEAD".to_string()
+    } else {
+        format!("on {}", plan.branch)
+    };
+    let changes = match plan.dirty_files {
+        0 => "no uncommitted changes".to_string(),
+        1 => "1 uncommitted file".to_string(),
+        n => format!("{} uncommitted files", n),
+    };
+    let mut output = format!("{} {} ({}, {}):\n", "Plan for".bold(), name, head, changes);
+    for (number, step) in plan.steps.iter().enumerate() {
+        let description = format_step_message(step).trim_end_matches("...");
+        output.push_str(&format!("  {}. {}\n", number + 1, description));
+    }
+    if let Some(reason) = &plan.skip_reason {
+        output.push_str(&format!("  {} {}\n", "then skip:".yellow(), reason));
+    }
+    if let Some(reason) = &plan.fail_reason {
+        output.push_str(&format!("  {} {}\n", "then fail:".red(), reason));
+    }
+    if repo_count > 1 {
+        output.push_str(&format!(
+            "{}\n",
+            format!(
+                "  (first of {} repos; each follows these rules for its own state)",
+                repo_count
+            )
+            .dimmed()
+        ));
+    }
+    output.push('\n');
+    output
+}
+
+fn build_rejected_dir_line(path: &Path, reason: &NotARepoReason) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_else(|| path.to_string_lossy());
+    format!("  {} {}: {}", "ignored".dimmed(), name, reason)
+}
+
+fn build_compact_progress_line(completed: usize, total: usize, failed: usize) -> String {
+    let mut line = format!("Updating repos: {}/{}", completed, total);
+    if failed > 0 {
+        line.push_str(&format!(" ({} failed)", failed));
+    }
+    line
+}
+
+/// A repository's line in [`ProgressMode::Lines`] while it is being updated.
+fn build_repo_step_line(repo_name: &str, step: &UpdateStep) -> String {
+    format!(
+        "{} {}  {}",
+        "·".cyan(),
+        repo_name,
+        format_step_message(step).trim_end_matches("...").dimmed()
+    )
+}
+
+/// A repository's line in [`ProgressMode::Lines`] once it is done.
+fn build_repo_done_line(repo_name: &str, outcome: &UpdateOutcome) -> String {
+    match outcome {
+        UpdateOutcome::Success(_) => format!("{} {}", "✓".green(), repo_name),
+        UpdateOutcome::Failed(failure) => format!(
+            "{} {}  {}",
+            "✗".red(),
+            repo_name,
+            format!("failed at {:?}", failure.step).red()
+        ),
+        UpdateOutcome::Skipped(_) => {
+            format!("{} {}  {}", "-".yellow(), repo_name, "skipped".yellow())
+        }
+    }
+}
+
 fn build_no_repos_line() -> String {
     "No git repositories found".yellow().bold().to_string()
 }
 
 fn build_quiet_summary(results: &[UpdateResult]) -> (String, Vec<String>) {
-    let (successes, failures): (Vec<_>, Vec<_>) = results
-        .iter()
-        .partition(|r| matches!(r.outcome, UpdateOutcome::Success(_)));
-
-    let stdout_line = format!("{}/{} repositories updated", successes.len(), results.len());
-    let stderr_lines = failures
+    let groups = group_results(results);
+
+    let mut stdout_line = format!(
+        "{}/{} repositories updated",
+        groups.successes.len(),
+        results.len()
+    );
+    if !groups.skipped.is_empty() {
+        stdout_line.push_str(&format!(", {} skipped", groups.skipped.len()));
+    }
+    let stderr_lines = groups
+        .failures
         .iter()
         .filter_map(|result| match &result.outcome {
-            UpdateOutcome::Failed(failure) => Some(format!(
-                "error: {}: {}",
-                result.path.display(),
-                failure.error
-            )),
+            UpdateOutcome::Failed(failure) => {
+                let mut line = format!("error: {}: {}", result.path.display(), failure.error);
+                if let Some(hint) = &failure.recovery_hint {
+                    line.push_str(&format!("\n  {}", hint));
+                }
+                Some(line)
+            }
             _ => None,
         })
         .collect();
@@ -411,46 +1049,440 @@ fn build_quiet_summary(results: &[UpdateResult]) -> (String, Vec<String>) {
     (stdout_line, stderr_lines)
 }
 
-fn build_normal_summary(results: &[UpdateResult], duration: Duration) -> String {
+/// Results split by outcome, preserving input order within each group.
+struct GroupedResults<'a> {
+    successes: Vec<&'a UpdateResult>,
+    failures: Vec<&'a UpdateResult>,
+    skipped: Vec<&'a UpdateResult>,
+}
+
+fn group_results(results: &[UpdateResult]) -> GroupedResults<'_> {
+    let mut groups = GroupedResults {
+        successes: Vec::new(),
+        failures: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for result in results {
+        match result.outcome {
+            UpdateOutcome::Success(_) => groups.successes.push(result),
+            UpdateOutcome::Failed(_) => groups.failures.push(result),
+            UpdateOutcome::Skipped(_) => groups.skipped.push(result),
+        }
+    }
+    groups
+}
+
+fn build_normal_summary(
+    results: &[UpdateResult],
+    duration: Duration,
+    stats: &WorkspaceStats,
+    config: &Config,
+) -> String {
     let mut output = String::new();
     output.push_str(&build_section("Summary"));
 
-    let (successes, failures): (Vec<_>, Vec<_>) = results
-        .iter()
-        .partition(|r| matches!(r.outcome, UpdateOutcome::Success(_)));
+    let groups = group_results(results);
 
-    output.push_str(&build_success_lines(&successes));
-    output.push_str(&build_failure_lines(&failures));
+    output.push_str(&build_success_lines(&groups.successes, config));
+    output.push_str(&build_warning_lines(&groups.successes));
+    output.push_str(&build_skipped_lines(&groups.skipped));
+    if config.collapse_failures {
+        output.push_str(&build_collapsed_failure_lines(
+            &groups.failures,
+            config.is_verbose(),
+        ));
+    } else {
+        output.push_str(&build_failure_lines(&groups.failures));
+    }
     output.push_str(&format!(
         "{}: {}/{} repos in {}",
         "Total".white().bold(),
-        successes.len(),
+        groups.successes.len(),
         results.len(),
         format_duration(duration)
     ));
+    output.push_str(&build_totals_suffix(stats));
     output.push('\n');
 
     output
 }
 
-fn format_duration(duration: Duration) -> String {
-    format!("{:.2}s", duration.as_secs_f32())
-}
-
-fn build_section(title: &str) -> String {
-    let line = "=".repeat(50).cyan().dimmed();
-    let padding = (50 - title.len()) / 2;
-    let centered = format!("{:>width$}", title, width = padding + title.len());
-    format!("\n{}\n{}\n{}\n\n", line, centered.cyan().bold(), line)
+fn build_host_groups(groups: &[HostGroup]) -> String {
+    if groups.is_empty() {
+        return String::new();
+    }
+    let mut output = build_section("By Host");
+    let width = groups
+        .iter()
+        .map(|group| group.host.len())
+        .max()
+        .unwrap_or(0);
+    for group in groups {
+        let mut counts = vec![format!("{} updated", group.succeeded)];
+        if group.failed > 0 {
+            counts.push(format!("{} failed", group.failed));
+        }
+        if group.skipped > 0 {
+            counts.push(format!("{} skipped", group.skipped));
+        }
+        let line = format!("  {:<width$}  {}", group.host, counts.join(", "));
+        let line = if group.failed == group.total() {
+            line.as_str().red().to_string()
+        } else if group.failed > 0 {
+            line.as_str().yellow().to_string()
+        } else {
+            line
+        };
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output
 }
 
-fn build_success_lines(successes: &[&UpdateResult]) -> String {
-    let mut output = String::new();
-    if successes.is_empty() {
-        return output;
+fn build_health_report(report: &HealthReport, stale_after: Duration) -> String {
+    let mut output = build_section("Health");
+    let mut tally = Vec::new();
+    for bucket in HealthBucket::ALL {
+        let repos = report.in_bucket(bucket);
+        if repos.is_empty() {
+            continue;
+        }
+        let attention = if bucket == HealthBucket::Diverged {
+            " — needs attention"
+        } else {
+            ""
+        };
+        let heading = format!(
+            "{} ({}){}:",
+            capitalize(&bucket.to_string()),
+            repos.len(),
+            attention
+        );
+        let heading = match bucket {
+            HealthBucket::CleanCurrent => heading.green().bold(),
+            HealthBucket::Diverged | HealthBucket::NoRemote => heading.red().bold(),
+            _ => heading.yellow().bold(),
+        };
+        output.push_str(&format!("{}\n", heading));
+        for repo in &repos {
+            let ages: Vec<String> = report
+                .default_branch_age(repo)
+                .map(format_branch_age)
+                .into_iter()
+                .chain(report.fetch_age(repo).map(format_fetch_age))
+                .collect();
+            let ages = if ages.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", ages.join(", ")).dimmed().to_string()
+            };
+            output.push_str(&format!("  {}{}\n", repo.display(), ages));
+        }
+        output.push('\n');
+        tally.push(format!(
+            "{} {}{}",
+            repos.len(),
+            health_tally_label(bucket),
+            attention
+        ));
     }
-    output.push_str(&format!(
-        "{}",
+    let stale = report.stale(stale_after);
+    if !stale.is_empty() {
+        output.push_str(&format!(
+            "{}\n",
+            format!(
+                "Stale ({}) — default branch older than {}:",
+                stale.len(),
+                format_days(stale_after)
+            )
+            .yellow()
+            .bold()
+        ));
+        for (repo, age) in &stale {
+            output.push_str(&format!(
+                "  {} ({})\n",
+                repo.display(),
+                format_branch_age(age)
+            ));
+        }
+        output.push('\n');
+        tally.push(format!("{} stale", stale.len()));
+    }
+    if !report.stale_stashes.is_empty() {
+        output.push_str(&format!(
+            "{}\n",
+            format!(
+                "Stale stashes ({}) — the next update that needs to stash will refuse:",
+                report.stale_stashes.len()
+            )
+            .red()
+            .bold()
+        ));
+        for (repo, stashes) in &report.stale_stashes {
+            output.push_str(&format!(
+                "  {}: {}\n",
+                repo.display(),
+                stashes.selectors.join(", ")
+            ));
+        }
+        output.push('\n');
+        tally.push(format!("{} with stale stashes", report.stale_stashes.len()));
+    }
+    if !report.errors.is_empty() {
+        output.push_str(&format!(
+            "{}\n",
+            format!("Could not scan ({}):", report.errors.len())
+                .red()
+                .bold()
+        ));
+        for (path, error) in &report.errors {
+            output.push_str(&format!("  {}: {}\n", path.display(), error.red()));
+        }
+        output.push('\n');
+        tally.push(format!("{} not scanned", report.errors.len()));
+    }
+    output.push_str(&format!(
+        "{}: {} repos",
+        "Total".white().bold(),
+        report.repos.len() + report.errors.len()
+    ));
+    if !tally.is_empty() {
+        output.push_str(&format!(" — {}", tally.join(", ")));
+    }
+    output.push('\n');
+    output
+}
+
+/// e.g. `main: 12 days ago`
+fn format_branch_age(age: &BranchAge) -> String {
+    if age.age.as_secs() < SECONDS_PER_DAY {
+        return format!("{}: today", age.branch);
+    }
+    format!("{}: {} ago", age.branch, format_days(age.age))
+}
+
+/// e.g. `fetched 30 days ago`
+fn format_fetch_age(age: Duration) -> String {
+    if age.as_secs() < SECONDS_PER_DAY {
+        return "fetched today".to_string();
+    }
+    format!("fetched {} ago", format_days(age))
+}
+
+/// Whole days in `duration`, e.g. `1 day` or `180 days`.
+fn format_days(duration: Duration) -> String {
+    match duration.as_secs() / SECONDS_PER_DAY {
+        1 => "1 day".to_string(),
+        days => format!("{} days", days),
+    }
+}
+
+fn health_tally_label(bucket: HealthBucket) -> &'static str {
+    match bucket {
+        HealthBucket::CleanCurrent => "current",
+        HealthBucket::CleanBehind => "behind",
+        HealthBucket::Dirty => "dirty",
+        HealthBucket::Detached => "detached",
+        HealthBucket::Diverged => "diverged",
+        HealthBucket::NoRemote => "no remote",
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn build_markdown(results: &[UpdateResult]) -> String {
+    let mut output = String::from("| Repo | Status | Branch | Commits | Time |\n");
+    output.push_str("|------|--------|--------|---------|------|\n");
+    for result in results {
+        let name = result
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_else(|| result.path.to_string_lossy());
+        let (status, branch, commits) = match &result.outcome {
+            UpdateOutcome::Success(success) => (
+                "✅ updated".to_string(),
+                // Brackets would read as link syntax in markdown
+                success
+                    .original_head
+                    .display()
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .to_string(),
+                success.commits_pulled.to_string(),
+            ),
+            UpdateOutcome::Failed(failure) => (
+                format!("❌ failed: {}", failure.error),
+                "-".to_string(),
+                "-".to_string(),
+            ),
+            UpdateOutcome::Skipped(skipped) => (
+                format!("⏭️ skipped: {}", skipped.reason),
+                "-".to_string(),
+                "-".to_string(),
+            ),
+        };
+        output.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            escape_markdown_cell(&name),
+            escape_markdown_cell(&status),
+            escape_markdown_cell(&branch),
+            commits,
+            format_duration(result.duration)
+        ));
+    }
+
+    let groups = group_results(results);
+    output.push_str(&format!(
+        "\n**{}/{} repositories updated**",
+        groups.successes.len(),
+        results.len()
+    ));
+    if !groups.skipped.is_empty() {
+        output.push_str(&format!(", {} skipped", groups.skipped.len()));
+    }
+    if !groups.failures.is_empty() {
+        output.push_str(&format!(", {} failed", groups.failures.len()));
+    }
+    output.push('\n');
+    output
+}
+
+/// One row per repository under a header, with the fields of its
+/// [`RepoReport`]; fields are quoted as RFC 4180 requires.
+fn build_csv(results: &[UpdateResult]) -> String {
+    let mut output = String::from("path,status,branch,commits_pulled,step,error,duration_ms\n");
+    for result in results {
+        let report = RepoReport::from_result(result);
+        let fields = [
+            report.path.to_string_lossy().into_owned(),
+            report_status_name(report.status).to_string(),
+            report.branch.unwrap_or_default(),
+            report.commits_pulled.to_string(),
+            report.step.unwrap_or_default(),
+            report.error.unwrap_or_default(),
+            report.duration_ms.to_string(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| escape_csv_field(field)).collect();
+        output.push_str(&fields.join(","));
+        output.push('\n');
+    }
+    output
+}
+
+fn escape_csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One tab-separated line per repository: status, path, branch, commits
+/// pulled, then the error or skip reason. Missing values are `-`, and tabs
+/// and line breaks inside values become spaces, so each repository is
+/// always exactly one line of five fields.
+fn build_porcelain(results: &[UpdateResult]) -> String {
+    let mut output = String::new();
+    for result in results {
+        let report = RepoReport::from_result(result);
+        let fields = [
+            report_status_name(report.status).to_string(),
+            report.path.to_string_lossy().into_owned(),
+            report.branch.unwrap_or_else(|| "-".to_string()),
+            report.commits_pulled.to_string(),
+            report.error.unwrap_or_else(|| "-".to_string()),
+        ];
+        let fields: Vec<String> = fields
+            .iter()
+            .map(|field| field.replace(['\t', '\n', '\r'], " "))
+            .collect();
+        output.push_str(&fields.join("\t"));
+        output.push('\n');
+    }
+    output
+}
+
+/// The lowercase status name used in JSON reports, also used by the CSV
+/// and porcelain formats.
+fn report_status_name(status: ReportStatus) -> &'static str {
+    match status {
+        ReportStatus::Success => "success",
+        ReportStatus::Failed => "failed",
+        ReportStatus::Skipped => "skipped",
+    }
+}
+
+/// Keeps pipes and line breaks in a value from breaking the table layout.
+fn escape_markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+fn build_run_id_line(run_id: &str) -> String {
+    format!("Run ID: {}", run_id).dimmed().to_string()
+}
+
+fn build_totals_suffix(stats: &WorkspaceStats) -> String {
+    let mut suffix = format!(", {} commits pulled", stats.commits_pulled());
+    if stats.files_stashed() > 0 {
+        suffix.push_str(&format!(", {} files stashed", stats.files_stashed()));
+    }
+    suffix.dimmed().to_string()
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{:.2} {}", value, unit)
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2}s", duration.as_secs_f32())
+}
+
+fn build_section(title: &str) -> String {
+    let line = "=".repeat(50).cyan().dimmed();
+    let padding = (50 - title.len()) / 2;
+    let centered = format!("{:>width$}", title, width = padding + title.len());
+    format!("\n{}\n{}\n{}\n\n", line, centered.cyan().bold(), line)
+}
+
+/// Names the default branch an update resolved to, e.g. ` → main`.
+///
+/// Empty outside default-branch mode, where no default branch is involved.
+fn default_branch_label(success: &UpdateSuccess, config: &Config) -> String {
+    match &success.master_branch {
+        Some(branch) if config.update_mode == UpdateMode::DefaultBranch => {
+            format!(" → {}", branch)
+        }
+        _ => String::new(),
+    }
+}
+
+fn build_success_lines(successes: &[&UpdateResult], config: &Config) -> String {
+    let mut output = String::new();
+    if successes.is_empty() {
+        return output;
+    }
+    output.push_str(&format!(
+        "{}",
         format!("Succeeded ({}):", successes.len()).green().bold()
     ));
     output.push('\n');
@@ -459,44 +1491,112 @@ fn build_success_lines(successes: &[&UpdateResult]) -> String {
         if let UpdateOutcome::Success(success) = &result.outcome {
             let stash_msg = if success.had_stash {
                 " (stash restored)".yellow()
+            } else if success.fast_skipped {
+                " (remote unchanged, not touched)".dimmed()
             } else {
                 "".normal()
             };
             output.push_str(&format!(
-                "  {} {} {} {} in {}",
+                "  {} {} {}{} {} in {}",
                 "OK".green().bold(),
                 result.path.display().to_string().white(),
                 success.original_head.display().cyan(),
+                default_branch_label(success, config).cyan(),
                 stash_msg,
                 format_duration(result.duration).dimmed(),
             ));
+            if let Some(describe) = &success.describe {
+                output.push_str(&format!(" {}", format!("@ {}", describe).dimmed()));
+            }
             output.push('\n');
+            if !success.new_remote_branches.is_empty() {
+                output.push_str(&format!(
+                    "     {}\n",
+                    format!(
+                        "new remote branches: {}",
+                        success.new_remote_branches.join(", ")
+                    )
+                    .dimmed()
+                ));
+            }
+            if !success.pruned_remote_branches.is_empty() {
+                output.push_str(&format!(
+                    "     {}\n",
+                    format!(
+                        "pruned {} stale remote {}: {}",
+                        success.pruned_remote_branches.len(),
+                        if success.pruned_remote_branches.len() == 1 {
+                            "branch"
+                        } else {
+                            "branches"
+                        },
+                        success.pruned_remote_branches.join(", ")
+                    )
+                    .dimmed()
+                ));
+            }
         }
     }
     output.push('\n');
     output
 }
 
-fn build_failure_lines(failures: &[&UpdateResult]) -> String {
+/// Lists the repositories that succeeded with warnings, so non-fatal issues
+/// are not buried among the successes. Warnings never affect the exit code.
+fn build_warning_lines(successes: &[&UpdateResult]) -> String {
+    let warned: Vec<_> = successes
+        .iter()
+        .filter_map(|result| match &result.outcome {
+            UpdateOutcome::Success(success) if !success.warnings.is_empty() => {
+                Some((result, &success.warnings))
+            }
+            _ => None,
+        })
+        .collect();
     let mut output = String::new();
-    if failures.is_empty() {
+    if warned.is_empty() {
         return output;
     }
 
     output.push_str(&format!(
         "{}",
-        format!("Failed ({}):", failures.len()).red().bold()
+        format!("Warnings ({}):", warned.len()).yellow().bold()
     ));
     output.push('\n');
 
-    for result in failures {
-        if let UpdateOutcome::Failed(failure) = &result.outcome {
+    for (result, warnings) in warned {
+        output.push_str(&format!(
+            "  {} {}\n",
+            "WARN".yellow().bold(),
+            result.path.display().to_string().white(),
+        ));
+        for warning in warnings {
+            output.push_str(&format!("     {}\n", warning.yellow()));
+        }
+    }
+    output.push('\n');
+    output
+}
+
+fn build_skipped_lines(skipped: &[&UpdateResult]) -> String {
+    let mut output = String::new();
+    if skipped.is_empty() {
+        return output;
+    }
+
+    output.push_str(&format!(
+        "{}",
+        format!("Skipped ({}):", skipped.len()).yellow().bold()
+    ));
+    output.push('\n');
+
+    for result in skipped {
+        if let UpdateOutcome::Skipped(skip) = &result.outcome {
             output.push_str(&format!(
-                "  {} {} {} in {}",
-                "FAIL".red().bold(),
+                "  {} {} {}",
+                "SKIP".yellow().bold(),
                 result.path.display().to_string().white(),
-                format!("at {:?}: {}", failure.step, failure.error).red(),
-                format_duration(result.duration).dimmed(),
+                skip.reason.yellow(),
             ));
             output.push('\n');
         }
@@ -505,6 +1605,140 @@ fn build_failure_lines(failures: &[&UpdateResult]) -> String {
     output
 }
 
+fn build_failure_lines(failures: &[&UpdateResult]) -> String {
+    let mut output = String::new();
+    if failures.is_empty() {
+        return output;
+    }
+
+    output.push_str(&format!(
+        "{}",
+        format!("Failed ({}):", failures.len()).red().bold()
+    ));
+    output.push('\n');
+
+    for result in failures {
+        output.push_str(&build_failure_line(result));
+    }
+    output.push('\n');
+    output
+}
+
+fn build_failure_line(result: &UpdateResult) -> String {
+    let mut output = String::new();
+    if let UpdateOutcome::Failed(failure) = &result.outcome {
+        output.push_str(&format!(
+            "  {} {} {} in {}",
+            "FAIL".red().bold(),
+            result.path.display().to_string().white(),
+            format!("at {:?}: {}", failure.step, failure.error).red(),
+            format_duration(result.duration).dimmed(),
+        ));
+        output.push('\n');
+        if let Some(hint) = &failure.recovery_hint {
+            output.push_str(&format!("     {}\n", hint.yellow()));
+        }
+    }
+    output
+}
+
+/// Failures that share a step and normalized error, in order of first appearance.
+struct FailureGroup<'a> {
+    step: UpdateStep,
+    error: String,
+    results: Vec<&'a UpdateResult>,
+}
+
+fn group_identical_failures<'a>(failures: &[&'a UpdateResult]) -> Vec<FailureGroup<'a>> {
+    let mut groups: Vec<FailureGroup<'a>> = Vec::new();
+    for result in failures {
+        let UpdateOutcome::Failed(failure) = &result.outcome else {
+            continue;
+        };
+        let error = normalize_error(&failure.error, &result.path);
+        match groups
+            .iter_mut()
+            .find(|group| group.step == failure.step && group.error == error)
+        {
+            Some(group) => group.results.push(result),
+            None => groups.push(FailureGroup {
+                step: failure.step,
+                error,
+                results: vec![result],
+            }),
+        }
+    }
+    groups
+}
+
+/// Replaces the repository's own path with `<repo>` and collapses whitespace,
+/// so otherwise identical errors from different repositories compare equal.
+fn normalize_error(error: &str, path: &Path) -> String {
+    error
+        .replace(&path.display().to_string(), "<repo>")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Like [`build_failure_lines`], but failures with the same step and error are
+/// one entry with a count and a few repository names. `verbose` lists every path.
+fn build_collapsed_failure_lines(failures: &[&UpdateResult], verbose: bool) -> String {
+    let mut output = String::new();
+    if failures.is_empty() {
+        return output;
+    }
+
+    output.push_str(&format!(
+        "{}",
+        format!("Failed ({}):", failures.len()).red().bold()
+    ));
+    output.push('\n');
+
+    for group in group_identical_failures(failures) {
+        if let [result] = group.results.as_slice() {
+            output.push_str(&build_failure_line(result));
+            continue;
+        }
+        output.push_str(&format!(
+            "  {} {} {} {}\n",
+            "FAIL".red().bold(),
+            format!("{} repos", group.results.len()).white(),
+            format!("at {:?}: {}", group.step, group.error).red(),
+            build_failure_sample(&group.results).dimmed(),
+        ));
+        if verbose {
+            for result in &group.results {
+                output.push_str(&format!("     {}\n", result.path.display()));
+            }
+        }
+    }
+    output.push('\n');
+    output
+}
+
+/// Names the first few repositories of a group, e.g. `(api, core, web, +27 more)`.
+fn build_failure_sample(results: &[&UpdateResult]) -> String {
+    let mut names: Vec<String> = results
+        .iter()
+        .take(MAX_COLLAPSED_FAILURE_SAMPLES)
+        .map(|result| {
+            result
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| DEFAULT_REPO_NAME.to_string())
+        })
+        .collect();
+    if results.len() > MAX_COLLAPSED_FAILURE_SAMPLES {
+        names.push(format!(
+            "+{} more",
+            results.len() - MAX_COLLAPSED_FAILURE_SAMPLES
+        ));
+    }
+    format!("({})", names.join(", "))
+}
+
 fn format_step_message(step: &UpdateStep) -> &'static str {
     match step {
         UpdateStep::Started => "Starting update...",
@@ -523,9 +1757,62 @@ fn format_step_message(step: &UpdateStep) -> &'static str {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::repo::{OriginalHead, UpdateFailure, UpdateSuccess};
+    use crate::cleanup::StaleStashes;
+    use crate::repo::{OriginalHead, UpdateFailure, UpdateSkipped, UpdateSuccess};
     use std::path::PathBuf;
 
+    struct RecordingCallbacks {
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl UpdateCallbacks for RecordingCallbacks {
+        fn on_update_start(&self, repo_name: &str) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("start {}", repo_name));
+        }
+        fn on_step(&self, step: &UpdateStep) {
+            self.events.lock().unwrap().push(format!("step {:?}", step));
+        }
+        fn on_complete(&self, result: &UpdateResult) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("complete {}", result.path.display()));
+        }
+    }
+
+    #[test]
+    fn test_composite_callbacks_fan_out_to_every_observer() {
+        let logs: Vec<_> = (0..3).map(|_| Arc::new(Mutex::new(Vec::new()))).collect();
+        let composite = logs
+            .iter()
+            .fold(CompositeCallbacks::default(), |c, events| {
+                c.with(RecordingCallbacks {
+                    events: Arc::clone(events),
+                })
+            });
+
+        composite.on_update_start("repo");
+        composite.on_step(&UpdateStep::Fetching);
+        composite.on_complete(&UpdateResult {
+            path: PathBuf::from("/ws/repo"),
+            outcome: UpdateOutcome::Skipped(UpdateSkipped {
+                reason: "dirty".to_string(),
+                step: UpdateStep::CheckingChanges,
+            }),
+            duration: Duration::ZERO,
+        });
+
+        for events in &logs {
+            assert_eq!(
+                *events.lock().unwrap(),
+                vec!["start repo", "step Fetching", "complete /ws/repo"]
+            );
+        }
+    }
+
     #[test]
     fn test_format_duration_rounds_to_two_decimals() {
         assert_eq!(format_duration(Duration::from_millis(1234)), "1.23s");
@@ -533,6 +1820,47 @@ mod tests {
         assert_eq!(format_duration(Duration::from_secs(42)), "42.00s");
     }
 
+    #[test]
+    fn test_build_rejected_dir_line_names_dir_and_reason() {
+        colored::control::set_override(false);
+        let line = build_rejected_dir_line(Path::new("/ws/notes"), &NotARepoReason::NoGitDir);
+        assert_eq!(line, "  ignored notes: no .git directory");
+    }
+
+    #[test]
+    fn test_build_update_plan_numbers_steps_and_notes_skip() {
+        colored::control::set_override(false);
+        let plan = UpdatePlan {
+            branch: "feature".to_string(),
+            dirty_files: 3,
+            steps: vec![UpdateStep::DetectingBranch, UpdateStep::CheckingChanges],
+            skip_reason: Some("3 uncommitted files exceed the limit of 1".to_string()),
+            fail_reason: None,
+            checkout_branch: None,
+            remote_behind: None,
+            restore_to: None,
+        };
+
+        let output = build_update_plan(Path::new("/ws/app"), &plan, 4);
+
+        assert_eq!(
+            output,
+            "Plan for app (on feature, 3 uncommitted files):\n\
+             \x20 1. Detecting current branch\n\
+             \x20 2. Checking for uncommitted changes\n\
+             \x20 then skip: 3 uncommitted files exceed the limit of 1\n\
+             \x20 (first of 4 repos; each follows these rules for its own state)\n\n"
+        );
+    }
+
+    #[test]
+    fn test_format_bytes_picks_binary_units() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(2560), "2.50 KiB");
+        assert_eq!(format_bytes(1_258_291), "1.20 MiB");
+        assert_eq!(format_bytes(3 << 30), "3.00 GiB");
+    }
+
     #[test]
     fn test_format_step_message_covers_all_known_steps() {
         // Ensure all known steps have meaningful messages
@@ -581,9 +1909,8 @@ mod tests {
         let result = UpdateResult {
             path: PathBuf::from("/test/repo"),
             outcome: UpdateOutcome::Success(UpdateSuccess {
-                original_head: OriginalHead::Branch("main".to_string()),
-                master_branch: "main",
-                had_stash: false,
+                master_branch: Some("main".to_string()),
+                ..UpdateSuccess::new(OriginalHead::Branch("main".to_string()))
             }),
             duration: Duration::from_secs(1),
         };
@@ -602,19 +1929,16 @@ mod tests {
         let success = UpdateResult {
             path: PathBuf::from("/test/success"),
             outcome: UpdateOutcome::Success(UpdateSuccess {
-                original_head: OriginalHead::Branch("feature".to_string()),
-                master_branch: "master",
+                master_branch: Some("master".to_string()),
                 had_stash: true,
+                ..UpdateSuccess::new(OriginalHead::Branch("feature".to_string()))
             }),
             duration: Duration::from_secs(2),
         };
 
         let failure = UpdateResult {
             path: PathBuf::from("/test/failure"),
-            outcome: UpdateOutcome::Failed(UpdateFailure {
-                error: "test error".to_string(),
-                step: UpdateStep::Fetching,
-            }),
+            outcome: UpdateOutcome::Failed(UpdateFailure::new(UpdateStep::Fetching, "test error")),
             duration: Duration::from_millis(500),
         };
 
@@ -638,44 +1962,52 @@ mod tests {
         let success = UpdateResult {
             path: PathBuf::from("/test/success"),
             outcome: UpdateOutcome::Success(UpdateSuccess {
-                original_head: OriginalHead::Branch("main".to_string()),
-                master_branch: "main",
-                had_stash: false,
+                master_branch: Some("main".to_string()),
+                ..UpdateSuccess::new(OriginalHead::Branch("main".to_string()))
             }),
             duration: Duration::from_secs(1),
         };
 
         let failure = UpdateResult {
             path: PathBuf::from("/test/failure"),
-            outcome: UpdateOutcome::Failed(UpdateFailure {
-                error: "test error".to_string(),
-                step: UpdateStep::Pulling,
-            }),
+            outcome: UpdateOutcome::Failed(UpdateFailure::new(UpdateStep::Pulling, "test error")),
             duration: Duration::from_millis(200),
         };
 
         let quiet_config = Config {
             verbosity: crate::config::Verbosity::Quiet,
+            ..Default::default()
         };
         let normal_config = Config {
             verbosity: crate::config::Verbosity::Normal,
+            ..Default::default()
         };
 
         let (stdout_line, stderr_lines) = build_quiet_summary(&[success.clone(), failure.clone()]);
         assert_eq!(stdout_line, "1/2 repositories updated");
         assert_eq!(stderr_lines.len(), 1);
 
-        let output =
-            build_normal_summary(&[success.clone(), failure.clone()], Duration::from_secs(2));
+        let output = build_normal_summary(
+            &[success.clone(), failure.clone()],
+            Duration::from_secs(2),
+            &WorkspaceStats::default(),
+            &Config::default(),
+        );
         assert!(output.contains("Summary"));
         assert!(output.contains("Total"));
 
         print_summary(
             &[success.clone(), failure.clone()],
             Duration::from_secs(2),
+            &WorkspaceStats::default(),
             &quiet_config,
         );
-        print_summary(&[success, failure], Duration::from_secs(2), &normal_config);
+        print_summary(
+            &[success, failure],
+            Duration::from_secs(2),
+            &WorkspaceStats::default(),
+            &normal_config,
+        );
     }
 
     #[test]
@@ -684,14 +2016,18 @@ mod tests {
         let success = UpdateResult {
             path: PathBuf::from("/test/success"),
             outcome: UpdateOutcome::Success(UpdateSuccess {
-                original_head: OriginalHead::Branch("main".to_string()),
-                master_branch: "main",
-                had_stash: false,
+                master_branch: Some("main".to_string()),
+                ..UpdateSuccess::new(OriginalHead::Branch("main".to_string()))
             }),
             duration: Duration::from_secs(1),
         };
 
-        let output = build_normal_summary(&[success], Duration::from_secs(1));
+        let output = build_normal_summary(
+            &[success],
+            Duration::from_secs(1),
+            &WorkspaceStats::default(),
+            &Config::default(),
+        );
         assert!(output.contains("Succeeded (1):"));
         assert!(!output.contains("Failed ("));
     }
@@ -701,41 +2037,217 @@ mod tests {
         colored::control::set_override(false);
         let failure = UpdateResult {
             path: PathBuf::from("/test/failure"),
-            outcome: UpdateOutcome::Failed(UpdateFailure {
-                error: "boom".to_string(),
-                step: UpdateStep::Fetching,
-            }),
+            outcome: UpdateOutcome::Failed(UpdateFailure::new(UpdateStep::Fetching, "boom")),
             duration: Duration::from_secs(1),
         };
 
-        let output = build_normal_summary(&[failure], Duration::from_secs(1));
+        let output = build_normal_summary(
+            &[failure],
+            Duration::from_secs(1),
+            &WorkspaceStats::default(),
+            &Config::default(),
+        );
         assert!(output.contains("Failed (1):"));
         assert!(!output.contains("Succeeded ("));
     }
 
+    #[test]
+    fn test_build_host_groups_aligns_hosts_and_omits_zero_counts() {
+        colored::control::set_override(false);
+        let groups = [
+            HostGroup {
+                host: "github.com".to_string(),
+                succeeded: 4,
+                failed: 0,
+                skipped: 1,
+            },
+            HostGroup {
+                host: "gitlab.internal".to_string(),
+                succeeded: 0,
+                failed: 3,
+                skipped: 0,
+            },
+        ];
+
+        let output = build_host_groups(&groups);
+
+        assert!(output.contains("By Host"));
+        assert!(
+            output.ends_with(
+                "  github.com       4 updated, 1 skipped\n  gitlab.internal  0 updated, 3 failed\n"
+            ),
+            "{}",
+            output
+        );
+        assert_eq!(build_host_groups(&[]), "");
+    }
+
+    #[test]
+    fn test_build_health_report_groups_repos_and_flags_diverged() {
+        colored::control::set_override(false);
+        let report = HealthReport {
+            repos: vec![
+                (PathBuf::from("/ws/a"), HealthBucket::CleanCurrent),
+                (PathBuf::from("/ws/b"), HealthBucket::Diverged),
+                (PathBuf::from("/ws/c"), HealthBucket::CleanCurrent),
+            ],
+            errors: vec![(PathBuf::from("/ws/d"), "boom".to_string())],
+            default_branch_ages: Vec::new(),
+            fetch_ages: Vec::new(),
+            stale_stashes: Vec::new(),
+        };
+
+        let output = build_health_report(&report, Duration::from_secs(180 * SECONDS_PER_DAY));
+
+        assert!(
+            output.contains("Clean and up to date (2):\n  /ws/a\n  /ws/c\n"),
+            "{}",
+            output
+        );
+        assert!(output.contains("Diverged from upstream (1) — needs attention:\n  /ws/b\n"));
+        assert!(output.contains("Could not scan (1):\n  /ws/d: boom\n"));
+        assert!(!output.contains("Dirty"));
+        assert!(output.ends_with(
+            "Total: 4 repos — 2 current, 1 diverged — needs attention, 1 not scanned\n"
+        ));
+    }
+
+    #[test]
+    fn test_build_health_report_lists_stale_default_branches_oldest_first() {
+        colored::control::set_override(false);
+        let days = |n: u64| Duration::from_secs(n * SECONDS_PER_DAY);
+        let age = |branch: &str, n: u64| BranchAge {
+            branch: branch.to_string(),
+            age: days(n),
+        };
+        let report = HealthReport {
+            repos: vec![
+                (PathBuf::from("/ws/a"), HealthBucket::CleanCurrent),
+                (PathBuf::from("/ws/b"), HealthBucket::CleanCurrent),
+                (PathBuf::from("/ws/c"), HealthBucket::Dirty),
+            ],
+            errors: Vec::new(),
+            default_branch_ages: vec![
+                (PathBuf::from("/ws/a"), age("master", 0)),
+                (PathBuf::from("/ws/b"), age("main", 200)),
+                (PathBuf::from("/ws/c"), age("master", 400)),
+            ],
+            fetch_ages: vec![(PathBuf::from("/ws/b"), days(30))],
+            stale_stashes: vec![(
+                PathBuf::from("/ws/a"),
+                StaleStashes {
+                    selectors: vec!["stash@{0}".to_string(), "stash@{1}".to_string()],
+                },
+            )],
+        };
+
+        let output = build_health_report(&report, days(180));
+
+        assert!(
+            output.contains(
+                "Clean and up to date (2):\n  /ws/a (master: today)\n  /ws/b (main: 200 days ago, fetched 30 days ago)\n"
+            ),
+            "{}",
+            output
+        );
+        assert!(output.contains(
+            "Stale (2) — default branch older than 180 days:\n  /ws/c (master: 400 days ago)\n  /ws/b (main: 200 days ago)\n"
+        ));
+        assert!(output.contains(
+            "Stale stashes (1) — the next update that needs to stash will refuse:\n  /ws/a: stash@{0}, stash@{1}\n"
+        ));
+        assert!(
+            output
+                .ends_with("Total: 3 repos — 2 current, 1 dirty, 2 stale, 1 with stale stashes\n")
+        );
+    }
+
+    fn failed_at(path: &str, step: UpdateStep, error: &str) -> UpdateResult {
+        UpdateResult {
+            path: PathBuf::from(path),
+            outcome: UpdateOutcome::Failed(UpdateFailure::new(step, error.to_string())),
+            duration: Duration::from_millis(100),
+        }
+    }
+
+    #[test]
+    fn test_build_normal_summary_collapses_identical_failures() {
+        colored::control::set_override(false);
+        let mut results: Vec<UpdateResult> = ["api", "core", "docs", "web", "cli"]
+            .iter()
+            .map(|name| {
+                let path = format!("/ws/{}", name);
+                let error = format!("git fetch failed in {}: Could not resolve host", path);
+                failed_at(&path, UpdateStep::Fetching, &error)
+            })
+            .collect();
+        results.push(failed_at(
+            "/ws/odd",
+            UpdateStep::Pulling,
+            "not a fast-forward",
+        ));
+        let config = Config {
+            collapse_failures: true,
+            ..Default::default()
+        };
+
+        let output = build_normal_summary(
+            &results,
+            Duration::from_secs(1),
+            &WorkspaceStats::default(),
+            &config,
+        );
+
+        assert!(output.contains("Failed (6):"), "{}", output);
+        assert!(output.contains(
+            "  FAIL 5 repos at Fetching: git fetch failed in <repo>: Could not resolve host (api, core, docs, +2 more)\n"
+        ), "{}", output);
+        assert!(output.contains("  FAIL /ws/odd at Pulling: not a fast-forward in 0.10s\n"));
+        assert!(!output.contains("     /ws/api\n"));
+
+        let verbose = Config {
+            verbosity: crate::config::Verbosity::Verbose,
+            ..config
+        };
+        let output = build_normal_summary(
+            &results,
+            Duration::from_secs(1),
+            &WorkspaceStats::default(),
+            &verbose,
+        );
+        assert!(
+            output.contains("     /ws/api\n     /ws/core\n"),
+            "{}",
+            output
+        );
+        assert!(output.contains("     /ws/cli\n"), "{}", output);
+    }
+
     #[test]
     fn test_build_normal_summary_golden_output() {
         colored::control::set_override(false);
         let success = UpdateResult {
             path: PathBuf::from("/test/success"),
             outcome: UpdateOutcome::Success(UpdateSuccess {
-                original_head: OriginalHead::Branch("feature".to_string()),
-                master_branch: "master",
+                master_branch: Some("master".to_string()),
                 had_stash: true,
+                ..UpdateSuccess::new(OriginalHead::Branch("feature".to_string()))
             }),
             duration: Duration::from_secs(2),
         };
 
         let failure = UpdateResult {
             path: PathBuf::from("/test/failure"),
-            outcome: UpdateOutcome::Failed(UpdateFailure {
-                error: "boom".to_string(),
-                step: UpdateStep::Fetching,
-            }),
+            outcome: UpdateOutcome::Failed(UpdateFailure::new(UpdateStep::Fetching, "boom")),
             duration: Duration::from_millis(500),
         };
 
-        let output = build_normal_summary(&[success, failure], Duration::from_secs(3));
+        let output = build_normal_summary(
+            &[success, failure],
+            Duration::from_secs(3),
+            &WorkspaceStats::default(),
+            &Config::default(),
+        );
         let expected = [
             "",
             "==================================================",
@@ -743,12 +2255,12 @@ mod tests {
             "==================================================",
             "",
             "Succeeded (1):",
-            "  OK /test/success [feature]  (stash restored) in 2.00s",
+            "  OK /test/success [feature] → master  (stash restored) in 2.00s",
             "",
             "Failed (1):",
             "  FAIL /test/failure at Fetching: boom in 0.50s",
             "",
-            "Total: 1/2 repos in 3.00s",
+            "Total: 1/2 repos in 3.00s, 0 commits pulled",
             "",
         ]
         .join("\n");
@@ -756,6 +2268,285 @@ mod tests {
         assert_eq!(output, expected);
     }
 
+    #[test]
+    fn test_write_markdown_emits_table_with_one_row_per_repo() {
+        let success = UpdateResult {
+            path: PathBuf::from("/test/api"),
+            outcome: UpdateOutcome::Success(UpdateSuccess {
+                master_branch: Some("main".to_string()),
+                commits_pulled: 3,
+                ..UpdateSuccess::new(OriginalHead::Branch("feature".to_string()))
+            }),
+            duration: Duration::from_secs(2),
+        };
+        let failure = UpdateResult {
+            path: PathBuf::from("/test/web"),
+            outcome: UpdateOutcome::Failed(UpdateFailure::new(UpdateStep::Fetching, "a | b")),
+            duration: Duration::from_millis(500),
+        };
+
+        let mut buffer = Vec::new();
+        write_markdown(&[success, failure], &mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], "| Repo | Status | Branch | Commits | Time |");
+        assert_eq!(lines[1], "|------|--------|--------|---------|------|");
+        assert_eq!(lines[2], "| api | ✅ updated | feature | 3 | 2.00s |");
+        assert_eq!(lines[3], "| web | ❌ failed: a \\| b | - | - | 0.50s |");
+        assert_eq!(lines[4], "");
+        assert_eq!(lines[5], "**1/2 repositories updated**, 1 failed");
+        assert_eq!(lines.len(), 6);
+    }
+
+    #[test]
+    fn test_write_formatted_starts_with_each_formats_leading_bytes() {
+        let results = [
+            UpdateResult {
+                path: PathBuf::from("/test/api"),
+                outcome: UpdateOutcome::Success(UpdateSuccess {
+                    master_branch: Some("main".to_string()),
+                    commits_pulled: 2,
+                    ..UpdateSuccess::new(OriginalHead::Branch("main".to_string()))
+                }),
+                duration: Duration::from_millis(1500),
+            },
+            UpdateResult {
+                path: PathBuf::from("/test/web"),
+                outcome: UpdateOutcome::Failed(UpdateFailure {
+                    git_exit_code: Some(128),
+                    ..UpdateFailure::new(UpdateStep::Fetching, "fatal: \"origin\", gone\nretry")
+                }),
+                duration: Duration::from_millis(500),
+            },
+        ];
+        let write = |format| {
+            let config = Config {
+                format,
+                ..Config::default()
+            };
+            let mut buffer = Vec::new();
+            write_formatted(&results, &config, &mut buffer).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        assert_eq!(write(OutputFormat::Human), "");
+        assert!(write(OutputFormat::Json).starts_with('{'));
+        assert!(write(OutputFormat::Markdown).starts_with("| Repo |"));
+
+        let csv = write(OutputFormat::Csv);
+        assert!(csv.starts_with("path,status,branch,commits_pulled,step,error,duration_ms\n"));
+        assert!(csv.contains("/test/api,success,main,2,,,1500\n"), "{}", csv);
+        assert!(
+            csv.contains(
+                "/test/web,failed,,0,Fetching,\"fatal: \"\"origin\"\", gone\nretry\",500\n"
+            ),
+            "{}",
+            csv
+        );
+
+        assert_eq!(
+            write(OutputFormat::Porcelain),
+            "success\t/test/api\tmain\t2\t-\n\
+             failed\t/test/web\t-\t0\tfatal: \"origin\", gone retry\n"
+        );
+    }
+
+    #[test]
+    fn test_build_totals_suffix_reports_commits_and_stashed_files() {
+        colored::control::set_override(false);
+        let stats = WorkspaceStats::default();
+        assert_eq!(build_totals_suffix(&stats), ", 0 commits pulled");
+
+        stats.record(&UpdateResult {
+            path: PathBuf::from("/test/success"),
+            outcome: UpdateOutcome::Success(UpdateSuccess {
+                master_branch: Some("main".to_string()),
+                had_stash: true,
+                stashed_files: 3,
+                commits_pulled: 47,
+                ..UpdateSuccess::new(OriginalHead::Branch("feature".to_string()))
+            }),
+            duration: Duration::from_secs(1),
+        });
+        assert_eq!(
+            build_totals_suffix(&stats),
+            ", 47 commits pulled, 3 files stashed"
+        );
+    }
+
+    #[test]
+    fn test_summaries_show_recovery_hint_for_failures() {
+        colored::control::set_override(false);
+        let failure = UpdateResult {
+            path: PathBuf::from("/test/stuck"),
+            outcome: UpdateOutcome::Failed(UpdateFailure {
+                recovery_hint: Some(
+                    "to undo: git checkout feature (HEAD was at abc123; see `git reflog`)"
+                        .to_string(),
+                ),
+                ..UpdateFailure::new(UpdateStep::RestoringBranch, "boom")
+            }),
+            duration: Duration::from_millis(500),
+        };
+
+        let output = build_failure_lines(&[&failure]);
+        assert!(output.contains(
+            "in 0.50s\n     to undo: git checkout feature (HEAD was at abc123; see `git reflog`)\n"
+        ));
+
+        let (_, stderr_lines) = build_quiet_summary(std::slice::from_ref(&failure));
+        assert!(
+            stderr_lines[0].ends_with(
+                "\n  to undo: git checkout feature (HEAD was at abc123; see `git reflog`)"
+            )
+        );
+    }
+
+    #[test]
+    fn test_summaries_list_skipped_repos_separately() {
+        colored::control::set_override(false);
+        let skipped = UpdateResult {
+            path: PathBuf::from("/test/wip"),
+            outcome: UpdateOutcome::Skipped(UpdateSkipped {
+                reason: "12 uncommitted files exceed the limit of 5".to_string(),
+                step: UpdateStep::CheckingChanges,
+            }),
+            duration: Duration::from_millis(100),
+        };
+
+        let output = build_normal_summary(
+            std::slice::from_ref(&skipped),
+            Duration::from_secs(1),
+            &WorkspaceStats::default(),
+            &Config::default(),
+        );
+        assert!(output.contains("Skipped (1):"));
+        assert!(output.contains("SKIP /test/wip 12 uncommitted files"));
+        assert!(!output.contains("Failed ("));
+
+        let (stdout_line, stderr_lines) = build_quiet_summary(&[skipped]);
+        assert_eq!(stdout_line, "0/1 repositories updated, 1 skipped");
+        assert!(stderr_lines.is_empty());
+    }
+
+    #[test]
+    fn test_build_success_lines_include_describe_when_present() {
+        colored::control::set_override(false);
+        let success = UpdateResult {
+            path: PathBuf::from("/test/success"),
+            outcome: UpdateOutcome::Success(UpdateSuccess {
+                master_branch: Some("main".to_string()),
+                describe: Some("v1.2.3-4-gabcdef0".to_string()),
+                ..UpdateSuccess::new(OriginalHead::Branch("main".to_string()))
+            }),
+            duration: Duration::from_secs(1),
+        };
+
+        let output = build_success_lines(&[&success], &Config::default());
+        assert!(output.contains("in 1.00s @ v1.2.3-4-gabcdef0"));
+    }
+
+    #[test]
+    fn test_build_normal_summary_lists_warnings_in_their_own_section() {
+        colored::control::set_override(false);
+        let success = |path: &str, warnings: Vec<String>| UpdateResult {
+            path: PathBuf::from(path),
+            outcome: UpdateOutcome::Success(UpdateSuccess {
+                master_branch: Some("main".to_string()),
+                warnings,
+                ..UpdateSuccess::new(OriginalHead::Branch("feature".to_string()))
+            }),
+            duration: Duration::from_secs(1),
+        };
+        let warned = success(
+            "/test/both",
+            vec!["both 'master' and 'main' exist locally; using 'main'".to_string()],
+        );
+        let clean = success("/test/clean", Vec::new());
+
+        let output = build_normal_summary(
+            &[warned, clean],
+            Duration::from_secs(2),
+            &WorkspaceStats::default(),
+            &Config::default(),
+        );
+        assert!(output.contains("Succeeded (2):"), "{}", output);
+        assert!(
+            output.contains(
+                "Warnings (1):\n  WARN /test/both\n     both 'master' and 'main' exist locally; using 'main'\n\n"
+            ),
+            "{}",
+            output
+        );
+        assert!(!output.contains("WARN /test/clean"), "{}", output);
+        assert!(!output.contains("Failed"), "{}", output);
+    }
+
+    #[test]
+    fn test_build_success_lines_list_new_remote_branches() {
+        colored::control::set_override(false);
+        let success = UpdateResult {
+            path: PathBuf::from("/test/app"),
+            outcome: UpdateOutcome::Success(UpdateSuccess {
+                master_branch: Some("main".to_string()),
+                new_remote_branches: vec!["feature-x".to_string(), "feature-y".to_string()],
+                ..UpdateSuccess::new(OriginalHead::Branch("main".to_string()))
+            }),
+            duration: Duration::from_secs(1),
+        };
+
+        let output = build_success_lines(&[&success], &Config::default());
+        assert!(
+            output.contains("in 1.00s\n     new remote branches: feature-x, feature-y\n"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_build_success_lines_show_each_resolved_default_branch() {
+        colored::control::set_override(false);
+        let success = |path: &str, branch: &str| UpdateResult {
+            path: PathBuf::from(path),
+            outcome: UpdateOutcome::Success(UpdateSuccess {
+                master_branch: Some(branch.to_string()),
+                ..UpdateSuccess::new(OriginalHead::Branch("feature".to_string()))
+            }),
+            duration: Duration::from_secs(1),
+        };
+        let api = success("/ws/api", "main");
+        let legacy = success("/ws/legacy", "master");
+
+        let output = build_success_lines(&[&api, &legacy], &Config::default());
+        assert!(
+            output.contains("  OK /ws/api [feature] → main  in"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("  OK /ws/legacy [feature] → master  in"),
+            "{}",
+            output
+        );
+
+        let current_branch = Config {
+            update_mode: UpdateMode::CurrentBranch,
+            ..Config::default()
+        };
+        let output = build_success_lines(&[&api], &current_branch);
+        assert!(!output.contains("→"), "{}", output);
+    }
+
+    #[test]
+    fn test_build_nothing_matched_line() {
+        colored::control::set_override(false);
+        assert_eq!(
+            build_nothing_matched_line(40, "all already updated according to --continue-from"),
+            "No repositories matched (40 found, all already updated according to --continue-from)"
+        );
+    }
+
     #[test]
     fn test_build_repo_header_line() {
         colored::control::set_override(false);
@@ -804,14 +2595,29 @@ mod tests {
         assert!(empty_line.contains("No git repositories found"));
     }
 
+    #[test]
+    fn test_build_resume_skipped_line_pluralizes() {
+        colored::control::set_override(false);
+        assert_eq!(
+            build_resume_skipped_line(1),
+            "Resuming: skipping 1 already updated repository"
+        );
+        assert_eq!(
+            build_resume_skipped_line(4),
+            "Resuming: skipping 4 already updated repositories"
+        );
+    }
+
     #[test]
     fn test_print_repo_header_step_and_completion_verbose_only() {
         colored::control::set_override(false);
         let verbose = Config {
             verbosity: crate::config::Verbosity::Verbose,
+            ..Default::default()
         };
         let normal = Config {
             verbosity: crate::config::Verbosity::Normal,
+            ..Default::default()
         };
 
         print_repo_header(&normal, "repo-a");
@@ -829,9 +2635,11 @@ mod tests {
         colored::control::set_override(false);
         let quiet = Config {
             verbosity: crate::config::Verbosity::Quiet,
+            ..Default::default()
         };
         let normal = Config {
             verbosity: crate::config::Verbosity::Normal,
+            ..Default::default()
         };
 
         print_working_dir(Path::new("/tmp/repo"), &quiet);
@@ -847,6 +2655,7 @@ mod tests {
         colored::control::set_override(false);
         let config = Config {
             verbosity: crate::config::Verbosity::Normal,
+            ..Default::default()
         };
         let progress = create_single_repo_progress(&config);
         let callbacks = SingleRepoCallbacks::new(progress, config);
@@ -854,9 +2663,8 @@ mod tests {
         let success = UpdateResult {
             path: PathBuf::from("/test/success"),
             outcome: UpdateOutcome::Success(UpdateSuccess {
-                original_head: OriginalHead::Branch("main".to_string()),
-                master_branch: "main",
-                had_stash: false,
+                master_branch: Some("main".to_string()),
+                ..UpdateSuccess::new(OriginalHead::Branch("main".to_string()))
             }),
             duration: Duration::from_secs(1),
         };
@@ -869,10 +2677,7 @@ mod tests {
 
         let failure = UpdateResult {
             path: PathBuf::from("/test/failure"),
-            outcome: UpdateOutcome::Failed(UpdateFailure {
-                error: "boom".to_string(),
-                step: UpdateStep::Fetching,
-            }),
+            outcome: UpdateOutcome::Failed(UpdateFailure::new(UpdateStep::Fetching, "boom")),
             duration: Duration::from_secs(1),
         };
         callbacks.on_completion_status(false, Some("boom"));
@@ -885,17 +2690,18 @@ mod tests {
         colored::control::set_override(false);
         let config = Config {
             verbosity: crate::config::Verbosity::Normal,
+            ..Default::default()
         };
-        let progress = create_workspace_progress(MAX_VISIBLE_COMPLETIONS + 2, &config);
+        let progress =
+            create_workspace_progress(MAX_VISIBLE_COMPLETIONS + 2, &config, Arc::default());
         let tracker = progress.create_repo_tracker("repo-a", config);
 
         for i in 0..(MAX_VISIBLE_COMPLETIONS + 2) {
             let result = UpdateResult {
                 path: PathBuf::from(format!("/tmp/repo-{}", i)),
                 outcome: UpdateOutcome::Success(UpdateSuccess {
-                    original_head: OriginalHead::Branch("main".to_string()),
-                    master_branch: "main",
-                    had_stash: false,
+                    master_branch: Some("main".to_string()),
+                    ..UpdateSuccess::new(OriginalHead::Branch("main".to_string()))
                 }),
                 duration: Duration::from_secs(1),
             };
@@ -909,6 +2715,7 @@ mod tests {
                 .expect("WorkspaceProgress state mutex poisoned");
             assert_eq!(state.repos.len(), MAX_VISIBLE_COMPLETIONS);
         }
+        assert_eq!(progress.stats.repos_updated(), MAX_VISIBLE_COMPLETIONS + 2);
 
         progress.finish();
     }
@@ -918,16 +2725,16 @@ mod tests {
         colored::control::set_override(false);
         let quiet = Config {
             verbosity: crate::config::Verbosity::Quiet,
+            ..Default::default()
         };
-        let progress = create_workspace_progress(1, &quiet);
+        let progress = create_workspace_progress(1, &quiet, Arc::default());
         let tracker = progress.create_repo_tracker("repo-a", quiet);
 
         let result = UpdateResult {
             path: PathBuf::from("/tmp/repo"),
             outcome: UpdateOutcome::Success(UpdateSuccess {
-                original_head: OriginalHead::Branch("main".to_string()),
-                master_branch: "main",
-                had_stash: false,
+                master_branch: Some("main".to_string()),
+                ..UpdateSuccess::new(OriginalHead::Branch("main".to_string()))
             }),
             duration: Duration::from_secs(1),
         };
@@ -939,20 +2746,201 @@ mod tests {
     fn test_workspace_progress_mark_completed_smoke() {
         let config = Config {
             verbosity: crate::config::Verbosity::Normal,
+            ..Default::default()
+        };
+        let progress = create_workspace_progress(2, &config, Arc::default());
+        progress.mark_completed("repo-a", true);
+        progress.mark_completed("repo-b", false);
+        progress.finish();
+    }
+
+    #[test]
+    fn test_compact_workspace_progress_updates_single_line_counts() {
+        let config = Config {
+            progress: ProgressMode::Compact,
+            ..Default::default()
         };
-        let progress = create_workspace_progress(2, &config);
+        let progress = create_workspace_progress(3, &config, Arc::default());
+        assert!(progress.completion_slots.is_empty());
+        assert_eq!(progress.main_bar.message(), "Updating repos: 0/3");
+
         progress.mark_completed("repo-a", true);
+        assert_eq!(progress.main_bar.message(), "Updating repos: 1/3");
+
         progress.mark_completed("repo-b", false);
+        progress.mark_completed("repo-c", true);
+        assert_eq!(
+            progress.main_bar.message(),
+            "Updating repos: 3/3 (1 failed)"
+        );
         progress.finish();
     }
 
+    fn finished(name: &str, failed: bool) -> UpdateResult {
+        let outcome = if failed {
+            UpdateOutcome::Failed(UpdateFailure::new(UpdateStep::Fetching, "boom"))
+        } else {
+            UpdateOutcome::Skipped(UpdateSkipped {
+                reason: "already current".to_string(),
+                step: UpdateStep::CheckingChanges,
+            })
+        };
+        UpdateResult {
+            path: PathBuf::from("/ws").join(name),
+            outcome,
+            duration: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn test_lines_progress_gives_each_repo_a_line_that_tracks_its_step() {
+        colored::control::set_override(false);
+        let config = Config {
+            progress: ProgressMode::Lines,
+            ..Default::default()
+        };
+        let progress = build_workspace_progress(3, &config, Arc::default(), 20);
+        let trackers: Vec<_> = ["repo-a", "repo-b", "repo-c"]
+            .iter()
+            .map(|name| progress.create_repo_tracker(name, config.clone()))
+            .collect();
+        for tracker in &trackers {
+            tracker.on_update_start(&tracker.repo_name);
+        }
+
+        let lines = progress.repo_lines.lock().unwrap().lines.clone();
+        assert_eq!(lines.len(), 3);
+        assert!(progress.completion_slots.is_empty());
+        assert_eq!(lines[0].message(), "· repo-a  Starting update");
+
+        trackers[1].on_step(&UpdateStep::Fetching);
+        assert_eq!(lines[1].message(), "· repo-b  Fetching from origin");
+        trackers[1].on_complete(&finished("repo-b", true));
+        assert_eq!(lines[1].message(), "✗ repo-b  failed at Fetching");
+        assert_eq!(
+            progress.main_bar.message(),
+            "Updating repos: 1/3 (1 failed)"
+        );
+        progress.finish();
+    }
+
+    #[test]
+    fn test_lines_progress_reuses_finished_lines_beyond_the_terminal_height() {
+        colored::control::set_override(false);
+        let config = Config {
+            progress: ProgressMode::Lines,
+            ..Default::default()
+        };
+        let progress = build_workspace_progress(4, &config, Arc::default(), 2);
+        let tracker = |name: &str| progress.create_repo_tracker(name, config.clone());
+        let (a, b, c, d) = (tracker("a"), tracker("b"), tracker("c"), tracker("d"));
+
+        a.on_update_start("a");
+        b.on_update_start("b");
+        b.on_complete(&finished("b", true));
+        a.on_complete(&finished("a", false));
+        // a's line is reused, b's failure stays on screen
+        c.on_update_start("c");
+        // Every line is taken: d is only counted on the main line
+        d.on_update_start("d");
+
+        let lines = progress.repo_lines.lock().unwrap().lines.clone();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].message(), "· c  Starting update");
+        assert_eq!(lines[1].message(), "✗ b  failed at Fetching");
+        assert!(d.line.lock().unwrap().is_none());
+        progress.finish();
+    }
+
+    #[test]
+    fn test_state_file_callbacks_append_one_report_per_repo() {
+        let writer = Arc::new(Mutex::new(Vec::new()));
+        let callbacks = StateFileCallbacks::new(Arc::clone(&writer));
+        let result = UpdateResult {
+            path: PathBuf::from("/test/repo"),
+            outcome: UpdateOutcome::Skipped(UpdateSkipped {
+                reason: "no-stash".to_string(),
+                step: UpdateStep::CheckingChanges,
+            }),
+            duration: Duration::from_millis(5),
+        };
+
+        callbacks.on_step(&UpdateStep::Fetching);
+        callbacks.on_complete(&result);
+        callbacks.on_complete(&result);
+
+        let output = String::from_utf8(writer.lock().unwrap().clone()).unwrap();
+        let state = crate::report::RunState::from_lines(&output).unwrap();
+        assert_eq!(state.repos.len(), 2);
+        assert_eq!(state.repos[0], RepoReport::from_result(&result));
+    }
+
+    #[test]
+    fn test_ndjson_callbacks_emit_one_json_object_per_line() {
+        let writer = Arc::new(Mutex::new(Vec::new()));
+        let callbacks = NdjsonCallbacks::new(Path::new("/test/repo"), Arc::clone(&writer));
+        let failure = UpdateResult {
+            path: PathBuf::from("/test/repo"),
+            outcome: UpdateOutcome::Failed(UpdateFailure {
+                git_exit_code: Some(128),
+                ..UpdateFailure::new(UpdateStep::Fetching, "boom")
+            }),
+            duration: Duration::from_millis(1500),
+        };
+
+        callbacks.on_update_start("repo");
+        callbacks.on_step(&UpdateStep::Fetching);
+        callbacks.on_complete(&failure);
+
+        let output = String::from_utf8(writer.lock().unwrap().clone()).unwrap();
+        let events: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("invalid JSON line"))
+            .collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0]["event"], "update_start");
+        assert_eq!(events[1]["step"], "Fetching");
+        assert_eq!(events[2]["status"], "failed");
+        assert_eq!(events[2]["error"], "boom");
+        assert_eq!(events[2]["git_exit_code"], 128);
+        assert_eq!(events[2]["disk_full"], false);
+        assert_eq!(events[2]["duration_ms"], 1500);
+    }
+
+    #[test]
+    fn test_ndjson_callbacks_omit_master_branch_for_fetch_only_updates() {
+        let writer = Arc::new(Mutex::new(Vec::new()));
+        let callbacks = NdjsonCallbacks::new(Path::new("/test/repo"), Arc::clone(&writer));
+        let success = |master_branch: Option<&str>| UpdateResult {
+            path: PathBuf::from("/test/repo"),
+            outcome: UpdateOutcome::Success(UpdateSuccess {
+                master_branch: master_branch.map(str::to_string),
+                ..UpdateSuccess::new(OriginalHead::Branch("feature".to_string()))
+            }),
+            duration: Duration::ZERO,
+        };
+
+        callbacks.on_complete(&success(Some("main")));
+        callbacks.on_complete(&success(None));
+
+        let output = String::from_utf8(writer.lock().unwrap().clone()).unwrap();
+        let events: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("invalid JSON line"))
+            .collect();
+        assert_eq!(events[0]["master_branch"], "main");
+        assert!(events[1].get("master_branch").is_none(), "{}", events[1]);
+    }
+
     #[test]
     fn test_single_repo_progress_smoke() {
         let normal_config = Config {
             verbosity: crate::config::Verbosity::Normal,
+            ..Default::default()
         };
         let quiet_config = Config {
             verbosity: crate::config::Verbosity::Quiet,
+            ..Default::default()
         };
 
         let normal_progress = create_single_repo_progress(&normal_config);
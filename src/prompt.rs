@@ -1 +1,100 @@
+//! Yes/no confirmations.
+//!
+//! Features that need the user's go-ahead ask through a [`Prompter`] via
+//! [`confirm`], so stdin handling and `--assume-yes` live in one place and
+//! tests can substitute their own answers.
 
+use crate::config::Config;
+use std::io::{self, IsTerminal, Write};
+
+/// Asks the user yes/no questions.
+pub trait Prompter {
+    /// Returns true only if the user answered yes to `question`.
+    fn confirm(&self, question: &str) -> bool;
+}
+
+/// Asks on stderr and reads the answer from stdin.
+///
+/// When stdin is not a terminal nobody can answer, so every question is
+/// declined without waiting.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdinPrompter;
+
+impl Prompter for StdinPrompter {
+    fn confirm(&self, question: &str) -> bool {
+        if !io::stdin().is_terminal() {
+            return false;
+        }
+        eprint!("{} [y/N] ", question);
+        let _ = io::stderr().flush();
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer).is_ok() && is_yes(&answer)
+    }
+}
+
+/// Returns true if `config.assume_yes` is set, otherwise asks `prompter`.
+pub fn confirm(config: &Config, prompter: &dyn Prompter, question: &str) -> bool {
+    config.assume_yes || prompter.confirm(question)
+}
+
+/// Accepts `y` or `yes` in any case; anything else, including an empty line, is no.
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Answers every question with `answer`, recording what was asked.
+    struct MockPrompter {
+        answer: bool,
+        asked: RefCell<Vec<String>>,
+    }
+
+    impl MockPrompter {
+        fn new(answer: bool) -> Self {
+            Self {
+                answer,
+                asked: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl Prompter for MockPrompter {
+        fn confirm(&self, question: &str) -> bool {
+            self.asked.borrow_mut().push(question.to_string());
+            self.answer
+        }
+    }
+
+    #[test]
+    fn test_confirm_asks_the_prompter_without_assume_yes() {
+        let prompter = MockPrompter::new(false);
+
+        assert!(!confirm(&Config::default(), &prompter, "Update 300 repos?"));
+        assert_eq!(*prompter.asked.borrow(), vec!["Update 300 repos?"]);
+    }
+
+    #[test]
+    fn test_confirm_with_assume_yes_bypasses_the_prompter() {
+        let config = Config {
+            assume_yes: true,
+            ..Config::default()
+        };
+        let prompter = MockPrompter::new(false);
+
+        assert!(confirm(&config, &prompter, "Update 300 repos?"));
+        assert!(prompter.asked.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_is_yes_accepts_only_explicit_yes() {
+        assert!(is_yes("y\n"));
+        assert!(is_yes(" YES \n"));
+        assert!(!is_yes("\n"));
+        assert!(!is_yes("n\n"));
+        assert!(!is_yes("yep\n"));
+    }
+}
@@ -3,13 +3,19 @@
 //! This module provides the core update functionality for git repositories,
 //! including detecting branches, stashing changes, and fetching updates.
 
-use crate::config::Config;
-use crate::constants::{DEFAULT_REPO_NAME, GIT_DIR, MAIN_BRANCH, MASTER_BRANCH};
+use crate::cleanup;
+use crate::config::{BranchPreference, Config, UntrackedConflictPolicy, UpdateMode};
+use crate::constants::{
+    DEFAULT_REPO_NAME, DETACHED_HEAD, GIT_DIR, LOCAL_REMOTE_HOST, MAIN_BRANCH, MASTER_BRANCH,
+    NO_REMOTE_HOST, RAMP_INTERVAL_MS, discovery_timeout,
+};
 use crate::git;
 use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 
 /// Callbacks for monitoring repository update progress and output.
 ///
@@ -26,17 +32,40 @@ use std::time::Duration;
 ///
 /// # Optional Methods (have default no-op implementations)
 ///
+/// - [`should_update`]: Asked before each workspace update - return `false` to skip the repo
+/// - [`on_begin`]: Called with the resolved config - use to read run options
 /// - [`on_update_start`]: Called before update begins - use for repo-level setup
 /// - [`on_step_execute`]: Called just before step executes - use for verbose logging
 /// - [`on_completion_status`]: Called with final status - use for success/error messages
+/// - [`on_skipped`]: Called when a repository is skipped - use for skip messages
+/// - [`on_fetch_progress`]: Called as fetch data arrives - use for download bars
 ///
 /// [`on_step`]: UpdateCallbacks::on_step
 /// [`on_complete`]: UpdateCallbacks::on_complete
+/// [`should_update`]: UpdateCallbacks::should_update
+/// [`on_begin`]: UpdateCallbacks::on_begin
 /// [`on_update_start`]: UpdateCallbacks::on_update_start
 /// [`on_step_execute`]: UpdateCallbacks::on_step_execute
 /// [`on_completion_status`]: UpdateCallbacks::on_completion_status
+/// [`on_skipped`]: UpdateCallbacks::on_skipped
+/// [`on_fetch_progress`]: UpdateCallbacks::on_fetch_progress
 /// [`output::NoOpCallbacks`]: crate::output::NoOpCallbacks
 pub trait UpdateCallbacks: Send + Sync {
+    /// Asked by [`update_workspace`] before it starts on `repo`; returning
+    /// `false` skips the repo with the reason "vetoed by callback" and no
+    /// git command is run in it.
+    ///
+    /// Optional - default implementation returns `true`.
+    fn should_update(&self, _repo: &Path) -> bool {
+        true
+    }
+
+    /// Called once per repository, before `on_update_start`, with the config
+    /// the update runs under (remote, branch preferences, flags).
+    ///
+    /// Optional - default implementation does nothing.
+    fn on_begin(&self, _config: &Config) {}
+
     /// Called when a repository update begins.
     ///
     /// Optional - default implementation does nothing.
@@ -61,6 +90,18 @@ pub trait UpdateCallbacks: Send + Sync {
     ///
     /// Optional - default implementation does nothing.
     fn on_completion_status(&self, _success: bool, _error: Option<&str>) {}
+
+    /// Called instead of `on_completion_status` when a repository is skipped.
+    ///
+    /// Optional - default implementation does nothing.
+    fn on_skipped(&self, _reason: &str) {}
+
+    /// Called while fetching, each time git reports how much data it has received.
+    /// `total_bytes` is `None` until the transfer completes, since git does not
+    /// announce the total size up front.
+    ///
+    /// Optional - default implementation does nothing.
+    fn on_fetch_progress(&self, _received_bytes: u64, _total_bytes: Option<u64>) {}
 }
 
 /// Represents a step in the repository update process.
@@ -69,8 +110,9 @@ pub trait UpdateCallbacks: Send + Sync {
 /// in future versions. When matching on `UpdateStep`, use a wildcard pattern
 /// to handle unknown variants gracefully.
 #[non_exhaustive]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
 pub enum UpdateStep {
+    #[value(skip)]
     Started,
     DetectingBranch,
     CheckingChanges,
@@ -80,6 +122,7 @@ pub enum UpdateStep {
     Pulling,
     RestoringBranch,
     PoppingStash,
+    #[value(skip)]
     Completed,
 }
 
@@ -109,11 +152,15 @@ pub struct UpdateResult {
     pub duration: Duration,
 }
 
-/// Outcome of an update: success or failure.
+/// Outcome of an update: success, failure, or a deliberate skip.
+// One outcome per repository, so boxing the larger success variant buys nothing
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum UpdateOutcome {
     Success(UpdateSuccess),
     Failed(UpdateFailure),
+    /// The repository was left untouched on purpose (not an error).
+    Skipped(UpdateSkipped),
 }
 
 /// The original state of HEAD before an update operation.
@@ -123,6 +170,9 @@ pub enum OriginalHead {
     Branch(String),
     /// HEAD was detached at a specific commit SHA.
     DetachedAt(String),
+    /// HEAD was detached at a commit that a tag points to (e.g., "v1.2.3").
+    /// Restoration still uses the SHA; the tag is kept for display.
+    DetachedAtTag { tag: String, sha: String },
 }
 
 impl OriginalHead {
@@ -132,13 +182,17 @@ impl OriginalHead {
         match self {
             OriginalHead::Branch(name) => name,
             OriginalHead::DetachedAt(sha) => sha,
+            OriginalHead::DetachedAtTag { sha, .. } => sha,
         }
     }
 
     /// Returns true if HEAD was detached.
     #[must_use]
     pub fn is_detached(&self) -> bool {
-        matches!(self, OriginalHead::DetachedAt(_))
+        matches!(
+            self,
+            OriginalHead::DetachedAt(_) | OriginalHead::DetachedAtTag { .. }
+        )
     }
 
     /// Returns a display-friendly representation for summaries.
@@ -150,6 +204,7 @@ impl OriginalHead {
                 let short = if sha.len() > 7 { &sha[..7] } else { sha };
                 format!("[{}...detached]", short)
             }
+            OriginalHead::DetachedAtTag { tag, .. } => format!("[at tag {}]", tag),
         }
     }
 }
@@ -158,8 +213,69 @@ impl OriginalHead {
 #[derive(Debug, Clone)]
 pub struct UpdateSuccess {
     pub original_head: OriginalHead,
-    pub master_branch: &'static str,
+    /// The branch that was brought up to date; `None` in fetch-only mode.
+    pub master_branch: Option<String>,
     pub had_stash: bool,
+    /// Number of uncommitted files that were stashed (0 when nothing was stashed).
+    pub stashed_files: usize,
+    /// Number of commits the pull brought into the master branch.
+    pub commits_pulled: usize,
+    /// Non-fatal issues noticed during the update (e.g. ambiguous master branch).
+    pub warnings: Vec<String>,
+    /// `git describe` of the final HEAD (e.g. `v1.2.3-4-gabcdef0-dirty`).
+    /// Only collected in verbose mode to avoid an extra git call per repo.
+    pub describe: Option<String>,
+    /// HEAD before and after the update. Unchanged unless the updated branch
+    /// is the one that was checked out.
+    pub head_sha: ShaChange,
+    /// Tip of `master_branch` before and after the pull.
+    pub master_sha: ShaChange,
+    /// Branches of the remote that the fetch created, sorted (empty when
+    /// the update did not fetch on its own, as with `--current-branch`).
+    pub new_remote_branches: Vec<String>,
+    /// Remote-tracking branches removed by [`UpdateMode::PruneRemoteOnly`],
+    /// sorted (empty in every other mode).
+    pub pruned_remote_branches: Vec<String>,
+    /// The remote branch had not moved and the repository was clean, so
+    /// nothing was fetched, stashed or checked out (`--skip-unchanged`).
+    pub fast_skipped: bool,
+}
+
+impl UpdateSuccess {
+    /// A success that changed nothing, starting from `original_head`; set the
+    /// fields that differ with struct-update syntax.
+    #[must_use]
+    pub fn new(original_head: OriginalHead) -> Self {
+        UpdateSuccess {
+            original_head,
+            master_branch: None,
+            had_stash: false,
+            stashed_files: 0,
+            commits_pulled: 0,
+            warnings: Vec::new(),
+            describe: None,
+            head_sha: ShaChange::default(),
+            master_sha: ShaChange::default(),
+            new_remote_branches: Vec::new(),
+            pruned_remote_branches: Vec::new(),
+            fast_skipped: false,
+        }
+    }
+}
+
+/// A commit before and after an update, for an audit trail of what a run did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShaChange {
+    pub before: String,
+    pub after: String,
+}
+
+impl ShaChange {
+    /// Returns true if the update moved this ref.
+    #[must_use]
+    pub fn changed(&self) -> bool {
+        self.before != self.after
+    }
 }
 
 /// Details of a failed update.
@@ -167,6 +283,29 @@ pub struct UpdateSuccess {
 pub struct UpdateFailure {
     pub error: String,
     pub step: UpdateStep,
+    /// Manual restore instructions, present when the failure left the
+    /// repository away from its original branch or commit.
+    pub recovery_hint: Option<String>,
+    /// The disk filled up ([`git::DiskFull`]); updates of other repositories
+    /// on it would fail the same way.
+    pub disk_full: bool,
+    /// Exit code of the git command that failed, when a git command caused
+    /// the failure (128 is a fatal git error).
+    pub git_exit_code: Option<i32>,
+}
+
+impl UpdateFailure {
+    /// A failure at `step` with no recovery hint, disk-full flag or git exit code.
+    #[must_use]
+    pub fn new(step: UpdateStep, error: impl Into<String>) -> Self {
+        UpdateFailure {
+            error: error.into(),
+            step,
+            recovery_hint: None,
+            disk_full: false,
+            git_exit_code: None,
+        }
+    }
 }
 
 impl fmt::Display for UpdateFailure {
@@ -175,9 +314,140 @@ impl fmt::Display for UpdateFailure {
     }
 }
 
+/// Details of a skipped update.
+#[derive(Debug, Clone)]
+pub struct UpdateSkipped {
+    pub reason: String,
+    pub step: UpdateStep,
+}
+
+impl fmt::Display for UpdateSkipped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "skipped at {:?}: {}", self.step, self.reason)
+    }
+}
+
+/// Workspace-wide totals, safe to update concurrently from parallel updates.
+///
+/// Feed each finished repository to [`WorkspaceStats::record`] (typically from
+/// [`UpdateCallbacks::on_complete`]); only successful updates contribute.
+#[derive(Debug, Default)]
+pub struct WorkspaceStats {
+    repos_updated: AtomicUsize,
+    commits_pulled: AtomicUsize,
+    files_stashed: AtomicUsize,
+}
+
+impl WorkspaceStats {
+    /// Adds a finished repository's numbers to the totals.
+    pub fn record(&self, result: &UpdateResult) {
+        if let UpdateOutcome::Success(success) = &result.outcome {
+            self.repos_updated.fetch_add(1, Ordering::Relaxed);
+            self.commits_pulled
+                .fetch_add(success.commits_pulled, Ordering::Relaxed);
+            self.files_stashed
+                .fetch_add(success.stashed_files, Ordering::Relaxed);
+        }
+    }
+
+    #[must_use]
+    pub fn repos_updated(&self) -> usize {
+        self.repos_updated.load(Ordering::Relaxed)
+    }
+
+    #[must_use]
+    pub fn commits_pulled(&self) -> usize {
+        self.commits_pulled.load(Ordering::Relaxed)
+    }
+
+    #[must_use]
+    pub fn files_stashed(&self) -> usize {
+        self.files_stashed.load(Ordering::Relaxed)
+    }
+}
+
+/// Update outcomes of the repositories whose remote is on one host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostGroup {
+    /// Host from the remote URL, or [`LOCAL_REMOTE_HOST`] / [`NO_REMOTE_HOST`].
+    pub host: String,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+impl HostGroup {
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.succeeded + self.failed + self.skipped
+    }
+}
+
+/// Groups results by the host of each repository's remote URL, sorted by
+/// host, so a server that is down stands out from one misconfigured repo.
+pub fn group_by_host(results: &[UpdateResult], config: &Config) -> Vec<HostGroup> {
+    let label = |result: &UpdateResult| remote_host_label(&result.path, config);
+    let hosts: Vec<String> = match local_pool(config) {
+        Some(pool) => pool.install(|| results.par_iter().map(label).collect()),
+        None => results.iter().map(label).collect(),
+    };
+
+    let mut groups: Vec<HostGroup> = Vec::new();
+    for (result, host) in results.iter().zip(hosts) {
+        let index = match groups.iter().position(|group| group.host == host) {
+            Some(index) => index,
+            None => {
+                groups.push(HostGroup {
+                    host,
+                    ..HostGroup::default()
+                });
+                groups.len() - 1
+            }
+        };
+        let group = &mut groups[index];
+        match result.outcome {
+            UpdateOutcome::Success(_) => group.succeeded += 1,
+            UpdateOutcome::Failed(_) => group.failed += 1,
+            UpdateOutcome::Skipped(_) => group.skipped += 1,
+        }
+    }
+    groups.sort_by(|a, b| a.host.cmp(&b.host));
+    groups
+}
+
+fn remote_host_label(path: &Path, config: &Config) -> String {
+    match git::remote_url(path, config, config.remote(), config.git_logger()) {
+        Ok(Some(url)) => git::remote_host(&url).unwrap_or_else(|| LOCAL_REMOTE_HOST.to_string()),
+        Ok(None) | Err(_) => NO_REMOTE_HOST.to_string(),
+    }
+}
+
 struct UpdateError {
     source: anyhow::Error,
     step: UpdateStep,
+    recovery_hint: Option<String>,
+    /// Changes stashed by the failed update are still in the stash.
+    stash_left: bool,
+}
+
+impl UpdateError {
+    /// Returns true if the update failed at a step `config` retries and left
+    /// the repository as it started, so running it again is safe.
+    fn is_retryable(&self, config: &Config) -> bool {
+        config.retries_failures_at(self.step) && self.recovery_hint.is_none() && !self.stash_left
+    }
+
+    fn is_untracked_conflict(&self) -> bool {
+        self.source
+            .downcast_ref::<git::UntrackedConflict>()
+            .is_some()
+    }
+
+    fn is_local_changes_conflict(&self) -> bool {
+        self.source
+            .downcast_ref::<git::LocalChangesConflict>()
+            .is_some()
+    }
 }
 
 /// Formats the full error chain from an anyhow error.
@@ -191,226 +461,2235 @@ fn format_error_chain(error: &anyhow::Error) -> String {
     chain.join(": ")
 }
 
-/// Returns true if the given path contains a `.git` directory.
-#[must_use]
-pub fn is_git_repo(path: &Path) -> bool {
-    path.join(GIT_DIR).is_dir()
+/// How a directory was classified during repository discovery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirClassification {
+    /// Contains a `.git` directory.
+    Repo,
+    /// Contains a `.git` file pointing at an existing git directory
+    /// (linked worktrees and submodules).
+    LinkedRepo { git_dir: PathBuf },
+    /// Not recognized as a repository, with the reason why.
+    NotARepo(NotARepoReason),
 }
 
-/// Finds all immediate child directories that are git repositories.
-/// Does not search recursively into nested directories.
-#[must_use]
-pub fn find_git_repos(path: &Path) -> Vec<PathBuf> {
-    std::fs::read_dir(path)
-        .ok()
-        .into_iter()
-        .flatten()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().is_dir() && is_git_repo(&e.path()))
-        .map(|e| e.path())
-        .collect()
+impl DirClassification {
+    #[must_use]
+    pub fn is_repo(&self) -> bool {
+        !matches!(self, DirClassification::NotARepo(_))
+    }
 }
 
-/// Updates a single repository with callbacks for progress and output.
-pub fn update<C>(path: &Path, callbacks: &C, config: &Config) -> UpdateResult
-where
-    C: UpdateCallbacks,
-{
-    callbacks.on_step(&UpdateStep::Started);
-
-    let repo_name = path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or(DEFAULT_REPO_NAME);
-    callbacks.on_update_start(repo_name);
-
-    let start = std::time::Instant::now();
-    let result = do_update(path, callbacks, config);
-    let duration = start.elapsed();
-
-    callbacks.on_step(&UpdateStep::Completed);
+/// Why a directory was not recognized as a git repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotARepoReason {
+    /// There is no `.git` entry at all.
+    NoGitDir,
+    /// `.git` is a file whose `gitdir:` target does not exist.
+    DanglingGitFile { target: PathBuf },
+    /// `.git` is a file without a `gitdir:` line.
+    InvalidGitFile,
+    /// The directory or its `.git` entry could not be read.
+    PermissionDenied,
+    /// The directory entry could not be statted (a broken symlink, or a path
+    /// Windows cannot open).
+    Unreadable { error: String },
+    /// The name is reserved on Windows (`CON`, `NUL`, `COM1`, ...), so git
+    /// cannot work inside it.
+    ReservedName,
+}
 
-    match result {
-        Ok(success) => {
-            callbacks.on_completion_status(true, None);
-            UpdateResult {
-                path: path.to_path_buf(),
-                outcome: UpdateOutcome::Success(success),
-                duration,
+impl fmt::Display for NotARepoReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotARepoReason::NoGitDir => write!(f, "no {} directory", GIT_DIR),
+            NotARepoReason::DanglingGitFile { target } => {
+                write!(
+                    f,
+                    "{} file points to missing '{}'",
+                    GIT_DIR,
+                    target.display()
+                )
             }
+            NotARepoReason::InvalidGitFile => write!(f, "{} file has no gitdir line", GIT_DIR),
+            NotARepoReason::PermissionDenied => write!(f, "permission denied"),
+            NotARepoReason::Unreadable { error } => write!(f, "cannot be read: {}", error),
+            NotARepoReason::ReservedName => write!(f, "reserved name on Windows"),
         }
-        Err(error) => {
-            // Format full error chain for better debugging
-            let error_chain = format_error_chain(&error.source);
-            callbacks.on_completion_status(false, Some(&error_chain));
-            UpdateResult {
-                path: path.to_path_buf(),
-                outcome: UpdateOutcome::Failed(UpdateFailure {
-                    error: error_chain,
-                    step: error.step,
-                }),
-                duration,
-            }
+    }
+}
+
+/// A multi-step git operation the user has started but not finished.
+///
+/// Switching branches in the middle of one of these would disrupt the user's
+/// work, so repositories in this state are skipped rather than updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InProgressOperation {
+    Rebase,
+    Merge,
+    Bisect,
+    CherryPick,
+    Revert,
+}
+
+impl InProgressOperation {
+    /// The command that finishes or abandons the operation.
+    #[must_use]
+    pub fn abort_hint(self) -> &'static str {
+        match self {
+            InProgressOperation::Rebase => "git rebase --continue or --abort",
+            InProgressOperation::Merge => "git merge --continue or --abort",
+            InProgressOperation::Bisect => "git bisect reset",
+            InProgressOperation::CherryPick => "git cherry-pick --continue or --abort",
+            InProgressOperation::Revert => "git revert --continue or --abort",
         }
     }
 }
 
-/// Updates multiple repositories in parallel with per-repository callbacks.
-/// In verbose mode, runs sequentially for readable output.
-pub fn update_workspace<F, C>(
-    repos: &[PathBuf],
-    make_callbacks: F,
+impl fmt::Display for InProgressOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            InProgressOperation::Rebase => "rebase",
+            InProgressOperation::Merge => "merge",
+            InProgressOperation::Bisect => "bisect",
+            InProgressOperation::CherryPick => "cherry-pick",
+            InProgressOperation::Revert => "revert",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Returns the operation in progress in the repository at `path`, if any.
+pub fn operation_in_progress(
+    path: &Path,
     config: &Config,
-) -> Vec<UpdateResult>
-where
-    F: Fn(&Path) -> C + Sync,
-    C: UpdateCallbacks,
-{
-    let process_repo = |path: &PathBuf| {
-        let callbacks = make_callbacks(path);
-        let result = update(path, &callbacks, config);
-        callbacks.on_complete(&result);
-        result
-    };
+) -> anyhow::Result<Option<InProgressOperation>> {
+    let git_dir = git::git_dir(path, config, config.git_logger())?;
+    Ok(detect_operation(&git_dir))
+}
 
-    if config.is_verbose() {
-        // Sequential for readable verbose output
-        repos.iter().map(process_repo).collect()
+/// Inspects the state files git leaves in the git directory while an
+/// operation is under way.
+fn detect_operation(git_dir: &Path) -> Option<InProgressOperation> {
+    let has = |name: &str| git_dir.join(name).exists();
+
+    if has("rebase-merge") || has("rebase-apply") {
+        Some(InProgressOperation::Rebase)
+    } else if has("MERGE_HEAD") {
+        Some(InProgressOperation::Merge)
+    } else if has("CHERRY_PICK_HEAD") {
+        Some(InProgressOperation::CherryPick)
+    } else if has("REVERT_HEAD") {
+        Some(InProgressOperation::Revert)
+    } else if has("BISECT_LOG") {
+        Some(InProgressOperation::Bisect)
+    } else if has("sequencer") {
+        // A multi-commit cherry-pick or revert paused between commits
+        Some(InProgressOperation::CherryPick)
     } else {
-        // Parallel for performance
-        repos.par_iter().map(process_repo).collect()
+        None
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Classifies a directory as a repository or explains why it is not one.
+#[must_use]
+pub fn classify_dir(path: &Path) -> DirClassification {
+    use std::io::ErrorKind;
 
-    #[test]
-    fn test_update_step_display_labels() {
-        assert_eq!(UpdateStep::Started.to_string(), "Starting");
-        assert_eq!(UpdateStep::DetectingBranch.to_string(), "Detecting branch");
-        assert_eq!(UpdateStep::CheckingChanges.to_string(), "Checking changes");
-        assert_eq!(UpdateStep::Fetching.to_string(), "Fetching");
-        assert_eq!(UpdateStep::Stashing.to_string(), "Stashing");
-        assert_eq!(UpdateStep::CheckingOut.to_string(), "Checking out");
-        assert_eq!(UpdateStep::Pulling.to_string(), "Pulling");
-        assert_eq!(UpdateStep::RestoringBranch.to_string(), "Restoring branch");
-        assert_eq!(UpdateStep::PoppingStash.to_string(), "Popping stash");
-        assert_eq!(UpdateStep::Completed.to_string(), "Completed");
+    let git_path = path.join(GIT_DIR);
+    let metadata = match std::fs::metadata(&git_path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+            return DirClassification::NotARepo(NotARepoReason::PermissionDenied);
+        }
+        Err(_) => return DirClassification::NotARepo(NotARepoReason::NoGitDir),
+    };
+    if metadata.is_dir() {
+        return DirClassification::Repo;
     }
 
-    #[test]
-    fn test_original_head_detached_display_and_ref() {
-        let head = OriginalHead::DetachedAt("abcdef1234567890".to_string());
-        assert_eq!(head.git_ref(), "abcdef1234567890");
-        assert!(head.is_detached());
-        assert_eq!(head.display(), "[abcdef1...detached]");
-    }
+    let contents = match std::fs::read_to_string(&git_path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+            return DirClassification::NotARepo(NotARepoReason::PermissionDenied);
+        }
+        Err(_) => return DirClassification::NotARepo(NotARepoReason::InvalidGitFile),
+    };
+    let Some(target) = contents
+        .lines()
+        .find_map(|line| line.strip_prefix("gitdir:"))
+        .map(str::trim)
+        .filter(|target| !target.is_empty())
+    else {
+        return DirClassification::NotARepo(NotARepoReason::InvalidGitFile);
+    };
 
-    #[test]
-    fn test_original_head_branch_display_and_ref() {
-        let head = OriginalHead::Branch("feature-x".to_string());
-        assert_eq!(head.git_ref(), "feature-x");
-        assert!(!head.is_detached());
-        assert_eq!(head.display(), "[feature-x]");
+    // Relative gitdir paths are relative to the directory containing `.git`
+    let git_dir = path.join(target);
+    if git_dir.is_dir() {
+        DirClassification::LinkedRepo { git_dir }
+    } else {
+        DirClassification::NotARepo(NotARepoReason::DanglingGitFile { target: git_dir })
     }
+}
 
-    #[test]
-    fn test_update_failure_display() {
-        let failure = UpdateFailure {
-            error: "boom".to_string(),
-            step: UpdateStep::Fetching,
-        };
-        assert_eq!(failure.to_string(), "failed at Fetching: boom");
-    }
+/// Returns true if the given path is a git repository: it contains a `.git`
+/// directory, or a `.git` file pointing at one (see [`classify_dir`]).
+#[must_use]
+pub fn is_git_repo(path: &Path) -> bool {
+    classify_dir(path).is_repo()
 }
 
-fn run_step<T, C>(
-    step: UpdateStep,
-    path: &Path,
-    callbacks: &C,
-    operation: impl FnOnce() -> anyhow::Result<T>,
-) -> Result<T, UpdateError>
-where
-    C: UpdateCallbacks,
-{
-    use anyhow::Context;
-    callbacks.on_step(&step);
-    callbacks.on_step_execute(&step);
-    operation()
-        .with_context(|| format!("in repository '{}'", path.display()))
-        .map_err(|e| UpdateError { source: e, step })
+/// Returns true if `path` has a `.git` file whose `gitdir:` target no longer
+/// exists, typically a linked worktree whose main repository was moved.
+#[must_use]
+pub fn is_broken_worktree_link(path: &Path) -> bool {
+    matches!(
+        classify_dir(path),
+        DirClassification::NotARepo(NotARepoReason::DanglingGitFile { .. })
+    )
 }
 
-/// Checks out the master branch, falling back to main if master doesn't exist.
-fn checkout_master_or_main_branch<C>(
-    path: &Path,
-    callbacks: &C,
-    config: &Config,
-) -> Result<&'static str, UpdateError>
-where
-    C: UpdateCallbacks,
-{
-    let logger = config.git_logger();
-    match run_step(UpdateStep::CheckingOut, path, callbacks, || {
-        git::checkout(path, config, MASTER_BRANCH, logger)
-    }) {
-        Ok(_) => Ok(MASTER_BRANCH),
-        Err(_) => {
-            run_step(UpdateStep::CheckingOut, path, callbacks, || {
-                git::checkout(path, config, MAIN_BRANCH, logger)
-            })?;
-            Ok(MAIN_BRANCH)
-        }
+/// Fails with a "broken worktree link" diagnosis when [`is_broken_worktree_link`]
+/// holds, before any git command would fail on it less clearly.
+pub fn check_worktree_link(path: &Path) -> anyhow::Result<()> {
+    if let DirClassification::NotARepo(reason @ NotARepoReason::DanglingGitFile { .. }) =
+        classify_dir(path)
+    {
+        anyhow::bail!(broken_worktree_link_message(path, &reason));
     }
+    Ok(())
 }
 
-/// Core update logic: stash, checkout main, fetch, restore branch, pop stash.
-fn do_update<C>(path: &Path, callbacks: &C, config: &Config) -> Result<UpdateSuccess, UpdateError>
-where
-    C: UpdateCallbacks,
-{
-    let logger = config.git_logger();
+fn broken_worktree_link_message(path: &Path, reason: &NotARepoReason) -> String {
+    format!(
+        "broken worktree link: {}; if the main repository was moved, run \
+         `git worktree repair {}` from it",
+        reason,
+        path.display()
+    )
+}
 
-    let branch_name = run_step(UpdateStep::DetectingBranch, path, callbacks, || {
-        git::get_current_branch(path, config, logger)
-    })?;
+/// Returns true if `path` is laid out like a bare repository (`git clone
+/// --bare` or `--mirror`): a `HEAD` file next to `objects` and `refs`
+/// directories. Workspace discovery does not look for these; they are only
+/// updated when named directly, e.g. by running from inside one.
+#[must_use]
+pub fn is_bare_repo(path: &Path) -> bool {
+    path.join("HEAD").is_file() && path.join("objects").is_dir() && path.join("refs").is_dir()
+}
 
-    // Handle detached HEAD: store commit SHA instead of "HEAD"
-    let original_head = if branch_name == "HEAD" {
-        let commit = run_step(UpdateStep::DetectingBranch, path, callbacks, || {
-            git::get_current_commit(path, config, logger)
-        })?;
-        OriginalHead::DetachedAt(commit)
-    } else {
-        OriginalHead::Branch(branch_name)
-    };
+/// Lists the immediate child directories that were not recognized as
+/// repositories, with the reason for each. Useful to explain discovery results.
+#[must_use]
+pub fn rejected_dirs(path: &Path) -> Vec<(PathBuf, NotARepoReason)> {
+    discover_workspace(path).rejected
+}
 
-    let is_dirty = run_step(UpdateStep::CheckingChanges, path, callbacks, || {
-        git::has_uncommitted_changes(path, config, logger)
-    })?;
+/// Finds all immediate child directories that are git repositories.
+/// Does not search recursively into nested directories.
+///
+/// Results are sorted by path. Entries that resolve to the same directory
+/// (symlinks, or case variants on case-insensitive filesystems) are reported once.
+/// Entries that cannot be statted are skipped (see [`rejected_dirs`]).
+#[must_use]
+pub fn find_git_repos(path: &Path) -> Vec<PathBuf> {
+    discover_workspace(path).repos
+}
 
-    run_step(UpdateStep::Fetching, path, callbacks, || {
-        git::fetch_prune(path, config, logger)
-    })?;
+/// The immediate children of a workspace, as found by [`discover_workspace`].
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceDiscovery {
+    /// The repositories, as returned by [`find_git_repos`].
+    pub repos: Vec<PathBuf>,
+    /// The directories that are not repositories, as returned by [`rejected_dirs`].
+    pub rejected: Vec<(PathBuf, NotARepoReason)>,
+}
 
-    let had_stash = if is_dirty {
-        run_step(UpdateStep::Stashing, path, callbacks, || {
-            git::stash(path, config, logger)
-        })?
-    } else {
-        false
-    };
+/// Classifies the immediate children of `path` once, splitting them into
+/// repositories and rejected directories.
+#[must_use]
+pub fn discover_workspace(path: &Path) -> WorkspaceDiscovery {
+    let mut repos = Vec::new();
+    let mut rejected = Vec::new();
+    for (child, classification) in classify_children(path) {
+        match classification {
+            DirClassification::NotARepo(reason) => rejected.push((child, reason)),
+            _ => repos.push(child),
+        }
+    }
+    // Prefer real directories over symlinks when both point at the same repo
+    repos.sort_by_key(|p| (p.is_symlink(), p.clone()));
+    let mut repos = dedupe_repos(repos, is_case_insensitive_fs(path));
+    repos.sort();
+    rejected.sort_by(|a, b| a.0.cmp(&b.0));
+    WorkspaceDiscovery { repos, rejected }
+}
 
-    let master_branch = checkout_master_or_main_branch(path, callbacks, config)?;
+/// Classifies every immediate child of `path` that is, or might be, a directory.
+///
+/// Plain files are left out. An entry whose metadata cannot be read is kept as
+/// [`NotARepoReason::Unreadable`] so one bad entry never hides the rest.
+fn classify_children(path: &Path) -> Vec<(PathBuf, DirClassification)> {
+    std::fs::read_dir(extended_length_path(path))
+        .ok()
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter_map(|entry| {
+            let path = extended_length_path(&entry.path());
+            let classification = match std::fs::metadata(&path) {
+                Ok(metadata) if !metadata.is_dir() => return None,
+                Ok(_)
+                    if cfg!(windows) && path.file_name().is_some_and(is_windows_reserved_name) =>
+                {
+                    DirClassification::NotARepo(NotARepoReason::ReservedName)
+                }
+                Ok(_) => classify_dir(&path),
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                    DirClassification::NotARepo(NotARepoReason::PermissionDenied)
+                }
+                Err(e) => DirClassification::NotARepo(NotARepoReason::Unreadable {
+                    error: e.to_string(),
+                }),
+            };
+            Some((path, classification))
+        })
+        .collect()
+}
+
+/// Returns true if `name` is a device name Windows reserves in every
+/// directory, with or without an extension (`nul`, `COM1.txt`, `aux `).
+fn is_windows_reserved_name(name: &std::ffi::OsStr) -> bool {
+    let Some(name) = name.to_str() else {
+        return false;
+    };
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    let upper = stem.to_ascii_uppercase();
+    match upper.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => ["COM", "LPT"].iter().any(|prefix| {
+            upper
+                .strip_prefix(prefix)
+                .is_some_and(|n| n.len() == 1 && matches!(n.as_bytes()[0], b'1'..=b'9'))
+        }),
+    }
+}
+
+/// Adds the `\\?\` extended-length prefix to absolute Windows paths that are
+/// too long for the classic `MAX_PATH` APIs, so discovery can still stat them.
+#[cfg(windows)]
+fn extended_length_path(path: &Path) -> PathBuf {
+    use crate::constants::WINDOWS_MAX_PATH;
+
+    let Some(raw) = path.to_str() else {
+        return path.to_path_buf();
+    };
+    if raw.len() < WINDOWS_MAX_PATH || raw.starts_with(r"\\?\") || !path.is_absolute() {
+        return path.to_path_buf();
+    }
+    match raw.strip_prefix(r"\\") {
+        Some(unc) => PathBuf::from(format!(r"\\?\UNC\{}", unc)),
+        None => PathBuf::from(format!(r"\\?\{}", raw)),
+    }
+}
+
+#[cfg(not(windows))]
+fn extended_length_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Verifies once that the network is reachable before a workspace update.
+///
+/// Contacts `origin` of the first repository only, so a workspace whose repos
+/// live on different hosts may still see per-repo fetch failures. When the
+/// network is down, this turns N identical fetch timeouts into one error.
+pub fn check_connectivity(repos: &[PathBuf], config: &Config) -> anyhow::Result<()> {
+    let Some(first) = repos.first() else {
+        return Ok(());
+    };
+    git::ls_remote_head(first, config, config.remote(), config.git_logger())
+        .map_err(|e| anyhow::anyhow!("network unreachable: {:#}", e))
+}
+
+/// Where a repository stands, as reported by [`health_scan`].
+///
+/// Comparisons use the remote-tracking refs from the last fetch; the scan
+/// itself never fetches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HealthBucket {
+    /// No changes, and the current branch has nothing left to pull.
+    /// Branches without an upstream land here too.
+    CleanCurrent,
+    /// No changes, but the current branch is behind its upstream.
+    CleanBehind,
+    /// Uncommitted changes (including untracked files).
+    Dirty,
+    /// HEAD is not on a branch.
+    Detached,
+    /// The current branch and its upstream both have commits the other lacks.
+    Diverged,
+    /// The configured remote does not exist.
+    NoRemote,
+}
+
+impl HealthBucket {
+    /// Every bucket, in the order a report lists them.
+    pub const ALL: [HealthBucket; 6] = [
+        HealthBucket::CleanCurrent,
+        HealthBucket::CleanBehind,
+        HealthBucket::Dirty,
+        HealthBucket::Detached,
+        HealthBucket::Diverged,
+        HealthBucket::NoRemote,
+    ];
+}
+
+impl fmt::Display for HealthBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HealthBucket::CleanCurrent => write!(f, "clean and up to date"),
+            HealthBucket::CleanBehind => write!(f, "clean, behind upstream"),
+            HealthBucket::Dirty => write!(f, "uncommitted changes"),
+            HealthBucket::Detached => write!(f, "detached HEAD"),
+            HealthBucket::Diverged => write!(f, "diverged from upstream"),
+            HealthBucket::NoRemote => write!(f, "no remote"),
+        }
+    }
+}
+
+/// Result of [`health_scan`]: each repository's bucket, in input order, plus
+/// the repositories that could not be inspected.
+#[derive(Debug, Clone, Default)]
+pub struct HealthReport {
+    pub repos: Vec<(PathBuf, HealthBucket)>,
+    pub errors: Vec<(PathBuf, String)>,
+    /// Age of each repository's default branch, where one exists locally.
+    pub default_branch_ages: Vec<(PathBuf, BranchAge)>,
+    /// Time since each repository last fetched, where it ever has (see [`last_fetched`]).
+    pub fetch_ages: Vec<(PathBuf, Duration)>,
+    /// Repositories with more git-daily stashes than an update stashes on
+    /// top of (see [`cleanup::stale_stashes`]).
+    pub stale_stashes: Vec<(PathBuf, cleanup::StaleStashes)>,
+}
+
+/// How long ago a branch was last committed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchAge {
+    pub branch: String,
+    pub age: Duration,
+}
+
+impl HealthReport {
+    /// Repositories whose default branch is older than `threshold`, oldest first.
+    #[must_use]
+    pub fn stale(&self, threshold: Duration) -> Vec<(&Path, &BranchAge)> {
+        let mut stale: Vec<(&Path, &BranchAge)> = self
+            .default_branch_ages
+            .iter()
+            .filter(|(_, age)| age.age > threshold)
+            .map(|(path, age)| (path.as_path(), age))
+            .collect();
+        stale.sort_by_key(|(_, age)| std::cmp::Reverse(age.age));
+        stale
+    }
+
+    /// The default branch age of the repository at `path`, if known.
+    #[must_use]
+    pub fn default_branch_age(&self, path: &Path) -> Option<&BranchAge> {
+        self.default_branch_ages
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, age)| age)
+    }
+
+    /// Time since the repository at `path` last fetched, if known.
+    #[must_use]
+    pub fn fetch_age(&self, path: &Path) -> Option<Duration> {
+        self.fetch_ages
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, age)| *age)
+    }
+
+    /// Repositories by time since their last fetch, least recently fetched first.
+    #[must_use]
+    pub fn least_recently_fetched(&self) -> Vec<(&Path, Duration)> {
+        let mut ages: Vec<(&Path, Duration)> = self
+            .fetch_ages
+            .iter()
+            .map(|(path, age)| (path.as_path(), *age))
+            .collect();
+        ages.sort_by_key(|(_, age)| std::cmp::Reverse(*age));
+        ages
+    }
+
+    /// The repositories in `bucket`, in input order.
+    #[must_use]
+    pub fn in_bucket(&self, bucket: HealthBucket) -> Vec<&Path> {
+        self.repos
+            .iter()
+            .filter(|(_, b)| *b == bucket)
+            .map(|(path, _)| path.as_path())
+            .collect()
+    }
+}
+
+/// Classifies each repository into a [`HealthBucket`] without changing
+/// anything: no fetch, checkout, or stash.
+///
+/// Checks run in order and the first match wins: missing remote, detached
+/// HEAD, uncommitted changes, then the current branch against its upstream.
+pub fn health_scan(repos: &[PathBuf], config: &Config) -> HealthReport {
+    let now = SystemTime::now();
+    let scan = |path: &PathBuf| {
+        (
+            path.clone(),
+            classify_health(path, config),
+            default_branch_age(path, config, now),
+            last_fetched(path, config).map(|fetched| age_at(now, fetched)),
+            // Best effort, like the ages: a failed `git stash list` shows as no pile-up
+            cleanup::stale_stashes(path, config).ok().flatten(),
+        )
+    };
+    let scanned: Vec<_> = match local_pool(config) {
+        Some(pool) => pool.install(|| repos.par_iter().map(scan).collect()),
+        None => repos.iter().map(scan).collect(),
+    };
+
+    let mut report = HealthReport::default();
+    for (path, bucket, age, fetch_age, stale_stashes) in scanned {
+        if let Some(stale_stashes) = stale_stashes {
+            report.stale_stashes.push((path.clone(), stale_stashes));
+        }
+        if let Some(age) = age {
+            report.default_branch_ages.push((path.clone(), age));
+        }
+        if let Some(fetch_age) = fetch_age {
+            report.fetch_ages.push((path.clone(), fetch_age));
+        }
+        match bucket {
+            Ok(bucket) => report.repos.push((path, bucket)),
+            Err(e) => report.errors.push((path, format_error_chain(&e))),
+        }
+    }
+    report
+}
+
+/// Age of the last commit on the first default branch that exists locally
+/// (a branch override wins). Best effort: any error yields `None`.
+fn default_branch_age(path: &Path, config: &Config, now: SystemTime) -> Option<BranchAge> {
+    let logger = config.git_logger();
+    let branch = match config.branch_override(path) {
+        Some(branch) => branch,
+        None => config.default_branches().into_iter().find(|branch| {
+            git::local_branch_exists(path, config, branch, logger).unwrap_or(false)
+        })?,
+    };
+    let committed = git::commit_time(path, config, branch, logger).ok()?;
+    Some(BranchAge {
+        branch: branch.to_string(),
+        age: age_at(now, committed),
+    })
+}
+
+/// Time from `then` to `now`; a time in the future (clock skew) counts as brand new.
+fn age_at(now: SystemTime, then: SystemTime) -> Duration {
+    now.duration_since(then).unwrap_or_default()
+}
+
+/// When the repository last fetched: the modification time of `FETCH_HEAD`,
+/// or for a repository that has never run `git fetch` itself (a push with
+/// `-u` also creates remote-tracking refs), the newest loose ref under
+/// `refs/remotes`, or `packed-refs` if it holds remote-tracking refs (after
+/// `git gc` or `git pack-refs` there may be no loose ones left). Best effort:
+/// `None` when none of these exists or on any error.
+pub fn last_fetched(path: &Path, config: &Config) -> Option<SystemTime> {
+    let git_dir = git::git_dir(path, config, config.git_logger()).ok()?;
+    if let Ok(modified) = std::fs::metadata(git_dir.join("FETCH_HEAD")).and_then(|m| m.modified()) {
+        return Some(modified);
+    }
+    let loose = newest_modified(&git_dir.join("refs").join("remotes"));
+    loose.max(packed_remote_refs_modified(&git_dir))
+}
+
+/// Modification time of `packed-refs`, if it lists any remote-tracking ref.
+fn packed_remote_refs_modified(git_dir: &Path) -> Option<SystemTime> {
+    let packed = git_dir.join("packed-refs");
+    let contents = std::fs::read_to_string(&packed).ok()?;
+    contents
+        .lines()
+        .any(|line| line.contains(" refs/remotes/"))
+        .then(|| std::fs::metadata(&packed).and_then(|m| m.modified()).ok())
+        .flatten()
+}
+
+/// Newest modification time of the files under `dir`, recursively.
+fn newest_modified(dir: &Path) -> Option<SystemTime> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let file_type = entry.file_type().ok()?;
+            if file_type.is_dir() {
+                newest_modified(&entry.path())
+            } else {
+                entry.metadata().and_then(|m| m.modified()).ok()
+            }
+        })
+        .max()
+}
+
+fn classify_health(path: &Path, config: &Config) -> anyhow::Result<HealthBucket> {
+    let logger = config.git_logger();
+    if !git::has_remote(path, config, config.remote(), logger)? {
+        return Ok(HealthBucket::NoRemote);
+    }
+    let branch = git::get_current_branch(path, config, logger)?;
+    if branch == DETACHED_HEAD {
+        return Ok(HealthBucket::Detached);
+    }
+    if git::count_uncommitted_files(path, config, logger)? > 0 {
+        return Ok(HealthBucket::Dirty);
+    }
+    let Some(upstream) = git::branch_upstream(path, config, &branch, logger)? else {
+        return Ok(HealthBucket::CleanCurrent);
+    };
+    let bucket = match git::ahead_behind(path, config, &branch, &upstream, logger)? {
+        (_, 0) => HealthBucket::CleanCurrent,
+        (0, _) => HealthBucket::CleanBehind,
+        _ => HealthBucket::Diverged,
+    };
+    Ok(bucket)
+}
+
+/// Read-only state of one repository, from [`status`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoScan {
+    pub path: PathBuf,
+    /// Checked-out branch, or `HEAD` when detached.
+    pub branch: String,
+    /// Uncommitted files, counted the way an update counts them (honouring
+    /// `ignore_dirty_paths` and `ignore_whitespace_dirty`).
+    pub dirty_files: usize,
+    /// Local branches (`refs/heads/*`).
+    pub local_branches: usize,
+    /// Local branches with no upstream configured; a growing number of these
+    /// usually means finished work that was never cleaned up.
+    pub branches_without_upstream: usize,
+    /// Time since the last fetch (see [`last_fetched`]); `None` if it never fetched.
+    pub fetched_ago: Option<Duration>,
+}
+
+impl RepoScan {
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_files > 0
+    }
+}
+
+/// Result of [`status`]: one [`RepoScan`] per repository, in input order,
+/// plus the repositories that could not be inspected.
+#[derive(Debug, Clone, Default)]
+pub struct StatusScan {
+    pub repos: Vec<RepoScan>,
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl StatusScan {
+    /// Paths of the scanned repositories `keep` selects, in input order,
+    /// ready to pass to [`update_workspace`].
+    #[must_use]
+    pub fn paths_where<P>(&self, keep: P) -> Vec<PathBuf>
+    where
+        P: Fn(&RepoScan) -> bool,
+    {
+        self.repos
+            .iter()
+            .filter(|scan| keep(scan))
+            .map(|scan| scan.path.clone())
+            .collect()
+    }
+
+    /// Paths of the repositories with uncommitted changes.
+    #[must_use]
+    pub fn dirty_paths(&self) -> Vec<PathBuf> {
+        self.paths_where(RepoScan::is_dirty)
+    }
+}
+
+/// Reads the branch, dirtiness, local branch counts and last fetch of each
+/// repository without changing anything, for a scan-then-update workflow: review the
+/// scan, then pass the selected paths to [`update_workspace`].
+pub fn status(repos: &[PathBuf], config: &Config) -> StatusScan {
+    let scan = |path: &PathBuf| (path.clone(), scan_repo(path, config));
+    let scanned: Vec<_> = match local_pool(config) {
+        Some(pool) => pool.install(|| repos.par_iter().map(scan).collect()),
+        None => repos.iter().map(scan).collect(),
+    };
+
+    let mut report = StatusScan::default();
+    for (path, scan) in scanned {
+        match scan {
+            Ok(scan) => report.repos.push(scan),
+            Err(e) => report.errors.push((path, format_error_chain(&e))),
+        }
+    }
+    report
+}
+
+fn scan_repo(path: &Path, config: &Config) -> anyhow::Result<RepoScan> {
+    let excluded = config.ignore_dirty_paths.as_deref().unwrap_or_default();
+    let status = git::repo_status(path, config, excluded, config.git_logger())?;
+    let local_branches = git::local_branches(path, config, config.git_logger())?;
+    let tracking = git::list_branches_with_upstream(path, config, config.git_logger())?;
+    let branches_without_upstream = tracking
+        .lines()
+        .filter(|line| {
+            line.split_once('|')
+                .is_some_and(|(_, upstream)| upstream.is_empty())
+        })
+        .count();
+    Ok(RepoScan {
+        path: path.to_path_buf(),
+        branch: status.branch,
+        dirty_files: discount_whitespace_dirt(path, config, status.dirty_files)?,
+        local_branches: local_branches.len(),
+        branches_without_upstream,
+        fetched_ago: last_fetched(path, config).map(|fetched| age_at(SystemTime::now(), fetched)),
+    })
+}
+
+/// The steps an update would run on a repository in its current state,
+/// as shown by `--explain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdatePlan {
+    /// Checked-out branch, or `HEAD` when detached.
+    pub branch: String,
+    /// Changes the update would stash (0 when clean).
+    pub dirty_files: usize,
+    /// Steps in the order they would run.
+    pub steps: Vec<UpdateStep>,
+    /// Set when the update would stop after the last step and skip the repo.
+    pub skip_reason: Option<String>,
+    /// Set when the update would stop after the last step and fail.
+    pub fail_reason: Option<String>,
+    /// Branch the update would check out and pull (`None` when it pulls the
+    /// current branch in place or does not pull at all).
+    pub checkout_branch: Option<String>,
+    /// Commits the pulled branch is behind its remote-tracking branch, as of
+    /// the last fetch (the update's own fetch may find more). `None` when
+    /// nothing would be pulled or the branches cannot be compared.
+    pub remote_behind: Option<usize>,
+    /// Branch, or commit when detached, the update would return to afterwards.
+    pub restore_to: Option<String>,
+}
+
+impl UpdatePlan {
+    /// Returns true if the update would stash changes.
+    #[must_use]
+    pub fn would_stash(&self) -> bool {
+        self.steps.contains(&UpdateStep::Stashing)
+    }
+
+    /// Returns true if the update would pop its stash at the end.
+    #[must_use]
+    pub fn would_pop(&self) -> bool {
+        self.steps.contains(&UpdateStep::PoppingStash)
+    }
+}
+
+/// Works out which steps an update of `path` would run, without changing
+/// anything. Takes the same route as the update itself (see
+/// [`route_update`]): fetch-only mode and bare repositories, in-progress
+/// operations, dirty-file limits, stashing, and whether the default branch or
+/// the current branch is pulled.
+pub fn plan_update(path: &Path, config: &Config) -> anyhow::Result<UpdatePlan> {
+    plan_update_inner(path, None, config)
+}
+
+/// Like [`plan_update`], for an update of `target_branch` with [`update_to`].
+pub fn plan_update_to(
+    path: &Path,
+    target_branch: &str,
+    config: &Config,
+) -> anyhow::Result<UpdatePlan> {
+    plan_update_inner(path, Some(target_branch), config)
+}
+
+fn plan_update_inner(
+    path: &Path,
+    target: Option<&str>,
+    config: &Config,
+) -> anyhow::Result<UpdatePlan> {
+    if let Some(target) = target {
+        git::validate_branch_name(target)?;
+    }
+    check_worktree_link(path)?;
+    let branch = git::get_current_branch(path, config, config.git_logger())?;
+    let mut plan = UpdatePlan {
+        branch,
+        dirty_files: 0,
+        steps: vec![UpdateStep::DetectingBranch],
+        skip_reason: None,
+        fail_reason: None,
+        checkout_branch: None,
+        remote_behind: None,
+        restore_to: None,
+    };
+    if git::is_bare_repository(path, config, config.git_logger())? {
+        plan.steps.push(UpdateStep::Fetching);
+        return Ok(plan);
+    }
+
+    let detached = plan.branch == DETACHED_HEAD;
+    let mut dirty_files = None;
+    let route = route_update(
+        target,
+        config,
+        detached,
+        || operation_in_progress(path, config),
+        || {
+            let count = count_dirty_files(path, config)?;
+            dirty_files = Some(count);
+            Ok(count)
+        },
+    )?;
+    plan.dirty_files = dirty_files.unwrap_or(0);
+    if route != UpdateRoute::Fetch {
+        plan.steps.push(UpdateStep::CheckingChanges);
+    }
+    let stash = match route {
+        UpdateRoute::Fetch => {
+            plan.steps.push(UpdateStep::Fetching);
+            return Ok(plan);
+        }
+        UpdateRoute::Skip { reason, .. } => {
+            plan.skip_reason = Some(reason);
+            return Ok(plan);
+        }
+        UpdateRoute::Fail(reason) => {
+            plan.fail_reason = Some(reason);
+            return Ok(plan);
+        }
+        UpdateRoute::DefaultBranch { dirty_files } | UpdateRoute::CurrentBranch { dirty_files } => {
+            dirty_files > 0
+        }
+    };
+
+    if matches!(route, UpdateRoute::DefaultBranch { .. }) {
+        if config.skip_unchanged
+            && !stash
+            && unchanged_default_branch(path, target, config)?.is_some()
+        {
+            plan.steps.push(UpdateStep::Fetching);
+            return Ok(plan);
+        }
+        plan.steps.push(UpdateStep::Fetching);
+        if stash {
+            plan.steps.push(UpdateStep::Stashing);
+        }
+        plan.steps.extend([
+            UpdateStep::CheckingOut,
+            UpdateStep::Pulling,
+            UpdateStep::RestoringBranch,
+        ]);
+        plan.checkout_branch = planned_default_branch(path, target, config)?;
+        plan.remote_behind = plan.checkout_branch.as_deref().and_then(|branch| {
+            planned_behind(
+                path,
+                config,
+                branch,
+                &format!("{}/{}", config.remote(), branch),
+            )
+        });
+        plan.restore_to = Some(if detached {
+            git::get_current_commit(path, config, config.git_logger())?
+        } else {
+            plan.branch.clone()
+        });
+    } else if config.update_mode == UpdateMode::CurrentBranchRebase {
+        // `--autostash` stashes and restores around the rebase on its own
+        plan.steps.push(UpdateStep::Pulling);
+        plan.remote_behind = current_branch_behind(path, config, &plan.branch)?;
+        return Ok(plan);
+    } else {
+        if stash {
+            plan.steps.push(UpdateStep::Stashing);
+        }
+        plan.steps.push(UpdateStep::Pulling);
+        plan.remote_behind = current_branch_behind(path, config, &plan.branch)?;
+    }
+    if stash {
+        plan.steps.push(UpdateStep::PoppingStash);
+    }
+    Ok(plan)
+}
+
+/// Like [`find_git_repos`], but gives up after `timeout`.
+///
+/// Enumerating a flaky network mount (NFS/SMB) can block in `read_dir` with
+/// no git command involved, so the per-command git timeout does not help.
+/// Discovery runs on its own thread; on timeout that thread is abandoned
+/// (a blocked `read_dir` cannot be interrupted) and an error is returned.
+pub fn find_git_repos_with_timeout(path: &Path, timeout: Duration) -> anyhow::Result<Vec<PathBuf>> {
+    Ok(discover_workspace_with_timeout(path, timeout)?.repos)
+}
+
+/// Like [`discover_workspace`], but gives up after `timeout`, as
+/// [`find_git_repos_with_timeout`] does.
+pub fn discover_workspace_with_timeout(
+    path: &Path,
+    timeout: Duration,
+) -> anyhow::Result<WorkspaceDiscovery> {
+    let path = path.to_path_buf();
+    run_with_timeout(timeout, move || discover_workspace(&path))
+        .ok_or_else(|| anyhow::anyhow!("workspace discovery timed out after {:?}", timeout))
+}
+
+/// Runs `task` on a separate thread, returning `None` if it does not finish in time.
+fn run_with_timeout<T, F>(timeout: Duration, task: F) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        // The receiver is gone if we already timed out
+        let _ = tx.send(task());
+    });
+    rx.recv_timeout(timeout).ok()
+}
+
+/// Removes paths that resolve to an already-seen directory, keeping the first.
+fn dedupe_repos(paths: Vec<PathBuf>, case_insensitive: bool) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    paths
+        .into_iter()
+        .filter(|path| {
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+            let key = canonical.to_string_lossy();
+            let key = if case_insensitive {
+                key.to_lowercase()
+            } else {
+                key.into_owned()
+            };
+            seen.insert(key)
+        })
+        .collect()
+}
+
+/// Returns true if `dir` is on a case-insensitive filesystem.
+///
+/// Probes by flipping the case of the directory's own name and checking whether
+/// that spelling resolves to the same directory.
+fn is_case_insensitive_fs(dir: &Path) -> bool {
+    let Ok(canonical) = dir.canonicalize() else {
+        return false;
+    };
+    let Some(name) = canonical.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    let flipped: String = name
+        .chars()
+        .map(|c| {
+            if c.is_lowercase() {
+                c.to_ascii_uppercase()
+            } else {
+                c.to_ascii_lowercase()
+            }
+        })
+        .collect();
+    if flipped == name {
+        // No letters to flip, so the probe can't tell
+        return false;
+    }
+    is_same_dir(&canonical, &canonical.with_file_name(flipped))
+}
+
+#[cfg(unix)]
+fn is_same_dir(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_dir(_a: &Path, b: &Path) -> bool {
+    // Without inode numbers, the flipped spelling existing at all means the
+    // filesystem matched it case-insensitively
+    b.is_dir()
+}
+
+/// Updates a single repository with callbacks for progress and output.
+pub fn update<C>(path: &Path, callbacks: &C, config: &Config) -> UpdateResult
+where
+    C: UpdateCallbacks,
+{
+    run_update(path, None, callbacks, config)
+}
+
+/// Updates a single repository, syncing `target_branch` instead of detecting
+/// master/main, with the same stash and restore around it.
+///
+/// The target is validated before anything is touched and takes precedence
+/// over branch overrides and `config.update_mode`.
+pub fn update_to<C>(
+    path: &Path,
+    target_branch: &str,
+    callbacks: &C,
+    config: &Config,
+) -> UpdateResult
+where
+    C: UpdateCallbacks,
+{
+    run_update(path, Some(target_branch), callbacks, config)
+}
+
+fn run_update<C>(path: &Path, target: Option<&str>, callbacks: &C, config: &Config) -> UpdateResult
+where
+    C: UpdateCallbacks,
+{
+    callbacks.on_step(&UpdateStep::Started);
+
+    let repo_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(DEFAULT_REPO_NAME);
+    callbacks.on_begin(config);
+    callbacks.on_update_start(repo_name);
+
+    let start = std::time::Instant::now();
+    let mut retries = 0;
+    let mut result = do_update(path, target, callbacks, config);
+    while let Err(error) = &result
+        && retries < config.repo_retries
+        && error.is_retryable(config)
+    {
+        retries += 1;
+        result = do_update(path, target, callbacks, config);
+    }
+    if retries > 0
+        && let Ok(UpdateOutcome::Success(success)) = &mut result
+    {
+        success.warnings.push(format!(
+            "succeeded after {} {}",
+            retries,
+            if retries == 1 { "retry" } else { "retries" }
+        ));
+    }
+    if config.prune_worktrees
+        && let Ok(UpdateOutcome::Success(success)) = &mut result
+        && let Err(error) = git::prune_worktrees(path, config, config.git_logger())
+    {
+        success.warnings.push(format_error_chain(&error));
+    }
+    let duration = start.elapsed();
+
+    callbacks.on_step(&UpdateStep::Completed);
+
+    match result {
+        Ok(outcome) => {
+            match &outcome {
+                UpdateOutcome::Skipped(skipped) => callbacks.on_skipped(&skipped.reason),
+                _ => callbacks.on_completion_status(true, None),
+            }
+            UpdateResult {
+                path: path.to_path_buf(),
+                outcome,
+                duration,
+            }
+        }
+        Err(error) => {
+            let disk_full = error.source.downcast_ref::<git::DiskFull>();
+            // A full disk says what to do on its own; otherwise show the full
+            // error chain for better debugging
+            let error_chain = match disk_full {
+                Some(disk_full) => disk_full.to_string(),
+                None => format_error_chain(&error.source),
+            };
+            let disk_full = disk_full.is_some();
+            let git_exit_code = git::exit_code(&error.source);
+            callbacks.on_completion_status(false, Some(&error_chain));
+            UpdateResult {
+                path: path.to_path_buf(),
+                outcome: UpdateOutcome::Failed(UpdateFailure {
+                    recovery_hint: error.recovery_hint,
+                    disk_full,
+                    git_exit_code,
+                    ..UpdateFailure::new(error.step, error_chain)
+                }),
+                duration,
+            }
+        }
+    }
+}
+
+/// Why repositories not yet started are skipped after a disk-full failure.
+const DISK_FULL_SKIP_REASON: &str = "not started: an earlier update ran out of disk space";
+
+/// Why a repository is skipped when [`UpdateCallbacks::should_update`] returns `false`.
+pub const VETOED_SKIP_REASON: &str = "vetoed by callback";
+
+/// A repository skipped before any step ran.
+fn skipped_before_start(
+    path: &Path,
+    reason: &str,
+    callbacks: &impl UpdateCallbacks,
+) -> UpdateResult {
+    callbacks.on_skipped(reason);
+    UpdateResult {
+        path: path.to_path_buf(),
+        outcome: UpdateOutcome::Skipped(UpdateSkipped {
+            reason: reason.to_string(),
+            step: UpdateStep::Started,
+        }),
+        duration: Duration::ZERO,
+    }
+}
+
+/// Updates multiple repositories in parallel with per-repository callbacks.
+/// In verbose mode, runs sequentially for readable output.
+///
+/// A repository for which [`UpdateCallbacks::should_update`] returns `false`
+/// is skipped with [`VETOED_SKIP_REASON`] before any git command runs.
+///
+/// After an update fails because the disk is full, repositories that have
+/// not started yet are skipped instead of failing one by one.
+///
+/// With a [`Config::manifest`], the manifest's waves are updated one after
+/// another, each wave in parallel.
+///
+/// Results are returned in the same order as `repos`, whatever order the
+/// updates finish in.
+///
+/// Parallel updates run on a dedicated thread pool sized by
+/// [`Config::thread_count`]; the global rayon pool is never used or configured.
+/// With [`Config::ramp`], the first updates of each wave are staggered instead
+/// of all starting at once.
+pub fn update_workspace<F, C>(
+    repos: &[PathBuf],
+    make_callbacks: F,
+    config: &Config,
+) -> Vec<UpdateResult>
+where
+    F: Fn(&Path) -> C + Sync,
+    C: UpdateCallbacks,
+{
+    // Once the disk is full every further fetch fails too, so the rest are skipped
+    let disk_full = AtomicBool::new(false);
+    let process_repo = |path: &PathBuf| {
+        let callbacks = make_callbacks(path);
+        let result = if !callbacks.should_update(path) {
+            skipped_before_start(path, VETOED_SKIP_REASON, &callbacks)
+        } else if disk_full.load(Ordering::Relaxed) {
+            skipped_before_start(path, DISK_FULL_SKIP_REASON, &callbacks)
+        } else {
+            update(path, &callbacks, config)
+        };
+        if let UpdateOutcome::Failed(failure) = &result.outcome
+            && failure.disk_full
+        {
+            disk_full.store(true, Ordering::Relaxed);
+        }
+        callbacks.on_complete(&result);
+        result
+    };
+
+    let pool = local_pool(config);
+    let run_wave = |wave: &[PathBuf]| -> Vec<UpdateResult> {
+        match &pool {
+            // Collecting an indexed parallel iterator places each result at its input position
+            Some(pool) => {
+                let ramp = config
+                    .ramp
+                    .map(|per_interval| StartRamp::new(per_interval, config.thread_count()));
+                pool.install(|| {
+                    wave.par_iter()
+                        .map(|path| {
+                            if let Some(ramp) = &ramp {
+                                ramp.wait_turn();
+                            }
+                            process_repo(path)
+                        })
+                        .collect()
+                })
+            }
+            // Degrade to sequential rather than failing the whole run
+            None => wave.iter().map(process_repo).collect(),
+        }
+    };
+
+    let Some(manifest) = &config.manifest else {
+        return run_wave(repos);
+    };
+    let position: HashMap<&Path, usize> = repos
+        .iter()
+        .enumerate()
+        .map(|(index, path)| (path.as_path(), index))
+        .collect();
+    let mut results: Vec<UpdateResult> = manifest
+        .waves(repos)
+        .iter()
+        .flat_map(|wave| run_wave(wave))
+        .collect();
+    results.sort_by_key(|result| position.get(result.path.as_path()).copied());
+    results
+}
+
+/// Staggers the start of parallel updates (`--ramp`): the n-th update to
+/// start waits until `n / per_interval` intervals have passed, so a burst of
+/// fetches does not hit DNS and the remote all at once.
+///
+/// Only the first `full_after` starts wait. By then every worker is busy, and
+/// the pool itself limits how fast the rest begin.
+struct StartRamp {
+    started_at: Instant,
+    per_interval: usize,
+    full_after: usize,
+    next: AtomicUsize,
+}
+
+impl StartRamp {
+    fn new(per_interval: usize, full_after: usize) -> Self {
+        Self {
+            started_at: Instant::now(),
+            per_interval: per_interval.max(1),
+            full_after,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Blocks the calling worker until its update may start.
+    fn wait_turn(&self) {
+        let position = self.next.fetch_add(1, Ordering::Relaxed);
+        if position >= self.full_after {
+            return;
+        }
+        let slot = (position / self.per_interval) as u32;
+        let start_at = self.started_at + Duration::from_millis(RAMP_INTERVAL_MS) * slot;
+        std::thread::sleep(start_at.saturating_duration_since(Instant::now()));
+    }
+}
+
+/// Builds the thread pool for per-repository work, or `None` to run sequentially.
+///
+/// Sequential for readable verbose output. Otherwise a local pool leaves any
+/// global rayon pool owned by an embedding application untouched.
+fn local_pool(config: &Config) -> Option<rayon::ThreadPool> {
+    if config.is_verbose() {
+        return None;
+    }
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(config.thread_count())
+        .build()
+        .ok()
+}
+
+/// Everything a workspace run did: one result per repository (updated,
+/// failed, or skipped with a reason) plus the directories it left out.
+#[derive(Debug, Clone)]
+pub struct WorkspaceOutcome {
+    /// One result per repository, in the order the repositories were given,
+    /// followed by a skipped result for each child whose `.git` file points
+    /// at a missing gitdir (a broken worktree link).
+    pub results: Vec<UpdateResult>,
+    /// Other child directories of the root that were not recognized as
+    /// repositories.
+    pub filtered_out: Vec<(PathBuf, NotARepoReason)>,
+    /// Time taken by discovery and the updates together.
+    pub duration: Duration,
+}
+
+impl WorkspaceOutcome {
+    /// Repositories an update ran on, whether it succeeded or failed.
+    pub fn updated(&self) -> impl Iterator<Item = &UpdateResult> {
+        self.results
+            .iter()
+            .filter(|result| !matches!(result.outcome, UpdateOutcome::Skipped(_)))
+    }
+
+    /// Repositories left untouched, with the reason for each.
+    pub fn skipped(&self) -> impl Iterator<Item = (&Path, &UpdateSkipped)> {
+        self.results
+            .iter()
+            .filter_map(|result| match &result.outcome {
+                UpdateOutcome::Skipped(skipped) => Some((result.path.as_path(), skipped)),
+                _ => None,
+            })
+    }
+}
+
+/// Discovers the repositories directly under `root` and updates them with
+/// [`update_workspace`], reporting the directories discovery rejected alongside.
+pub fn run_workspace<F, C>(
+    root: &Path,
+    make_callbacks: F,
+    config: &Config,
+) -> anyhow::Result<WorkspaceOutcome>
+where
+    F: Fn(&Path) -> C + Sync,
+    C: UpdateCallbacks,
+{
+    run_workspace_with(root, |repos| {
+        Ok(update_workspace(&repos, make_callbacks, config))
+    })
+}
+
+/// Like [`run_workspace`], but hands the discovered repositories to `update`,
+/// which may narrow them down and decides how they are updated (callbacks
+/// that need the repository count, connection sharing, ...).
+pub fn run_workspace_with<U>(root: &Path, update: U) -> anyhow::Result<WorkspaceOutcome>
+where
+    U: FnOnce(Vec<PathBuf>) -> anyhow::Result<Vec<UpdateResult>>,
+{
+    let start = std::time::Instant::now();
+    let discovery = discover_workspace_with_timeout(root, discovery_timeout())?;
+    // A dangling `.git` file is a repository that lost its main checkout, not
+    // a stray directory, so it is reported with the other results
+    let (broken_links, filtered_out): (Vec<_>, Vec<_>) = discovery
+        .rejected
+        .into_iter()
+        .partition(|(_, reason)| matches!(reason, NotARepoReason::DanglingGitFile { .. }));
+    let mut results = update(discovery.repos)?;
+    results.extend(broken_links.into_iter().map(|(path, reason)| UpdateResult {
+        outcome: UpdateOutcome::Skipped(UpdateSkipped {
+            reason: broken_worktree_link_message(&path, &reason),
+            step: UpdateStep::DetectingBranch,
+        }),
+        path,
+        duration: Duration::ZERO,
+    }));
+    Ok(WorkspaceOutcome {
+        results,
+        filtered_out,
+        duration: start.elapsed(),
+    })
+}
+
+fn run_step<T, C>(
+    step: UpdateStep,
+    path: &Path,
+    callbacks: &C,
+    operation: impl FnOnce() -> anyhow::Result<T>,
+) -> Result<T, UpdateError>
+where
+    C: UpdateCallbacks,
+{
+    use anyhow::Context;
+    callbacks.on_step(&step);
+    callbacks.on_step_execute(&step);
+    operation()
+        .with_context(|| format!("in repository '{}'", path.display()))
+        .map_err(|e| UpdateError {
+            source: e,
+            step,
+            recovery_hint: None,
+            stash_left: false,
+        })
+}
+
+/// Checks out the master branch, falling back to main if master doesn't exist
+/// (or the first of `config.default_branches` that can be checked out).
+///
+/// An explicit `target`, or else a branch override for this repository, is
+/// checked out as-is, without detection.
+/// When both master and main exist locally, [`resolve_ambiguous_master`] picks one
+/// and a warning is pushed to `warnings`.
+fn checkout_master_or_main_branch<C>(
+    path: &Path,
+    target: Option<&str>,
+    callbacks: &C,
+    config: &Config,
+    warnings: &mut Vec<String>,
+) -> Result<String, UpdateError>
+where
+    C: UpdateCallbacks,
+{
+    if let Some(branch) = target.or_else(|| config.branch_override(path)) {
+        run_step(UpdateStep::CheckingOut, path, callbacks, || {
+            checkout_local_branch(path, config, branch)
+        })?;
+        return Ok(branch.to_string());
+    }
+
+    let preferred = run_step(UpdateStep::CheckingOut, path, callbacks, || {
+        resolve_ambiguous_master(path, config)
+    })?;
+    if let Some((branch, warning)) = preferred {
+        warnings.push(warning);
+        run_step(UpdateStep::CheckingOut, path, callbacks, || {
+            checkout_local_branch(path, config, branch)
+        })?;
+        return Ok(branch.to_string());
+    }
+
+    let mut last_error = None;
+    for branch in config.default_branches() {
+        match run_step(UpdateStep::CheckingOut, path, callbacks, || {
+            checkout_local_branch(path, config, branch)
+        }) {
+            Ok(_) => return Ok(branch.to_string()),
+            // The branch exists but is blocked; trying the next one would hide the real problem
+            Err(err) if err.is_untracked_conflict() || err.is_local_changes_conflict() => {
+                return Err(err);
+            }
+            Err(err) => last_error = Some(err),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| UpdateError {
+        source: anyhow::anyhow!("No default branches configured"),
+        step: UpdateStep::CheckingOut,
+        recovery_hint: None,
+        stash_left: false,
+    }))
+}
+
+/// Checks out `branch` as a local branch, creating it from `origin/<branch>`
+/// when only the remote one exists, and verifies HEAD did not end up detached.
+fn checkout_local_branch(path: &Path, config: &Config, branch: &str) -> anyhow::Result<()> {
+    let logger = config.git_logger();
+    let remote_ref = format!("{}/{}", config.remote(), branch);
+    if !git::local_branch_exists(path, config, branch, logger)?
+        && git::remote_ref_exists(path, config, &remote_ref, logger)?
+    {
+        git::checkout_tracking(path, config, branch, &remote_ref, logger)?;
+    } else {
+        git::checkout(path, config, branch, logger)?;
+    }
+
+    let current = git::get_current_branch(path, config, logger)?;
+    if current != branch {
+        anyhow::bail!(
+            "Checking out '{}' left HEAD on '{}' instead of the local branch",
+            branch,
+            current
+        );
+    }
+    Ok(())
+}
+
+/// Stashes uncommitted changes, limited to `config.stash_pathspec` if set.
+///
+/// Refuses when earlier interrupted runs left too many stashes behind (see
+/// [`cleanup::ensure_stash_room`]); returns whether anything was stashed and
+/// a warning if those stashes were dropped first.
+fn stash_changes(path: &Path, config: &Config) -> anyhow::Result<(bool, Option<String>)> {
+    let dropped = cleanup::ensure_stash_room(path, config)?;
+    let stashed = match &config.stash_pathspec {
+        Some(pathspecs) => git::stash_paths(path, config, pathspecs, config.git_logger())?,
+        None => git::stash(path, config, config.git_logger())?,
+    };
+    Ok((stashed, dropped))
+}
+
+/// Builds a "to undo" hint if a failed update left the repository away from
+/// where it started. Returns `None` when HEAD is back on the original ref.
+fn recovery_hint(
+    path: &Path,
+    config: &Config,
+    original_head: &OriginalHead,
+    start_sha: &str,
+    had_stash: bool,
+) -> Option<String> {
+    if is_at_original_head(path, config, original_head) {
+        return None;
+    }
+
+    let detach = if original_head.is_detached() {
+        "--detach "
+    } else {
+        ""
+    };
+    let stash_pop = if had_stash { " && git stash pop" } else { "" };
+    Some(format!(
+        "to undo: git checkout {}{}{} (HEAD was at {}; see `git reflog`)",
+        detach,
+        original_head.git_ref(),
+        stash_pop,
+        start_sha
+    ))
+}
+
+/// Returns true if HEAD is on the original branch, or detached at the original commit.
+fn is_at_original_head(path: &Path, config: &Config, original_head: &OriginalHead) -> bool {
+    let logger = config.git_logger();
+    match original_head {
+        OriginalHead::Branch(name) => {
+            git::get_current_branch(path, config, logger).is_ok_and(|branch| &branch == name)
+        }
+        OriginalHead::DetachedAt(sha) | OriginalHead::DetachedAtTag { sha, .. } => {
+            git::get_current_branch(path, config, logger)
+                .is_ok_and(|branch| branch == DETACHED_HEAD)
+                && git::get_current_commit(path, config, logger).is_ok_and(|head| &head == sha)
+        }
+    }
+}
+
+/// Checks out the original branch, or detaches explicitly at the original commit.
+fn restore_original_head(
+    path: &Path,
+    config: &Config,
+    original_head: &OriginalHead,
+) -> anyhow::Result<()> {
+    let logger = config.git_logger();
+    match original_head {
+        OriginalHead::Branch(name) => git::checkout(path, config, name, logger),
+        OriginalHead::DetachedAt(sha) | OriginalHead::DetachedAtTag { sha, .. } => {
+            git::checkout_detached(path, config, sha, logger)
+        }
+    }
+}
+
+/// Picks a branch when both `master` and `main` exist locally, returning it
+/// with a warning that explains the choice, or `None` when there is no ambiguity.
+///
+/// An explicit `config.branch_preference` decides. Without one, if exactly one
+/// of them tracks a live remote branch, the other is assumed to be left over
+/// from a `master` -> `main` rename and that one wins; if both do, the one
+/// tracking the remote's default branch (`origin/HEAD`) wins. Otherwise
+/// `master` is used.
+fn resolve_ambiguous_master(
+    path: &Path,
+    config: &Config,
+) -> anyhow::Result<Option<(&'static str, String)>> {
+    // An explicit candidate list already says which branch wins
+    if config.default_branches.is_some() {
+        return Ok(None);
+    }
+    let logger = config.git_logger();
+    let has_master = git::local_branch_exists(path, config, MASTER_BRANCH, logger)?;
+    let has_main = git::local_branch_exists(path, config, MAIN_BRANCH, logger)?;
+    if !(has_master && has_main) {
+        return Ok(None);
+    }
+    if let Some(preference) = config.branch_preference {
+        let branch = preferred_branch(path, config, preference)?;
+        return Ok(Some((branch, both_exist_warning(branch))));
+    }
+
+    let master_tracks_live = tracks_live_upstream(path, config, MASTER_BRANCH)?;
+    let main_tracks_live = tracks_live_upstream(path, config, MAIN_BRANCH)?;
+    if master_tracks_live != main_tracks_live {
+        let (live, stale) = if main_tracks_live {
+            (MAIN_BRANCH, MASTER_BRANCH)
+        } else {
+            (MASTER_BRANCH, MAIN_BRANCH)
+        };
+        return Ok(Some((
+            live,
+            format!(
+                "'{}' does not track a live remote branch (renamed to '{}'?); using '{}', consider deleting '{}'",
+                stale, live, live, stale
+            ),
+        )));
+    }
+
+    // Both are live: the remote's own default branch (origin/HEAD) decides
+    if let Some(remote_head) = git::remote_default_branch(path, config, config.remote(), logger)? {
+        for branch in [MAIN_BRANCH, MASTER_BRANCH] {
+            if git::branch_upstream(path, config, branch, logger)?.as_deref()
+                == Some(remote_head.as_str())
+            {
+                return Ok(Some((
+                    branch,
+                    format!(
+                        "both '{}' and '{}' track {}; using '{}', which tracks {}/HEAD ({})",
+                        MASTER_BRANCH,
+                        MAIN_BRANCH,
+                        config.remote(),
+                        branch,
+                        config.remote(),
+                        remote_head
+                    ),
+                )));
+            }
+        }
+    }
+
+    Ok(Some((MASTER_BRANCH, both_exist_warning(MASTER_BRANCH))))
+}
+
+fn both_exist_warning(branch: &str) -> String {
+    format!(
+        "both '{}' and '{}' exist locally; using '{}'",
+        MASTER_BRANCH, MAIN_BRANCH, branch
+    )
+}
+
+/// The branch `preference` picks when both `master` and `main` exist locally.
+fn preferred_branch(
+    path: &Path,
+    config: &Config,
+    preference: BranchPreference,
+) -> anyhow::Result<&'static str> {
+    let logger = config.git_logger();
+    Ok(match preference {
+        BranchPreference::Master => MASTER_BRANCH,
+        BranchPreference::Main => MAIN_BRANCH,
+        BranchPreference::Upstream => {
+            let remote = config.remote();
+            let master_live = git::remote_ref_exists(
+                path,
+                config,
+                &format!("{}/{}", remote, MASTER_BRANCH),
+                logger,
+            )?;
+            let main_live = git::remote_ref_exists(
+                path,
+                config,
+                &format!("{}/{}", remote, MAIN_BRANCH),
+                logger,
+            )?;
+            if main_live && !master_live {
+                MAIN_BRANCH
+            } else {
+                MASTER_BRANCH
+            }
+        }
+    })
+}
+
+/// Returns true if `branch` has an upstream whose remote-tracking ref still exists.
+fn tracks_live_upstream(path: &Path, config: &Config, branch: &str) -> anyhow::Result<bool> {
+    let logger = config.git_logger();
+    match git::branch_upstream(path, config, branch, logger)? {
+        // Upstreams that are other local branches have no remote to be live on
+        Some(upstream) if upstream.contains('/') => {
+            git::remote_ref_exists(path, config, &upstream, logger)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Where an update goes once the repository's state is known. [`do_update`]
+/// and [`plan_update`] both follow [`route_update`], so `--explain` and
+/// `--dry-run-json` show the route a real run takes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum UpdateRoute {
+    /// Only refresh the remote refs (`--fetch-only`, `--prune-remote-only`).
+    Fetch,
+    /// Leave the repository untouched, reported at `step`.
+    Skip { reason: String, step: UpdateStep },
+    /// Refuse to touch the repository (a `--fail-dirty-over` limit).
+    Fail(String),
+    /// Check out the default branch (or the target), bring it up to date and
+    /// restore the original head.
+    DefaultBranch { dirty_files: usize },
+    /// Bring the current branch up to date in place.
+    CurrentBranch { dirty_files: usize },
+}
+
+/// Decides the [`UpdateRoute`] of a non-bare repository. The working tree is
+/// only probed when the route depends on it, in-progress operations first,
+/// so fetch-only updates never look at it.
+///
+/// A `target` branch always takes the default-branch route, whatever
+/// `config.update_mode` says.
+fn route_update<E>(
+    target: Option<&str>,
+    config: &Config,
+    detached: bool,
+    operation: impl FnOnce() -> Result<Option<InProgressOperation>, E>,
+    dirty_files: impl FnOnce() -> Result<usize, E>,
+) -> Result<UpdateRoute, E> {
+    let updates_default_branch =
+        target.is_some() || config.update_mode == UpdateMode::DefaultBranch;
+    if !updates_default_branch
+        && matches!(
+            config.update_mode,
+            UpdateMode::FetchOnly | UpdateMode::PruneRemoteOnly
+        )
+    {
+        return Ok(UpdateRoute::Fetch);
+    }
+
+    if let Some(operation) = operation()? {
+        return Ok(UpdateRoute::Skip {
+            reason: in_progress_skip_reason(operation),
+            step: UpdateStep::CheckingChanges,
+        });
+    }
+    let dirty_files = dirty_files()?;
+    if let Some(reason) = dirty_skip_reason(dirty_files, config) {
+        return Ok(UpdateRoute::Skip {
+            reason,
+            step: UpdateStep::CheckingChanges,
+        });
+    }
+    if let Some(failure) = dirty_failure(dirty_files, config) {
+        return Ok(UpdateRoute::Fail(failure));
+    }
+
+    if updates_default_branch {
+        Ok(UpdateRoute::DefaultBranch { dirty_files })
+    } else if detached {
+        Ok(UpdateRoute::Skip {
+            reason: DETACHED_CURRENT_BRANCH_REASON.to_string(),
+            step: UpdateStep::DetectingBranch,
+        })
+    } else {
+        Ok(UpdateRoute::CurrentBranch { dirty_files })
+    }
+}
+
+/// Core update logic: stash, checkout main, fetch, restore branch, pop stash.
+///
+/// A `target` branch replaces master/main detection and always takes the
+/// default-branch path, whatever `config.update_mode` says.
+fn do_update<C>(
+    path: &Path,
+    target: Option<&str>,
+    callbacks: &C,
+    config: &Config,
+) -> Result<UpdateOutcome, UpdateError>
+where
+    C: UpdateCallbacks,
+{
+    let logger = config.git_logger();
+
+    if let Some(target) = target {
+        run_step(UpdateStep::CheckingOut, path, callbacks, || {
+            git::validate_branch_name(target)
+        })?;
+    }
+
+    run_step(UpdateStep::DetectingBranch, path, callbacks, || {
+        check_worktree_link(path)
+    })?;
+
+    // Bare clones and mirrors have no working tree: fetching is all there is to do
+    let bare = run_step(UpdateStep::DetectingBranch, path, callbacks, || {
+        git::is_bare_repository(path, config, logger)
+    })?;
+    if bare {
+        return update_bare(path, target, callbacks, config);
+    }
+
+    // Branch, commit and dirtiness come from one `git status` call
+    let status = run_step(UpdateStep::DetectingBranch, path, callbacks, || {
+        let excluded = config.ignore_dirty_paths.as_deref().unwrap_or_default();
+        git::repo_status(path, config, excluded, logger)
+    })?;
+    let branch_name = status.branch;
+    // Recorded for every repo so a failed run can always point back to it
+    let start_sha = run_step(UpdateStep::DetectingBranch, path, callbacks, || {
+        status
+            .commit
+            .ok_or_else(|| anyhow::anyhow!("Failed to get current commit: HEAD has no commits yet"))
+    })?;
+
+    // Handle detached HEAD: store commit SHA instead of "HEAD", which is not a
+    // branch and would not bring back the original commit if checked out
+    let original_head = if branch_name == DETACHED_HEAD {
+        let commit = start_sha.clone();
+        let tag = run_step(UpdateStep::DetectingBranch, path, callbacks, || {
+            git::get_exact_tag(path, config, logger)
+        })?;
+        match tag {
+            Some(tag) => OriginalHead::DetachedAtTag { tag, sha: commit },
+            None => OriginalHead::DetachedAt(commit),
+        }
+    } else {
+        OriginalHead::Branch(branch_name)
+    };
+
+    let detached = !matches!(original_head, OriginalHead::Branch(_));
+    let route = route_update(
+        target,
+        config,
+        detached,
+        || {
+            run_step(UpdateStep::CheckingChanges, path, callbacks, || {
+                operation_in_progress(path, config)
+            })
+        },
+        || {
+            run_step(UpdateStep::CheckingChanges, path, callbacks, || {
+                discount_whitespace_dirt(path, config, status.dirty_files)
+            })
+        },
+    )?;
+    let (updates_default_branch, dirty_files) = match route {
+        UpdateRoute::Fetch => {
+            return fetch_only(path, callbacks, config, original_head, start_sha);
+        }
+        UpdateRoute::Skip { reason, step } => {
+            return Ok(UpdateOutcome::Skipped(UpdateSkipped { reason, step }));
+        }
+        UpdateRoute::Fail(failure) => {
+            return Err(UpdateError {
+                source: anyhow::anyhow!(failure),
+                step: UpdateStep::CheckingChanges,
+                recovery_hint: None,
+                stash_left: false,
+            });
+        }
+        UpdateRoute::DefaultBranch { dirty_files } => (true, dirty_files),
+        UpdateRoute::CurrentBranch { dirty_files } => (false, dirty_files),
+    };
+
+    if config.skip_unchanged && updates_default_branch && dirty_files == 0 {
+        let unchanged = run_step(UpdateStep::Fetching, path, callbacks, || {
+            unchanged_default_branch(path, target, config)
+        })?;
+        if let Some((master_branch, master_sha)) = unchanged {
+            return Ok(UpdateOutcome::Success(UpdateSuccess {
+                master_branch: Some(master_branch),
+                head_sha: ShaChange {
+                    before: start_sha.clone(),
+                    after: start_sha,
+                },
+                master_sha: ShaChange {
+                    before: master_sha.clone(),
+                    after: master_sha,
+                },
+                fast_skipped: true,
+                ..UpdateSuccess::new(original_head)
+            }));
+        }
+    }
+
+    let mut had_stash = false;
+    let result = if updates_default_branch {
+        sync_master(
+            path,
+            target,
+            callbacks,
+            config,
+            &original_head,
+            &start_sha,
+            dirty_files,
+            &mut had_stash,
+        )
+    } else {
+        sync_current_branch(
+            path,
+            callbacks,
+            config,
+            &original_head,
+            dirty_files,
+            &mut had_stash,
+        )
+    };
+    result.map_err(|mut error| {
+        // Put a retryable failure back as it started so the retry begins clean
+        if config.repo_retries > 0
+            && config.retries_failures_at(error.step)
+            && undo_partial_update(path, config, &original_head, had_stash).is_ok()
+        {
+            had_stash = false;
+        }
+        error.stash_left = had_stash;
+        error.recovery_hint = recovery_hint(path, config, &original_head, &start_sha, had_stash);
+        error
+    })
+}
+
+/// For `--skip-unchanged`: returns the default branch and its commit when
+/// there is nothing to pull, because the remote's tip of the branch (one
+/// `git ls-remote`) equals both `<remote>/<branch>` and the local branch.
+///
+/// `None` means the update has to run, including when the branch to update
+/// is ambiguous (both master and main) and would come with a warning.
+fn unchanged_default_branch(
+    path: &Path,
+    target: Option<&str>,
+    config: &Config,
+) -> anyhow::Result<Option<(String, String)>> {
+    let logger = config.git_logger();
+    let branch = match target.or_else(|| config.branch_override(path)) {
+        Some(branch) => Some(branch),
+        None if resolve_ambiguous_master(path, config)?.is_some() => return Ok(None),
+        None => config
+            .default_branches()
+            .into_iter()
+            .find(|branch| git::local_branch_exists(path, config, branch, logger).unwrap_or(false)),
+    };
+    let Some(branch) = branch else {
+        return Ok(None);
+    };
+    let Some(local) = git::resolve_commit(path, config, branch, logger)? else {
+        return Ok(None);
+    };
+    let tracking = format!("{}/{}", config.remote(), branch);
+    if git::resolve_commit(path, config, &tracking, logger)?.as_ref() != Some(&local) {
+        return Ok(None);
+    }
+    let remote = git::remote_branch_tip(path, config, config.remote(), branch, logger)?;
+    Ok((remote.as_ref() == Some(&local)).then(|| (branch.to_string(), local)))
+}
+
+/// The branch a default-branch update would check out, worked out without
+/// changing anything: the target or a branch override, the winner when both
+/// master and main exist, or the first default branch that exists locally or
+/// on the remote.
+fn planned_default_branch(
+    path: &Path,
+    target: Option<&str>,
+    config: &Config,
+) -> anyhow::Result<Option<String>> {
+    if let Some(branch) = target.or_else(|| config.branch_override(path)) {
+        return Ok(Some(branch.to_string()));
+    }
+    if let Some((branch, _)) = resolve_ambiguous_master(path, config)? {
+        return Ok(Some(branch.to_string()));
+    }
+    let logger = config.git_logger();
+    for branch in config.default_branches() {
+        let tracking = format!("{}/{}", config.remote(), branch);
+        if git::local_branch_exists(path, config, branch, logger)?
+            || git::remote_ref_exists(path, config, &tracking, logger)?
+        {
+            return Ok(Some(branch.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// How far the current branch is behind its upstream, if it has one.
+fn current_branch_behind(
+    path: &Path,
+    config: &Config,
+    branch: &str,
+) -> anyhow::Result<Option<usize>> {
+    let upstream = git::branch_upstream(path, config, branch, config.git_logger())?;
+    Ok(upstream.and_then(|upstream| planned_behind(path, config, branch, &upstream)))
+}
+
+/// Commits `branch` is behind `upstream`; `None` if either does not exist
+/// yet (a branch only on the remote is created by the update).
+fn planned_behind(path: &Path, config: &Config, branch: &str, upstream: &str) -> Option<usize> {
+    git::ahead_behind(path, config, branch, upstream, config.git_logger())
+        .ok()
+        .map(|(_, behind)| behind)
+}
+
+fn in_progress_skip_reason(operation: InProgressOperation) -> String {
+    format!(
+        "{} in progress; finish it first ({})",
+        operation,
+        operation.abort_hint()
+    )
+}
+
+/// Counts the changes an update would stash.
+fn count_dirty_files(path: &Path, config: &Config) -> anyhow::Result<usize> {
+    let logger = config.git_logger();
+    let count = match &config.ignore_dirty_paths {
+        Some(excluded) => git::count_uncommitted_files_excluding(path, config, excluded, logger)?,
+        None => git::count_uncommitted_files(path, config, logger)?,
+    };
+    discount_whitespace_dirt(path, config, count)
+}
+
+/// Returns 0 instead of `count` with `--ignore-whitespace-dirty` when the
+/// only uncommitted changes are whitespace, which then stay in the working
+/// tree instead of being stashed.
+fn discount_whitespace_dirt(path: &Path, config: &Config, count: usize) -> anyhow::Result<usize> {
+    if count > 0
+        && config.ignore_whitespace_dirty
+        && git::has_only_whitespace_changes(path, config, config.git_logger())?
+    {
+        return Ok(0);
+    }
+    Ok(count)
+}
+
+/// Why `dirty_files` uncommitted files keep a repository from being updated
+/// (`--skip-dirty-over`, `--no-stash`), if they do.
+fn dirty_skip_reason(dirty_files: usize, config: &Config) -> Option<String> {
+    if let Some(limit) = config.skip_dirty_over
+        && dirty_files > limit
+    {
+        return Some(format!(
+            "{} uncommitted files exceed the limit of {}",
+            dirty_files, limit
+        ));
+    }
+    if config.no_stash && dirty_files > 0 {
+        return Some(format!(
+            "{} uncommitted files; refusing to stash (commit or stash your changes first)",
+            dirty_files
+        ));
+    }
+    None
+}
+
+/// Why `dirty_files` uncommitted files fail the update under
+/// `--fail-on-dirty`, if they do.
+fn dirty_failure(dirty_files: usize, config: &Config) -> Option<String> {
+    (config.fail_on_dirty && dirty_files > 0).then(|| {
+        format!(
+            "repository is dirty: {} uncommitted {} (--fail-on-dirty never stashes)",
+            dirty_files,
+            if dirty_files == 1 { "file" } else { "files" }
+        )
+    })
+}
+
+/// Returns HEAD to `original_head` if a failed update moved it, then pops
+/// the update's stash if it made one.
+fn undo_partial_update(
+    path: &Path,
+    config: &Config,
+    original_head: &OriginalHead,
+    had_stash: bool,
+) -> anyhow::Result<()> {
+    if !is_at_original_head(path, config, original_head) {
+        restore_original_head(path, config, original_head)?;
+    }
+    if had_stash {
+        git::stash_pop(path, config, config.git_logger())?;
+    }
+    Ok(())
+}
+
+/// Refreshes the remote-tracking refs without touching any branch: a fetch
+/// with prune, or under [`UpdateMode::PruneRemoteOnly`] only `git remote prune`.
+/// Returns the remote branches created and the ones pruned.
+fn refresh_remote_refs<C>(
+    path: &Path,
+    callbacks: &C,
+    config: &Config,
+) -> Result<(Vec<String>, Vec<String>), UpdateError>
+where
+    C: UpdateCallbacks,
+{
+    run_step(UpdateStep::Fetching, path, callbacks, || {
+        let logger = config.git_logger();
+        if config.update_mode == UpdateMode::PruneRemoteOnly {
+            return Ok((Vec::new(), git::remote_prune(path, config, logger)?));
+        }
+        let created = git::fetch_prune_new_branches(path, config, logger, &|progress| {
+            callbacks.on_fetch_progress(progress.received_bytes, progress.total_bytes)
+        })?;
+        Ok((created, Vec::new()))
+    })
+}
+
+/// Fetches with prune and nothing else: no branch, stash, or working tree
+/// change, so HEAD is reported unchanged.
+fn fetch_only<C>(
+    path: &Path,
+    callbacks: &C,
+    config: &Config,
+    original_head: OriginalHead,
+    start_sha: String,
+) -> Result<UpdateOutcome, UpdateError>
+where
+    C: UpdateCallbacks,
+{
+    let (new_remote_branches, pruned_remote_branches) =
+        refresh_remote_refs(path, callbacks, config)?;
+
+    Ok(UpdateOutcome::Success(UpdateSuccess {
+        head_sha: ShaChange {
+            before: start_sha.clone(),
+            after: start_sha,
+        },
+        new_remote_branches,
+        pruned_remote_branches,
+        ..UpdateSuccess::new(original_head)
+    }))
+}
+
+/// Updates a bare clone or mirror, which has nothing to stash or check out:
+/// fetches with prune and reports how far HEAD's branch moved (a mirror's
+/// fetch updates its branches directly; a plain bare clone's stay put).
+/// Under [`UpdateMode::PruneRemoteOnly`] it only prunes, like [`fetch_only`].
+fn update_bare<C>(
+    path: &Path,
+    target: Option<&str>,
+    callbacks: &C,
+    config: &Config,
+) -> Result<UpdateOutcome, UpdateError>
+where
+    C: UpdateCallbacks,
+{
+    let logger = config.git_logger();
+    if let Some(target) = target {
+        run_step(
+            UpdateStep::CheckingOut,
+            path,
+            callbacks,
+            || -> anyhow::Result<()> {
+                anyhow::bail!("cannot check out '{}' in a bare repository", target)
+            },
+        )?;
+    }
+
+    let (branch, before) = run_step(UpdateStep::DetectingBranch, path, callbacks, || {
+        let branch = git::get_current_branch(path, config, logger)?;
+        let commit = git::get_current_commit(path, config, logger)?;
+        Ok((branch, commit))
+    })?;
+    let (new_remote_branches, pruned_remote_branches) =
+        refresh_remote_refs(path, callbacks, config)?;
+    let (master_sha, commits_pulled) = run_step(UpdateStep::Fetching, path, callbacks, || {
+        pulled_commits(path, config, before)
+    })?;
+
+    Ok(UpdateOutcome::Success(UpdateSuccess {
+        master_branch: Some(branch.clone()),
+        commits_pulled,
+        head_sha: master_sha.clone(),
+        master_sha,
+        new_remote_branches,
+        pruned_remote_branches,
+        ..UpdateSuccess::new(OriginalHead::Branch(branch))
+    }))
+}
+
+/// Fetches, stashes, updates the master branch, and restores the original state.
+///
+/// `had_stash` is set as soon as changes are stashed, so the caller knows about
+/// the stash even when a later step fails.
+#[allow(clippy::too_many_arguments)]
+fn sync_master<C>(
+    path: &Path,
+    target: Option<&str>,
+    callbacks: &C,
+    config: &Config,
+    original_head: &OriginalHead,
+    start_sha: &str,
+    dirty_files: usize,
+    had_stash: &mut bool,
+) -> Result<UpdateOutcome, UpdateError>
+where
+    C: UpdateCallbacks,
+{
+    let logger = config.git_logger();
+
+    let new_remote_branches = run_step(UpdateStep::Fetching, path, callbacks, || {
+        git::fetch_prune_new_branches(path, config, logger, &|progress| {
+            callbacks.on_fetch_progress(progress.received_bytes, progress.total_bytes)
+        })
+    })?;
+
+    let mut dropped_stashes = None;
+    let mut stashed_files = dirty_files;
+    if dirty_files > 0 {
+        let (stashed, dropped) = run_step(UpdateStep::Stashing, path, callbacks, || {
+            stash_changes(path, config)
+        })?;
+        *had_stash = stashed;
+        dropped_stashes = dropped;
+    }
+
+    let mut warnings = Vec::new();
+    let master_branch =
+        match checkout_master_or_main_branch(path, target, callbacks, config, &mut warnings) {
+            Err(err) if err.is_untracked_conflict() => {
+                match config.untracked_conflict {
+                    UntrackedConflictPolicy::Fail => return Err(err),
+                    UntrackedConflictPolicy::Skip => {
+                        // The refused checkout left HEAD in place; only the stash needs undoing
+                        if *had_stash {
+                            run_step(UpdateStep::PoppingStash, path, callbacks, || {
+                                git::stash_pop(path, config, logger)
+                            })?;
+                            *had_stash = false;
+                        }
+                        return Ok(UpdateOutcome::Skipped(UpdateSkipped {
+                            reason: format!("{:#}", err.source.root_cause()),
+                            step: UpdateStep::CheckingOut,
+                        }));
+                    }
+                    UntrackedConflictPolicy::StashUntracked => {
+                        // Re-stash everything as a single entry so one pop restores it all
+                        if *had_stash {
+                            run_step(UpdateStep::PoppingStash, path, callbacks, || {
+                                git::stash_pop(path, config, logger)
+                            })?;
+                            *had_stash = false;
+                        }
+                        *had_stash = run_step(UpdateStep::Stashing, path, callbacks, || {
+                            git::stash_include_untracked(path, config, logger)
+                        })?;
+                        warnings.clear();
+                        checkout_master_or_main_branch(
+                            path,
+                            target,
+                            callbacks,
+                            config,
+                            &mut warnings,
+                        )?
+                    }
+                }
+            }
+            Err(err) if err.is_local_changes_conflict() => {
+                // Changes the dirty count or `--stash-path` left in the tree differ
+                // on the branch: stash everything after all, as a single entry
+                if *had_stash {
+                    run_step(UpdateStep::PoppingStash, path, callbacks, || {
+                        git::stash_pop(path, config, logger)
+                    })?;
+                    *had_stash = false;
+                }
+                let (stashed, dropped) = run_step(UpdateStep::Stashing, path, callbacks, || {
+                    stashed_files = git::count_uncommitted_files(path, config, logger)?;
+                    let dropped = cleanup::ensure_stash_room(path, config)?;
+                    Ok((git::stash(path, config, logger)?, dropped))
+                })?;
+                *had_stash = stashed;
+                dropped_stashes = dropped_stashes.or(dropped);
+                warnings.clear();
+                checkout_master_or_main_branch(path, target, callbacks, config, &mut warnings)?
+            }
+            result => result?,
+        };
+    warnings.extend(dropped_stashes);
+    let had_stash = *had_stash;
 
-    run_step(UpdateStep::Pulling, path, callbacks, || {
-        git::pull(path, config, master_branch, logger)
+    let (master_sha, commits_pulled) = run_step(UpdateStep::Pulling, path, callbacks, || {
+        let before = git::get_current_commit(path, config, logger)?;
+        // Fast-forward to what the Fetching step brought in rather than
+        // pulling: a second fetch would cost another round trip and could
+        // bring in a tip pushed since
+        let pulled = if config.verify_signatures {
+            // Only the commit that was verified may be merged
+            match verify_incoming_tip(path, config, &master_branch)? {
+                Some(sha) => git::fast_forward_to(
+                    path,
+                    config,
+                    config.remote(),
+                    &master_branch,
+                    &sha,
+                    logger,
+                ),
+                None => Ok(()),
+            }
+        } else {
+            let fetched = format!("refs/remotes/{}/{}", config.remote(), master_branch);
+            git::fast_forward_to(
+                path,
+                config,
+                config.remote(),
+                &master_branch,
+                &fetched,
+                logger,
+            )
+        };
+        match pulled {
+            // Local commits on the default branch are the user's to reconcile;
+            // on request, restoring their branch matters more than this fast-forward
+            Err(err)
+                if config.keep_diverged_default
+                    && err.downcast_ref::<git::NotFastForward>().is_some() =>
+            {
+                warnings.push(local_commits_warning(path, config, &master_branch));
+            }
+            result => result?,
+        }
+        pulled_commits(path, config, before)
     })?;
 
-    run_step(UpdateStep::RestoringBranch, path, callbacks, || {
-        git::checkout(path, config, original_head.git_ref(), logger)
+    let head_after = run_step(UpdateStep::RestoringBranch, path, callbacks, || {
+        restore_original_head(path, config, original_head)?;
+        git::get_current_commit(path, config, logger)
     })?;
 
     if had_stash {
@@ -419,9 +2698,458 @@ where
         })?;
     }
 
-    Ok(UpdateSuccess {
-        original_head,
-        master_branch,
+    // Best effort: a describe failure must not turn a successful update into a failure
+    let describe = if config.is_verbose() {
+        git::describe(path, config, logger).ok()
+    } else {
+        None
+    };
+    if let OriginalHead::Branch(branch) = original_head
+        && *branch != master_branch
+        && let Some(warning) = behind_upstream_warning(path, config, branch)
+    {
+        warnings.push(warning);
+    }
+
+    Ok(UpdateOutcome::Success(UpdateSuccess {
+        master_branch: Some(master_branch),
         had_stash,
+        stashed_files: if had_stash { stashed_files } else { 0 },
+        commits_pulled,
+        warnings,
+        describe,
+        head_sha: ShaChange {
+            before: start_sha.to_string(),
+            after: head_after,
+        },
+        master_sha,
+        new_remote_branches,
+        ..UpdateSuccess::new(original_head.clone())
+    }))
+}
+
+/// Checks the signature of the fetched remote tip of `branch` when it has
+/// commits to bring in (`--verify-signatures`) and returns its SHA, the only
+/// commit the branch may then be fast-forwarded to. Returns `None` when there
+/// is nothing to bring in; a tip already checked out is not checked again.
+fn verify_incoming_tip(
+    path: &Path,
+    config: &Config,
+    branch: &str,
+) -> anyhow::Result<Option<String>> {
+    use anyhow::Context;
+
+    let logger = config.git_logger();
+    let upstream = format!("{}/{}", config.remote(), branch);
+    let Some(tip) = git::resolve_commit(path, config, &upstream, logger)? else {
+        anyhow::bail!("'{}' does not exist; fetch it first", upstream);
+    };
+    let (_, behind) = git::ahead_behind(path, config, "HEAD", &tip, logger)?;
+    if behind == 0 {
+        return Ok(None);
+    }
+    git::verify_commit(path, config, &tip, logger).with_context(|| {
+        format!(
+            "Refusing to fast-forward '{}' to unverified '{}'",
+            branch, upstream
+        )
+    })?;
+    Ok(Some(tip))
+}
+
+/// Explains that `branch` was left as-is because it has commits of its own
+/// that the remote branch does not.
+fn local_commits_warning(path: &Path, config: &Config, branch: &str) -> String {
+    let upstream = format!("{}/{}", config.remote(), branch);
+    let ahead = git::ahead_behind(path, config, branch, &upstream, config.git_logger())
+        .map(|(ahead, _)| ahead)
+        .unwrap_or_default();
+    let commits = match ahead {
+        0 => "local commits".to_string(),
+        1 => "1 local commit".to_string(),
+        n => format!("{} local commits", n),
+    };
+    format!(
+        "{} has {} not on {}; left as-is instead of fast-forwarding",
+        branch, commits, upstream
+    )
+}
+
+/// Warns when `branch` is behind its upstream after the fetch, e.g. because
+/// someone pushed to it from elsewhere. Best effort: any error yields `None`.
+fn behind_upstream_warning(path: &Path, config: &Config, branch: &str) -> Option<String> {
+    let logger = config.git_logger();
+    let upstream = git::branch_upstream(path, config, branch, logger).ok()??;
+    let (_, behind) = git::ahead_behind(path, config, branch, &upstream, logger).ok()?;
+    (behind > 0).then(|| {
+        format!(
+            "{} is {} behind {} — consider pulling",
+            branch, behind, upstream
+        )
     })
 }
+
+/// Records where a branch moved during a pull that started at `before`,
+/// along with the number of commits the pull brought in.
+///
+/// The count comes from comparing SHAs, never from git's output, which is
+/// localized.
+fn pulled_commits(
+    path: &Path,
+    config: &Config,
+    before: String,
+) -> anyhow::Result<(ShaChange, usize)> {
+    let logger = config.git_logger();
+    let after = git::get_current_commit(path, config, logger)?;
+    // An unmoved HEAD pulled nothing, whatever (possibly translated) message
+    // git printed, such as "Already up to date."
+    let count = if after == before {
+        0
+    } else {
+        git::count_commits_since(path, config, &before, logger)?
+    };
+    Ok((ShaChange { before, after }, count))
+}
+
+/// Why [`UpdateMode::CurrentBranch`] skips a repository with a detached HEAD.
+const DETACHED_CURRENT_BRANCH_REASON: &str = "detached HEAD has no current branch to pull";
+
+/// Fast-forwards the current branch in place from its upstream with `git pull --ff-only`,
+/// stashing around it if needed, or rebases it onto its upstream with
+/// `git pull --rebase --autostash` under [`UpdateMode::CurrentBranchRebase`].
+/// Detached HEADs are skipped.
+fn sync_current_branch<C>(
+    path: &Path,
+    callbacks: &C,
+    config: &Config,
+    original_head: &OriginalHead,
+    dirty_files: usize,
+    had_stash: &mut bool,
+) -> Result<UpdateOutcome, UpdateError>
+where
+    C: UpdateCallbacks,
+{
+    let logger = config.git_logger();
+
+    let OriginalHead::Branch(branch) = original_head else {
+        return Ok(UpdateOutcome::Skipped(UpdateSkipped {
+            reason: DETACHED_CURRENT_BRANCH_REASON.to_string(),
+            step: UpdateStep::DetectingBranch,
+        }));
+    };
+
+    let rebase = config.update_mode == UpdateMode::CurrentBranchRebase;
+    // `--autostash` stashes and restores around the rebase on its own
+    let mut dropped_stashes = None;
+    if dirty_files > 0 && !rebase {
+        let (stashed, dropped) = run_step(UpdateStep::Stashing, path, callbacks, || {
+            stash_changes(path, config)
+        })?;
+        *had_stash = stashed;
+        dropped_stashes = dropped;
+    }
+    let had_stash = *had_stash;
+
+    let (master_sha, commits_pulled) = run_step(UpdateStep::Pulling, path, callbacks, || {
+        // Pull from the branch's own upstream; a branch without one pulls
+        // its namesake on the configured remote
+        let (remote, upstream_branch) = git::branch_upstream_source(path, config, branch, logger)?
+            .unwrap_or_else(|| (config.remote().to_string(), branch.clone()));
+        let before = git::get_current_commit(path, config, logger)?;
+        if !rebase {
+            git::pull_ff_only(path, config, &remote, &upstream_branch, logger)?;
+            return pulled_commits(path, config, before);
+        }
+        git::pull_rebase_autostash(path, config, &remote, &upstream_branch, logger)?;
+        let (sha, count) = pulled_commits(path, config, before)?;
+        // Rebased local commits get new SHAs too; only the upstream's were pulled
+        let (local, _) = git::ahead_behind(path, config, "HEAD", "FETCH_HEAD", logger)?;
+        Ok((sha, count.saturating_sub(local)))
+    })?;
+
+    if had_stash {
+        run_step(UpdateStep::PoppingStash, path, callbacks, || {
+            git::stash_pop(path, config, logger)
+        })?;
+    }
+
+    let describe = if config.is_verbose() {
+        git::describe(path, config, logger).ok()
+    } else {
+        None
+    };
+
+    Ok(UpdateOutcome::Success(UpdateSuccess {
+        master_branch: Some(branch.clone()),
+        had_stash: had_stash || (rebase && dirty_files > 0),
+        stashed_files: if had_stash || rebase { dirty_files } else { 0 },
+        commits_pulled,
+        warnings: dropped_stashes.into_iter().collect(),
+        describe,
+        // The pulled branch is the checked-out one, so HEAD moved with it
+        head_sha: master_sha.clone(),
+        master_sha,
+        ..UpdateSuccess::new(original_head.clone())
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_with_timeout_gives_up_on_slow_task() {
+        let result = run_with_timeout(Duration::from_millis(20), || {
+            std::thread::sleep(Duration::from_secs(2));
+        });
+        assert!(result.is_none());
+
+        assert_eq!(run_with_timeout(Duration::from_secs(5), || 42), Some(42));
+    }
+
+    #[test]
+    fn test_find_git_repos_with_timeout_returns_repos() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(workspace.path().join("repo").join(GIT_DIR)).unwrap();
+        std::fs::create_dir(workspace.path().join("notes")).unwrap();
+
+        let repos = find_git_repos_with_timeout(workspace.path(), Duration::from_secs(5)).unwrap();
+
+        assert_eq!(repos, vec![workspace.path().join("repo")]);
+    }
+
+    #[test]
+    fn test_detect_operation_from_state_files() {
+        let git_dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(detect_operation(git_dir.path()), None);
+
+        std::fs::write(git_dir.path().join("BISECT_LOG"), "").unwrap();
+        assert_eq!(
+            detect_operation(git_dir.path()),
+            Some(InProgressOperation::Bisect)
+        );
+
+        std::fs::write(git_dir.path().join("REVERT_HEAD"), "").unwrap();
+        assert_eq!(
+            detect_operation(git_dir.path()),
+            Some(InProgressOperation::Revert)
+        );
+
+        std::fs::create_dir(git_dir.path().join("rebase-merge")).unwrap();
+        assert_eq!(
+            detect_operation(git_dir.path()),
+            Some(InProgressOperation::Rebase)
+        );
+    }
+
+    #[test]
+    fn test_update_step_display_labels() {
+        assert_eq!(UpdateStep::Started.to_string(), "Starting");
+        assert_eq!(UpdateStep::DetectingBranch.to_string(), "Detecting branch");
+        assert_eq!(UpdateStep::CheckingChanges.to_string(), "Checking changes");
+        assert_eq!(UpdateStep::Fetching.to_string(), "Fetching");
+        assert_eq!(UpdateStep::Stashing.to_string(), "Stashing");
+        assert_eq!(UpdateStep::CheckingOut.to_string(), "Checking out");
+        assert_eq!(UpdateStep::Pulling.to_string(), "Pulling");
+        assert_eq!(UpdateStep::RestoringBranch.to_string(), "Restoring branch");
+        assert_eq!(UpdateStep::PoppingStash.to_string(), "Popping stash");
+        assert_eq!(UpdateStep::Completed.to_string(), "Completed");
+    }
+
+    #[test]
+    fn test_original_head_detached_display_and_ref() {
+        let head = OriginalHead::DetachedAt("abcdef1234567890".to_string());
+        assert_eq!(head.git_ref(), "abcdef1234567890");
+        assert!(head.is_detached());
+        assert_eq!(head.display(), "[abcdef1...detached]");
+    }
+
+    #[test]
+    fn test_original_head_branch_display_and_ref() {
+        let head = OriginalHead::Branch("feature-x".to_string());
+        assert_eq!(head.git_ref(), "feature-x");
+        assert!(!head.is_detached());
+        assert_eq!(head.display(), "[feature-x]");
+    }
+
+    #[test]
+    fn test_original_head_tag_display_and_ref() {
+        let head = OriginalHead::DetachedAtTag {
+            tag: "v1.2.3".to_string(),
+            sha: "abcdef1234567890".to_string(),
+        };
+        assert_eq!(head.git_ref(), "abcdef1234567890");
+        assert!(head.is_detached());
+        assert_eq!(head.display(), "[at tag v1.2.3]");
+    }
+
+    #[test]
+    fn test_update_failure_display() {
+        let failure = UpdateFailure::new(UpdateStep::Fetching, "boom");
+        assert_eq!(failure.to_string(), "failed at Fetching: boom");
+    }
+
+    #[test]
+    fn test_dedupe_repos_folds_case_only_on_case_insensitive_fs() {
+        let paths = vec![
+            PathBuf::from("/no/such/workspace/Repo"),
+            PathBuf::from("/no/such/workspace/repo"),
+        ];
+
+        let folded = dedupe_repos(paths.clone(), true);
+        assert_eq!(folded, vec![PathBuf::from("/no/such/workspace/Repo")]);
+
+        let distinct = dedupe_repos(paths.clone(), false);
+        assert_eq!(distinct, paths);
+    }
+
+    #[test]
+    fn test_case_insensitive_fs_probe_matches_filesystem() {
+        let dir = std::env::temp_dir().join(format!("git-daily-case-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let upper = dir.with_file_name(dir.file_name().unwrap().to_string_lossy().to_uppercase());
+        // Guarded: the expectation depends on the filesystem hosting the temp dir
+        let expected = upper.is_dir();
+        assert_eq!(is_case_insensitive_fs(&dir), expected);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_workspace_stats_sums_successes_only() {
+        let success = |commits, stashed| UpdateResult {
+            path: PathBuf::from("/ws/ok"),
+            outcome: UpdateOutcome::Success(UpdateSuccess {
+                master_branch: Some("main".to_string()),
+                had_stash: stashed > 0,
+                stashed_files: stashed,
+                commits_pulled: commits,
+                ..UpdateSuccess::new(OriginalHead::Branch("main".to_string()))
+            }),
+            duration: Duration::ZERO,
+        };
+        let failure = UpdateResult {
+            path: PathBuf::from("/ws/broken"),
+            outcome: UpdateOutcome::Failed(UpdateFailure::new(UpdateStep::Fetching, "boom")),
+            duration: Duration::ZERO,
+        };
+
+        let stats = WorkspaceStats::default();
+        stats.record(&success(3, 0));
+        stats.record(&success(4, 2));
+        stats.record(&failure);
+
+        assert_eq!(stats.repos_updated(), 2);
+        assert_eq!(stats.commits_pulled(), 7);
+        assert_eq!(stats.files_stashed(), 2);
+    }
+
+    #[test]
+    fn test_classify_dir_recognizes_git_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        assert_eq!(classify_dir(dir.path()), DirClassification::Repo);
+        assert!(is_git_repo(dir.path()));
+    }
+
+    #[test]
+    fn test_classify_dir_reports_missing_git_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(
+            classify_dir(dir.path()),
+            DirClassification::NotARepo(NotARepoReason::NoGitDir)
+        );
+        assert!(!is_git_repo(dir.path()));
+    }
+
+    #[test]
+    fn test_classify_dir_follows_git_file_to_existing_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("real-git-dir")).unwrap();
+        std::fs::write(dir.path().join(".git"), "gitdir: real-git-dir\n").unwrap();
+        assert_eq!(
+            classify_dir(dir.path()),
+            DirClassification::LinkedRepo {
+                git_dir: dir.path().join("real-git-dir")
+            }
+        );
+    }
+
+    #[test]
+    fn test_classify_dir_reports_dangling_git_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join(".git"), "gitdir: /no/such/worktree\n").unwrap();
+        assert_eq!(
+            classify_dir(dir.path()),
+            DirClassification::NotARepo(NotARepoReason::DanglingGitFile {
+                target: PathBuf::from("/no/such/worktree")
+            })
+        );
+    }
+
+    #[test]
+    fn test_classify_dir_reports_git_file_without_gitdir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join(".git"), "garbage\n").unwrap();
+        assert_eq!(
+            classify_dir(dir.path()),
+            DirClassification::NotARepo(NotARepoReason::InvalidGitFile)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_classify_dir_reports_permission_denied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let locked = dir.path().join("locked");
+        std::fs::create_dir_all(locked.join(".git")).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+
+        // Guarded: privileged users (e.g. root in CI containers) bypass file permissions
+        let enforced = std::fs::metadata(locked.join(".git")).is_err();
+        let classification = classify_dir(&locked);
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        if enforced {
+            assert_eq!(
+                classification,
+                DirClassification::NotARepo(NotARepoReason::PermissionDenied)
+            );
+        } else {
+            assert_eq!(classification, DirClassification::Repo);
+        }
+    }
+
+    #[test]
+    fn test_not_a_repo_reason_display() {
+        assert_eq!(NotARepoReason::NoGitDir.to_string(), "no .git directory");
+        assert_eq!(
+            NotARepoReason::PermissionDenied.to_string(),
+            "permission denied"
+        );
+        assert_eq!(
+            NotARepoReason::ReservedName.to_string(),
+            "reserved name on Windows"
+        );
+    }
+
+    #[test]
+    fn test_is_windows_reserved_name() {
+        for name in ["CON", "nul", "Aux.txt", "com1", "LPT9.tar.gz", "prn "] {
+            assert!(is_windows_reserved_name(name.as_ref()), "{}", name);
+        }
+        for name in ["console", "nully", "com0", "com10", "lpt", "repo-a"] {
+            assert!(!is_windows_reserved_name(name.as_ref()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_update_skipped_display() {
+        let skipped = UpdateSkipped {
+            reason: "too dirty".to_string(),
+            step: UpdateStep::CheckingChanges,
+        };
+        assert_eq!(skipped.to_string(), "skipped at CheckingChanges: too dirty");
+    }
+}
@@ -0,0 +1,437 @@
+//! Serializable run reports.
+//!
+//! A [`RunReport`] is a plain-data snapshot of a run's results that can be
+//! written as JSON and loaded back later (for example, to retry only the
+//! repositories that failed). A [`RunState`] is the line-by-line variant that
+//! is appended to while a run is in progress, so an interrupted run can resume.
+//! A [`PlanReport`] describes what an update would do, before it runs.
+
+use crate::repo::{UpdateOutcome, UpdatePlan, UpdateResult};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Machine-readable summary of a whole run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunReport {
+    /// Identifier of the run that produced this report, if one was assigned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    /// Repositories skipped as already updated by `--continue-from`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resumed: Option<usize>,
+    pub repos: Vec<RepoReport>,
+}
+
+/// Machine-readable result for a single repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoReport {
+    pub path: PathBuf,
+    pub status: ReportStatus,
+    /// Branch (or commit SHA when detached) the repository was on before the run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub master_branch: Option<String>,
+    #[serde(default)]
+    pub had_stash: bool,
+    #[serde(default)]
+    pub commits_pulled: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Remote branches the fetch created.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub new_remote_branches: Vec<String>,
+    /// Remote-tracking branches `--prune-remote-only` removed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pruned_remote_branches: Vec<String>,
+    /// HEAD and master branch commits before and after a successful update.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_before: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_after: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub master_before: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub master_after: Option<String>,
+    /// Step at which the update failed or was skipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<String>,
+    /// Error message for failures, or the reason for skips.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Manual restore instructions for failures that left the repo off its original ref.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_hint: Option<String>,
+    /// Exit code of the git command behind a failure (128 is a fatal git error).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_exit_code: Option<i32>,
+    pub duration_ms: u64,
+}
+
+/// Final status of a repository in a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportStatus {
+    Success,
+    Failed,
+    Skipped,
+}
+
+impl RunReport {
+    #[must_use]
+    pub fn from_results(results: &[UpdateResult]) -> Self {
+        Self {
+            run_id: None,
+            resumed: None,
+            repos: results.iter().map(RepoReport::from_result).collect(),
+        }
+    }
+
+    /// Records the run ID in the report.
+    #[must_use]
+    pub fn with_run_id(mut self, run_id: Option<&str>) -> Self {
+        self.run_id = run_id.map(str::to_string);
+        self
+    }
+
+    /// Records how many repositories a resumed run skipped.
+    #[must_use]
+    pub fn with_resumed(mut self, resumed: Option<usize>) -> Self {
+        self.resumed = resumed;
+        self
+    }
+
+    /// Serializes the report as pretty-printed JSON.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize run report")
+    }
+
+    /// Parses a report previously produced by [`RunReport::to_json`].
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        serde_json::from_str(json).context("Failed to parse run report")
+    }
+
+    /// Reads and parses a report from a file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read run report '{}'", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    /// Process exit code for the run: 0 when nothing failed, 2 when every
+    /// repository failed, 1 otherwise. Skipped repositories count toward the
+    /// total but not as failures.
+    ///
+    /// With a `fail_threshold` (a fraction of all repositories), failures are
+    /// tolerated, and the code is 0, while their share does not exceed it.
+    #[must_use]
+    pub fn exit_code(&self, fail_threshold: Option<f32>) -> i32 {
+        let failed = self
+            .repos
+            .iter()
+            .filter(|r| r.status == ReportStatus::Failed)
+            .count();
+        if failed == 0 {
+            return 0;
+        }
+        if let Some(threshold) = fail_threshold
+            && failed as f32 / self.repos.len() as f32 <= threshold
+        {
+            return 0;
+        }
+        if failed == self.repos.len() { 2 } else { 1 }
+    }
+
+    /// Returns the paths of repositories that failed, in report order.
+    #[must_use]
+    pub fn failed_paths(&self) -> Vec<PathBuf> {
+        self.repos
+            .iter()
+            .filter(|r| r.status == ReportStatus::Failed)
+            .map(|r| r.path.clone())
+            .collect()
+    }
+}
+
+/// Record of a resumable run, read from a state file with one [`RepoReport`]
+/// JSON object per line.
+///
+/// Lines are appended as each repository finishes (see
+/// [`StateFileCallbacks`](crate::output::StateFileCallbacks)), so a run that
+/// was interrupted still leaves every completed repository on record.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunState {
+    pub repos: Vec<RepoReport>,
+}
+
+impl RunState {
+    /// Parses state file contents. A malformed final line is ignored, since an
+    /// interrupted run may have stopped in the middle of writing it.
+    pub fn from_lines(contents: &str) -> anyhow::Result<Self> {
+        let lines: Vec<&str> = contents.lines().filter(|l| !l.trim().is_empty()).collect();
+        let mut repos = Vec::with_capacity(lines.len());
+        for (index, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(repo) => repos.push(repo),
+                Err(_) if index + 1 == lines.len() => break,
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to parse run state entry {}", index + 1));
+                }
+            }
+        }
+        Ok(Self { repos })
+    }
+
+    /// Reads a state file. A missing file is an empty state (nothing done yet).
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Self::from_lines(&contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to read run state '{}'", path.display()))
+            }
+        }
+    }
+
+    /// Returns the repositories whose latest recorded outcome is a success.
+    #[must_use]
+    pub fn completed_paths(&self) -> HashSet<PathBuf> {
+        let latest: HashMap<&Path, ReportStatus> = self
+            .repos
+            .iter()
+            .map(|r| (r.path.as_path(), r.status))
+            .collect();
+        latest
+            .into_iter()
+            .filter(|(_, status)| *status == ReportStatus::Success)
+            .map(|(path, _)| path.to_path_buf())
+            .collect()
+    }
+}
+
+impl RepoReport {
+    #[must_use]
+    pub fn from_result(result: &UpdateResult) -> Self {
+        let mut report = Self {
+            path: result.path.clone(),
+            status: ReportStatus::Success,
+            branch: None,
+            master_branch: None,
+            had_stash: false,
+            commits_pulled: 0,
+            warnings: Vec::new(),
+            new_remote_branches: Vec::new(),
+            pruned_remote_branches: Vec::new(),
+            head_before: None,
+            head_after: None,
+            master_before: None,
+            master_after: None,
+            step: None,
+            error: None,
+            recovery_hint: None,
+            git_exit_code: None,
+            duration_ms: result.duration.as_millis() as u64,
+        };
+        match &result.outcome {
+            UpdateOutcome::Success(success) => {
+                report.branch = Some(success.original_head.git_ref().to_string());
+                report.master_branch = success.master_branch.clone();
+                report.had_stash = success.had_stash;
+                report.commits_pulled = success.commits_pulled;
+                report.warnings = success.warnings.clone();
+                report.new_remote_branches = success.new_remote_branches.clone();
+                report.pruned_remote_branches = success.pruned_remote_branches.clone();
+                report.head_before = Some(success.head_sha.before.clone());
+                report.head_after = Some(success.head_sha.after.clone());
+                report.master_before = Some(success.master_sha.before.clone());
+                report.master_after = Some(success.master_sha.after.clone());
+            }
+            UpdateOutcome::Failed(failure) => {
+                report.status = ReportStatus::Failed;
+                report.step = Some(format!("{:?}", failure.step));
+                report.error = Some(failure.error.clone());
+                report.recovery_hint = failure.recovery_hint.clone();
+                report.git_exit_code = failure.git_exit_code;
+            }
+            UpdateOutcome::Skipped(skipped) => {
+                report.status = ReportStatus::Skipped;
+                report.step = Some(format!("{:?}", skipped.step));
+                report.error = Some(skipped.reason.clone());
+            }
+        }
+        report
+    }
+}
+
+/// Machine-readable plan for one repository (`--dry-run-json`): what an
+/// update would do, worked out without changing anything. Tooling can read
+/// the plans and decide whether to run the update.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanReport {
+    pub repo: PathBuf,
+    pub would_stash: bool,
+    /// Uncommitted files the stash would hold (0 when nothing is stashed).
+    pub stash_file_count: usize,
+    pub checkout_branch: Option<String>,
+    /// Commits behind the remote-tracking branch as of the last fetch.
+    pub remote_behind: Option<usize>,
+    pub would_pop: bool,
+    pub restore_to: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fail_reason: Option<String>,
+    /// Why no plan could be made for this repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl PlanReport {
+    #[must_use]
+    pub fn from_plan(repo: &Path, plan: &UpdatePlan) -> Self {
+        let would_stash = plan.would_stash();
+        Self {
+            repo: repo.to_path_buf(),
+            would_stash,
+            stash_file_count: if would_stash { plan.dirty_files } else { 0 },
+            checkout_branch: plan.checkout_branch.clone(),
+            remote_behind: plan.remote_behind,
+            would_pop: plan.would_pop(),
+            restore_to: plan.restore_to.clone(),
+            skip_reason: plan.skip_reason.clone(),
+            fail_reason: plan.fail_reason.clone(),
+            error: None,
+        }
+    }
+
+    /// A repository whose state could not be read.
+    #[must_use]
+    pub fn from_error(repo: &Path, error: String) -> Self {
+        Self {
+            repo: repo.to_path_buf(),
+            would_stash: false,
+            stash_file_count: 0,
+            checkout_branch: None,
+            remote_behind: None,
+            would_pop: false,
+            restore_to: None,
+            skip_reason: None,
+            fail_reason: None,
+            error: Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::{OriginalHead, UpdateFailure, UpdateStep, UpdateSuccess};
+    use std::time::Duration;
+
+    fn sample_results() -> Vec<UpdateResult> {
+        vec![
+            UpdateResult {
+                path: PathBuf::from("/ws/ok"),
+                outcome: UpdateOutcome::Success(UpdateSuccess {
+                    master_branch: Some("main".to_string()),
+                    had_stash: true,
+                    ..UpdateSuccess::new(OriginalHead::Branch("feature".to_string()))
+                }),
+                duration: Duration::from_millis(1200),
+            },
+            UpdateResult {
+                path: PathBuf::from("/ws/broken"),
+                outcome: UpdateOutcome::Failed(UpdateFailure {
+                    git_exit_code: Some(128),
+                    ..UpdateFailure::new(UpdateStep::Fetching, "boom")
+                }),
+                duration: Duration::from_millis(300),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_run_report_round_trips_through_json() {
+        let report = RunReport::from_results(&sample_results());
+        let json = report.to_json().unwrap();
+        let parsed = RunReport::from_json(&json).unwrap();
+
+        assert_eq!(parsed, report);
+        assert_eq!(parsed.repos[0].status, ReportStatus::Success);
+        assert_eq!(parsed.repos[0].branch.as_deref(), Some("feature"));
+        assert_eq!(parsed.repos[1].step.as_deref(), Some("Fetching"));
+        assert_eq!(parsed.repos[1].duration_ms, 300);
+        assert_eq!(parsed.repos[1].git_exit_code, Some(128));
+    }
+
+    #[test]
+    fn test_run_id_is_serialized_only_when_set() {
+        let report = RunReport::from_results(&sample_results());
+        assert!(!report.to_json().unwrap().contains("run_id"));
+
+        let tagged = report.with_run_id(Some("1760600000-42"));
+        let parsed = RunReport::from_json(&tagged.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.run_id.as_deref(), Some("1760600000-42"));
+    }
+
+    #[test]
+    fn test_exit_code_honors_fail_threshold() {
+        // One of two repositories failed: a failure ratio of 0.5
+        let report = RunReport::from_results(&sample_results());
+
+        assert_eq!(report.exit_code(None), 1);
+        assert_eq!(report.exit_code(Some(0.0)), 1);
+        assert_eq!(report.exit_code(Some(0.4)), 1);
+        assert_eq!(report.exit_code(Some(0.5)), 0);
+        assert_eq!(report.exit_code(Some(1.0)), 0);
+
+        let all_failed = RunReport::from_results(&sample_results()[1..]);
+        assert_eq!(all_failed.exit_code(Some(0.5)), 2);
+        assert_eq!(all_failed.exit_code(Some(1.0)), 0);
+        assert_eq!(RunReport::from_results(&[]).exit_code(None), 0);
+    }
+
+    #[test]
+    fn test_failed_paths_selects_only_failures() {
+        let report = RunReport::from_results(&sample_results());
+        let parsed = RunReport::from_json(&report.to_json().unwrap()).unwrap();
+
+        assert_eq!(parsed.failed_paths(), vec![PathBuf::from("/ws/broken")]);
+    }
+
+    #[test]
+    fn test_run_state_uses_latest_outcome_per_repo() {
+        let results = sample_results();
+        let line = |result: &UpdateResult| {
+            serde_json::to_string(&RepoReport::from_result(result)).unwrap()
+        };
+        let mut retried = results[1].clone();
+        retried.outcome = results[0].outcome.clone();
+        // A run interrupted mid-write leaves a truncated last line
+        let contents = format!(
+            "{}\n{}\n{}\n{{\"path\":",
+            line(&results[0]),
+            line(&results[1]),
+            line(&retried)
+        );
+
+        let state = RunState::from_lines(&contents).unwrap();
+
+        assert_eq!(state.repos.len(), 3);
+        assert_eq!(
+            state.completed_paths(),
+            HashSet::from([PathBuf::from("/ws/ok"), PathBuf::from("/ws/broken")])
+        );
+        assert!(RunState::from_lines("garbage\n{}\n").is_err());
+    }
+
+    #[test]
+    fn test_from_json_rejects_garbage() {
+        let result = RunReport::from_json("not json");
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("run report"));
+    }
+}
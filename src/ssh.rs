@@ -0,0 +1,199 @@
+//! SSH connection sharing for a workspace run (`--ssh-multiplex`).
+//!
+//! Every fetch and pull over SSH normally opens its own connection, paying the
+//! handshake (and any key prompt or agent round-trip) once per git command.
+//! [`SshMultiplex`] points `GIT_SSH_COMMAND` at a ControlMaster socket in a
+//! private temp directory, so commands to the same host reuse one connection.
+//! Remotes over HTTPS or the local filesystem are unaffected.
+
+use crate::config::Config;
+use crate::constants::SSH_CONTROL_PERSIST_SECS;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Environment variable git runs instead of plain `ssh`.
+pub const GIT_SSH_COMMAND_VAR: &str = "GIT_SSH_COMMAND";
+
+/// Older environment variable naming the ssh program git runs.
+pub const GIT_SSH_VAR: &str = "GIT_SSH";
+
+/// Keeps directories of multiplexers started by one process apart.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A control socket directory shared by the git processes of one run.
+///
+/// Dropping it closes the master connections and removes the directory.
+#[derive(Debug)]
+pub struct SshMultiplex {
+    control_dir: PathBuf,
+}
+
+impl SshMultiplex {
+    /// Creates the control directory, readable only by the current user.
+    ///
+    /// Fails if the directory already exists: under the shared `/tmp` it
+    /// could belong to someone else, who would then see the sockets.
+    pub fn start() -> io::Result<Self> {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let control_dir =
+            socket_base_dir().join(format!("git-daily-ssh-{}-{}", std::process::id(), id));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            // Private from the moment it exists, not after a chmod
+            builder.mode(0o700);
+        }
+        builder.create(&control_dir)?;
+        Ok(Self { control_dir })
+    }
+
+    #[must_use]
+    pub fn control_dir(&self) -> &Path {
+        &self.control_dir
+    }
+
+    /// The `ssh` invocation that opens or reuses a master connection per host.
+    #[must_use]
+    pub fn ssh_command(&self) -> String {
+        // `%C` is a hash of host, port and user, so each destination gets its
+        // own short socket name. Quoted because git runs this through a shell.
+        let control_path = self.control_dir.join("%C");
+        format!(
+            "ssh -o ControlMaster=auto -o ControlPath='{}' -o ControlPersist={}",
+            control_path.display(),
+            SSH_CONTROL_PERSIST_SECS
+        )
+    }
+
+    /// Sets `GIT_SSH_COMMAND` for every git process spawned with `config`.
+    ///
+    /// An ssh the user already chose is kept, and the returned warning says
+    /// multiplexing is off: `GIT_SSH_COMMAND` or `GIT_SSH` set through
+    /// `--env` or `inherited` (looks a variable up in the process
+    /// environment), or a `core.sshCommand` from the git config
+    /// (`configured`), which `GIT_SSH_COMMAND` would otherwise override.
+    pub fn apply(
+        &self,
+        config: &mut Config,
+        inherited: impl Fn(&str) -> Option<String>,
+        configured: Option<&str>,
+    ) -> Option<String> {
+        for var in [GIT_SSH_COMMAND_VAR, GIT_SSH_VAR] {
+            if config.env.contains_key(var) || inherited(var).is_some() {
+                return Some(format!("--ssh-multiplex ignored: {} is already set", var));
+            }
+        }
+        if configured.is_some() {
+            return Some("--ssh-multiplex ignored: core.sshCommand is set".to_string());
+        }
+        config
+            .env
+            .insert(GIT_SSH_COMMAND_VAR.to_string(), self.ssh_command());
+        None
+    }
+}
+
+/// Where control directories are created.
+///
+/// Unix socket paths are limited to about 100 bytes, which macOS's per-user
+/// temp dir plus a 40-character `%C` socket name would exceed.
+fn socket_base_dir() -> PathBuf {
+    if cfg!(unix) {
+        PathBuf::from("/tmp")
+    } else {
+        std::env::temp_dir()
+    }
+}
+
+impl Drop for SshMultiplex {
+    fn drop(&mut self) {
+        // Masters would otherwise linger for ControlPersist after the run
+        if let Ok(entries) = fs::read_dir(&self.control_dir) {
+            for socket in entries.flatten() {
+                let _ = Command::new("ssh")
+                    .arg("-o")
+                    .arg(format!("ControlPath={}", socket.path().display()))
+                    .args(["-O", "exit", "git-daily"])
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status();
+            }
+        }
+        let _ = fs::remove_dir_all(&self.control_dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_sets_git_ssh_command_to_a_control_master() {
+        let multiplex = SshMultiplex::start().unwrap();
+        let mut config = Config::default();
+
+        assert_eq!(multiplex.apply(&mut config, |_| None, None), None);
+
+        let command = &config.env[GIT_SSH_COMMAND_VAR];
+        assert!(command.starts_with("ssh "));
+        assert!(command.contains("ControlMaster=auto"));
+        assert!(command.contains(&multiplex.control_dir().display().to_string()));
+    }
+
+    #[test]
+    fn test_apply_keeps_a_user_chosen_ssh_command() {
+        let multiplex = SshMultiplex::start().unwrap();
+        let mut config = Config::default();
+        config
+            .env
+            .insert(GIT_SSH_COMMAND_VAR.to_string(), "ssh -i key".to_string());
+
+        assert!(multiplex.apply(&mut config, |_| None, None).is_some());
+        assert_eq!(config.env[GIT_SSH_COMMAND_VAR], "ssh -i key");
+
+        for var in [GIT_SSH_COMMAND_VAR, GIT_SSH_VAR] {
+            let mut config = Config::default();
+            let inherited = |name: &str| (name == var).then(|| "ssh -v".to_string());
+            let warning = multiplex.apply(&mut config, inherited, None).unwrap();
+            assert!(warning.contains(var), "{}", warning);
+            assert!(!config.env.contains_key(GIT_SSH_COMMAND_VAR));
+        }
+
+        let mut config = Config::default();
+        let warning = multiplex
+            .apply(&mut config, |_| None, Some("ssh -i key"))
+            .unwrap();
+        assert!(warning.contains("core.sshCommand"), "{}", warning);
+        assert!(!config.env.contains_key(GIT_SSH_COMMAND_VAR));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_start_creates_a_private_control_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let multiplex = SshMultiplex::start().unwrap();
+
+        let mode = fs::metadata(multiplex.control_dir())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
+    #[test]
+    fn test_drop_removes_the_control_dir() {
+        let multiplex = SshMultiplex::start().unwrap();
+        let control_dir = multiplex.control_dir().to_path_buf();
+        assert!(control_dir.is_dir());
+
+        drop(multiplex);
+
+        assert!(!control_dir.exists());
+    }
+}
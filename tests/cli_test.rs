@@ -0,0 +1,495 @@
+mod common;
+
+use common::{init_repo, push_upstream_commits, setup_workspace_with_repos, test_config};
+use git_daily_rust::git;
+use git_daily_rust::report::{RepoReport, ReportStatus, RunReport, RunState};
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn run_cli(cwd: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_git-daily-v2"))
+        .args(args)
+        .current_dir(cwd)
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("failed to run git-daily-v2")
+}
+
+#[test]
+fn test_cli_report_only_changed_is_silent_when_nothing_changed() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master"), ("b", "main")])?;
+
+    let output = run_cli(workspace.path(), &["--report-only-changed"]);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(
+        output.stdout.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cli_report_only_changed_prints_summary_when_commits_pulled() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master"), ("b", "main")])?;
+    push_upstream_commits(&workspace.path().join("a-remote"), "master", 2)?;
+
+    let output = run_cli(workspace.path(), &["--report-only-changed"]);
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("2 commits pulled"), "{}", stdout);
+    Ok(())
+}
+
+#[test]
+fn test_cli_quiet_on_success_prints_one_line_when_all_succeed() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master"), ("b", "main")])?;
+
+    let output = run_cli(workspace.path(), &["--quiet-on-success"]);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "2/2 repositories updated\n"
+    );
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cli_quiet_on_success_prints_full_summary_when_a_repo_fails() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master"), ("b", "main")])?;
+    std::fs::remove_dir_all(workspace.path().join("b-remote"))?;
+
+    let output = run_cli(workspace.path(), &["--quiet-on-success"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Summary"), "{}", stdout);
+    assert!(stdout.contains("Total: 1/2 repos"), "{}", stdout);
+    assert!(!stdout.contains("repositories updated"), "{}", stdout);
+    Ok(())
+}
+
+fn repo_report(path: std::path::PathBuf, status: ReportStatus) -> RepoReport {
+    RepoReport {
+        path,
+        status,
+        branch: None,
+        master_branch: None,
+        had_stash: false,
+        commits_pulled: 0,
+        warnings: Vec::new(),
+        new_remote_branches: Vec::new(),
+        pruned_remote_branches: Vec::new(),
+        head_before: None,
+        head_after: None,
+        master_before: None,
+        master_after: None,
+        step: None,
+        error: None,
+        recovery_hint: None,
+        git_exit_code: None,
+        duration_ms: 0,
+    }
+}
+
+#[test]
+fn test_cli_list_repos_prints_discovered_repos_without_updating() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("beta", "master"), ("alpha", "main")])?;
+    push_upstream_commits(&workspace.path().join("alpha-remote"), "main", 1)?;
+    std::fs::create_dir(workspace.path().join("notes"))?;
+
+    let output = run_cli(workspace.path(), &["--list-repos"]);
+
+    assert_eq!(output.status.code(), Some(0));
+    let expected = format!(
+        "{}\n{}\n",
+        workspace.path().join("alpha").display(),
+        workspace.path().join("beta").display()
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    // Nothing was fetched: origin/main still has only the initial commit
+    let known_upstream = git::run_git(
+        &workspace.path().join("alpha"),
+        &test_config(),
+        &["rev-list", "--count", "origin/main"],
+    )?;
+    assert_eq!(known_upstream, "1");
+    Ok(())
+}
+
+#[test]
+fn test_cli_list_repos_applies_only_failed_from_filter() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(
+        &workspace,
+        &[("a", "master"), ("b", "master"), ("c", "master")],
+    )?;
+    let report = RunReport {
+        run_id: None,
+        resumed: None,
+        repos: vec![
+            repo_report(workspace.path().join("a"), ReportStatus::Success),
+            repo_report(workspace.path().join("c"), ReportStatus::Failed),
+            repo_report(workspace.path().join("b"), ReportStatus::Failed),
+        ],
+    };
+    let report_path = workspace.path().join("report.json");
+    std::fs::write(&report_path, report.to_json()?)?;
+
+    let output = run_cli(
+        workspace.path(),
+        &[
+            "--list-repos",
+            "--only-failed-from",
+            report_path.to_str().unwrap(),
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    let expected = format!(
+        "{}\n{}\n",
+        workspace.path().join("b").display(),
+        workspace.path().join("c").display()
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    Ok(())
+}
+
+#[test]
+fn test_cli_only_failed_from_rejects_paths_outside_work_trees() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master")])?;
+    let not_a_repo = workspace.path().join("notes");
+    std::fs::create_dir(&not_a_repo)?;
+    let report = RunReport {
+        run_id: None,
+        resumed: None,
+        repos: vec![
+            repo_report(workspace.path().join("a"), ReportStatus::Failed),
+            repo_report(not_a_repo.clone(), ReportStatus::Failed),
+        ],
+    };
+    let report_path = workspace.path().join("report.json");
+    std::fs::write(&report_path, report.to_json()?)?;
+
+    let output = run_cli(
+        workspace.path(),
+        &["--only-failed-from", report_path.to_str().unwrap()],
+    );
+
+    assert_ne!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!("'{}'", not_a_repo.display()))
+            && stderr.contains("is not inside a git work tree"),
+        "{}",
+        stderr
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cli_continue_from_skips_repos_already_updated() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master"), ("b", "main")])?;
+    push_upstream_commits(&workspace.path().join("a-remote"), "master", 1)?;
+    let config = test_config();
+    let a_head = git::get_current_commit(&workspace.path().join("a"), &config, git::no_op_logger)?;
+    let state_path = workspace.path().join("state.jsonl");
+    let done = repo_report(workspace.path().join("a"), ReportStatus::Success);
+    std::fs::write(&state_path, format!("{}\n", serde_json::to_string(&done)?))?;
+
+    let output = run_cli(
+        workspace.path(),
+        &[
+            "--quiet",
+            "--json",
+            "--continue-from",
+            state_path.to_str().unwrap(),
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    let report = RunReport::from_json(&String::from_utf8_lossy(&output.stdout))?;
+    let paths: Vec<_> = report.repos.iter().map(|r| r.path.clone()).collect();
+    assert_eq!(paths, vec![workspace.path().join("b")]);
+    assert_eq!(report.resumed, Some(1));
+    // The already-done repo was not touched, so its new upstream commit is still unpulled
+    assert_eq!(
+        git::get_current_commit(&workspace.path().join("a"), &config, git::no_op_logger)?,
+        a_head
+    );
+
+    let state = RunState::load(&state_path)?;
+    assert_eq!(state.repos.len(), 2);
+    assert_eq!(
+        state.completed_paths(),
+        [workspace.path().join("a"), workspace.path().join("b")]
+            .into_iter()
+            .collect()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cli_refuses_home_as_workspace_root_unless_forced() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master")])?;
+    let run_from_home = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_git-daily-v2"))
+            .args(args)
+            .current_dir(workspace.path())
+            .env("NO_COLOR", "1")
+            .env("HOME", workspace.path())
+            .output()
+            .expect("failed to run git-daily-v2")
+    };
+
+    let refused = run_from_home(&[]);
+    assert_eq!(refused.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&refused.stderr);
+    assert!(stderr.contains("your home directory"), "{}", stderr);
+    assert!(stderr.contains("--force"), "{}", stderr);
+
+    let forced = run_from_home(&["--force"]);
+    assert_eq!(
+        forced.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&forced.stderr)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cli_json_stdout_is_a_single_json_document() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master"), ("b", "main")])?;
+    push_upstream_commits(&workspace.path().join("a-remote"), "master", 1)?;
+
+    for args in [&["--quiet", "--json"][..], &["--json"][..]] {
+        let output = run_cli(workspace.path(), args);
+
+        assert_eq!(output.status.code(), Some(0), "{:?}", args);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let trimmed = stdout.trim();
+        assert!(
+            trimmed.starts_with('{') && trimmed.ends_with('}'),
+            "{:?}: {}",
+            args,
+            stdout
+        );
+        serde_json::from_str::<serde_json::Value>(&stdout)?;
+    }
+
+    let quiet = run_cli(workspace.path(), &["--quiet", "--json"]);
+    assert!(
+        quiet.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&quiet.stderr)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cli_warns_when_repo_root_holds_repos_and_workspace_updates_them() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master")])?;
+    init_repo(workspace.path(), "master")?;
+    push_upstream_commits(&workspace.path().join("a-remote"), "master", 1)?;
+    let config = test_config();
+    let a_head = git::get_current_commit(&workspace.path().join("a"), &config, git::no_op_logger)?;
+
+    let single = run_cli(workspace.path(), &[]);
+    let stderr = String::from_utf8_lossy(&single.stderr);
+    assert!(
+        stderr.contains("is a git repository, so only it is updated"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("--workspace"), "{}", stderr);
+    assert_eq!(
+        git::get_current_commit(&workspace.path().join("a"), &config, git::no_op_logger)?,
+        a_head
+    );
+
+    let nested = run_cli(workspace.path(), &["--workspace"]);
+    assert_eq!(
+        nested.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&nested.stderr)
+    );
+    assert!(!String::from_utf8_lossy(&nested.stderr).contains("only it is updated"));
+    assert_ne!(
+        git::get_current_commit(&workspace.path().join("a"), &config, git::no_op_logger)?,
+        a_head
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cli_exits_3_when_filtering_leaves_no_repos() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master"), ("b", "main")])?;
+    let state_path = workspace.path().join("state.jsonl");
+    let done: Vec<String> = ["a", "b"]
+        .iter()
+        .map(|name| {
+            serde_json::to_string(&repo_report(
+                workspace.path().join(name),
+                ReportStatus::Success,
+            ))
+        })
+        .collect::<Result<_, _>>()?;
+    std::fs::write(&state_path, done.join("\n") + "\n")?;
+
+    let output = run_cli(
+        workspace.path(),
+        &["--continue-from", state_path.to_str().unwrap()],
+    );
+
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "No repositories matched (2 found, all already updated according to --continue-from)"
+        ),
+        "{}",
+        stderr
+    );
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Summary"));
+    Ok(())
+}
+
+#[test]
+fn test_cli_nothing_matched_still_prints_the_json_report() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master")])?;
+    let state_path = workspace.path().join("state.jsonl");
+    let done = repo_report(workspace.path().join("a"), ReportStatus::Success);
+    std::fs::write(&state_path, format!("{}\n", serde_json::to_string(&done)?))?;
+
+    let output = run_cli(
+        workspace.path(),
+        &[
+            "--format",
+            "json",
+            "--continue-from",
+            state_path.to_str().unwrap(),
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(3));
+    let report = RunReport::from_json(&String::from_utf8_lossy(&output.stdout))?;
+    assert!(report.repos.is_empty());
+    assert_eq!(report.resumed, Some(1));
+    Ok(())
+}
+
+#[test]
+fn test_cli_only_failed_from_without_failures_exits_0() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master")])?;
+    let report = RunReport {
+        run_id: None,
+        resumed: None,
+        repos: vec![repo_report(
+            workspace.path().join("a"),
+            ReportStatus::Success,
+        )],
+    };
+    let report_path = workspace.path().join("report.json");
+    std::fs::write(&report_path, report.to_json()?)?;
+
+    let output = run_cli(
+        workspace.path(),
+        &["--only-failed-from", report_path.to_str().unwrap()],
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 found, none failed in"), "{}", stderr);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Summary"));
+    Ok(())
+}
+
+#[test]
+fn test_cli_dry_run_json_plans_dirty_behind_repo_without_changing_it() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("app", "master")])?;
+    let app = workspace.path().join("app");
+    git::run_git(&app, &config, &["checkout", "-b", "feature"])?;
+    std::fs::write(app.join("README.md"), "# WIP\n")?;
+    push_upstream_commits(&workspace.path().join("app-remote"), "master", 3)?;
+    // The plan reads remote-tracking refs as of the last fetch
+    git::fetch_prune(&app, &config, git::no_op_logger)?;
+
+    let output = run_cli(workspace.path(), &["--dry-run-json"]);
+
+    assert_eq!(output.status.code(), Some(0));
+    let plans: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(plans.len(), 1);
+    let plan = &plans[0];
+    assert_eq!(plan["repo"], app.to_str().unwrap());
+    assert_eq!(plan["would_stash"], true);
+    assert_eq!(plan["stash_file_count"], 1);
+    assert_eq!(plan["checkout_branch"], "master");
+    assert_eq!(plan["remote_behind"], 3);
+    assert_eq!(plan["would_pop"], true);
+    assert_eq!(plan["restore_to"], "feature");
+    // Nothing was stashed, checked out or pulled
+    assert_eq!(
+        git::get_current_branch(&app, &config, git::no_op_logger)?,
+        "feature"
+    );
+    assert_eq!(std::fs::read_to_string(app.join("README.md"))?, "# WIP\n");
+    assert_eq!(
+        git::ahead_behind(&app, &config, "master", "origin/master", git::no_op_logger)?,
+        (0, 3)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cli_read_only_commands_treat_bare_repo_as_single_repo() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("app", "master")])?;
+    let mirror = workspace.path().join("app-remote");
+
+    let output = run_cli(&mirror, &["--list-repos"]);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout)?.trim(),
+        mirror.to_str().unwrap()
+    );
+
+    let output = run_cli(&mirror, &["--dry-run-json"]);
+
+    assert_eq!(output.status.code(), Some(0));
+    let plans: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(plans.len(), 1);
+    assert_eq!(plans[0]["repo"], mirror.to_str().unwrap());
+    Ok(())
+}
@@ -7,9 +7,10 @@ use anyhow::Result;
 use git_daily_rust::config::Config;
 use git_daily_rust::git::run_git;
 use git_daily_rust::repo::{UpdateCallbacks, UpdateResult, UpdateStep};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use tempfile::TempDir;
 
 /// Default config for tests (normal verbosity, no special options).
@@ -91,6 +92,12 @@ impl TestRepo {
         Ok(())
     }
 
+    /// Changes only whitespace and line endings in a tracked file.
+    pub fn make_whitespace_dirty(&self) -> Result<()> {
+        std::fs::write(self.path.join("README.md"), "#  Test Repo \r\n")?;
+        Ok(())
+    }
+
     /// Creates an untracked file.
     pub fn make_untracked(&self) -> Result<()> {
         std::fs::write(self.path.join("untracked.txt"), "untracked content\n")?;
@@ -147,6 +154,63 @@ impl UpdateCallbacks for CountingCallbacks {
     }
 }
 
+/// Callbacks that record, per repository, the ordered steps each update
+/// entered, so tests can pin exact step sequences.
+///
+/// Consecutive reports of the same step count once: a step may report
+/// itself several times as it runs. Clones share one log, so a workspace
+/// update records every repo with `|path| recorder.for_repo(path)`.
+#[derive(Clone, Default)]
+pub struct RecordingCallbacks {
+    log: Arc<Mutex<HashMap<PathBuf, Vec<UpdateStep>>>>,
+    repo: PathBuf,
+}
+
+impl RecordingCallbacks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Callbacks recording into the same log under `repo`.
+    pub fn for_repo(&self, repo: &Path) -> Self {
+        Self {
+            log: Arc::clone(&self.log),
+            repo: repo.to_path_buf(),
+        }
+    }
+
+    /// Steps recorded for `repo`, in order, from `Started` to `Completed`.
+    pub fn steps(&self, repo: &Path) -> Vec<UpdateStep> {
+        self.log
+            .lock()
+            .unwrap()
+            .get(repo)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Steps recorded for `repo` without `Started` and `Completed`, as
+    /// [`git_daily_rust::repo::plan_update`] lists them.
+    pub fn work_steps(&self, repo: &Path) -> Vec<UpdateStep> {
+        self.steps(repo)
+            .into_iter()
+            .filter(|step| !matches!(step, UpdateStep::Started | UpdateStep::Completed))
+            .collect()
+    }
+}
+
+impl UpdateCallbacks for RecordingCallbacks {
+    fn on_step(&self, step: &UpdateStep) {
+        let mut log = self.log.lock().unwrap();
+        let steps = log.entry(self.repo.clone()).or_default();
+        if steps.last() != Some(step) {
+            steps.push(*step);
+        }
+    }
+
+    fn on_complete(&self, _result: &UpdateResult) {}
+}
+
 /// Initializes a git repository at the given path with an initial commit.
 pub fn init_repo(path: &Path, branch: &str) -> Result<()> {
     let config = test_config();
@@ -183,3 +247,72 @@ pub fn setup_workspace_with_repos(
     }
     Ok(())
 }
+
+/// Pushes `count` new commits to `branch` of the bare remote at `remote_path`,
+/// as if another developer had pushed them.
+pub fn push_upstream_commits(remote_path: &Path, branch: &str, count: usize) -> Result<()> {
+    let config = test_config();
+    let clone_dir = TempDir::new()?;
+    let clone_path = clone_dir.path().join("clone");
+    run_git(
+        clone_dir.path(),
+        &config,
+        &[
+            "clone",
+            "--branch",
+            branch,
+            remote_path.to_str().unwrap(),
+            "clone",
+        ],
+    )?;
+    run_git(
+        &clone_path,
+        &config,
+        &["config", "user.email", "other@example.com"],
+    )?;
+    run_git(&clone_path, &config, &["config", "user.name", "Other User"])?;
+    for i in 0..count {
+        let file = format!("upstream-{}.txt", i);
+        std::fs::write(clone_path.join(&file), format!("change {}\n", i))?;
+        run_git(&clone_path, &config, &["add", &file])?;
+        run_git(
+            &clone_path,
+            &config,
+            &["commit", "-m", &format!("Upstream change {}", i)],
+        )?;
+    }
+    run_git(&clone_path, &config, &["push", "origin", branch])?;
+    Ok(())
+}
+
+/// Clones the bare remote at `remote_path` into `dest` as a blobless partial
+/// clone (`--filter=blob:none`), so blobs of older commits stay on the remote.
+pub fn partial_clone(remote_path: &Path, dest: &Path) -> Result<()> {
+    let config = test_config();
+    run_git(
+        remote_path,
+        &config,
+        &["config", "uploadpack.allowFilter", "true"],
+    )?;
+    // Local paths bypass the transport that honors filters; a file:// URL does not
+    let url = format!("file://{}", remote_path.display());
+    let parent = dest.parent().expect("clone destination has a parent");
+    run_git(
+        parent,
+        &config,
+        &["clone", "--filter=blob:none", &url, dest.to_str().unwrap()],
+    )?;
+    run_git(dest, &config, &["config", "user.email", "test@example.com"])?;
+    run_git(dest, &config, &["config", "user.name", "Test User"])?;
+    Ok(())
+}
+
+/// Counts objects the repository at `path` knows of but has not downloaded.
+pub fn missing_object_count(path: &Path) -> Result<usize> {
+    let objects = run_git(
+        path,
+        &test_config(),
+        &["rev-list", "--objects", "--all", "--missing=print"],
+    )?;
+    Ok(objects.lines().filter(|line| line.starts_with('?')).count())
+}
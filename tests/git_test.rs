@@ -1,6 +1,8 @@
 mod common;
 
-use common::{TestRepo, test_config};
+use common::{
+    TestRepo, partial_clone, push_upstream_commits, setup_workspace_with_repos, test_config,
+};
 use git_daily_rust::git::{self, no_op_logger};
 use std::path::PathBuf;
 use tempfile::TempDir;
@@ -27,6 +29,72 @@ fn test_repo_with_remote() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_partial_clone_filter_only_for_promisor_remotes() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    assert_eq!(
+        git::partial_clone_filter(repo.path(), &config, "origin", logger())?,
+        None
+    );
+
+    let remote = git::run_git(repo.path(), &config, &["remote", "get-url", "origin"])?;
+    let workspace = TempDir::new()?;
+    let clone = workspace.path().join("clone");
+    partial_clone(&PathBuf::from(remote), &clone)?;
+
+    assert_eq!(
+        git::partial_clone_filter(&clone, &config, "origin", logger())?.as_deref(),
+        Some("blob:none")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_commit_time_reads_committer_date() -> anyhow::Result<()> {
+    let mut config = test_config();
+    let repo = TestRepo::new()?;
+    config.env.insert(
+        "GIT_COMMITTER_DATE".to_string(),
+        "@1700000000 +0200".to_string(),
+    );
+    git::run_git(
+        repo.path(),
+        &config,
+        &["commit", "--allow-empty", "-m", "Known moment"],
+    )?;
+
+    let time = git::commit_time(repo.path(), &config, "HEAD", logger())?;
+
+    let expected = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+    let difference = time
+        .duration_since(expected)
+        .unwrap_or_else(|e| e.duration());
+    assert!(difference < std::time::Duration::from_secs(1), "{:?}", time);
+    Ok(())
+}
+
+#[test]
+fn test_fetch_prune_returns_updated_refs() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    let remote = git::run_git(repo.path(), &config, &["remote", "get-url", "origin"])?;
+    push_upstream_commits(&PathBuf::from(remote), "master", 1)?;
+
+    let summary = git::fetch_prune(repo.path(), &config, logger())?;
+
+    assert!(summary[0].starts_with("From "), "{:?}", summary);
+    assert!(
+        summary
+            .iter()
+            .any(|line| line.ends_with("-> origin/master")),
+        "{:?}",
+        summary
+    );
+    assert!(git::fetch_prune(repo.path(), &config, logger())?.is_empty());
+    Ok(())
+}
+
 #[test]
 fn test_create_branch() -> anyhow::Result<()> {
     let config = test_config();
@@ -61,6 +129,45 @@ fn test_make_dirty() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_has_only_whitespace_changes() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::new()?;
+    assert!(git::has_only_whitespace_changes(
+        repo.path(),
+        &config,
+        logger()
+    )?);
+
+    repo.make_whitespace_dirty()?;
+    assert!(git::has_uncommitted_changes(
+        repo.path(),
+        &config,
+        logger()
+    )?);
+    assert!(git::has_only_whitespace_changes(
+        repo.path(),
+        &config,
+        logger()
+    )?);
+
+    repo.make_untracked()?;
+    assert!(!git::has_only_whitespace_changes(
+        repo.path(),
+        &config,
+        logger()
+    )?);
+
+    std::fs::remove_file(repo.path().join("untracked.txt"))?;
+    repo.make_dirty()?;
+    assert!(!git::has_only_whitespace_changes(
+        repo.path(),
+        &config,
+        logger()
+    )?);
+    Ok(())
+}
+
 #[test]
 fn test_make_untracked() -> anyhow::Result<()> {
     let config = test_config();
@@ -87,6 +194,52 @@ fn test_has_stash() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_stash_paths_stashes_only_matching_paths() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::new()?;
+    std::fs::write(repo.path().join("generated.txt"), "v1\n")?;
+    git::run_git(repo.path(), &config, &["add", "generated.txt"])?;
+    git::run_git(
+        repo.path(),
+        &config,
+        &["commit", "-m", "Add generated file"],
+    )?;
+    repo.make_dirty()?;
+    std::fs::write(repo.path().join("generated.txt"), "v2\n")?;
+
+    let stashed = git::stash_paths(
+        repo.path(),
+        &config,
+        &[":(exclude)generated.txt".to_string()],
+        logger(),
+    )?;
+
+    assert!(stashed);
+    let in_stash = git::run_git(repo.path(), &config, &["stash", "show", "--name-only"])?;
+    assert_eq!(in_stash, "README.md");
+    let still_modified = git::run_git(repo.path(), &config, &["status", "--porcelain"])?;
+    assert_eq!(still_modified, "M generated.txt");
+    Ok(())
+}
+
+#[test]
+fn test_stash_paths_rejects_option_like_pathspec() -> anyhow::Result<()> {
+    let repo = TestRepo::new()?;
+    repo.make_dirty()?;
+
+    let result = git::stash_paths(
+        repo.path(),
+        &test_config(),
+        &["--all".to_string()],
+        logger(),
+    );
+
+    assert!(result.is_err());
+    assert!(!repo.has_stash()?);
+    Ok(())
+}
+
 #[test]
 fn test_file_exists() -> anyhow::Result<()> {
     let repo = TestRepo::new()?;
@@ -361,3 +514,473 @@ fn test_delete_branch_fails_on_unmerged_branch() -> anyhow::Result<()> {
     assert!(result.is_err());
     Ok(())
 }
+
+#[test]
+fn test_describe_includes_tag_and_dirty_marker() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::new()?;
+    git::run_git(repo.path(), &config, &["tag", "v1.2.3"])?;
+
+    assert_eq!(git::describe(repo.path(), &config, logger())?, "v1.2.3");
+
+    repo.make_dirty()?;
+    let described = git::describe(repo.path(), &config, logger())?;
+    assert!(described.starts_with("v1.2.3"));
+    assert!(described.ends_with("-dirty"));
+    Ok(())
+}
+
+#[test]
+fn test_describe_falls_back_to_sha_without_tags() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::new()?;
+    let commit = git::get_current_commit(repo.path(), &config, logger())?;
+
+    let described = git::describe(repo.path(), &config, logger())?;
+    assert!(commit.starts_with(&described));
+    Ok(())
+}
+
+#[test]
+fn test_pull_ff_only_advances_current_branch() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo = workspace.path().join("repo");
+    push_upstream_commits(&workspace.path().join("repo-remote"), "master", 2)?;
+    let before = git::get_current_commit(&repo, &config, logger())?;
+
+    git::pull_ff_only(&repo, &config, "origin", "master", logger())?;
+
+    assert_eq!(git::get_current_branch(&repo, &config, logger())?, "master");
+    assert_eq!(
+        git::count_commits_since(&repo, &config, &before, logger())?,
+        2
+    );
+    Ok(())
+}
+
+#[test]
+fn test_pull_ff_only_reports_divergence_distinctly() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo = workspace.path().join("repo");
+    push_upstream_commits(&workspace.path().join("repo-remote"), "master", 1)?;
+    std::fs::write(repo.join("local.txt"), "local\n")?;
+    git::run_git(&repo, &config, &["add", "local.txt"])?;
+    git::run_git(&repo, &config, &["commit", "-m", "Local change"])?;
+
+    let error = git::pull_ff_only(&repo, &config, "origin", "master", logger()).unwrap_err();
+
+    let not_ff = error
+        .downcast_ref::<git::NotFastForward>()
+        .expect("expected a NotFastForward error");
+    assert_eq!(not_ff.branch, "master");
+    assert!(format!("{:#}", error).contains("cannot be fast-forwarded"));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_configured_env_reaches_git_hooks() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut config = test_config();
+    let repo = TestRepo::new()?;
+    repo.create_branch("feature")?;
+    let hook = repo.path().join(".git/hooks/post-checkout");
+    std::fs::write(
+        &hook,
+        "#!/bin/sh\necho \"$GIT_DAILY_TEST_VAR\" > hook-env.txt\n",
+    )?;
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+
+    config
+        .env
+        .insert("GIT_DAILY_TEST_VAR".to_string(), "from-config".to_string());
+    git::checkout(repo.path(), &config, "feature", logger())?;
+
+    let recorded = std::fs::read_to_string(repo.path().join("hook-env.txt"))?;
+    assert_eq!(recorded.trim(), "from-config");
+    Ok(())
+}
+
+#[test]
+fn test_ahead_behind_compares_arbitrary_refs() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::new()?;
+    let base = git::get_current_commit(repo.path(), &config, logger())?;
+    git::run_git(repo.path(), &config, &["tag", "v1"])?;
+    for message in ["one", "two"] {
+        git::run_git(
+            repo.path(),
+            &config,
+            &["commit", "--allow-empty", "-m", message],
+        )?;
+    }
+
+    assert_eq!(
+        git::ahead_behind(repo.path(), &config, "HEAD", "v1", logger())?,
+        (2, 0)
+    );
+    assert_eq!(
+        git::ahead_behind(repo.path(), &config, &base, "master", logger())?,
+        (0, 2)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_ahead_behind_reports_unknown_ref() -> anyhow::Result<()> {
+    let repo = TestRepo::new()?;
+
+    let err = git::ahead_behind(repo.path(), &test_config(), "HEAD", "no-such-ref", logger())
+        .unwrap_err();
+
+    assert_eq!(err.to_string(), "Unknown ref 'no-such-ref'");
+    Ok(())
+}
+
+#[test]
+fn test_repo_status_matches_the_separate_queries() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::new()?;
+    repo.create_branch("feature")?;
+    git::checkout(repo.path(), &config, "feature", logger())?;
+    repo.make_dirty()?;
+    repo.make_untracked()?;
+
+    let status = git::repo_status(repo.path(), &config, &[], logger())?;
+
+    assert_eq!(status.branch, "feature");
+    assert_eq!(
+        status.commit,
+        Some(git::get_current_commit(repo.path(), &config, logger())?)
+    );
+    assert_eq!(
+        status.dirty_files,
+        git::count_uncommitted_files(repo.path(), &config, logger())?
+    );
+    assert_eq!(status.dirty_files, 2);
+
+    let excluded = ["untracked.txt".to_string()];
+    let status = git::repo_status(repo.path(), &config, &excluded, logger())?;
+    assert_eq!(status.dirty_files, 1);
+
+    let sha = status.commit.unwrap();
+    git::checkout_detached(repo.path(), &config, &sha, logger())?;
+    let status = git::repo_status(repo.path(), &config, &[], logger())?;
+    assert_eq!(status.branch, "HEAD");
+    Ok(())
+}
+
+#[test]
+fn test_remote_branch_tip_and_resolve_commit_agree_after_a_push() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    let head = git::get_current_commit(repo.path(), &config, logger())?;
+
+    assert_eq!(
+        git::remote_branch_tip(repo.path(), &config, "origin", "master", logger())?,
+        Some(head.clone())
+    );
+    assert_eq!(
+        git::remote_branch_tip(repo.path(), &config, "origin", "nope", logger())?,
+        None
+    );
+    assert_eq!(
+        git::resolve_commit(repo.path(), &config, "master", logger())?,
+        Some(head)
+    );
+    assert_eq!(
+        git::resolve_commit(repo.path(), &config, "no-such-ref", logger())?,
+        None
+    );
+    Ok(())
+}
+
+#[test]
+fn test_verify_commit_rejects_unsigned_commit() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::new()?;
+
+    let error = git::verify_commit(repo.path(), &config, "HEAD", logger()).unwrap_err();
+
+    let unsigned = error
+        .downcast_ref::<git::UnsignedCommit>()
+        .expect("expected an UnsignedCommit error");
+    assert_eq!(unsigned.reason, "no signature");
+    Ok(())
+}
+
+#[test]
+fn test_verify_commit_checks_ssh_signatures() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::new()?;
+    let keys = TempDir::new()?;
+    let key = keys.path().join("key");
+    let keygen = std::process::Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", ""])
+        .arg("-f")
+        .arg(&key)
+        .output();
+    if !keygen.is_ok_and(|output| output.status.success()) {
+        eprintln!("ssh-keygen not available; skipping");
+        return Ok(());
+    }
+    let public_key = std::fs::read_to_string(keys.path().join("key.pub"))?;
+    let allowed = keys.path().join("allowed_signers");
+    std::fs::write(&allowed, format!("test@example.com {}", public_key))?;
+    git::run_git(repo.path(), &config, &["config", "gpg.format", "ssh"])?;
+    git::run_git(
+        repo.path(),
+        &config,
+        &["config", "user.signingkey", key.to_str().unwrap()],
+    )?;
+    git::run_git(
+        repo.path(),
+        &config,
+        &["commit", "-S", "--allow-empty", "-m", "Signed"],
+    )?;
+
+    // Without allowed signers the signature cannot be checked, which is not a pass
+    let error = git::verify_commit(repo.path(), &config, "HEAD", logger()).unwrap_err();
+    assert!(
+        error
+            .downcast_ref::<git::SignatureCheckUnavailable>()
+            .is_some(),
+        "{:#}",
+        error
+    );
+
+    git::run_git(
+        repo.path(),
+        &config,
+        &[
+            "config",
+            "gpg.ssh.allowedSignersFile",
+            allowed.to_str().unwrap(),
+        ],
+    )?;
+    git::verify_commit(repo.path(), &config, "HEAD", logger())?;
+    assert!(git::verify_commit(repo.path(), &config, "HEAD~1", logger()).is_err());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_checkout_detached_is_explicit_and_silent() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let repo = TestRepo::new()?;
+    let sha = git::get_current_commit(repo.path(), &test_config(), logger())?;
+    // Record each checkout's arguments and what git wrote to stderr
+    let bin_dir = TempDir::new()?;
+    let calls = bin_dir.path().join("calls.log");
+    let stderr = bin_dir.path().join("stderr.log");
+    let wrapper = bin_dir.path().join("git-wrapper");
+    std::fs::write(
+        &wrapper,
+        format!(
+            "#!/bin/sh\ncase \" $* \" in\n  *\" checkout \"*) echo \"$@\" >> '{}'; exec git \"$@\" 2>> '{}' ;;\nesac\nexec git \"$@\"\n",
+            calls.display(),
+            stderr.display()
+        ),
+    )?;
+    std::fs::set_permissions(&wrapper, std::fs::Permissions::from_mode(0o755))?;
+    let mut config = test_config();
+    config.git_binary = Some(wrapper);
+
+    git::checkout_detached(repo.path(), &config, &sha, logger())?;
+
+    assert_eq!(
+        git::get_current_branch(repo.path(), &config, logger())?,
+        "HEAD"
+    );
+    assert_eq!(
+        git::get_current_commit(repo.path(), &config, logger())?,
+        sha
+    );
+    let calls = std::fs::read_to_string(&calls)?;
+    assert_eq!(
+        calls.trim(),
+        format!("-c advice.detachedHead=false checkout --detach {}", sha)
+    );
+    let stderr = std::fs::read_to_string(&stderr)?;
+    assert!(stderr.contains("HEAD is now at"), "{}", stderr);
+    assert!(!stderr.contains("detached HEAD"), "{}", stderr);
+    Ok(())
+}
+
+#[test]
+fn test_is_inside_work_tree_distinguishes_repos_from_plain_dirs() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::new()?;
+    let subdir = repo.path().join("src");
+    std::fs::create_dir(&subdir)?;
+    let plain = TempDir::new()?;
+
+    assert!(git::is_inside_work_tree(repo.path(), &config, logger())?);
+    assert!(git::is_inside_work_tree(&subdir, &config, logger())?);
+    assert!(!git::is_inside_work_tree(plain.path(), &config, logger())?);
+    assert!(!git::is_inside_work_tree(
+        &plain.path().join("missing"),
+        &config,
+        logger()
+    )?);
+    Ok(())
+}
+
+#[test]
+fn test_update_submodules_advances_branch_tracking_submodule_to_remote_tip() -> anyhow::Result<()> {
+    let mut config = test_config();
+    // Local file:// submodules are refused by default since git 2.38.1
+    config.env.insert("GIT_CONFIG_COUNT".into(), "1".into());
+    config
+        .env
+        .insert("GIT_CONFIG_KEY_0".into(), "protocol.file.allow".into());
+    config
+        .env
+        .insert("GIT_CONFIG_VALUE_0".into(), "always".into());
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("lib", "main"), ("app", "master")])?;
+    let lib_remote = workspace.path().join("lib-remote");
+    let app = workspace.path().join("app");
+    git::run_git(
+        &app,
+        &config,
+        &[
+            "submodule",
+            "add",
+            "-b",
+            "main",
+            lib_remote.to_str().unwrap(),
+            "lib",
+        ],
+    )?;
+    git::run_git(&app, &config, &["commit", "-m", "Add lib"])?;
+    let pinned = git::get_current_commit(&app.join("lib"), &config, logger())?;
+    push_upstream_commits(&lib_remote, "main", 1)?;
+    let remote_tip = git::run_git(&lib_remote, &config, &["rev-parse", "main"])?;
+
+    let tracked = git::update_submodules(&app, &config, logger())?;
+
+    assert_eq!(
+        tracked,
+        vec![git::TrackedSubmodule {
+            path: "lib".to_string(),
+            branch: "main".to_string(),
+        }]
+    );
+    let head = git::get_current_commit(&app.join("lib"), &config, logger())?;
+    assert_ne!(head, pinned);
+    assert_eq!(head, remote_tip);
+    Ok(())
+}
+
+#[test]
+fn test_tracked_submodules_is_empty_without_gitmodules() -> anyhow::Result<()> {
+    let repo = TestRepo::new()?;
+    assert!(git::tracked_submodules(repo.path(), &test_config(), logger())?.is_empty());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_git_binary_runs_configured_executable() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let repo = TestRepo::new()?;
+    let bin_dir = TempDir::new()?;
+    let calls = bin_dir.path().join("calls.log");
+    let wrapper = bin_dir.path().join("git-wrapper");
+    std::fs::write(
+        &wrapper,
+        format!(
+            "#!/bin/sh\necho \"$@\" >> '{}'\nexec git \"$@\"\n",
+            calls.display()
+        ),
+    )?;
+    std::fs::set_permissions(&wrapper, std::fs::Permissions::from_mode(0o755))?;
+    let mut config = test_config();
+    config.git_binary = Some(wrapper);
+
+    assert!(git::check_git_binary(&config)?.starts_with("git version"));
+    let branch = git::get_current_branch(repo.path(), &config, logger())?;
+
+    assert_eq!(branch, "master");
+    let calls = std::fs::read_to_string(&calls)?;
+    assert_eq!(calls.lines().next(), Some("--version"));
+    assert_eq!(calls.lines().count(), 2, "{}", calls);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_fetch_appends_extra_fetch_args() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let repo = TestRepo::with_remote(None)?;
+    let bin_dir = TempDir::new()?;
+    let calls = bin_dir.path().join("calls.log");
+    let wrapper = bin_dir.path().join("git-wrapper");
+    std::fs::write(
+        &wrapper,
+        format!(
+            "#!/bin/sh\necho \"$@\" >> '{}'\nexec git \"$@\"\n",
+            calls.display()
+        ),
+    )?;
+    std::fs::set_permissions(&wrapper, std::fs::Permissions::from_mode(0o755))?;
+    let mut config = test_config();
+    config.git_binary = Some(wrapper);
+    config.extra_fetch_args = vec!["--no-tags".to_string(), "--force".to_string()];
+
+    git::fetch_prune(repo.path(), &config, logger())?;
+
+    let calls = std::fs::read_to_string(&calls)?;
+    let fetch = calls.lines().find(|line| line.contains("fetch")).unwrap();
+    assert!(
+        fetch.ends_with("fetch --prune origin --no-tags --force"),
+        "{}",
+        fetch
+    );
+
+    config.extra_fetch_args = vec!["--upload-pack=touch pwned".to_string()];
+    assert!(git::fetch_prune(repo.path(), &config, logger()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_check_git_binary_reports_missing_executable() {
+    let mut config = test_config();
+    config.git_binary = Some(PathBuf::from("/nonexistent/git"));
+
+    let err = git::check_git_binary(&config).unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("Cannot run git binary '/nonexistent/git'"),
+        "{:#}",
+        err
+    );
+}
+
+#[test]
+fn test_read_only_refuses_checkout_but_allows_reads() -> anyhow::Result<()> {
+    let repo = TestRepo::new()?;
+    repo.create_branch("feature")?;
+    let mut config = test_config();
+    config.read_only = true;
+
+    let err = git::checkout(repo.path(), &config, "feature", logger()).unwrap_err();
+
+    let violation = err
+        .downcast_ref::<git::ReadOnlyViolation>()
+        .expect("read-only violation");
+    assert_eq!(violation.command, "checkout feature");
+    assert_eq!(
+        git::get_current_branch(repo.path(), &config, logger())?,
+        "master"
+    );
+    Ok(())
+}
@@ -1,9 +1,18 @@
 mod common;
 
-use common::{TestRepo, test_config};
+use common::{
+    RecordingCallbacks, TestRepo, missing_object_count, partial_clone, push_upstream_commits,
+    setup_workspace_with_repos, test_config,
+};
+use git_daily_rust::config::{BranchPreference, Config, UntrackedConflictPolicy, UpdateMode};
+use git_daily_rust::constants::GIT_DAILY_STASH_MESSAGE;
 use git_daily_rust::git::{self, no_op_logger};
 use git_daily_rust::output::NoOpCallbacks;
-use git_daily_rust::repo::{self, OriginalHead, UpdateOutcome, UpdateStep};
+use git_daily_rust::repo::{
+    self, OriginalHead, UpdateCallbacks, UpdateOutcome, UpdateResult, UpdateStep,
+};
+use std::path::PathBuf;
+use std::sync::Mutex;
 use tempfile::TempDir;
 
 /// Shorthand for the test logger (no-op for tests)
@@ -26,6 +35,115 @@ fn test_update_returns_to_original_branch() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_update_to_syncs_target_branch_and_restores_original() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("app", "master")])?;
+    let app = workspace.path().join("app");
+    git::run_git(&app, &config, &["branch", "develop"])?;
+    git::run_git(&app, &config, &["push", "-u", "origin", "develop"])?;
+    push_upstream_commits(&workspace.path().join("app-remote"), "develop", 1)?;
+    git::run_git(&app, &config, &["checkout", "-b", "feature"])?;
+    std::fs::write(app.join("README.md"), "# Modified\n")?;
+
+    let result = repo::update_to(&app, "develop", &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = result.outcome else {
+        panic!("expected success, got {:?}", result.outcome);
+    };
+    assert_eq!(success.master_branch.as_deref(), Some("develop"));
+    assert_eq!(success.commits_pulled, 1);
+    assert!(success.had_stash);
+    assert_eq!(git::get_current_branch(&app, &config, logger())?, "feature");
+    assert!(git::has_uncommitted_changes(&app, &config, logger())?);
+    Ok(())
+}
+
+#[test]
+fn test_update_to_rejects_invalid_target_before_touching_repo() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    repo.make_dirty()?;
+
+    let result = repo::update_to(repo.path(), "--upload-pack=evil", &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Failed(failure) = result.outcome else {
+        panic!("expected failure, got {:?}", result.outcome);
+    };
+    assert_eq!(failure.step, UpdateStep::CheckingOut);
+    assert!(
+        failure.error.contains("cannot start with '-'"),
+        "{}",
+        failure.error
+    );
+    assert!(!repo.has_stash()?);
+    assert!(git::has_uncommitted_changes(
+        repo.path(),
+        &config,
+        logger()
+    )?);
+    Ok(())
+}
+
+#[test]
+fn test_update_diagnoses_git_file_pointing_at_moved_gitdir() {
+    let config = test_config();
+    let dir = TempDir::new().unwrap();
+    let missing = dir.path().join("moved-main-repo/.git/worktrees/feature");
+    std::fs::write(
+        dir.path().join(".git"),
+        format!("gitdir: {}\n", missing.display()),
+    )
+    .unwrap();
+    assert!(repo::is_broken_worktree_link(dir.path()));
+
+    let result = repo::update(dir.path(), &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Failed(failure) = result.outcome else {
+        panic!("expected failure, got {:?}", result.outcome);
+    };
+    assert_eq!(failure.step, UpdateStep::DetectingBranch);
+    assert!(
+        failure
+            .error
+            .contains("broken worktree link: .git file points to missing"),
+        "{}",
+        failure.error
+    );
+    assert!(
+        failure.error.contains("git worktree repair"),
+        "{}",
+        failure.error
+    );
+    // Diagnosed before git ran, so there is no git exit code to report
+    assert_eq!(failure.git_exit_code, None);
+}
+
+#[test]
+fn test_update_reports_remote_branches_created_by_the_fetch() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("app", "master")])?;
+    let remote = workspace.path().join("app-remote");
+    git::run_git(&remote, &config, &["branch", "feature-y", "master"])?;
+    git::run_git(&remote, &config, &["branch", "feature-x", "master"])?;
+    let app = workspace.path().join("app");
+
+    let first = repo::update(&app, &NoOpCallbacks, &config);
+    let UpdateOutcome::Success(success) = first.outcome else {
+        panic!("expected success, got {:?}", first.outcome);
+    };
+    assert_eq!(success.new_remote_branches, vec!["feature-x", "feature-y"]);
+
+    let second = repo::update(&app, &NoOpCallbacks, &config);
+    let UpdateOutcome::Success(success) = second.outcome else {
+        panic!("expected success, got {:?}", second.outcome);
+    };
+    assert!(success.new_remote_branches.is_empty());
+    Ok(())
+}
+
 #[test]
 fn test_update_stashes_and_restores_uncommitted_changes() -> anyhow::Result<()> {
     let config = test_config();
@@ -49,6 +167,201 @@ fn test_update_stashes_and_restores_uncommitted_changes() -> anyhow::Result<()>
     Ok(())
 }
 
+#[test]
+fn test_update_ignore_whitespace_dirty_treats_whitespace_changes_as_clean() -> anyhow::Result<()> {
+    let repo = TestRepo::with_remote(None)?;
+    repo.make_whitespace_dirty()?;
+
+    let stashed = repo::update(repo.path(), &NoOpCallbacks, &test_config());
+    let UpdateOutcome::Success(success) = stashed.outcome else {
+        panic!("expected success, got {:?}", stashed.outcome);
+    };
+    assert!(success.had_stash);
+
+    let mut config = test_config();
+    config.ignore_whitespace_dirty = true;
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = result.outcome else {
+        panic!("expected success, got {:?}", result.outcome);
+    };
+    assert!(!success.had_stash);
+    assert_eq!(success.stashed_files, 0);
+    assert!(!repo.has_stash()?);
+    // The whitespace change was left in place, not stashed away
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("README.md"))?,
+        "#  Test Repo \r\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_ignore_dirty_path_treats_excluded_changes_as_clean() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.ignore_dirty_paths = Some(vec!["build-info.txt".to_string()]);
+    let repo = TestRepo::with_remote(None)?;
+    std::fs::write(repo.path().join("build-info.txt"), "built at 0\n")?;
+    git::run_git(repo.path(), &config, &["add", "build-info.txt"])?;
+    git::run_git(repo.path(), &config, &["commit", "-m", "Add build info"])?;
+    git::run_git(repo.path(), &config, &["push", "origin", "master"])?;
+    std::fs::write(repo.path().join("build-info.txt"), "built at 1\n")?;
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = result.outcome else {
+        panic!("expected success, got {:?}", result.outcome);
+    };
+    assert!(!success.had_stash);
+    assert!(!repo.has_stash()?);
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("build-info.txt"))?,
+        "built at 1\n"
+    );
+
+    // Any other change still counts
+    repo.make_dirty()?;
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+    let UpdateOutcome::Success(success) = result.outcome else {
+        panic!("expected success, got {:?}", result.outcome);
+    };
+    assert!(success.had_stash);
+    assert_eq!(success.stashed_files, 1);
+    Ok(())
+}
+
+#[test]
+fn test_update_stashes_ignored_changes_that_conflict_with_the_default_branch() -> anyhow::Result<()>
+{
+    let mut config = test_config();
+    config.ignore_dirty_paths = Some(vec!["build-info.txt".to_string()]);
+    let repo = TestRepo::with_remote(None)?;
+    std::fs::write(repo.path().join("build-info.txt"), "built at 0\n")?;
+    git::run_git(repo.path(), &config, &["add", "build-info.txt"])?;
+    git::run_git(repo.path(), &config, &["commit", "-m", "Add build info"])?;
+    git::run_git(repo.path(), &config, &["push", "origin", "master"])?;
+    // The feature branch has its own version of the file, so the edit
+    // cannot ride along the checkout of master
+    repo.create_branch("feature")?;
+    git::checkout(repo.path(), &config, "feature", logger())?;
+    std::fs::write(repo.path().join("build-info.txt"), "built at 1\n")?;
+    git::run_git(repo.path(), &config, &["commit", "-am", "Rebuild"])?;
+    std::fs::write(repo.path().join("build-info.txt"), "built at 2\n")?;
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = result.outcome else {
+        panic!("expected success, got {:?}", result.outcome);
+    };
+    assert!(success.had_stash);
+    assert_eq!(success.stashed_files, 1);
+    assert!(!repo.has_stash()?);
+    assert_eq!(
+        git::get_current_branch(repo.path(), &config, logger())?,
+        "feature"
+    );
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("build-info.txt"))?,
+        "built at 2\n"
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_update_no_checkout_hooks_keeps_post_checkout_hook_out_of_the_way() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let repo = TestRepo::with_remote(None)?;
+    repo.create_branch("feature")?;
+    git::checkout(repo.path(), &test_config(), "feature", logger())?;
+    // Regenerates a tracked file on every checkout, which would collide with the stash
+    let hook = repo.path().join(".git/hooks/post-checkout");
+    std::fs::write(&hook, "#!/bin/sh\necho generated >> README.md\n")?;
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+    repo.make_dirty()?;
+
+    let mut config = test_config();
+    config.no_checkout_hooks = true;
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    assert!(
+        matches!(result.outcome, UpdateOutcome::Success(_)),
+        "{:?}",
+        result.outcome
+    );
+    assert_eq!(
+        git::get_current_branch(repo.path(), &config, logger())?,
+        "feature"
+    );
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("README.md"))?,
+        "# Modified\n"
+    );
+    assert!(!repo.has_stash()?);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_update_repo_retries_rerun_update_after_transient_checkout_failure() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let repo = TestRepo::with_remote(None)?;
+    repo.create_branch("feature")?;
+    git::checkout(repo.path(), &test_config(), "feature", logger())?;
+    // Fails the first checkout only, like a file briefly locked by a virus scanner
+    let hook = repo.path().join(".git/hooks/post-checkout");
+    std::fs::write(
+        &hook,
+        "#!/bin/sh\n[ -e .git/failed-once ] && exit 0\ntouch .git/failed-once\nexit 1\n",
+    )?;
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+    repo.make_dirty()?;
+
+    let mut config = test_config();
+    config.repo_retries = 1;
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = result.outcome else {
+        panic!("expected success, got {:?}", result.outcome);
+    };
+    assert!(repo.path().join(".git/failed-once").exists());
+    assert_eq!(success.warnings, vec!["succeeded after 1 retry"]);
+    assert_eq!(
+        git::get_current_branch(repo.path(), &config, logger())?,
+        "feature"
+    );
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("README.md"))?,
+        "# Modified\n"
+    );
+    assert!(!repo.has_stash()?);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_update_repo_retries_skip_failures_at_unlisted_steps() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let repo = TestRepo::with_remote(None)?;
+    let hook = repo.path().join(".git/hooks/post-checkout");
+    std::fs::write(&hook, "#!/bin/sh\nexit 1\n")?;
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+
+    let mut config = test_config();
+    config.repo_retries = 2;
+    config.repo_retry_steps = Some(vec![UpdateStep::Fetching]);
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Failed(failure) = result.outcome else {
+        panic!("expected failure, got {:?}", result.outcome);
+    };
+    assert_eq!(failure.step, UpdateStep::CheckingOut);
+    Ok(())
+}
+
 #[test]
 fn test_update_untracked_only_no_pop() -> anyhow::Result<()> {
     let config = test_config();
@@ -99,13 +412,14 @@ fn test_update_falls_back_to_main_when_no_master_branch() -> anyhow::Result<()>
 
     match result.outcome {
         UpdateOutcome::Success(success) => {
-            assert_eq!(success.master_branch, "main");
+            assert_eq!(success.master_branch.as_deref(), Some("main"));
             assert_eq!(
                 success.original_head,
                 OriginalHead::Branch("feature".to_string())
             );
         }
         UpdateOutcome::Failed(failure) => anyhow::bail!("update failed: {}", failure.error),
+        UpdateOutcome::Skipped(skipped) => anyhow::bail!("unexpected skip: {}", skipped.reason),
     }
 
     let branch = git::get_current_branch(repo.path(), &config, logger())?;
@@ -130,8 +444,11 @@ fn test_update_reports_failure_when_fetch_fails_without_remote() -> anyhow::Resu
                 "Expected error to mention 'fetch' or 'remote', got: {}",
                 failure.error
             );
+            // Nothing was changed yet, so there is nothing to undo
+            assert!(failure.recovery_hint.is_none());
         }
         UpdateOutcome::Success(_) => anyhow::bail!("expected update to fail without a remote"),
+        UpdateOutcome::Skipped(skipped) => anyhow::bail!("unexpected skip: {}", skipped.reason),
     }
     Ok(())
 }
@@ -184,11 +501,56 @@ fn test_update_handles_detached_head() -> anyhow::Result<()> {
         UpdateOutcome::Failed(failure) => {
             anyhow::bail!("Expected success, got failure: {}", failure.error)
         }
+        UpdateOutcome::Skipped(skipped) => anyhow::bail!("unexpected skip: {}", skipped.reason),
     }
 
     let current_commit = git::get_current_commit(repo.path(), &config, logger())?;
     assert_eq!(current_commit, original_commit);
+    // Restored by commit, not by checking out a branch literally named "HEAD"
+    assert_eq!(
+        git::get_current_branch(repo.path(), &config, logger())?,
+        "HEAD"
+    );
+    assert!(!git::local_branch_exists(
+        repo.path(),
+        &config,
+        "HEAD",
+        logger()
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_update_reports_tag_when_detached_at_tag() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
 
+    let original_commit = git::get_current_commit(repo.path(), &config, logger())?;
+    git::run_git(repo.path(), &config, &["tag", "v1.2.3"])?;
+    git::run_git(repo.path(), &config, &["checkout", "--detach", "v1.2.3"])?;
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match &result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(
+                success.original_head,
+                OriginalHead::DetachedAtTag {
+                    tag: "v1.2.3".to_string(),
+                    sha: original_commit.clone(),
+                }
+            );
+            assert!(success.original_head.display().contains("at tag v1.2.3"));
+        }
+        UpdateOutcome::Failed(failure) => {
+            anyhow::bail!("Expected success, got failure: {}", failure.error)
+        }
+        UpdateOutcome::Skipped(skipped) => anyhow::bail!("unexpected skip: {}", skipped.reason),
+    }
+
+    let current_commit = git::get_current_commit(repo.path(), &config, logger())?;
+    assert_eq!(current_commit, original_commit);
     Ok(())
 }
 
@@ -203,6 +565,7 @@ fn test_update_clean_repo_reports_no_stash() -> anyhow::Result<()> {
             assert!(!success.had_stash);
         }
         UpdateOutcome::Failed(failure) => anyhow::bail!("update failed: {}", failure.error),
+        UpdateOutcome::Skipped(skipped) => anyhow::bail!("unexpected skip: {}", skipped.reason),
     }
     Ok(())
 }
@@ -240,6 +603,7 @@ fn test_update_fails_when_no_master_or_main() -> anyhow::Result<()> {
             assert_eq!(failure.step, UpdateStep::CheckingOut);
         }
         UpdateOutcome::Success(_) => anyhow::bail!("expected update to fail without master/main"),
+        UpdateOutcome::Skipped(skipped) => anyhow::bail!("unexpected skip: {}", skipped.reason),
     }
     Ok(())
 }
@@ -265,6 +629,1625 @@ fn test_update_handles_empty_repo() -> anyhow::Result<()> {
             assert_eq!(failure.step, UpdateStep::DetectingBranch);
         }
         UpdateOutcome::Success(_) => anyhow::bail!("expected update to fail for empty repo"),
+        UpdateOutcome::Skipped(skipped) => anyhow::bail!("unexpected skip: {}", skipped.reason),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_update_skips_repo_with_too_many_dirty_files() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.skip_dirty_over = Some(3);
+    let repo = TestRepo::with_remote(None)?;
+    repo.create_branch("feature")?;
+    git::checkout(repo.path(), &config, "feature", logger())?;
+    for i in 0..5 {
+        std::fs::write(repo.path().join(format!("wip-{}.txt", i)), "wip\n")?;
+    }
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Skipped(skipped) => {
+            assert_eq!(skipped.step, UpdateStep::CheckingChanges);
+            assert!(skipped.reason.contains("5 uncommitted files"));
+        }
+        other => anyhow::bail!("expected skip, got {:?}", other),
+    }
+    assert!(!repo.has_stash()?);
+    assert_eq!(
+        git::get_current_branch(repo.path(), &config, logger())?,
+        "feature"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_proceeds_when_dirty_files_within_limit() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.skip_dirty_over = Some(3);
+    let repo = TestRepo::with_remote(None)?;
+    repo.make_dirty()?;
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => assert!(success.had_stash),
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_update_keeps_partial_clone_partial() -> anyhow::Result<()> {
+    let config = test_config();
+    let origin = TestRepo::with_remote(None)?;
+    // Older README versions are blobs a blobless clone never downloads
+    for version in 1..=2 {
+        std::fs::write(origin.path().join("README.md"), format!("# v{}\n", version))?;
+        git::run_git(origin.path(), &config, &["commit", "-am", "Edit README"])?;
+    }
+    git::run_git(origin.path(), &config, &["push", "origin", "master"])?;
+    let remote = PathBuf::from(git::run_git(
+        origin.path(),
+        &config,
+        &["remote", "get-url", "origin"],
+    )?);
+    let workspace = TempDir::new()?;
+    let clone = workspace.path().join("clone");
+    partial_clone(&remote, &clone)?;
+    git::run_git(&clone, &config, &["checkout", "-b", "feature"])?;
+    let missing_before = missing_object_count(&clone)?;
+    assert!(missing_before > 0);
+    push_upstream_commits(&remote, "master", 2)?;
+
+    let result = repo::update(&clone, &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => assert_eq!(success.commits_pulled, 2),
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    assert_eq!(
+        git::partial_clone_filter(&clone, &config, "origin", logger())?.as_deref(),
+        Some("blob:none")
+    );
+    assert!(missing_object_count(&clone)? >= missing_before);
+    Ok(())
+}
+
+#[test]
+fn test_plan_update_explains_dirty_feature_branch_and_matches_the_run() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    repo.create_branch("feature")?;
+    git::checkout(repo.path(), &config, "feature", logger())?;
+    repo.make_dirty()?;
+
+    let plan = repo::plan_update(repo.path(), &config)?;
+
+    assert_eq!(plan.branch, "feature");
+    assert_eq!(plan.dirty_files, 1);
+    assert_eq!(plan.skip_reason, None);
+    assert_eq!(
+        plan.steps,
+        [
+            UpdateStep::DetectingBranch,
+            UpdateStep::CheckingChanges,
+            UpdateStep::Fetching,
+            UpdateStep::Stashing,
+            UpdateStep::CheckingOut,
+            UpdateStep::Pulling,
+            UpdateStep::RestoringBranch,
+            UpdateStep::PoppingStash,
+        ]
+    );
+    // Planning changes nothing
+    assert!(!repo.has_stash()?);
+
+    let recorder = RecordingCallbacks::new().for_repo(repo.path());
+    let result = repo::update(repo.path(), &recorder, &config);
+    assert!(matches!(result.outcome, UpdateOutcome::Success(_)));
+    assert_eq!(recorder.work_steps(repo.path()), plan.steps);
+    Ok(())
+}
+
+#[test]
+fn test_plan_update_to_follows_the_target_like_update_to() -> anyhow::Result<()> {
+    let mut config = test_config();
+    // A target wins over the update mode, in the plan as in the run
+    config.update_mode = UpdateMode::FetchOnly;
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("app", "master")])?;
+    let app = workspace.path().join("app");
+    git::run_git(&app, &config, &["branch", "develop"])?;
+    git::run_git(&app, &config, &["push", "-u", "origin", "develop"])?;
+    push_upstream_commits(&workspace.path().join("app-remote"), "develop", 1)?;
+    git::run_git(&app, &config, &["fetch"])?;
+    git::run_git(&app, &config, &["checkout", "-b", "feature"])?;
+
+    let plan = repo::plan_update_to(&app, "develop", &config)?;
+
+    assert_eq!(plan.checkout_branch.as_deref(), Some("develop"));
+    assert_eq!(plan.remote_behind, Some(1));
+    assert_eq!(plan.restore_to.as_deref(), Some("feature"));
+    let recorder = RecordingCallbacks::new().for_repo(&app);
+    let result = repo::update_to(&app, "develop", &recorder, &config);
+    assert!(matches!(result.outcome, UpdateOutcome::Success(_)));
+    // The run opens with the target's validation, reported as CheckingOut
+    assert_eq!(recorder.work_steps(&app)[1..], plan.steps);
+    Ok(())
+}
+
+#[test]
+fn test_update_bare_mirror_only_fetches() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("app", "master")])?;
+    let remote = workspace.path().join("app-remote");
+    let mirror = workspace.path().join("app-mirror.git");
+    git::run_git(
+        workspace.path(),
+        &config,
+        &[
+            "clone",
+            "--mirror",
+            remote.to_str().unwrap(),
+            mirror.to_str().unwrap(),
+        ],
+    )?;
+    push_upstream_commits(&remote, "master", 2)?;
+    assert!(repo::is_bare_repo(&mirror));
+
+    let plan = repo::plan_update(&mirror, &config)?;
+    let recorder = RecordingCallbacks::new().for_repo(&mirror);
+    let result = repo::update(&mirror, &recorder, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(success.master_branch.as_deref(), Some("master"));
+            assert_eq!(success.commits_pulled, 2);
+            assert!(success.master_sha.changed());
+            assert!(!success.had_stash);
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
     }
+    assert_eq!(
+        recorder.work_steps(&mirror),
+        vec![UpdateStep::DetectingBranch, UpdateStep::Fetching]
+    );
+    assert_eq!(recorder.work_steps(&mirror), plan.steps);
+    assert_eq!(
+        git::run_git(&mirror, &config, &["rev-parse", "master"])?,
+        git::run_git(&remote, &config, &["rev-parse", "master"])?
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_bare_mirror_prune_remote_only_does_not_fetch() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("app", "master")])?;
+    let remote = workspace.path().join("app-remote");
+    let mirror = workspace.path().join("app-mirror.git");
+    git::run_git(
+        workspace.path(),
+        &config,
+        &[
+            "clone",
+            "--mirror",
+            remote.to_str().unwrap(),
+            mirror.to_str().unwrap(),
+        ],
+    )?;
+    let master_before = git::run_git(&mirror, &config, &["rev-parse", "master"])?;
+    push_upstream_commits(&remote, "master", 2)?;
+
+    let prune_config = Config {
+        update_mode: UpdateMode::PruneRemoteOnly,
+        ..test_config()
+    };
+    let result = repo::update(&mirror, &NoOpCallbacks, &prune_config);
+
+    let UpdateOutcome::Success(success) = &result.outcome else {
+        panic!("expected success, got {:?}", result.outcome);
+    };
+    assert_eq!(success.commits_pulled, 0);
+    assert!(!success.master_sha.changed());
+    assert_eq!(
+        git::run_git(&mirror, &config, &["rev-parse", "master"])?,
+        master_before
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_fetch_failure_on_dirty_feature_branch_leaves_repo_untouched() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    repo.create_branch("feature")?;
+    git::checkout(repo.path(), &config, "feature", logger())?;
+    repo.make_dirty()?;
+    git::run_git(
+        repo.path(),
+        &config,
+        &["remote", "set-url", "origin", "/nope"],
+    )?;
+
+    let recorder = RecordingCallbacks::new().for_repo(repo.path());
+    let result = repo::update(repo.path(), &recorder, &config);
+
+    match result.outcome {
+        UpdateOutcome::Failed(failure) => {
+            assert_eq!(failure.step, UpdateStep::Fetching);
+            assert!(failure.recovery_hint.is_none());
+        }
+        other => anyhow::bail!("expected failure, got {:?}", other),
+    }
+    // The fetch runs on the user's branch, before any stash or checkout
+    assert_eq!(
+        recorder.work_steps(repo.path()),
+        vec![
+            UpdateStep::DetectingBranch,
+            UpdateStep::CheckingChanges,
+            UpdateStep::Fetching,
+        ]
+    );
+    assert_eq!(
+        git::get_current_branch(repo.path(), &config, logger())?,
+        "feature"
+    );
+    assert!(!repo.has_stash()?);
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("README.md"))?,
+        "# Modified\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_skip_unchanged_leaves_repo_alone_until_the_remote_moves() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.skip_unchanged = true;
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    git::run_git(&repo_path, &config, &["checkout", "-b", "feature"])?;
+
+    let plan = repo::plan_update(&repo_path, &config)?;
+    let recorder = RecordingCallbacks::new().for_repo(&repo_path);
+    let result = repo::update(&repo_path, &recorder, &config);
+
+    let UpdateOutcome::Success(success) = result.outcome else {
+        anyhow::bail!("expected success, got {:?}", result.outcome);
+    };
+    assert!(success.fast_skipped);
+    assert_eq!(success.master_branch.as_deref(), Some("master"));
+    assert!(!success.master_sha.changed());
+    // No stash, checkout, pull or branch restore
+    assert_eq!(
+        recorder.work_steps(&repo_path),
+        vec![
+            UpdateStep::DetectingBranch,
+            UpdateStep::CheckingChanges,
+            UpdateStep::Fetching
+        ]
+    );
+    assert_eq!(recorder.work_steps(&repo_path), plan.steps);
+
+    push_upstream_commits(&workspace.path().join("repo-remote"), "master", 1)?;
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = result.outcome else {
+        anyhow::bail!("expected success, got {:?}", result.outcome);
+    };
+    assert!(!success.fast_skipped);
+    assert_eq!(success.commits_pulled, 1);
+    assert_eq!(
+        git::get_current_branch(&repo_path, &config, logger())?,
+        "feature"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_uses_configured_preference_when_master_and_main_both_exist() -> anyhow::Result<()> {
+    let mut config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    repo.create_branch("main")?;
+    git::run_git(repo.path(), &config, &["push", "-u", "origin", "main"])?;
+    // Without a remote default branch (origin/HEAD), only the preference decides
+    let remote_path = PathBuf::from(git::run_git(
+        repo.path(),
+        &config,
+        &["remote", "get-url", "origin"],
+    )?);
+    git::run_git(
+        &remote_path,
+        &config,
+        &["symbolic-ref", "HEAD", "refs/heads/unborn"],
+    )?;
+
+    config.branch_preference = Some(BranchPreference::Main);
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(success.master_branch.as_deref(), Some("main"));
+            assert_eq!(
+                success.warnings,
+                vec!["both 'master' and 'main' exist locally; using 'main'".to_string()]
+            );
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+
+    config.branch_preference = Some(BranchPreference::Master);
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(success.master_branch.as_deref(), Some("master"));
+            assert_eq!(success.warnings.len(), 1);
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_update_prefers_branch_tracking_remote_head_when_both_are_live() -> anyhow::Result<()> {
+    let mut config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    repo.create_branch("main")?;
+    git::run_git(repo.path(), &config, &["push", "-u", "origin", "main"])?;
+    let remote_path = PathBuf::from(git::run_git(
+        repo.path(),
+        &config,
+        &["remote", "get-url", "origin"],
+    )?);
+    git::run_git(
+        &remote_path,
+        &config,
+        &["symbolic-ref", "HEAD", "refs/heads/main"],
+    )?;
+    git::run_git(
+        repo.path(),
+        &config,
+        &["remote", "set-head", "origin", "--auto"],
+    )?;
+
+    // No preference given, and origin/HEAD points to main
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(success.master_branch.as_deref(), Some("main"));
+            assert_eq!(
+                success.warnings,
+                vec![
+                    "both 'master' and 'main' track origin; using 'main', which tracks \
+                     origin/HEAD (origin/main)"
+                        .to_string()
+                ]
+            );
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+
+    // An explicit preference wins over origin/HEAD
+    config.branch_preference = Some(BranchPreference::Master);
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(success.master_branch.as_deref(), Some("master"));
+            assert_eq!(
+                success.warnings,
+                vec!["both 'master' and 'main' exist locally; using 'master'".to_string()]
+            );
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_update_prefers_live_main_over_stale_master_after_rename() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    repo.create_branch("main")?;
+    git::run_git(repo.path(), &config, &["push", "-u", "origin", "main"])?;
+    // The old master is left behind without an upstream after the rename
+    git::run_git(
+        repo.path(),
+        &config,
+        &["branch", "--unset-upstream", "master"],
+    )?;
+
+    // Default preference is master, but the live upstream wins
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(success.master_branch.as_deref(), Some("main"));
+            assert_eq!(success.warnings.len(), 1);
+            assert!(
+                success.warnings[0].starts_with("'master' does not track a live remote branch"),
+                "{:?}",
+                success.warnings
+            );
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_update_failed_restore_reports_recovery_hint_with_original_sha() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    repo.create_branch("feature")?;
+    git::checkout(repo.path(), &config, "feature", logger())?;
+    let original_sha = git::get_current_commit(repo.path(), &config, logger())?;
+
+    // Rename the feature branch as soon as master is checked out, so restoring it fails
+    let hook = repo.path().join(".git/hooks/post-checkout");
+    std::fs::write(
+        &hook,
+        "#!/bin/sh\n\
+         if [ \"$(git rev-parse --abbrev-ref HEAD)\" = master ]; then\n\
+           git branch -m feature feature-gone\n\
+         fi\n",
+    )?;
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Failed(failure) => {
+            assert_eq!(failure.step, UpdateStep::RestoringBranch);
+            let hint = failure.recovery_hint.expect("expected a recovery hint");
+            assert!(
+                hint.starts_with("to undo: git checkout feature"),
+                "{}",
+                hint
+            );
+            assert!(hint.contains(&original_sha), "{}", hint);
+        }
+        other => anyhow::bail!("expected restore to fail, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_update_current_branch_mode_fast_forwards_checked_out_branch() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.update_mode = UpdateMode::CurrentBranch;
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    git::run_git(&repo_path, &config, &["checkout", "-b", "feature"])?;
+    git::run_git(&repo_path, &config, &["push", "-u", "origin", "feature"])?;
+    push_upstream_commits(&workspace.path().join("repo-remote"), "feature", 1)?;
+    std::fs::write(repo_path.join("README.md"), "# Local edit\n")?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(success.master_branch.as_deref(), Some("feature"));
+            assert_eq!(success.commits_pulled, 1);
+            assert!(success.had_stash);
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    assert_eq!(
+        git::get_current_branch(&repo_path, &config, logger())?,
+        "feature"
+    );
+    assert!(repo_path.join("upstream-0.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn test_update_current_branch_mode_pulls_from_the_tracked_upstream() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.update_mode = UpdateMode::CurrentBranch;
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    git::run_git(&repo_path, &config, &["push", "origin", "master:develop"])?;
+    git::run_git(&repo_path, &config, &["fetch", "origin"])?;
+    git::run_git(
+        &repo_path,
+        &config,
+        &["checkout", "-b", "feature", "--track", "origin/develop"],
+    )?;
+    push_upstream_commits(&workspace.path().join("repo-remote"), "develop", 2)?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = result.outcome else {
+        anyhow::bail!("expected success, got {:?}", result.outcome);
+    };
+    assert_eq!(success.commits_pulled, 2);
+    assert_eq!(
+        git::ahead_behind(&repo_path, &config, "feature", "origin/develop", logger())?,
+        (0, 0)
+    );
+    assert!(repo_path.join("upstream-1.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn test_update_fetch_only_refreshes_remote_refs_without_touching_the_repo() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.update_mode = UpdateMode::FetchOnly;
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    git::run_git(&repo_path, &config, &["checkout", "-b", "feature"])?;
+    let master_before = git::run_git(&repo_path, &config, &["rev-parse", "master"])?;
+    push_upstream_commits(&workspace.path().join("repo-remote"), "master", 2)?;
+    std::fs::write(repo_path.join("README.md"), "# Local edit\n")?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(success.master_branch, None);
+            assert!(!success.had_stash);
+            assert_eq!(success.commits_pulled, 0);
+            assert!(!success.head_sha.changed());
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    // The remote-tracking ref moved...
+    assert_eq!(
+        git::ahead_behind(&repo_path, &config, "master", "origin/master", logger())?,
+        (0, 2)
+    );
+    // ...but nothing local did
+    assert_eq!(
+        git::run_git(&repo_path, &config, &["rev-parse", "master"])?,
+        master_before
+    );
+    assert_eq!(
+        git::get_current_branch(&repo_path, &config, logger())?,
+        "feature"
+    );
+    assert_eq!(
+        std::fs::read_to_string(repo_path.join("README.md"))?,
+        "# Local edit\n"
+    );
+    assert!(git::run_git(&repo_path, &config, &["stash", "list"])?.is_empty());
+    Ok(())
+}
+
+/// Sets up `repo` on a pushed `feature` branch with one local commit on top
+/// of it and two new upstream commits, touching `local_file` and `upstream-*.txt`.
+fn setup_diverged_feature(workspace: &TempDir, local_file: &str) -> anyhow::Result<PathBuf> {
+    let config = test_config();
+    setup_workspace_with_repos(workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    git::run_git(&repo_path, &config, &["checkout", "-b", "feature"])?;
+    git::run_git(&repo_path, &config, &["push", "-u", "origin", "feature"])?;
+    push_upstream_commits(&workspace.path().join("repo-remote"), "feature", 2)?;
+    std::fs::write(repo_path.join(local_file), "local commit\n")?;
+    git::run_git(&repo_path, &config, &["add", local_file])?;
+    git::run_git(&repo_path, &config, &["commit", "-m", "Local work"])?;
+    Ok(repo_path)
+}
+
+#[test]
+fn test_update_current_branch_rebase_replays_local_commits_on_upstream() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.update_mode = UpdateMode::CurrentBranchRebase;
+    let workspace = TempDir::new()?;
+    let repo_path = setup_diverged_feature(&workspace, "local.txt")?;
+    std::fs::write(repo_path.join("README.md"), "# Local edit\n")?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(success.master_branch.as_deref(), Some("feature"));
+            assert_eq!(success.commits_pulled, 2);
+            assert!(success.head_sha.changed());
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    assert_eq!(
+        git::ahead_behind(&repo_path, &config, "feature", "origin/feature", logger())?,
+        (1, 0)
+    );
+    assert!(repo_path.join("upstream-1.txt").exists());
+    assert_eq!(
+        std::fs::read_to_string(repo_path.join("README.md"))?,
+        "# Local edit\n"
+    );
+    assert!(git::run_git(&repo_path, &config, &["stash", "list"])?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_update_current_branch_rebase_aborts_on_conflict() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.update_mode = UpdateMode::CurrentBranchRebase;
+    let workspace = TempDir::new()?;
+    // The upstream commits also create upstream-0.txt, so replaying the local one conflicts
+    let repo_path = setup_diverged_feature(&workspace, "upstream-0.txt")?;
+    let local_sha = git::get_current_commit(&repo_path, &config, logger())?;
+    std::fs::write(repo_path.join("README.md"), "# Local edit\n")?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Failed(failure) => {
+            assert_eq!(failure.step, UpdateStep::Pulling);
+            assert!(
+                failure.error.contains("the rebase was aborted"),
+                "{}",
+                failure.error
+            );
+        }
+        other => anyhow::bail!("expected failure, got {:?}", other),
+    }
+    assert_eq!(
+        git::get_current_branch(&repo_path, &config, logger())?,
+        "feature"
+    );
+    assert_eq!(
+        git::get_current_commit(&repo_path, &config, logger())?,
+        local_sha
+    );
+    assert_eq!(repo::operation_in_progress(&repo_path, &config)?, None);
+    assert_eq!(
+        std::fs::read_to_string(repo_path.join("README.md"))?,
+        "# Local edit\n"
+    );
+    assert!(git::run_git(&repo_path, &config, &["stash", "list"])?.is_empty());
+    Ok(())
+}
+
+/// Records the branch HEAD is on when the pull step starts.
+struct BranchAtPullCallbacks {
+    path: PathBuf,
+    branch: Mutex<Option<String>>,
+}
+
+impl UpdateCallbacks for BranchAtPullCallbacks {
+    fn on_step(&self, step: &UpdateStep) {
+        if *step == UpdateStep::Pulling {
+            let branch = git::get_current_branch(&self.path, &test_config(), logger()).ok();
+            *self.branch.lock().unwrap() = branch;
+        }
+    }
+
+    fn on_complete(&self, _result: &UpdateResult) {}
+}
+
+/// Points `origin` somewhere unreachable once the fetch is done, so any later
+/// step that talks to the remote fails.
+struct BreakRemoteAfterFetch {
+    path: PathBuf,
+}
+
+impl UpdateCallbacks for BreakRemoteAfterFetch {
+    fn on_step(&self, step: &UpdateStep) {
+        if *step == UpdateStep::CheckingOut {
+            git::run_git(
+                &self.path,
+                &test_config(),
+                &["remote", "set-url", "origin", "/nope"],
+            )
+            .unwrap();
+        }
+    }
+
+    fn on_complete(&self, _result: &UpdateResult) {}
+}
+
+#[test]
+fn test_update_fast_forwards_to_the_fetched_branch_without_a_second_fetch() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    git::run_git(&repo_path, &config, &["checkout", "-b", "feature"])?;
+    push_upstream_commits(&workspace.path().join("repo-remote"), "master", 2)?;
+
+    let callbacks = BreakRemoteAfterFetch {
+        path: repo_path.clone(),
+    };
+    let result = repo::update(&repo_path, &callbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => assert_eq!(success.commits_pulled, 2),
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    assert_eq!(
+        git::run_git(&repo_path, &config, &["rev-parse", "master"])?,
+        git::run_git(&repo_path, &config, &["rev-parse", "origin/master"])?
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_creates_local_branch_when_default_exists_only_on_remote() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "main")])?;
+    let repo_path = workspace.path().join("repo");
+    git::run_git(&repo_path, &config, &["checkout", "-b", "dev"])?;
+    git::run_git(&repo_path, &config, &["branch", "-D", "main"])?;
+    assert!(!git::local_branch_exists(
+        &repo_path,
+        &config,
+        "main",
+        logger()
+    )?);
+
+    let callbacks = BranchAtPullCallbacks {
+        path: repo_path.clone(),
+        branch: Mutex::new(None),
+    };
+    let result = repo::update(&repo_path, &callbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(success.master_branch.as_deref(), Some("main"))
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    // HEAD was on the real local branch (not a detached origin/main) while pulling
+    assert_eq!(callbacks.branch.lock().unwrap().as_deref(), Some("main"));
+    assert_eq!(
+        git::run_git(
+            &repo_path,
+            &config,
+            &["rev-parse", "--abbrev-ref", "main@{upstream}"]
+        )?,
+        "origin/main"
+    );
+    assert_eq!(
+        git::get_current_branch(&repo_path, &config, logger())?,
+        "dev"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_no_stash_leaves_dirty_repo_untouched() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.no_stash = true;
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    git::run_git(&repo_path, &config, &["checkout", "-b", "feature"])?;
+    push_upstream_commits(&workspace.path().join("repo-remote"), "master", 1)?;
+    std::fs::write(repo_path.join("README.md"), "# Work in progress\n")?;
+
+    let status_before = git::run_git(&repo_path, &config, &["status", "--porcelain"])?;
+    let remote_before = git::run_git(&repo_path, &config, &["rev-parse", "origin/master"])?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Skipped(skipped) => {
+            assert_eq!(skipped.step, UpdateStep::CheckingChanges);
+            assert!(skipped.reason.contains("refusing to stash"));
+        }
+        other => anyhow::bail!("expected skip, got {:?}", other),
+    }
+    assert_eq!(
+        git::get_current_branch(&repo_path, &config, logger())?,
+        "feature"
+    );
+    assert_eq!(
+        git::run_git(&repo_path, &config, &["status", "--porcelain"])?,
+        status_before
+    );
+    assert_eq!(git::run_git(&repo_path, &config, &["stash", "list"])?, "");
+    // Not even fetched
+    assert_eq!(
+        git::run_git(&repo_path, &config, &["rev-parse", "origin/master"])?,
+        remote_before
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_fails_default_branch_with_local_commits_by_default() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    std::fs::write(repo_path.join("local.txt"), "local\n")?;
+    git::run_git(&repo_path, &config, &["add", "local.txt"])?;
+    git::run_git(&repo_path, &config, &["commit", "-m", "Local on master"])?;
+    push_upstream_commits(&workspace.path().join("repo-remote"), "master", 1)?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Failed(failure) = result.outcome else {
+        anyhow::bail!("expected failure, got {:?}", result.outcome);
+    };
+    assert_eq!(failure.step, UpdateStep::Pulling);
+    assert!(
+        failure.error.contains("cannot be fast-forwarded"),
+        "unexpected error: {}",
+        failure.error
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_keep_diverged_default_leaves_local_commits_as_is() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.keep_diverged_default = true;
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    std::fs::write(repo_path.join("local.txt"), "local\n")?;
+    git::run_git(&repo_path, &config, &["add", "local.txt"])?;
+    git::run_git(&repo_path, &config, &["commit", "-m", "Local on master"])?;
+    let local_master = git::get_current_commit(&repo_path, &config, logger())?;
+    git::run_git(&repo_path, &config, &["checkout", "-b", "feature"])?;
+    std::fs::write(repo_path.join("README.md"), "# Work in progress\n")?;
+    push_upstream_commits(&workspace.path().join("repo-remote"), "master", 1)?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = result.outcome else {
+        anyhow::bail!("expected success, got {:?}", result.outcome);
+    };
+    assert_eq!(success.commits_pulled, 0);
+    assert_eq!(
+        success.warnings,
+        vec![
+            "master has 1 local commit not on origin/master; left as-is instead of fast-forwarding"
+        ]
+    );
+    assert_eq!(
+        git::run_git(&repo_path, &config, &["rev-parse", "master"])?,
+        local_master
+    );
+    assert_eq!(
+        git::get_current_branch(&repo_path, &config, logger())?,
+        "feature"
+    );
+    assert_eq!(
+        std::fs::read_to_string(repo_path.join("README.md"))?,
+        "# Work in progress\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_verify_signatures_fails_on_unsigned_tip() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.verify_signatures = true;
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    let master_before = git::get_current_commit(&repo_path, &config, logger())?;
+    push_upstream_commits(&workspace.path().join("repo-remote"), "master", 1)?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Failed(failure) => {
+            assert_eq!(failure.step, UpdateStep::Pulling);
+            assert!(
+                failure
+                    .error
+                    .contains("Refusing to fast-forward 'master' to unverified 'origin/master'")
+                    && failure
+                        .error
+                        .contains("is not validly signed: no signature"),
+                "{}",
+                failure.error
+            );
+        }
+        other => anyhow::bail!("expected failure, got {:?}", other),
+    }
+    assert_eq!(
+        git::run_git(&repo_path, &config, &["rev-parse", "master"])?,
+        master_before
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_update_verify_signatures_fast_forwards_only_to_the_verified_tip() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    let remote_path = workspace.path().join("repo-remote");
+    push_upstream_commits(&remote_path, "master", 1)?;
+    let verified_tip = git::run_git(&remote_path, &test_config(), &["rev-parse", "master"])?;
+
+    // A clone holding one more commit, pushed while the signature is checked
+    let late_dir = TempDir::new()?;
+    let late_clone = late_dir.path().join("late");
+    let late_config = test_config();
+    git::run_git(
+        late_dir.path(),
+        &late_config,
+        &["clone", remote_path.to_str().unwrap(), "late"],
+    )?;
+    git::run_git(
+        &late_clone,
+        &late_config,
+        &["config", "user.email", "late@example.com"],
+    )?;
+    git::run_git(
+        &late_clone,
+        &late_config,
+        &["config", "user.name", "Late Pusher"],
+    )?;
+    std::fs::write(late_clone.join("late.txt"), "unverified\n")?;
+    git::run_git(&late_clone, &late_config, &["add", "late.txt"])?;
+    git::run_git(
+        &late_clone,
+        &late_config,
+        &["commit", "-m", "Unverified change"],
+    )?;
+
+    // Pretend every signature is valid, moving the remote as it is checked
+    let bin_dir = TempDir::new()?;
+    let wrapper = bin_dir.path().join("git-wrapper");
+    std::fs::write(
+        &wrapper,
+        format!(
+            "#!/bin/sh\ncase \" $* \" in\n  *\" verify-commit \"*) git -C '{}' push -q origin master || exit 2; exit 0 ;;\nesac\nexec git \"$@\"\n",
+            late_clone.display()
+        ),
+    )?;
+    std::fs::set_permissions(&wrapper, std::fs::Permissions::from_mode(0o755))?;
+    let config = Config {
+        verify_signatures: true,
+        git_binary: Some(wrapper),
+        ..test_config()
+    };
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = &result.outcome else {
+        panic!("expected success, got {:?}", result.outcome);
+    };
+    assert_eq!(success.commits_pulled, 1);
+    assert_eq!(
+        git::run_git(&repo_path, &config, &["rev-parse", "master"])?,
+        verified_tip
+    );
+    assert!(!repo_path.join("late.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn test_update_fail_on_dirty_fails_dirty_repo_without_stashing() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.fail_on_dirty = true;
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    git::run_git(&repo_path, &config, &["checkout", "-b", "feature"])?;
+    std::fs::write(repo_path.join("README.md"), "# Work in progress\n")?;
+    let status_before = git::run_git(&repo_path, &config, &["status", "--porcelain"])?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Failed(failure) => {
+            assert_eq!(failure.step, UpdateStep::CheckingChanges);
+            assert!(
+                failure
+                    .error
+                    .contains("repository is dirty: 1 uncommitted file"),
+                "{}",
+                failure.error
+            );
+        }
+        other => anyhow::bail!("expected failure, got {:?}", other),
+    }
+    assert_eq!(
+        git::get_current_branch(&repo_path, &config, logger())?,
+        "feature"
+    );
+    assert_eq!(
+        git::run_git(&repo_path, &config, &["status", "--porcelain"])?,
+        status_before
+    );
+    assert_eq!(git::run_git(&repo_path, &config, &["stash", "list"])?, "");
+
+    // A clean repo is updated as usual
+    git::run_git(&repo_path, &config, &["checkout", "--", "README.md"])?;
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+    assert!(matches!(result.outcome, UpdateOutcome::Success(_)));
+    Ok(())
+}
+
+#[test]
+fn test_update_no_stash_still_updates_clean_repo() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.no_stash = true;
+    let repo = TestRepo::with_remote(None)?;
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    assert!(matches!(result.outcome, UpdateOutcome::Success(_)));
+    Ok(())
+}
+
+/// Repo on `feature` with a modified tracked file and an untracked `notes.txt`
+/// that `master` tracks, so checking out master would overwrite it.
+fn repo_with_untracked_conflict() -> anyhow::Result<TestRepo> {
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    repo.create_branch("feature")?;
+    std::fs::write(repo.path().join("notes.txt"), "shared notes\n")?;
+    git::run_git(repo.path(), &config, &["add", "notes.txt"])?;
+    git::run_git(repo.path(), &config, &["commit", "-m", "Add notes"])?;
+    git::run_git(repo.path(), &config, &["push", "origin", "master"])?;
+    git::checkout(repo.path(), &config, "feature", logger())?;
+    std::fs::write(repo.path().join("notes.txt"), "my local notes\n")?;
+    repo.make_dirty()?;
+    Ok(repo)
+}
+
+fn assert_working_tree_preserved(repo: &TestRepo) -> anyhow::Result<()> {
+    let config = test_config();
+    assert_eq!(
+        git::get_current_branch(repo.path(), &config, logger())?,
+        "feature"
+    );
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("notes.txt"))?,
+        "my local notes\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("README.md"))?,
+        "# Modified\n"
+    );
+    assert!(!repo.has_stash()?);
+    Ok(())
+}
+
+#[test]
+fn test_update_untracked_conflict_fail_names_files() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = repo_with_untracked_conflict()?;
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Failed(failure) => {
+            assert_eq!(failure.step, UpdateStep::CheckingOut);
+            assert!(
+                failure
+                    .error
+                    .contains("untracked files would be overwritten")
+                    && failure.error.contains("notes.txt"),
+                "{}",
+                failure.error
+            );
+        }
+        other => anyhow::bail!("expected failure, got {:?}", other),
+    }
+    // The stash is left for the user, as with any other mid-update failure
+    assert_eq!(
+        git::get_current_branch(repo.path(), &config, logger())?,
+        "feature"
+    );
+    assert!(repo.has_stash()?);
+    Ok(())
+}
+
+#[test]
+fn test_update_untracked_conflict_skip_restores_repo() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.untracked_conflict = UntrackedConflictPolicy::Skip;
+    let repo = repo_with_untracked_conflict()?;
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Skipped(skipped) => {
+            assert_eq!(skipped.step, UpdateStep::CheckingOut);
+            assert!(skipped.reason.contains("notes.txt"), "{}", skipped.reason);
+        }
+        other => anyhow::bail!("expected skip, got {:?}", other),
+    }
+    assert_working_tree_preserved(&repo)
+}
+
+#[test]
+fn test_update_untracked_conflict_stash_untracked_succeeds() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.untracked_conflict = UntrackedConflictPolicy::StashUntracked;
+    let repo = repo_with_untracked_conflict()?;
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(success.master_branch.as_deref(), Some("master"));
+            assert!(success.had_stash);
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    assert_working_tree_preserved(&repo)
+}
+
+#[test]
+fn test_update_uses_configured_remote_and_default_branches() -> anyhow::Result<()> {
+    let mut config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    git::run_git(
+        repo.path(),
+        &config,
+        &["remote", "rename", "origin", "upstream"],
+    )?;
+    repo.create_branch("develop")?;
+    git::run_git(repo.path(), &config, &["push", "-u", "upstream", "develop"])?;
+    repo.create_branch("feature")?;
+    git::checkout(repo.path(), &config, "feature", logger())?;
+
+    config.remote = Some("upstream".to_string());
+    config.default_branches = Some(vec!["develop".to_string(), "main".to_string()]);
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert_eq!(success.master_branch.as_deref(), Some("develop"));
+            assert!(success.warnings.is_empty());
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    assert_eq!(
+        git::get_current_branch(repo.path(), &config, logger())?,
+        "feature"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_failure_records_git_exit_code() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    git::run_git(
+        repo.path(),
+        &config,
+        &["remote", "set-url", "origin", "/nope"],
+    )?;
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Failed(failure) => {
+            assert_eq!(failure.step, UpdateStep::Fetching);
+            assert_eq!(failure.git_exit_code, Some(128));
+        }
+        other => anyhow::bail!("expected failure, got {:?}", other),
+    }
+    Ok(())
+}
+
+/// Records the remote each update was configured with.
+#[derive(Default)]
+struct RemoteRecorder {
+    remotes: Mutex<Vec<String>>,
+}
+
+impl UpdateCallbacks for RemoteRecorder {
+    fn on_begin(&self, config: &Config) {
+        self.remotes
+            .lock()
+            .unwrap()
+            .push(config.remote().to_string());
+    }
+
+    fn on_step(&self, _step: &UpdateStep) {}
+
+    fn on_complete(&self, _result: &UpdateResult) {}
+}
+
+#[test]
+fn test_callbacks_observe_configured_remote_on_begin() -> anyhow::Result<()> {
+    let mut config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    git::run_git(
+        repo.path(),
+        &config,
+        &["remote", "rename", "origin", "upstream"],
+    )?;
+    config.remote = Some("upstream".to_string());
+
+    let recorder = RemoteRecorder::default();
+    let result = repo::update(repo.path(), &recorder, &config);
+
+    assert!(matches!(result.outcome, UpdateOutcome::Success(_)));
+    assert_eq!(*recorder.remotes.lock().unwrap(), vec!["upstream"]);
+    Ok(())
+}
+
+#[test]
+fn test_update_refuses_to_stash_on_top_of_stale_git_daily_stashes() -> anyhow::Result<()> {
+    let mut config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    for content in ["# Crashed run 1\n", "# Crashed run 2\n"] {
+        std::fs::write(repo.path().join("README.md"), content)?;
+        git::run_git(
+            repo.path(),
+            &config,
+            &["stash", "push", "--message", GIT_DAILY_STASH_MESSAGE],
+        )?;
+    }
+    // Reported before any update needs to stash
+    let health = repo::health_scan(&[repo.path().to_path_buf()], &config);
+    assert_eq!(health.stale_stashes.len(), 1);
+    assert_eq!(
+        health.stale_stashes[0].1.selectors,
+        vec!["stash@{0}", "stash@{1}"]
+    );
+    repo.make_dirty()?;
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Failed(failure) => {
+            assert_eq!(failure.step, UpdateStep::Stashing);
+            assert!(
+                failure.error.contains("--drop-stale-stashes"),
+                "{}",
+                failure.error
+            );
+        }
+        other => anyhow::bail!("expected failure, got {:?}", other),
+    }
+    assert_eq!(git::stash_list(repo.path(), &config, logger())?.len(), 2);
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("README.md"))?,
+        "# Modified\n"
+    );
+
+    config.drop_stale_stashes = true;
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Success(success) => {
+            assert!(success.had_stash);
+            assert!(
+                success.warnings[0].starts_with("dropped 1 stale git-daily stash "),
+                "{:?}",
+                success.warnings
+            );
+        }
+        other => anyhow::bail!("expected success, got {:?}", other),
+    }
+    // The newest leftover, the likeliest to hold lost work, is kept
+    let stashes = git::stash_list(repo.path(), &config, logger())?;
+    assert_eq!(stashes.len(), 1);
+    assert_eq!(
+        git::run_git(repo.path(), &config, &["show", "stash@{0}:README.md"])?,
+        "# Crashed run 2"
+    );
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("README.md"))?,
+        "# Modified\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_skips_repo_with_bisect_in_progress() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    repo.create_branch("feature")?;
+    git::checkout(repo.path(), &config, "feature", logger())?;
+    git::run_git(repo.path(), &config, &["bisect", "start"])?;
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Skipped(skipped) => {
+            assert_eq!(skipped.step, UpdateStep::CheckingChanges);
+            assert!(
+                skipped.reason.contains("bisect in progress"),
+                "{}",
+                skipped.reason
+            );
+        }
+        other => anyhow::bail!("expected skip, got {:?}", other),
+    }
+    // The bisect session is left untouched
+    assert_eq!(
+        git::get_current_branch(repo.path(), &config, logger())?,
+        "feature"
+    );
+    assert_eq!(
+        repo::operation_in_progress(repo.path(), &config)?,
+        Some(repo::InProgressOperation::Bisect)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_skips_repo_with_cherry_pick_conflict() -> anyhow::Result<()> {
+    let config = test_config();
+    let repo = TestRepo::with_remote(None)?;
+    git::run_git(repo.path(), &config, &["checkout", "-b", "other"])?;
+    std::fs::write(repo.path().join("README.md"), "# Other\n")?;
+    git::run_git(repo.path(), &config, &["commit", "-am", "Other change"])?;
+    git::run_git(
+        repo.path(),
+        &config,
+        &["checkout", "-b", "feature", "master"],
+    )?;
+    std::fs::write(repo.path().join("README.md"), "# Feature\n")?;
+    git::run_git(repo.path(), &config, &["commit", "-am", "Feature change"])?;
+    assert!(git::run_git(repo.path(), &config, &["cherry-pick", "other"]).is_err());
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Skipped(skipped) => {
+            assert!(
+                skipped.reason.contains("cherry-pick in progress"),
+                "{}",
+                skipped.reason
+            );
+        }
+        other => anyhow::bail!("expected skip, got {:?}", other),
+    }
+    assert!(!repo.has_stash()?);
+    Ok(())
+}
+
+#[test]
+fn test_update_records_head_and_master_commits_before_and_after() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "main")])?;
+    let repo_path = workspace.path().join("repo");
+    git::run_git(&repo_path, &config, &["checkout", "-b", "feature"])?;
+    let feature_sha = git::get_current_commit(&repo_path, &config, logger())?;
+    push_upstream_commits(&workspace.path().join("repo-remote"), "main", 2)?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = result.outcome else {
+        anyhow::bail!("expected success, got {:?}", result.outcome);
+    };
+    // The user's branch is untouched
+    assert_eq!(success.head_sha.before, feature_sha);
+    assert_eq!(success.head_sha.after, feature_sha);
+    assert!(!success.head_sha.changed());
+    // Local main advanced to the remote tip
+    let remote_main = git::run_git(&repo_path, &config, &["rev-parse", "origin/main"])?;
+    assert_eq!(success.master_sha.before, feature_sha);
+    assert_eq!(success.master_sha.after, remote_main);
+    assert_eq!(success.commits_pulled, 2);
+    Ok(())
+}
+
+#[test]
+fn test_update_warns_when_original_branch_is_behind_its_upstream() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    git::run_git(&repo_path, &config, &["checkout", "-b", "feature"])?;
+    git::run_git(&repo_path, &config, &["push", "-u", "origin", "feature"])?;
+    push_upstream_commits(&workspace.path().join("repo-remote"), "feature", 3)?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = result.outcome else {
+        anyhow::bail!("expected success, got {:?}", result.outcome);
+    };
+    assert_eq!(
+        success.warnings,
+        vec!["feature is 3 behind origin/feature — consider pulling"]
+    );
+    // The feature branch itself is left for the user to pull
+    assert_eq!(
+        git::ahead_behind(&repo_path, &config, "feature", "origin/feature", logger())?,
+        (0, 3)
+    );
+
+    git::run_git(&repo_path, &config, &["pull", "--ff-only"])?;
+    let caught_up = repo::update(&repo_path, &NoOpCallbacks, &config);
+    let UpdateOutcome::Success(caught_up) = caught_up.outcome else {
+        anyhow::bail!("expected success, got {:?}", caught_up.outcome);
+    };
+    assert!(caught_up.warnings.is_empty(), "{:?}", caught_up.warnings);
+    Ok(())
+}
+
+#[test]
+fn test_update_reports_each_file_a_stash_pop_conflicted_in() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo", "master")])?;
+    let repo_path = workspace.path().join("repo");
+    std::fs::write(repo_path.join("notes.txt"), "original\n")?;
+    git::run_git(&repo_path, &config, &["add", "notes.txt"])?;
+    git::run_git(&repo_path, &config, &["commit", "-m", "Add notes"])?;
+    git::run_git(&repo_path, &config, &["push"])?;
+
+    // Someone else changes both files upstream
+    let clone_dir = TempDir::new()?;
+    let remote = workspace.path().join("repo-remote");
+    git::run_git(
+        clone_dir.path(),
+        &config,
+        &["clone", remote.to_str().unwrap(), "clone"],
+    )?;
+    let clone = clone_dir.path().join("clone");
+    std::fs::write(clone.join("README.md"), "# Upstream\n")?;
+    std::fs::write(clone.join("notes.txt"), "upstream\n")?;
+    git::run_git(
+        &clone,
+        &config,
+        &[
+            "-c",
+            "user.name=Upstream",
+            "-c",
+            "user.email=upstream@example.com",
+            "commit",
+            "-am",
+            "Upstream edits",
+        ],
+    )?;
+    git::run_git(&clone, &config, &["push"])?;
+
+    // ...while the same files have local edits
+    std::fs::write(repo_path.join("README.md"), "# Local\n")?;
+    std::fs::write(repo_path.join("notes.txt"), "local\n")?;
+
+    let result = repo::update(&repo_path, &NoOpCallbacks, &config);
+
+    match result.outcome {
+        UpdateOutcome::Failed(failure) => {
+            assert_eq!(failure.step, UpdateStep::PoppingStash);
+            assert!(
+                failure
+                    .error
+                    .contains("stash pop conflicted in README.md, notes.txt"),
+                "{}",
+                failure.error
+            );
+        }
+        other => anyhow::bail!("expected failure, got {:?}", other),
+    }
+    assert_eq!(
+        git::unmerged_paths(&repo_path, &config, logger())?,
+        vec!["README.md", "notes.txt"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_prune_worktrees_removes_dangling_worktree_entry() -> anyhow::Result<()> {
+    let repo = TestRepo::with_remote(None)?;
+    let scratch = TempDir::new()?;
+    let worktree = scratch.path().join("hotfix");
+    git::run_git(
+        repo.path(),
+        &test_config(),
+        &[
+            "worktree",
+            "add",
+            "-b",
+            "hotfix",
+            worktree.to_str().unwrap(),
+        ],
+    )?;
+    std::fs::remove_dir_all(&worktree)?;
+    let admin_entry = repo.path().join(".git").join("worktrees").join("hotfix");
+    assert!(admin_entry.exists());
+
+    let config = Config {
+        prune_worktrees: true,
+        ..test_config()
+    };
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = &result.outcome else {
+        panic!("expected success, got {:?}", result.outcome);
+    };
+    assert!(success.warnings.is_empty(), "{:?}", success.warnings);
+    assert!(!admin_entry.exists());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_update_already_up_to_date_counts_zero_from_shas_not_merge_output() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let repo = TestRepo::with_remote(None)?;
+    let bin_dir = TempDir::new()?;
+    let calls = bin_dir.path().join("calls.log");
+    let wrapper = bin_dir.path().join("git-wrapper");
+    // Replace the fast-forward's output with a translated message nothing should parse
+    std::fs::write(
+        &wrapper,
+        format!(
+            "#!/bin/sh\necho \"$@\" >> '{}'\ncase \" $* \" in\n  *\" merge \"*) git \"$@\" >/dev/null; status=$?; echo 'Bereits aktuell.'; exit $status ;;\nesac\nexec git \"$@\"\n",
+            calls.display()
+        ),
+    )?;
+    std::fs::set_permissions(&wrapper, std::fs::Permissions::from_mode(0o755))?;
+    let config = Config {
+        git_binary: Some(wrapper),
+        ..test_config()
+    };
+
+    let result = repo::update(repo.path(), &NoOpCallbacks, &config);
+
+    let UpdateOutcome::Success(success) = &result.outcome else {
+        panic!("expected success, got {:?}", result.outcome);
+    };
+    assert_eq!(success.commits_pulled, 0);
+    assert!(!success.master_sha.changed());
+    let calls = std::fs::read_to_string(&calls)?;
+    assert!(
+        calls.lines().any(|line| line.contains("merge --ff-only")),
+        "{}",
+        calls
+    );
+    // An unmoved HEAD needs no rev-list to know nothing was pulled
+    assert!(
+        !calls.lines().any(|line| line.contains("rev-list")),
+        "{}",
+        calls
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_prune_remote_only_drops_stale_tracking_refs_without_fetching() -> anyhow::Result<()>
+{
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("app", "master")])?;
+    let remote = workspace.path().join("app-remote");
+    let app = workspace.path().join("app");
+    git::run_git(&remote, &config, &["branch", "merged-fix", "master"])?;
+    git::run_git(&remote, &config, &["branch", "keep", "master"])?;
+    git::fetch_prune(&app, &config, logger())?;
+    git::run_git(&remote, &config, &["branch", "-D", "merged-fix"])?;
+    let origin_master_before = git::run_git(&app, &config, &["rev-parse", "origin/master"])?;
+    push_upstream_commits(&remote, "master", 1)?;
+
+    let prune_config = Config {
+        update_mode: UpdateMode::PruneRemoteOnly,
+        ..test_config()
+    };
+    let result = repo::update(&app, &NoOpCallbacks, &prune_config);
+
+    let UpdateOutcome::Success(success) = &result.outcome else {
+        panic!("expected success, got {:?}", result.outcome);
+    };
+    assert_eq!(success.pruned_remote_branches, vec!["merged-fix"]);
+    assert!(success.new_remote_branches.is_empty());
+    assert_eq!(success.commits_pulled, 0);
+    assert_eq!(
+        git::remote_branches(&app, &config, "origin", logger())?,
+        vec!["keep", "master"]
+    );
+    // Nothing new was fetched
+    assert_eq!(
+        git::run_git(&app, &config, &["rev-parse", "origin/master"])?,
+        origin_master_before
+    );
     Ok(())
 }
@@ -1,13 +1,19 @@
 mod common;
 
-use common::{CountingCallbacks, init_repo, setup_workspace_with_repos, test_config};
+use common::{
+    CountingCallbacks, RecordingCallbacks, init_repo, push_upstream_commits,
+    setup_workspace_with_repos, test_config,
+};
 use git_daily_rust::config::Verbosity;
 use git_daily_rust::git;
-use git_daily_rust::output::NoOpCallbacks;
-use git_daily_rust::repo::{self, UpdateCallbacks, UpdateOutcome, UpdateStep};
-use std::collections::HashSet;
-use std::sync::Arc;
+use git_daily_rust::manifest::Manifest;
+use git_daily_rust::output::{self, NdjsonCallbacks, NoOpCallbacks};
+use git_daily_rust::repo::{
+    self, HealthBucket, NotARepoReason, UpdateCallbacks, UpdateOutcome, UpdateStep, WorkspaceStats,
+};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tempfile::TempDir;
 
 #[test]
@@ -23,6 +29,35 @@ fn test_update_workspace_updates_multiple_repos() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_update_workspace_restores_detached_repo_to_its_commit() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo-a", "master"), ("repo-b", "master")])?;
+
+    let detached_path = workspace.path().join("repo-b");
+    let commit = git::get_current_commit(&detached_path, &config, git::no_op_logger)?;
+    git::run_git(&detached_path, &config, &["checkout", "--detach", "HEAD"])?;
+
+    let repos = repo::find_git_repos(workspace.path());
+    let results = repo::update_workspace(&repos, |_| NoOpCallbacks, &config);
+
+    assert!(
+        results
+            .iter()
+            .all(|r| matches!(r.outcome, UpdateOutcome::Success(_)))
+    );
+    assert_eq!(
+        git::get_current_branch(&detached_path, &config, git::no_op_logger)?,
+        "HEAD"
+    );
+    assert_eq!(
+        git::get_current_commit(&detached_path, &config, git::no_op_logger)?,
+        commit
+    );
+    Ok(())
+}
+
 #[test]
 fn test_workspace_mixed_success_and_failure() -> anyhow::Result<()> {
     let config = test_config();
@@ -142,20 +177,14 @@ fn test_workspace_order_independence() -> anyhow::Result<()> {
         &[("alpha", "master"), ("beta", "master"), ("gamma", "master")],
     )?;
 
-    let repos = repo::find_git_repos(workspace.path());
-    let expected_names: HashSet<&str> = ["alpha", "beta", "gamma"].into_iter().collect();
+    let mut repos = repo::find_git_repos(workspace.path());
+    // Results follow the input order, not completion or sorted order
+    repos.reverse();
 
     for _ in 0..3 {
         let results = repo::update_workspace(&repos, |_| NoOpCallbacks, &config);
-        assert_eq!(results.len(), 3);
-
-        let result_names: HashSet<&str> = results
-            .iter()
-            .filter_map(|r| r.path.file_name())
-            .filter_map(|n| n.to_str())
-            .collect();
-
-        assert_eq!(result_names, expected_names);
+        let result_paths: Vec<_> = results.iter().map(|r| r.path.clone()).collect();
+        assert_eq!(result_paths, repos);
     }
     Ok(())
 }
@@ -179,6 +208,7 @@ fn test_workspace_with_untracked_files_only() -> anyhow::Result<()> {
             assert!(!s.had_stash);
         }
         UpdateOutcome::Failed(f) => panic!("Expected success, got failure: {}", f.error),
+        UpdateOutcome::Skipped(s) => panic!("Expected success, got skip: {}", s.reason),
     }
 
     assert!(repo_path.join("untracked.txt").exists());
@@ -207,6 +237,7 @@ fn test_workspace_ignores_non_git_subdirs() -> anyhow::Result<()> {
 #[derive(Clone)]
 struct ConcurrencyCallbacks {
     active: Arc<AtomicUsize>,
+    max_active: Arc<AtomicUsize>,
     saw_concurrent: Arc<AtomicBool>,
 }
 
@@ -219,6 +250,7 @@ impl UpdateCallbacks for ConcurrencyCallbacks {
 
     fn on_update_start(&self, _repo_name: &str) {
         let previous = self.active.fetch_add(1, Ordering::SeqCst);
+        self.max_active.fetch_max(previous + 1, Ordering::SeqCst);
         if previous > 0 {
             self.saw_concurrent.store(true, Ordering::SeqCst);
         }
@@ -226,6 +258,262 @@ impl UpdateCallbacks for ConcurrencyCallbacks {
     }
 }
 
+/// Records when each repository's update started.
+#[derive(Clone)]
+struct StartTimeCallbacks {
+    starts: Arc<Mutex<Vec<std::time::Instant>>>,
+}
+
+impl UpdateCallbacks for StartTimeCallbacks {
+    fn on_step(&self, _step: &UpdateStep) {}
+
+    fn on_complete(&self, _result: &repo::UpdateResult) {}
+
+    fn on_update_start(&self, _repo_name: &str) {
+        self.starts.lock().unwrap().push(std::time::Instant::now());
+    }
+}
+
+/// Updates four repos and returns the time between the first and last start.
+fn update_start_spread(config: &git_daily_rust::config::Config) -> anyhow::Result<Duration> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(
+        &workspace,
+        &[
+            ("repo-a", "master"),
+            ("repo-b", "master"),
+            ("repo-c", "master"),
+            ("repo-d", "master"),
+        ],
+    )?;
+    let callbacks = StartTimeCallbacks {
+        starts: Arc::new(Mutex::new(Vec::new())),
+    };
+
+    let repos = repo::find_git_repos(workspace.path());
+    let results = repo::update_workspace(&repos, |_| callbacks.clone(), config);
+    assert_eq!(results.len(), 4);
+
+    let starts = callbacks.starts.lock().unwrap();
+    let first = starts.iter().min().unwrap();
+    let last = starts.iter().max().unwrap();
+    Ok(*last - *first)
+}
+
+#[test]
+fn test_workspace_ramp_staggers_update_starts() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.jobs = Some(4);
+    config.ramp = Some(1);
+
+    // One start per 100ms: the fourth begins about 300ms after the first
+    assert!(update_start_spread(&config)? >= Duration::from_millis(250));
+    Ok(())
+}
+
+#[test]
+fn test_run_workspace_reports_updated_skipped_and_filtered_out() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.skip_dirty_over = Some(0);
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("clean", "master"), ("dirty", "main")])?;
+    std::fs::write(workspace.path().join("dirty").join("README.md"), "# WIP\n")?;
+    std::fs::create_dir(workspace.path().join("notes"))?;
+
+    let outcome = repo::run_workspace(workspace.path(), |_| NoOpCallbacks, &config)?;
+
+    assert_eq!(outcome.results.len(), 2);
+    let updated: Vec<_> = outcome
+        .updated()
+        .map(|result| result.path.clone())
+        .collect();
+    assert_eq!(updated, vec![workspace.path().join("clean")]);
+    let skipped: Vec<_> = outcome.skipped().collect();
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].0, workspace.path().join("dirty"));
+    assert!(skipped[0].1.reason.contains("exceed the limit of 0"));
+    let filtered_out: Vec<_> = outcome
+        .filtered_out
+        .iter()
+        .map(|(path, _)| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    // The bare remotes created next to each repo are rejected too
+    assert!(
+        filtered_out.contains(&"notes".to_string()),
+        "{:?}",
+        filtered_out
+    );
+    assert!(outcome.duration > std::time::Duration::ZERO);
+    Ok(())
+}
+
+#[test]
+fn test_run_workspace_reports_broken_worktree_links_as_skipped() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("clean", "master")])?;
+    let moved = workspace.path().join("moved");
+    std::fs::create_dir(&moved)?;
+    std::fs::write(moved.join(".git"), "gitdir: ../gone/.git/worktrees/moved\n")?;
+
+    let outcome = repo::run_workspace(workspace.path(), |_| NoOpCallbacks, &config)?;
+
+    assert_eq!(outcome.results.len(), 2);
+    let skipped: Vec<_> = outcome.skipped().collect();
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].0, moved);
+    assert!(
+        skipped[0].1.reason.contains("broken worktree link"),
+        "{}",
+        skipped[0].1.reason
+    );
+    assert!(skipped[0].1.reason.contains("git worktree repair"));
+    assert!(!outcome.filtered_out.iter().any(|(path, _)| *path == moved));
+    Ok(())
+}
+
+#[test]
+fn test_health_scan_reports_default_branch_age_and_flags_stale_repos() -> anyhow::Result<()> {
+    let mut config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("fresh", "master"), ("old", "main")])?;
+    let ws = workspace.path();
+    config.env.insert(
+        "GIT_COMMITTER_DATE".to_string(),
+        "@1500000000 +0000".to_string(),
+    );
+    git::run_git(
+        &ws.join("old"),
+        &config,
+        &["commit", "--allow-empty", "-m", "Long ago"],
+    )?;
+    config.env.clear();
+
+    let repos = repo::find_git_repos(ws);
+    let report = repo::health_scan(&repos, &config);
+
+    let fresh = report.default_branch_age(&ws.join("fresh")).unwrap();
+    assert_eq!(fresh.branch, "master");
+    assert!(fresh.age < Duration::from_secs(3600));
+    let old = report.default_branch_age(&ws.join("old")).unwrap();
+    assert_eq!(old.branch, "main");
+    let stale: Vec<_> = report
+        .stale(config.stale_after())
+        .into_iter()
+        .map(|(path, _)| path.to_path_buf())
+        .collect();
+    assert_eq!(stale, vec![ws.join("old")]);
+    Ok(())
+}
+
+#[test]
+fn test_health_scan_buckets_repos_by_state() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(
+        &workspace,
+        &[
+            ("current", "master"),
+            ("behind", "master"),
+            ("dirty", "main"),
+            ("detached", "master"),
+            ("diverged", "master"),
+        ],
+    )?;
+    let ws = workspace.path();
+    push_upstream_commits(&ws.join("behind-remote"), "master", 2)?;
+    git::run_git(&ws.join("behind"), &config, &["fetch"])?;
+    std::fs::write(ws.join("dirty").join("README.md"), "# WIP\n")?;
+    git::run_git(&ws.join("detached"), &config, &["checkout", "--detach"])?;
+    push_upstream_commits(&ws.join("diverged-remote"), "master", 1)?;
+    std::fs::write(ws.join("diverged").join("local.txt"), "local\n")?;
+    git::run_git(&ws.join("diverged"), &config, &["add", "local.txt"])?;
+    git::run_git(&ws.join("diverged"), &config, &["commit", "-m", "Local"])?;
+    git::run_git(&ws.join("diverged"), &config, &["fetch"])?;
+    std::fs::create_dir(ws.join("local-only"))?;
+    init_repo(&ws.join("local-only"), "master")?;
+
+    let repos = repo::find_git_repos(ws);
+    let report = repo::health_scan(&repos, &config);
+
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    for (bucket, name) in [
+        (HealthBucket::CleanCurrent, "current"),
+        (HealthBucket::CleanBehind, "behind"),
+        (HealthBucket::Dirty, "dirty"),
+        (HealthBucket::Detached, "detached"),
+        (HealthBucket::Diverged, "diverged"),
+        (HealthBucket::NoRemote, "local-only"),
+    ] {
+        assert_eq!(
+            report.in_bucket(bucket),
+            vec![ws.join(name)],
+            "{:?}",
+            bucket
+        );
+    }
+    // Read-only: nothing was pulled or cleaned up
+    assert_eq!(
+        git::ahead_behind(
+            &ws.join("behind"),
+            &config,
+            "master",
+            "origin/master",
+            git::no_op_logger
+        )?,
+        (0, 2)
+    );
+    assert!(ws.join("dirty").join("README.md").exists());
+    Ok(())
+}
+
+#[test]
+fn test_status_scan_paths_feed_update_workspace() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(
+        &workspace,
+        &[("alpha", "master"), ("beta", "master"), ("gamma", "master")],
+    )?;
+    let ws = workspace.path();
+    for name in ["alpha", "beta", "gamma"] {
+        push_upstream_commits(&ws.join(format!("{}-remote", name)), "master", 1)?;
+    }
+    std::fs::write(ws.join("alpha").join("README.md"), "# WIP\n")?;
+    std::fs::write(ws.join("gamma").join("README.md"), "# WIP\n")?;
+
+    let repos = repo::find_git_repos(ws);
+    let scan = repo::status(&repos, &config);
+    assert!(scan.errors.is_empty(), "{:?}", scan.errors);
+    assert_eq!(scan.repos.len(), 3);
+    assert!(scan.repos.iter().all(|repo| repo.branch == "master"));
+
+    let selected = scan.dirty_paths();
+    assert_eq!(selected, vec![ws.join("alpha"), ws.join("gamma")]);
+    let results = repo::update_workspace(&selected, |_| NoOpCallbacks, &config);
+
+    let updated: Vec<_> = results.iter().map(|result| result.path.clone()).collect();
+    assert_eq!(updated, selected);
+    for result in &results {
+        match &result.outcome {
+            UpdateOutcome::Success(success) => assert_eq!(success.commits_pulled, 1),
+            other => anyhow::bail!("expected success, got {:?}", other),
+        }
+    }
+    // The clean repo was left out, so it never fetched the new commit
+    assert_eq!(
+        git::ahead_behind(
+            &ws.join("beta"),
+            &config,
+            "master",
+            "origin/master",
+            git::no_op_logger
+        )?,
+        (0, 0)
+    );
+    Ok(())
+}
+
 #[test]
 fn test_workspace_verbose_runs_sequentially() -> anyhow::Result<()> {
     let mut config = test_config();
@@ -242,6 +530,7 @@ fn test_workspace_verbose_runs_sequentially() -> anyhow::Result<()> {
         &repos,
         |_| ConcurrencyCallbacks {
             active: Arc::clone(&active),
+            max_active: Arc::new(AtomicUsize::new(0)),
             saw_concurrent: Arc::clone(&saw_concurrent),
         },
         &config,
@@ -251,3 +540,598 @@ fn test_workspace_verbose_runs_sequentially() -> anyhow::Result<()> {
     assert!(!saw_concurrent.load(Ordering::SeqCst));
     Ok(())
 }
+
+#[test]
+fn test_workspace_parallelism_respects_configured_jobs() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.jobs = Some(2);
+
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(
+        &workspace,
+        &[
+            ("repo-a", "master"),
+            ("repo-b", "master"),
+            ("repo-c", "master"),
+            ("repo-d", "master"),
+        ],
+    )?;
+
+    let active = Arc::new(AtomicUsize::new(0));
+    let max_active = Arc::new(AtomicUsize::new(0));
+    let saw_concurrent = Arc::new(AtomicBool::new(false));
+
+    let repos = repo::find_git_repos(workspace.path());
+    let results = repo::update_workspace(
+        &repos,
+        |_| ConcurrencyCallbacks {
+            active: Arc::clone(&active),
+            max_active: Arc::clone(&max_active),
+            saw_concurrent: Arc::clone(&saw_concurrent),
+        },
+        &config,
+    );
+
+    assert_eq!(results.len(), 4);
+    assert!(max_active.load(Ordering::SeqCst) <= 2);
+    Ok(())
+}
+
+#[test]
+fn test_workspace_ndjson_events_are_well_formed() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo-a", "master"), ("repo-b", "master")])?;
+
+    let writer = Arc::new(Mutex::new(Vec::new()));
+    let repos = repo::find_git_repos(workspace.path());
+    let results = repo::update_workspace(
+        &repos,
+        |dir| NdjsonCallbacks::new(dir, Arc::clone(&writer)),
+        &config,
+    );
+    assert_eq!(results.len(), 2);
+
+    let output = String::from_utf8(writer.lock().unwrap().clone())?;
+    let events: Vec<serde_json::Value> = output
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+
+    for name in ["repo-a", "repo-b"] {
+        let repo_events: Vec<&serde_json::Value> = events
+            .iter()
+            .filter(|e| e["repo"].as_str().is_some_and(|r| r.ends_with(name)))
+            .collect();
+        assert!(repo_events.iter().any(|e| e["event"] == "update_start"));
+        assert!(repo_events.iter().any(|e| e["event"] == "step"));
+        let last = repo_events.last().unwrap();
+        assert_eq!(last["event"], "complete");
+        assert_eq!(last["status"], "success");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_workspace_ndjson_events_carry_the_run_id() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo-a", "master"), ("repo-b", "main")])?;
+
+    let writer = Arc::new(Mutex::new(Vec::new()));
+    let repos = repo::find_git_repos(workspace.path());
+    repo::update_workspace(
+        &repos,
+        |dir| NdjsonCallbacks::new(dir, Arc::clone(&writer)).with_run_id(Some("run-42")),
+        &config,
+    );
+
+    let output = String::from_utf8(writer.lock().unwrap().clone())?;
+    let events: Vec<serde_json::Value> = output
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert!(events.len() > 4);
+    assert!(events.iter().all(|e| e["run_id"] == "run-42"));
+    Ok(())
+}
+
+#[test]
+fn test_workspace_stats_total_matches_per_repo_commits() -> anyhow::Result<()> {
+    let config = git_daily_rust::config::Config {
+        verbosity: Verbosity::Quiet,
+        ..Default::default()
+    };
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(
+        &workspace,
+        &[
+            ("repo-a", "master"),
+            ("repo-b", "master"),
+            ("repo-c", "main"),
+        ],
+    )?;
+    push_upstream_commits(&workspace.path().join("repo-a-remote"), "master", 2)?;
+    push_upstream_commits(&workspace.path().join("repo-b-remote"), "master", 3)?;
+
+    let stats = Arc::new(WorkspaceStats::default());
+    let repos = repo::find_git_repos(workspace.path());
+    let progress = output::create_workspace_progress(repos.len(), &config, Arc::clone(&stats));
+    let results = repo::update_workspace(
+        &repos,
+        |_| progress.create_repo_tracker("repo", config.clone()),
+        &config,
+    );
+    progress.finish();
+
+    let per_repo: usize = results
+        .iter()
+        .map(|r| match &r.outcome {
+            UpdateOutcome::Success(success) => success.commits_pulled,
+            other => panic!("unexpected outcome: {:?}", other),
+        })
+        .sum();
+    assert_eq!(per_repo, 5);
+    assert_eq!(stats.commits_pulled(), per_repo);
+    assert_eq!(stats.repos_updated(), 3);
+    Ok(())
+}
+
+#[test]
+fn test_workspace_branch_override_applies_only_to_named_repo() -> anyhow::Result<()> {
+    let mut config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo-a", "master"), ("repo-b", "master")])?;
+    let repo_b = workspace.path().join("repo-b");
+    git::run_git(&repo_b, &config, &["branch", "develop"])?;
+    git::run_git(&repo_b, &config, &["push", "-u", "origin", "develop"])?;
+    push_upstream_commits(&workspace.path().join("repo-b-remote"), "develop", 1)?;
+
+    config
+        .branch_overrides
+        .insert("repo-b".to_string(), "develop".to_string());
+    let repos = repo::find_git_repos(workspace.path());
+    let results = repo::update_workspace(&repos, |_| NoOpCallbacks, &config);
+
+    for result in &results {
+        let UpdateOutcome::Success(success) = &result.outcome else {
+            panic!("unexpected outcome: {:?}", result.outcome);
+        };
+        if result.path.ends_with("repo-b") {
+            assert_eq!(success.master_branch.as_deref(), Some("develop"));
+            assert_eq!(success.commits_pulled, 1);
+        } else {
+            assert_eq!(success.master_branch.as_deref(), Some("master"));
+        }
+    }
+    assert_eq!(results.len(), 2);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_workspace_symlinked_repo_discovered_once() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo-a", "master")])?;
+    std::os::unix::fs::symlink(
+        workspace.path().join("repo-a"),
+        workspace.path().join("alias"),
+    )?;
+
+    let repos = repo::find_git_repos(workspace.path());
+    let names: Vec<_> = repos.iter().filter_map(|r| r.file_name()).collect();
+    assert_eq!(names, ["repo-a"]);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_discovery_skips_entries_that_cannot_be_statted() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("repo-a", "master")])?;
+    let broken = workspace.path().join("broken");
+    std::os::unix::fs::symlink(workspace.path().join("gone"), &broken)?;
+
+    let repos = repo::find_git_repos(workspace.path());
+    let names: Vec<_> = repos.iter().filter_map(|r| r.file_name()).collect();
+    assert_eq!(names, ["repo-a"]);
+
+    let rejected = repo::rejected_dirs(workspace.path());
+    let (_, reason) = rejected
+        .iter()
+        .find(|(path, _)| *path == broken)
+        .expect("broken symlink should be reported");
+    assert!(matches!(reason, NotARepoReason::Unreadable { .. }));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_workspace_stops_starting_updates_once_the_disk_is_full() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(
+        &workspace,
+        &[
+            ("repo-a", "master"),
+            ("repo-b", "master"),
+            ("repo-c", "master"),
+        ],
+    )?;
+    let bin_dir = TempDir::new()?;
+    let wrapper = bin_dir.path().join("git-wrapper");
+    std::fs::write(
+        &wrapper,
+        "#!/bin/sh\n\
+         if [ \"$1\" = fetch ]; then\n\
+         \x20 echo 'error: unable to write file tmp_pack: No space left on device' >&2\n\
+         \x20 exit 128\n\
+         fi\n\
+         exec git \"$@\"\n",
+    )?;
+    std::fs::set_permissions(&wrapper, std::fs::Permissions::from_mode(0o755))?;
+    let mut config = test_config();
+    config.git_binary = Some(wrapper);
+    config.jobs = Some(1);
+
+    let repos = repo::find_git_repos(workspace.path());
+    let results = repo::update_workspace(&repos, |_| NoOpCallbacks, &config);
+
+    let UpdateOutcome::Failed(failure) = &results[0].outcome else {
+        panic!("expected failure, got {:?}", results[0].outcome);
+    };
+    assert!(failure.disk_full);
+    assert!(failure.error.starts_with("disk full:"), "{}", failure.error);
+    assert!(failure.error.contains("tmp_pack"), "{}", failure.error);
+    for result in &results[1..] {
+        let UpdateOutcome::Skipped(skipped) = &result.outcome else {
+            panic!("expected skip, got {:?}", result.outcome);
+        };
+        assert!(skipped.reason.contains("disk space"), "{}", skipped.reason);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_group_by_host_counts_outcomes_per_remote_host() -> anyhow::Result<()> {
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(
+        &workspace,
+        &[("api", "master"), ("web", "master"), ("mirror", "master")],
+    )?;
+    let config = test_config();
+    let set_url = |repo: &str, url: &str| {
+        git::run_git(
+            &workspace.path().join(repo),
+            &config,
+            &["remote", "set-url", "origin", url],
+        )
+    };
+    // Fails at fetch; the URL is switched to its fake host after the run
+    set_url("mirror", "/nonexistent/mirror.git")?;
+
+    let repos = repo::find_git_repos(workspace.path());
+    let results = repo::update_workspace(&repos, |_| NoOpCallbacks, &config);
+    set_url("api", "git@github.example.com:team/api.git")?;
+    set_url("web", "https://github.example.com/team/web.git")?;
+    set_url("mirror", "https://gitlab.example.com/team/mirror.git")?;
+    let groups = repo::group_by_host(&results, &config);
+
+    assert_eq!(groups.len(), 2, "{:?}", groups);
+    assert_eq!(groups[0].host, "github.example.com");
+    assert_eq!((groups[0].succeeded, groups[0].failed), (2, 0));
+    assert_eq!(groups[1].host, "gitlab.example.com");
+    assert_eq!((groups[1].succeeded, groups[1].failed), (0, 1));
+    Ok(())
+}
+
+#[test]
+fn test_check_connectivity_short_circuits_on_unreachable_remote() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("a", "master"), ("b", "master")])?;
+    let repos = repo::find_git_repos(workspace.path());
+    assert!(repo::check_connectivity(&repos, &config).is_ok());
+
+    // Simulate an unreachable host by pointing the first repo at a remote that does not exist
+    let missing = workspace.path().join("gone.invalid");
+    git::run_git(
+        &repos[0],
+        &config,
+        &["remote", "set-url", "origin", missing.to_str().unwrap()],
+    )?;
+
+    let err = repo::check_connectivity(&repos, &config).unwrap_err();
+    assert!(err.to_string().starts_with("network unreachable"));
+    Ok(())
+}
+
+#[test]
+fn test_check_connectivity_with_no_repos_is_ok() {
+    assert!(repo::check_connectivity(&[], &test_config()).is_ok());
+}
+
+/// Records "start <repo>" and "end <repo>" events in the order they happen.
+struct EventLogCallbacks {
+    repo: String,
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl UpdateCallbacks for EventLogCallbacks {
+    fn on_step(&self, _step: &UpdateStep) {}
+
+    fn on_update_start(&self, _repo_name: &str) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("start {}", self.repo));
+    }
+
+    fn on_complete(&self, _result: &repo::UpdateResult) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("end {}", self.repo));
+    }
+}
+
+#[test]
+fn test_update_workspace_runs_manifest_waves_in_order() -> anyhow::Result<()> {
+    let mut config = test_config();
+    config.manifest = Some(Manifest::from_toml(
+        r#"
+        [[wave]]
+        repos = ["repo-c", "repo-d"]
+
+        [[wave]]
+        repos = ["repo-a"]
+        "#,
+    )?);
+
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(
+        &workspace,
+        &[
+            ("repo-a", "master"),
+            ("repo-b", "master"),
+            ("repo-c", "master"),
+            ("repo-d", "master"),
+        ],
+    )?;
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let repos = repo::find_git_repos(workspace.path());
+    let results = repo::update_workspace(
+        &repos,
+        |dir| EventLogCallbacks {
+            repo: dir.file_name().unwrap().to_string_lossy().to_string(),
+            events: Arc::clone(&events),
+        },
+        &config,
+    );
+
+    // Results still come back in input order
+    let paths: Vec<_> = results.iter().map(|r| r.path.clone()).collect();
+    assert_eq!(paths, repos);
+
+    let events = events.lock().unwrap();
+    let at = |event: &str| events.iter().position(|e| e == event).unwrap();
+    // Each wave starts only after every repo of the previous wave finished
+    assert!(at("start repo-a") > at("end repo-c"));
+    assert!(at("start repo-a") > at("end repo-d"));
+    // Unlisted repos run last by default
+    assert!(at("start repo-b") > at("end repo-a"));
+    Ok(())
+}
+
+/// Vetoes every repository whose directory name starts with `prefix`.
+struct PrefixVeto {
+    prefix: &'static str,
+}
+
+impl UpdateCallbacks for PrefixVeto {
+    fn should_update(&self, repo: &std::path::Path) -> bool {
+        !repo
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with(self.prefix))
+    }
+    fn on_step(&self, _step: &UpdateStep) {}
+    fn on_complete(&self, _result: &repo::UpdateResult) {}
+}
+
+#[test]
+fn test_update_workspace_skips_repos_vetoed_by_callback() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("keep-a", "master"), ("legacy-b", "master")])?;
+    let vetoed = workspace.path().join("legacy-b");
+    push_upstream_commits(&workspace.path().join("legacy-b-remote"), "master", 1)?;
+    let before = git::get_current_commit(&vetoed, &config, git::no_op_logger)?;
+
+    let repos = vec![workspace.path().join("keep-a"), vetoed.clone()];
+    let results = repo::update_workspace(&repos, |_| PrefixVeto { prefix: "legacy-" }, &config);
+
+    assert!(matches!(results[0].outcome, UpdateOutcome::Success(_)));
+    let UpdateOutcome::Skipped(skipped) = &results[1].outcome else {
+        panic!(
+            "expected the vetoed repo to be skipped, got {:?}",
+            results[1].outcome
+        );
+    };
+    assert_eq!(skipped.reason, repo::VETOED_SKIP_REASON);
+    assert_eq!(skipped.step, UpdateStep::Started);
+    assert_eq!(
+        git::get_current_commit(&vetoed, &config, git::no_op_logger)?,
+        before
+    );
+    Ok(())
+}
+
+#[test]
+fn test_status_scan_counts_local_branches_and_those_without_upstream() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(&workspace, &[("app", "master")])?;
+    let app = workspace.path().join("app");
+    for branch in ["old-spike", "wip-refactor", "tmp"] {
+        git::run_git(&app, &config, &["branch", branch])?;
+    }
+
+    let mut branches = git::local_branches(&app, &config, git::no_op_logger)?;
+    branches.sort();
+    assert_eq!(branches, ["master", "old-spike", "tmp", "wip-refactor"]);
+
+    let scan = repo::status(std::slice::from_ref(&app), &config);
+    assert!(scan.errors.is_empty(), "{:?}", scan.errors);
+    assert_eq!(scan.repos[0].local_branches, 4);
+    assert_eq!(scan.repos[0].branches_without_upstream, 3);
+    Ok(())
+}
+
+#[test]
+fn test_update_workspace_step_sequences_for_clean_dirty_and_detached_repos() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(
+        &workspace,
+        &[
+            ("clean", "master"),
+            ("dirty", "master"),
+            ("detached", "master"),
+        ],
+    )?;
+    let ws = workspace.path();
+    git::run_git(&ws.join("dirty"), &config, &["checkout", "-b", "feature"])?;
+    std::fs::write(ws.join("dirty").join("README.md"), "# WIP\n")?;
+    git::run_git(
+        &ws.join("detached"),
+        &config,
+        &["checkout", "--detach", "HEAD"],
+    )?;
+    let repos = vec![ws.join("clean"), ws.join("dirty"), ws.join("detached")];
+
+    let recorder = RecordingCallbacks::new();
+    let results = repo::update_workspace(&repos, |path| recorder.for_repo(path), &config);
+
+    assert!(
+        results
+            .iter()
+            .all(|result| matches!(result.outcome, UpdateOutcome::Success(_))),
+        "{:?}",
+        results
+    );
+    // Fetching always comes before anything that touches the working tree
+    assert_eq!(
+        recorder.steps(&repos[0]),
+        [
+            UpdateStep::Started,
+            UpdateStep::DetectingBranch,
+            UpdateStep::CheckingChanges,
+            UpdateStep::Fetching,
+            UpdateStep::CheckingOut,
+            UpdateStep::Pulling,
+            UpdateStep::RestoringBranch,
+            UpdateStep::Completed,
+        ]
+    );
+    assert_eq!(
+        recorder.steps(&repos[1]),
+        [
+            UpdateStep::Started,
+            UpdateStep::DetectingBranch,
+            UpdateStep::CheckingChanges,
+            UpdateStep::Fetching,
+            UpdateStep::Stashing,
+            UpdateStep::CheckingOut,
+            UpdateStep::Pulling,
+            UpdateStep::RestoringBranch,
+            UpdateStep::PoppingStash,
+            UpdateStep::Completed,
+        ]
+    );
+    // A detached HEAD is restored to its commit, just as a branch is checked out again
+    assert_eq!(
+        recorder.steps(&repos[2]),
+        [
+            UpdateStep::Started,
+            UpdateStep::DetectingBranch,
+            UpdateStep::CheckingChanges,
+            UpdateStep::Fetching,
+            UpdateStep::CheckingOut,
+            UpdateStep::Pulling,
+            UpdateStep::RestoringBranch,
+            UpdateStep::Completed,
+        ]
+    );
+    Ok(())
+}
+
+/// Sets the modification time of `path` to `days` days ago.
+fn backdate(path: &std::path::Path, days: u64) -> anyhow::Result<()> {
+    let then = std::time::SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+    std::fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(then)?;
+    Ok(())
+}
+
+#[test]
+fn test_health_and_status_report_time_since_last_fetch() -> anyhow::Result<()> {
+    let config = test_config();
+    let workspace = TempDir::new()?;
+    setup_workspace_with_repos(
+        &workspace,
+        &[
+            ("fresh", "master"),
+            ("month", "master"),
+            ("never", "master"),
+            ("packed", "master"),
+        ],
+    )?;
+    let ws = workspace.path();
+    git::fetch_prune(&ws.join("fresh"), &config, git::no_op_logger)?;
+    git::fetch_prune(&ws.join("month"), &config, git::no_op_logger)?;
+    backdate(&ws.join("month/.git/FETCH_HEAD"), 30)?;
+    // Never fetched: only the remote-tracking ref `push -u` created tells
+    assert!(!ws.join("never/.git/FETCH_HEAD").exists());
+    backdate(&ws.join("never/.git/refs/remotes/origin/master"), 90)?;
+    // Never fetched, and its remote-tracking refs were packed away
+    git::run_git(&ws.join("packed"), &config, &["pack-refs", "--all"])?;
+    assert!(!ws.join("packed/.git/refs/remotes/origin/master").exists());
+    backdate(&ws.join("packed/.git/packed-refs"), 60)?;
+    let repos = vec![
+        ws.join("fresh"),
+        ws.join("month"),
+        ws.join("packed"),
+        ws.join("never"),
+    ];
+
+    let health = repo::health_scan(&repos, &config);
+
+    let order: Vec<_> = health
+        .least_recently_fetched()
+        .into_iter()
+        .map(|(path, _)| path.to_path_buf())
+        .collect();
+    assert_eq!(
+        order,
+        [
+            ws.join("never"),
+            ws.join("packed"),
+            ws.join("month"),
+            ws.join("fresh")
+        ]
+    );
+    let days = |path: &std::path::Path| health.fetch_age(path).map(|age| age.as_secs() / 86_400);
+    assert_eq!(days(&ws.join("fresh")), Some(0));
+    assert_eq!(days(&ws.join("month")), Some(30));
+    assert_eq!(days(&ws.join("packed")), Some(60));
+    assert_eq!(days(&ws.join("never")), Some(90));
+
+    let scan = repo::status(&repos, &config);
+    let fetched_ago: Vec<_> = scan.repos.iter().map(|repo| repo.fetched_ago).collect();
+    assert!(fetched_ago.is_sorted());
+    Ok(())
+}